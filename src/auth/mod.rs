@@ -0,0 +1,134 @@
+// Authorization module for the SFU
+//
+// This module implements token-based authorization: a signed token decodes
+// into `Grants` describing what a session may do, inspired by access-token
+// + video-grant style signalling used by hosted conferencing platforms.
+
+use std::collections::HashMap;
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::{media::codec::CodecType, SfuError};
+
+/// Capabilities granted to a session by a verified token
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Grants {
+    /// Whether this session may publish tracks
+    pub can_publish: bool,
+    /// Whether this session may subscribe to tracks
+    pub can_subscribe: bool,
+    /// Codecs this session is allowed to publish
+    pub allowed_codecs: Vec<CodecType>,
+    /// Room this session is scoped to, if the deployment uses rooms
+    pub room: Option<String>,
+    /// Maximum number of simultaneously published tracks per `TrackKind`
+    /// name (`TrackKind::name`), unconstrained for kinds not present
+    pub publish_caps: HashMap<String, u32>,
+}
+
+impl Grants {
+    /// Grants allowing everything and every codec, for deployments that
+    /// don't enforce authorization (e.g. local development)
+    pub fn unrestricted() -> Self {
+        Self {
+            can_publish: true,
+            can_subscribe: true,
+            allowed_codecs: CodecType::ALL.to_vec(),
+            room: None,
+            publish_caps: HashMap::new(),
+        }
+    }
+
+    /// Whether `codec_type` may be published under these grants
+    pub fn allows_codec(&self, codec_type: CodecType) -> bool {
+        self.allowed_codecs.contains(&codec_type)
+    }
+
+    /// Maximum number of published tracks allowed for a `TrackKind` name, if capped
+    pub fn publish_cap(&self, kind_name: &str) -> Option<u32> {
+        self.publish_caps.get(kind_name).copied()
+    }
+}
+
+/// Verifies an opaque signed token and decodes it into the `Grants` it encodes
+pub trait TokenVerifier: Send + Sync {
+    /// Verify `token`, returning the `Grants` it encodes or
+    /// `SfuError::Unauthorized` if the token is invalid, expired, or malformed
+    fn verify(&self, token: &str) -> Result<Grants>;
+}
+
+/// Token verifier that decodes a JSON-serialized `Grants` payload with no
+/// signature check at all.
+///
+/// This exists so the rest of the authorization plumbing (`create_session`,
+/// `register_published_track`, `register_subscribed_track`) has something to
+/// call; it is not a real signing scheme. Deployments must supply their own
+/// `TokenVerifier` (e.g. backed by HMAC or JWT signatures) before accepting
+/// untrusted connections.
+pub struct InsecureJsonTokenVerifier;
+
+impl TokenVerifier for InsecureJsonTokenVerifier {
+    fn verify(&self, token: &str) -> Result<Grants> {
+        serde_json::from_str(token)
+            .map_err(|e| SfuError::Unauthorized(format!("Invalid token: {}", e)).into())
+    }
+}
+
+/// Token verifier that grants every session full access, ignoring whatever
+/// the token contains. For local development/testing where no real
+/// authorization is needed at all, including when no token is supplied.
+pub struct AllowAllTokenVerifier;
+
+impl TokenVerifier for AllowAllTokenVerifier {
+    fn verify(&self, _token: &str) -> Result<Grants> {
+        Ok(Grants::unrestricted())
+    }
+}
+
+/// Token verifier that decodes a signed JWT into `Grants`, via HS256 (a
+/// shared secret) or EdDSA (a public key), rejecting tokens with an invalid
+/// signature, wrong algorithm, or that don't decode into `Grants`
+pub struct JwtTokenVerifier {
+    decoding_key: jsonwebtoken::DecodingKey,
+    validation: jsonwebtoken::Validation,
+}
+
+impl JwtTokenVerifier {
+    /// Verify tokens signed with a shared HS256 secret
+    pub fn hs256(secret: &[u8]) -> Self {
+        Self {
+            decoding_key: jsonwebtoken::DecodingKey::from_secret(secret),
+            validation: Self::validation(jsonwebtoken::Algorithm::HS256),
+        }
+    }
+
+    /// Verify tokens signed with an EdDSA key pair, from the signer's
+    /// PEM-encoded public key
+    pub fn eddsa(public_key_pem: &[u8]) -> Result<Self> {
+        let decoding_key = jsonwebtoken::DecodingKey::from_ed_pem(public_key_pem)
+            .map_err(|e| SfuError::Unauthorized(format!("Invalid EdDSA public key: {}", e)))?;
+
+        Ok(Self {
+            decoding_key,
+            validation: Self::validation(jsonwebtoken::Algorithm::EdDSA),
+        })
+    }
+
+    /// `Grants` carries no standard registered claims (`exp`, `iat`, ...), so
+    /// don't require them - only the signature and algorithm are checked
+    fn validation(algorithm: jsonwebtoken::Algorithm) -> jsonwebtoken::Validation {
+        let mut validation = jsonwebtoken::Validation::new(algorithm);
+        validation.required_spec_claims.clear();
+        validation.validate_exp = false;
+        validation
+    }
+}
+
+impl TokenVerifier for JwtTokenVerifier {
+    fn verify(&self, token: &str) -> Result<Grants> {
+        jsonwebtoken::decode::<Grants>(token, &self.decoding_key, &self.validation)
+            .map(|data| data.claims)
+            .map_err(|e| SfuError::Unauthorized(format!("Invalid token: {}", e)).into())
+    }
+}
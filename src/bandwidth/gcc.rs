@@ -0,0 +1,302 @@
+// Delay-based congestion control (Google Congestion Control)
+//
+// Implements the receive-side delay-gradient trendline estimator, adaptive
+// overuse detector, and AIMD rate controller, in place of a coarse
+// linear-regression trend over historical bandwidth samples.
+
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+/// Packets whose send times fall within this window of each other are
+/// grouped into a single burst for inter-group delay variation purposes
+const BURST_TIME_WINDOW: Duration = Duration::from_millis(5);
+/// Number of delay samples kept for the trendline least-squares slope
+const TRENDLINE_WINDOW: usize = 20;
+/// Overuse must be sustained for at least this long before it is signaled
+const OVERUSE_TIME_THRESHOLD: Duration = Duration::from_millis(10);
+/// Gain applied to the least-squares slope to produce the modified trend `m(i)`
+const TRENDLINE_GAIN: f64 = 4.0;
+/// Adaptive threshold gain used while the modified trend exceeds the threshold
+const THRESHOLD_GAIN_UP: f64 = 0.01;
+/// Adaptive threshold gain used while the modified trend is within the threshold
+const THRESHOLD_GAIN_DOWN: f64 = 0.00018;
+/// Lower bound the adaptive threshold is clamped to
+const THRESHOLD_MIN: f64 = 6.0;
+/// Upper bound the adaptive threshold is clamped to
+const THRESHOLD_MAX: f64 = 600.0;
+
+/// Bandwidth usage signaled by the overuse detector
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BandwidthUsage {
+    /// Queuing delay is flat; the link is neither over- nor under-used
+    Normal,
+    /// Queuing delay is growing; the link is congested
+    Overuse,
+    /// Queuing delay is shrinking; the link has slack
+    Underuse,
+}
+
+/// One burst of packets that departed, and as a result arrived, close together
+struct PacketGroup {
+    first_departure: Instant,
+    first_arrival: Instant,
+    last_arrival: Instant,
+    size_bytes: usize,
+}
+
+/// Delay-gradient trendline estimator and adaptive overuse detector (GCC)
+pub struct GccEstimator {
+    /// Packet group still accumulating packets within `BURST_TIME_WINDOW`
+    current_group: Option<PacketGroup>,
+    /// Most recently completed packet group, awaiting the next one to diff against
+    previous_group: Option<PacketGroup>,
+    /// (arrival time offset in ms, accumulated delay in ms) samples for the trendline slope
+    delay_samples: VecDeque<(f64, f64)>,
+    /// Running sum of inter-group delay variation, the trendline's y-axis
+    accumulated_delay_ms: f64,
+    /// Arrival time of the first packet group, the trendline's time origin
+    trendline_origin: Option<Instant>,
+    /// Adaptive overuse threshold (gamma)
+    threshold: f64,
+    /// Arrival time of the last threshold update, for the `dt` term
+    last_threshold_update: Option<Instant>,
+    /// When the modified trend first crossed the threshold, if still above it
+    overuse_since: Option<Instant>,
+    /// Current detector state
+    state: BandwidthUsage,
+    /// Adaptive-threshold gain applied while the modified trend exceeds the threshold
+    k_up: f64,
+    /// Adaptive-threshold gain applied while the modified trend is within the threshold
+    k_down: f64,
+    /// How long overuse must be sustained before it is signaled
+    overuse_time_threshold: Duration,
+}
+
+impl Default for GccEstimator {
+    fn default() -> Self {
+        Self::with_gains(THRESHOLD_GAIN_UP, THRESHOLD_GAIN_DOWN, OVERUSE_TIME_THRESHOLD)
+    }
+}
+
+impl GccEstimator {
+    /// Create a detector with the default adaptive-threshold gains but a
+    /// configurable overuse confirmation window, i.e. how long sustained
+    /// queuing delay must persist before a sender reacts to it
+    pub fn with_overuse_threshold(overuse_time_threshold: Duration) -> Self {
+        Self::with_gains(THRESHOLD_GAIN_UP, THRESHOLD_GAIN_DOWN, overuse_time_threshold)
+    }
+
+    /// Create a detector with non-default adaptive-threshold gains and
+    /// overuse confirmation window, so `transport::adaptation::ControllerGains`
+    /// can make `AdaptationStrategy` actually change ramp behavior
+    pub fn with_gains(k_up: f64, k_down: f64, overuse_time_threshold: Duration) -> Self {
+        Self {
+            current_group: None,
+            previous_group: None,
+            delay_samples: VecDeque::with_capacity(TRENDLINE_WINDOW),
+            accumulated_delay_ms: 0.0,
+            trendline_origin: None,
+            threshold: 12.5, // GCC's recommended initial threshold
+            last_threshold_update: None,
+            overuse_since: None,
+            state: BandwidthUsage::Normal,
+            k_up,
+            k_down,
+            overuse_time_threshold,
+        }
+    }
+
+    /// Feed one received packet's departure (send) and arrival timestamps into
+    /// the estimator
+    ///
+    /// Returns the detector's current state and, whenever a packet group just
+    /// completed, the receive bitrate measured over that group in bps.
+    pub fn on_packet(&mut self, departure: Instant, arrival: Instant, size_bytes: usize) -> (BandwidthUsage, Option<f64>) {
+        if let Some(group) = &mut self.current_group {
+            if departure.duration_since(group.first_departure) <= BURST_TIME_WINDOW {
+                group.last_arrival = arrival;
+                group.size_bytes += size_bytes;
+                return (self.state, None);
+            }
+        }
+
+        let finished = self.current_group.replace(PacketGroup {
+            first_departure: departure,
+            first_arrival: arrival,
+            last_arrival: arrival,
+            size_bytes,
+        });
+
+        let Some(finished) = finished else {
+            return (self.state, None);
+        };
+
+        let receive_rate_bps = Self::group_bitrate_bps(&finished);
+
+        if let Some(previous) = self.previous_group.replace(finished) {
+            // `previous_group` now holds the group that just finished
+            let just_finished = self.previous_group.as_ref().unwrap();
+            let (first_departure, first_arrival) = (just_finished.first_departure, just_finished.first_arrival);
+            self.update_trendline(&previous, first_departure, first_arrival);
+        }
+
+        (self.state, Some(receive_rate_bps))
+    }
+
+    fn group_bitrate_bps(group: &PacketGroup) -> f64 {
+        let span = group.last_arrival.duration_since(group.first_arrival).as_secs_f64().max(0.001);
+        (group.size_bytes as f64 * 8.0) / span
+    }
+
+    fn update_trendline(&mut self, previous: &PacketGroup, current_first_departure: Instant, current_first_arrival: Instant) {
+        let inter_departure_ms = current_first_departure.duration_since(previous.first_departure).as_secs_f64() * 1000.0;
+        let inter_arrival_ms = current_first_arrival.duration_since(previous.first_arrival).as_secs_f64() * 1000.0;
+        let delay_variation_ms = inter_arrival_ms - inter_departure_ms;
+
+        self.accumulated_delay_ms += delay_variation_ms;
+
+        let origin = *self.trendline_origin.get_or_insert(current_first_arrival);
+        let x = current_first_arrival.duration_since(origin).as_secs_f64() * 1000.0;
+
+        self.delay_samples.push_back((x, self.accumulated_delay_ms));
+        if self.delay_samples.len() > TRENDLINE_WINDOW {
+            self.delay_samples.pop_front();
+        }
+
+        if self.delay_samples.len() < 2 {
+            return;
+        }
+
+        let slope = least_squares_slope(&self.delay_samples);
+        let window_ms = self.delay_samples.back().unwrap().0 - self.delay_samples.front().unwrap().0;
+        let modified_trend = slope * window_ms.max(1.0) * TRENDLINE_GAIN / self.delay_samples.len() as f64;
+
+        let dt = self
+            .last_threshold_update
+            .map(|t| current_first_arrival.duration_since(t).as_secs_f64())
+            .unwrap_or(0.0);
+        self.last_threshold_update = Some(current_first_arrival);
+
+        let gain = if modified_trend.abs() > self.threshold {
+            self.k_up
+        } else {
+            self.k_down
+        };
+        self.threshold = (self.threshold + gain * (modified_trend.abs() - self.threshold) * dt * 1000.0)
+            .clamp(THRESHOLD_MIN, THRESHOLD_MAX);
+
+        if modified_trend > self.threshold {
+            let since = *self.overuse_since.get_or_insert(current_first_arrival);
+            if current_first_arrival.duration_since(since) >= self.overuse_time_threshold {
+                self.state = BandwidthUsage::Overuse;
+            }
+        } else if modified_trend < -self.threshold {
+            self.overuse_since = None;
+            self.state = BandwidthUsage::Underuse;
+        } else {
+            self.overuse_since = None;
+            self.state = BandwidthUsage::Normal;
+        }
+    }
+}
+
+/// Ordinary least-squares slope of `(x, y)` samples
+fn least_squares_slope(samples: &VecDeque<(f64, f64)>) -> f64 {
+    let n = samples.len() as f64;
+    let (mut sum_x, mut sum_y, mut sum_xy, mut sum_xx) = (0.0, 0.0, 0.0, 0.0);
+
+    for (x, y) in samples {
+        sum_x += x;
+        sum_y += y;
+        sum_xy += x * y;
+        sum_xx += x * x;
+    }
+
+    let denom = n * sum_xx - sum_x * sum_x;
+    if denom.abs() < f64::EPSILON {
+        0.0
+    } else {
+        (n * sum_xy - sum_x * sum_y) / denom
+    }
+}
+
+/// Default multiplicative increase factor applied on sustained Normal state
+const DEFAULT_INCREASE_FACTOR: f64 = 1.08;
+/// Default multiplicative decrease factor applied on Overuse
+pub(crate) const DEFAULT_DECREASE_FACTOR: f64 = 0.85;
+
+/// AIMD rate controller driven by a `GccEstimator`'s detector state
+pub struct AimdRateController {
+    /// Current target bitrate in bps
+    target_bps: f64,
+    /// Target bitrate just before the most recent Overuse backoff, used to
+    /// detect when the controller has climbed back near the last known ceiling
+    last_max_bps: Option<f64>,
+    /// Multiplicative increase factor applied on sustained Normal state
+    increase_factor: f64,
+    /// Multiplicative decrease factor applied on Overuse
+    decrease_factor: f64,
+}
+
+impl AimdRateController {
+    /// Create a new rate controller starting at `initial_bps`
+    pub fn new(initial_bps: u32) -> Self {
+        Self::with_gains(initial_bps, DEFAULT_INCREASE_FACTOR, DEFAULT_DECREASE_FACTOR)
+    }
+
+    /// Create a rate controller with the default increase factor but a
+    /// configurable multiplicative decrease factor applied on overuse
+    pub fn with_decrease_factor(initial_bps: u32, decrease_factor: f64) -> Self {
+        Self::with_gains(initial_bps, DEFAULT_INCREASE_FACTOR, decrease_factor)
+    }
+
+    /// Create a rate controller with non-default AIMD gains, so
+    /// `transport::adaptation::ControllerGains` can make `AdaptationStrategy`
+    /// actually change ramp behavior
+    pub fn with_gains(initial_bps: u32, increase_factor: f64, decrease_factor: f64) -> Self {
+        Self {
+            target_bps: initial_bps as f64,
+            last_max_bps: None,
+            increase_factor,
+            decrease_factor,
+        }
+    }
+
+    /// Advance the controller by one detector update, returning the new target bitrate in bps
+    pub fn update(&mut self, usage: BandwidthUsage, receive_rate_bps: Option<f64>) -> u32 {
+        match usage {
+            BandwidthUsage::Overuse => {
+                if let Some(rate) = receive_rate_bps {
+                    self.last_max_bps = Some(self.target_bps);
+                    self.target_bps = (rate * self.decrease_factor).min(self.target_bps);
+                }
+            }
+            BandwidthUsage::Normal => {
+                let near_max = self
+                    .last_max_bps
+                    .map_or(false, |max| (self.target_bps - max).abs() < max * 0.05);
+
+                if near_max {
+                    // Additive increase: roughly one packet per response interval
+                    self.target_bps += 1_200.0 * 8.0;
+                } else {
+                    // Multiplicative increase
+                    self.target_bps *= self.increase_factor;
+                }
+            }
+            BandwidthUsage::Underuse => {
+                // Hold the current target steady
+            }
+        }
+
+        self.target_bps = self.target_bps.max(10_000.0);
+        self.target_bps as u32
+    }
+
+    /// Current target bitrate in bps
+    pub fn target_bps(&self) -> u32 {
+        self.target_bps as u32
+    }
+}
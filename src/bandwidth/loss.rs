@@ -0,0 +1,67 @@
+// Loss-based congestion control, complementing the delay-based GCC estimator
+//
+// Tracks fraction-lost samples per session over a rolling window and applies
+// the standard TCP-friendly AIMD rule driven by loss rate: back off sharply
+// above a high-loss threshold, hold steady in between, and probe upward when
+// loss is negligible.
+
+use std::time::{Duration, Instant};
+
+/// Rolling window over which fraction-lost samples are averaged
+const LOSS_WINDOW: Duration = Duration::from_secs(2);
+/// Below this loss fraction, the target is increased
+const LOW_LOSS_THRESHOLD: f32 = 0.02;
+/// Above this loss fraction, the target is aggressively reduced
+const HIGH_LOSS_THRESHOLD: f32 = 0.10;
+/// Multiplicative increase applied to the target when loss is low
+const INCREASE_FACTOR: f32 = 1.08;
+
+/// Loss-based target bitrate controller
+pub struct LossController {
+    /// Current target bitrate in bps
+    target_bps: f32,
+    /// Fraction-lost samples (sample time, fraction in 0.0-1.0) within `LOSS_WINDOW`
+    history: Vec<(Instant, f32)>,
+}
+
+impl LossController {
+    /// Create a new loss controller starting at `initial_bps`
+    pub fn new(initial_bps: u32) -> Self {
+        Self {
+            target_bps: initial_bps as f32,
+            history: Vec::new(),
+        }
+    }
+
+    /// Record one fraction-lost sample (0.0-1.0) and recompute the target
+    pub fn report_loss_fraction(&mut self, loss_fraction: f32) -> u32 {
+        let now = Instant::now();
+        self.history.push((now, loss_fraction));
+        self.history.retain(|(time, _)| now.duration_since(*time) <= LOSS_WINDOW);
+
+        let avg_loss = self.average_loss_fraction().unwrap_or(0.0);
+
+        if avg_loss < LOW_LOSS_THRESHOLD {
+            self.target_bps *= INCREASE_FACTOR;
+        } else if avg_loss > HIGH_LOSS_THRESHOLD {
+            self.target_bps *= 1.0 - 0.5 * avg_loss;
+        }
+        // Between the two thresholds: hold the target steady
+
+        self.target_bps = self.target_bps.max(10_000.0);
+        self.target_bps as u32
+    }
+
+    /// Current target bitrate in bps
+    pub fn target_bps(&self) -> u32 {
+        self.target_bps as u32
+    }
+
+    /// Average fraction lost over the rolling window, or `None` with no samples yet
+    pub fn average_loss_fraction(&self) -> Option<f32> {
+        if self.history.is_empty() {
+            return None;
+        }
+        Some(self.history.iter().map(|(_, loss)| *loss).sum::<f32>() / self.history.len() as f32)
+    }
+}
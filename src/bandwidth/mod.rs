@@ -2,8 +2,11 @@
 //
 // This module handles bandwidth estimation and adaptation.
 
+pub mod gcc;
+pub mod loss;
+
 use std::{
-    collections::HashMap,
+    collections::{HashMap, HashSet},
     sync::Arc,
     time::{Duration, Instant},
 };
@@ -13,12 +16,23 @@ use async_trait::async_trait;
 use tokio::sync::RwLock;
 
 use crate::{
+    bandwidth::{
+        gcc::{AimdRateController, BandwidthUsage, GccEstimator},
+        loss::LossController,
+    },
+    clock::{Clock, SystemClock},
     media::TrackId,
     session::SessionId,
     feedback::{BandwidthEstimation, BandwidthTrend},
     SfuError,
 };
 
+/// Initial target bitrate assumed before any packet groups have been observed
+const INITIAL_TARGET_BITRATE_BPS: u32 = 500_000;
+/// Bitrate allocated to a forwarded-but-not-prioritized track falling back to
+/// its lowest simulcast layer, a typical lowest-spatial-layer target
+const LOWEST_LAYER_BITRATE_BPS: u32 = 150_000;
+
 /// Bandwidth information
 pub struct BandwidthInfo {
     /// Estimated available upload bandwidth in bps
@@ -31,6 +45,12 @@ pub struct BandwidthInfo {
     pub history: Vec<(Instant, u32)>,
     /// Current bandwidth trend
     pub trend: BandwidthTrend,
+    /// Delay-gradient estimator and overuse detector driving `rate_controller`
+    pub gcc: GccEstimator,
+    /// AIMD rate controller driven by `gcc`, producing the delay-based target bitrate
+    pub rate_controller: AimdRateController,
+    /// Loss-based target bitrate controller, fed by receiver-reported fraction lost
+    pub loss_controller: LossController,
 }
 
 /// Bandwidth manager trait
@@ -56,7 +76,43 @@ pub trait BandwidthManager: Send + Sync {
     
     /// Process bandwidth estimation message
     async fn process_bandwidth_estimation(&self, estimation: BandwidthEstimation) -> Result<()>;
-    
+
+    /// Feed one received packet's departure (send) and arrival timestamps into
+    /// the session's delay-based congestion controller, advancing the overuse
+    /// detector and AIMD rate controller that back `get_recommended_bitrate`
+    /// and `get_bandwidth_trend`
+    async fn report_packet_group(
+        &self,
+        session_id: SessionId,
+        departure: Instant,
+        arrival: Instant,
+        size_bytes: usize,
+    ) -> Result<()>;
+
+    /// Feed one receiver-reported fraction-lost sample into the session's
+    /// loss-based congestion controller, the complement to the delay-based
+    /// signal fed by `report_packet_group`
+    async fn report_packet_loss(&self, session_id: SessionId, loss_fraction: f32) -> Result<()>;
+
+    /// Get the loss-based target bitrate for a session, independent of the
+    /// delay-based target tracked by `gcc`/`rate_controller`
+    async fn get_loss_based_bitrate(&self, session_id: SessionId) -> Result<u32>;
+
+    /// Declare the set of track IDs that must always be forwarded at full
+    /// quality for `session_id`, regardless of the `last_n` activity ranking
+    async fn set_selected_tracks(&self, session_id: SessionId, track_ids: HashSet<TrackId>) -> Result<()>;
+
+    /// Cap the number of simultaneously forwarded video tracks for
+    /// `session_id`, including selected tracks; tracks beyond the cap fall
+    /// back to the lowest simulcast layer, or are suspended once the budget
+    /// is exhausted
+    async fn set_last_n(&self, session_id: SessionId, last_n: usize) -> Result<()>;
+
+    /// Record that `track_id` was recently active (an active-speaker switch
+    /// or a freshly arrived key frame) for `session_id`, used to rank tracks
+    /// when `last_n` restricts how many are simultaneously forwarded
+    async fn report_track_activity(&self, session_id: SessionId, track_id: TrackId) -> Result<()>;
+
     /// Distribute bandwidth among tracks
     async fn distribute_bandwidth(
         &self,
@@ -66,71 +122,113 @@ pub trait BandwidthManager: Send + Sync {
     ) -> Result<HashMap<TrackId, u32>>;
 }
 
+/// Per-session "last-N" / selected-endpoint forwarding state
+#[derive(Default)]
+struct SelectionState {
+    /// Track IDs always forwarded regardless of activity ranking
+    selected_tracks: HashSet<TrackId>,
+    /// Cap on the number of simultaneously forwarded video tracks, including
+    /// `selected_tracks`; `None` means no cap, so every known track is forwarded
+    last_n: Option<usize>,
+    /// Most recent activity per track, used to rank non-selected tracks when
+    /// `last_n` restricts how many are simultaneously forwarded
+    track_activity: HashMap<TrackId, Instant>,
+}
+
+impl SelectionState {
+    /// Tracks to forward at full quality this round: all of `selected_tracks`
+    /// plus, if `last_n` is set, the most recently active tracks up to the cap
+    fn forwarded_tracks(&self, candidates: &HashMap<TrackId, u8>) -> HashSet<TrackId> {
+        let Some(last_n) = self.last_n else {
+            return candidates.keys().copied().collect();
+        };
+
+        let mut forwarded: HashSet<TrackId> = self
+            .selected_tracks
+            .iter()
+            .copied()
+            .filter(|track_id| candidates.contains_key(track_id))
+            .collect();
+
+        let mut by_activity: Vec<TrackId> = candidates
+            .keys()
+            .copied()
+            .filter(|track_id| !forwarded.contains(track_id))
+            .collect();
+        by_activity.sort_by_key(|track_id| std::cmp::Reverse(self.track_activity.get(track_id).copied()));
+
+        for track_id in by_activity {
+            if forwarded.len() >= last_n {
+                break;
+            }
+            forwarded.insert(track_id);
+        }
+
+        forwarded
+    }
+}
+
 /// Default implementation of the bandwidth manager
 pub struct DefaultBandwidthManager {
     /// Session bandwidth information
     session_bandwidth: Arc<RwLock<HashMap<SessionId, BandwidthInfo>>>,
     /// Track bitrate allocations
     track_bitrates: Arc<RwLock<HashMap<(SessionId, TrackId), u32>>>,
+    /// Per-session last-N / selected-endpoint forwarding state
+    selections: Arc<RwLock<HashMap<SessionId, SelectionState>>>,
     /// History window size
     history_window: Duration,
-    /// Minimum bandwidth for trend analysis
-    min_samples_for_trend: usize,
+    /// Source of the current time for bandwidth-history and staleness timestamps
+    clock: Arc<dyn Clock>,
 }
 
 impl DefaultBandwidthManager {
     /// Create a new bandwidth manager
     pub fn new() -> Self {
+        Self::with_clock(Arc::new(SystemClock))
+    }
+
+    /// Create a new bandwidth manager reading the time from `clock`, so tests
+    /// can advance it deterministically instead of sleeping for real
+    pub fn with_clock(clock: Arc<dyn Clock>) -> Self {
         Self {
             session_bandwidth: Arc::new(RwLock::new(HashMap::new())),
             track_bitrates: Arc::new(RwLock::new(HashMap::new())),
+            selections: Arc::new(RwLock::new(HashMap::new())),
             history_window: Duration::from_secs(10),
-            min_samples_for_trend: 5,
+            clock,
         }
     }
-    
-    /// Calculate bandwidth trend from history
-    fn calculate_trend(history: &[(Instant, u32)], min_samples: usize) -> BandwidthTrend {
-        if history.len() < min_samples {
-            return BandwidthTrend::Stable;
-        }
-        
-        // Simple linear regression to determine trend
-        let n = history.len() as f64;
-        let mut sum_x = 0.0;
-        let mut sum_y = 0.0;
-        let mut sum_xy = 0.0;
-        let mut sum_xx = 0.0;
-        
-        let base_time = history[0].0;
-        
-        for (time, bandwidth) in history {
-            let x = time.duration_since(base_time).as_secs_f64();
-            let y = *bandwidth as f64;
-            
-            sum_x += x;
-            sum_y += y;
-            sum_xy += x * y;
-            sum_xx += x * x;
-        }
-        
-        let slope = (n * sum_xy - sum_x * sum_y) / (n * sum_xx - sum_x * sum_x);
-        
-        // Determine trend based on slope
-        if slope > 1000.0 {
-            BandwidthTrend::Increasing
-        } else if slope < -1000.0 {
-            BandwidthTrend::Decreasing
-        } else {
-            BandwidthTrend::Stable
+
+    /// Map a GCC detector state to the coarse trend exposed by `get_bandwidth_trend`
+    fn trend_for_usage(usage: BandwidthUsage, increasing: bool) -> BandwidthTrend {
+        match usage {
+            BandwidthUsage::Overuse => BandwidthTrend::Decreasing,
+            BandwidthUsage::Underuse => BandwidthTrend::Stable,
+            BandwidthUsage::Normal if increasing => BandwidthTrend::Increasing,
+            BandwidthUsage::Normal => BandwidthTrend::Stable,
         }
     }
-    
+
     /// Prune old history entries
-    fn prune_history(history: &mut Vec<(Instant, u32)>, window: Duration) {
-        let now = Instant::now();
+    fn prune_history(&self, history: &mut Vec<(Instant, u32)>, window: Duration) {
+        let now = self.clock.now();
         history.retain(|(time, _)| now.duration_since(*time) <= window);
     }
+
+    /// Build a fresh `BandwidthInfo` for a session seen for the first time
+    fn new_bandwidth_info(&self) -> BandwidthInfo {
+        BandwidthInfo {
+            upload_bandwidth: 0,
+            download_bandwidth: 0,
+            last_update: self.clock.now(),
+            history: Vec::new(),
+            trend: BandwidthTrend::Stable,
+            gcc: GccEstimator::default(),
+            rate_controller: AimdRateController::new(INITIAL_TARGET_BITRATE_BPS),
+            loss_controller: LossController::new(INITIAL_TARGET_BITRATE_BPS),
+        }
+    }
 }
 
 #[async_trait]
@@ -144,67 +242,57 @@ impl BandwidthManager for DefaultBandwidthManager {
         let mut session_bandwidth = self.session_bandwidth.write().await;
         
         // Get or create bandwidth info
-        let bandwidth_info = session_bandwidth.entry(session_id).or_insert_with(|| BandwidthInfo {
-            upload_bandwidth: 0,
-            download_bandwidth: 0,
-            last_update: Instant::now(),
-            history: Vec::new(),
-            trend: BandwidthTrend::Stable,
-        });
-        
+        let bandwidth_info = session_bandwidth.entry(session_id).or_insert_with(|| self.new_bandwidth_info());
+
         // Update bandwidth
         if is_upload {
             bandwidth_info.upload_bandwidth = bandwidth;
         } else {
             bandwidth_info.download_bandwidth = bandwidth;
         }
-        
+
         // Update history
-        bandwidth_info.history.push((Instant::now(), bandwidth));
-        
+        bandwidth_info.history.push((self.clock.now(), bandwidth));
+
         // Prune old history entries
-        Self::prune_history(&mut bandwidth_info.history, self.history_window);
-        
-        // Calculate trend
-        bandwidth_info.trend = Self::calculate_trend(
-            &bandwidth_info.history,
-            self.min_samples_for_trend,
-        );
-        
+        self.prune_history(&mut bandwidth_info.history, self.history_window);
+
         // Update last update time
-        bandwidth_info.last_update = Instant::now();
-        
+        bandwidth_info.last_update = self.clock.now();
+
         Ok(())
     }
-    
+
     async fn get_recommended_bitrate(
         &self,
         session_id: SessionId,
         track_id: TrackId,
     ) -> Result<u32> {
         let track_bitrates = self.track_bitrates.read().await;
-        
+
         // Get bitrate allocation for the track
         if let Some(bitrate) = track_bitrates.get(&(session_id, track_id)) {
             return Ok(*bitrate);
         }
-        
-        // If no specific allocation, use a default based on session bandwidth
+
+        // If no specific allocation, use the smaller of the delay-based and
+        // loss-based targets for the session, so either signal can constrain it
         let session_bandwidth = self.session_bandwidth.read().await;
-        
+
         if let Some(bandwidth_info) = session_bandwidth.get(&session_id) {
-            // Use a conservative default (70% of available bandwidth)
-            let default_bitrate = (bandwidth_info.upload_bandwidth as f32 * 0.7) as u32;
-            Ok(default_bitrate)
+            Ok(bandwidth_info
+                .rate_controller
+                .target_bps()
+                .min(bandwidth_info.loss_controller.target_bps()))
         } else {
             // No bandwidth info available, use a very conservative default
-            Ok(500_000) // 500 kbps
+            Ok(INITIAL_TARGET_BITRATE_BPS)
         }
     }
-    
+
     async fn get_bandwidth_trend(&self, session_id: SessionId) -> Result<BandwidthTrend> {
         let session_bandwidth = self.session_bandwidth.read().await;
-        
+
         if let Some(bandwidth_info) = session_bandwidth.get(&session_id) {
             Ok(bandwidth_info.trend)
         } else {
@@ -212,7 +300,7 @@ impl BandwidthManager for DefaultBandwidthManager {
             Err(SfuError::Other(format!("No bandwidth info for session {}", session_id)).into())
         }
     }
-    
+
     async fn process_bandwidth_estimation(&self, estimation: BandwidthEstimation) -> Result<()> {
         // Update bandwidth based on estimation
         self.update_bandwidth(
@@ -221,7 +309,71 @@ impl BandwidthManager for DefaultBandwidthManager {
             false, // Assuming this is download bandwidth
         ).await
     }
-    
+
+    async fn report_packet_group(
+        &self,
+        session_id: SessionId,
+        departure: Instant,
+        arrival: Instant,
+        size_bytes: usize,
+    ) -> Result<()> {
+        let mut session_bandwidth = self.session_bandwidth.write().await;
+
+        let bandwidth_info = session_bandwidth.entry(session_id).or_insert_with(|| self.new_bandwidth_info());
+
+        let (usage, receive_rate_bps) = bandwidth_info.gcc.on_packet(departure, arrival, size_bytes);
+        let previous_target = bandwidth_info.rate_controller.target_bps();
+        let target = bandwidth_info.rate_controller.update(usage, receive_rate_bps);
+
+        bandwidth_info.download_bandwidth = target;
+        bandwidth_info.trend = Self::trend_for_usage(usage, target > previous_target);
+        bandwidth_info.last_update = self.clock.now();
+
+        Ok(())
+    }
+
+    async fn report_packet_loss(&self, session_id: SessionId, loss_fraction: f32) -> Result<()> {
+        let mut session_bandwidth = self.session_bandwidth.write().await;
+
+        let bandwidth_info = session_bandwidth.entry(session_id).or_insert_with(|| self.new_bandwidth_info());
+        bandwidth_info.loss_controller.report_loss_fraction(loss_fraction);
+        bandwidth_info.last_update = self.clock.now();
+
+        Ok(())
+    }
+
+    async fn get_loss_based_bitrate(&self, session_id: SessionId) -> Result<u32> {
+        let session_bandwidth = self.session_bandwidth.read().await;
+
+        if let Some(bandwidth_info) = session_bandwidth.get(&session_id) {
+            Ok(bandwidth_info.loss_controller.target_bps())
+        } else {
+            Ok(INITIAL_TARGET_BITRATE_BPS)
+        }
+    }
+
+    async fn set_selected_tracks(&self, session_id: SessionId, track_ids: HashSet<TrackId>) -> Result<()> {
+        let mut selections = self.selections.write().await;
+        selections.entry(session_id).or_default().selected_tracks = track_ids;
+        Ok(())
+    }
+
+    async fn set_last_n(&self, session_id: SessionId, last_n: usize) -> Result<()> {
+        let mut selections = self.selections.write().await;
+        selections.entry(session_id).or_default().last_n = Some(last_n);
+        Ok(())
+    }
+
+    async fn report_track_activity(&self, session_id: SessionId, track_id: TrackId) -> Result<()> {
+        let mut selections = self.selections.write().await;
+        selections
+            .entry(session_id)
+            .or_default()
+            .track_activity
+            .insert(track_id, self.clock.now());
+        Ok(())
+    }
+
     async fn distribute_bandwidth(
         &self,
         session_id: SessionId,
@@ -230,23 +382,74 @@ impl BandwidthManager for DefaultBandwidthManager {
     ) -> Result<HashMap<TrackId, u32>> {
         let mut track_bitrates = self.track_bitrates.write().await;
         let mut allocations = HashMap::new();
-        
+
         if track_priorities.is_empty() {
             return Ok(allocations);
         }
-        
-        // Calculate total priority weight
-        let total_priority: u32 = track_priorities.values().map(|p| *p as u32).sum();
-        
-        // Distribute bandwidth proportionally to priorities
-        for (track_id, priority) in track_priorities {
-            let allocation = (available_bandwidth as f32 * priority as f32 / total_priority as f32) as u32;
-            
-            // Update allocation
-            allocations.insert(track_id, allocation);
-            track_bitrates.insert((session_id, track_id), allocation);
+
+        // Clamp the caller-supplied ceiling to the session's congestion-controlled
+        // target, the smaller of the delay-based and loss-based estimates
+        let session_target = {
+            let session_bandwidth = self.session_bandwidth.read().await;
+            session_bandwidth.get(&session_id).map(|info| {
+                info.rate_controller.target_bps().min(info.loss_controller.target_bps())
+            })
+        };
+        let available_bandwidth = match session_target {
+            Some(target) => available_bandwidth.min(target),
+            None => available_bandwidth,
+        };
+
+        // Split tracks into those forwarded at full quality this round
+        // (selected + the last-N most active) and the rest, which fall back
+        // to the lowest simulcast layer or are suspended entirely
+        let forwarded_ids = {
+            let selections = self.selections.read().await;
+            match selections.get(&session_id) {
+                Some(selection) => selection.forwarded_tracks(&track_priorities),
+                None => track_priorities.keys().copied().collect(),
+            }
+        };
+
+        let forwarded_priority: u32 = track_priorities
+            .iter()
+            .filter(|(track_id, _)| forwarded_ids.contains(track_id))
+            .map(|(_, priority)| *priority as u32)
+            .sum();
+
+        let mut spent = 0u32;
+
+        for (track_id, priority) in track_priorities.iter().filter(|(track_id, _)| forwarded_ids.contains(track_id)) {
+            let allocation = if forwarded_priority == 0 {
+                0
+            } else {
+                (available_bandwidth as f32 * *priority as f32 / forwarded_priority as f32) as u32
+            };
+
+            spent = spent.saturating_add(allocation);
+            allocations.insert(*track_id, allocation);
+            track_bitrates.insert((session_id, *track_id), allocation);
         }
-        
+
+        // Remaining (non-forwarded) tracks share whatever budget is left over,
+        // capped at a single lowest-layer bitrate each; once the leftover is
+        // too small to cover that, they fall through to 0 (suspended)
+        let remaining_ids: Vec<TrackId> = track_priorities
+            .keys()
+            .copied()
+            .filter(|track_id| !forwarded_ids.contains(track_id))
+            .collect();
+
+        if !remaining_ids.is_empty() {
+            let leftover = available_bandwidth.saturating_sub(spent);
+            let per_track = (leftover / remaining_ids.len() as u32).min(LOWEST_LAYER_BITRATE_BPS);
+
+            for track_id in remaining_ids {
+                allocations.insert(track_id, per_track);
+                track_bitrates.insert((session_id, track_id), per_track);
+            }
+        }
+
         Ok(allocations)
     }
 }
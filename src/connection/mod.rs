@@ -10,23 +10,46 @@ use iroh::{endpoint::Connection, NodeId};
 
 use crate::SfuError;
 
+/// What kind of peer is on the other end of an `RtcConnection`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ConnectionKind {
+    /// A media-publishing/subscribing client
+    #[default]
+    Client,
+    /// Another `anton` node relaying tracks as part of a mesh, distinguished
+    /// from client connections so `signaling::broker` only announces to and
+    /// accepts `TrackSubscribe` forwarding from peers, not clients
+    PeerRelay,
+}
+
 /// RTC connection for media transport
+#[derive(Clone)]
 pub struct RtcConnection {
     /// Iroh connection
     connection: Connection,
     /// Remote node ID
     remote_node_id: NodeId,
+    /// Whether the remote end is a client or a peer relay node
+    kind: ConnectionKind,
 }
 
 impl RtcConnection {
-    /// Create a new RTC connection
+    /// Create a new RTC connection to a client
     pub fn new(connection: Connection, remote_node_id: NodeId) -> Self {
         Self {
             connection,
             remote_node_id,
+            kind: ConnectionKind::Client,
         }
     }
 
+    /// Tag this connection as a given kind, e.g. `ConnectionKind::PeerRelay`
+    /// for a connection to another `anton` node joining the mesh
+    pub fn with_kind(mut self, kind: ConnectionKind) -> Self {
+        self.kind = kind;
+        self
+    }
+
     /// Get the underlying Iroh connection
     pub fn connection(&self) -> &Connection {
         &self.connection
@@ -36,6 +59,11 @@ impl RtcConnection {
     pub fn remote_node_id(&self) -> &NodeId {
         &self.remote_node_id
     }
+
+    /// Whether the remote end is a client or a peer relay node
+    pub fn kind(&self) -> ConnectionKind {
+        self.kind
+    }
 }
 
 /// Connection manager trait
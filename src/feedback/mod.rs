@@ -2,19 +2,69 @@
 //
 // This module implements feedback mechanisms for media quality and simulcast control.
 
-use std::sync::Arc;
+pub mod rtcp_codec;
+
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{atomic::{AtomicU64, Ordering}, Arc},
+    time::{Duration, Instant},
+};
 
 use anyhow::Result;
 use async_trait::async_trait;
-use tokio::sync::mpsc;
+use iroh_roq::{ReceiveFlow, SendFlow};
+use tokio::sync::{Mutex, RwLock};
 
 use crate::{
-    media::TrackId,
-    session::SessionId,
+    bandwidth::{BandwidthManager, DefaultBandwidthManager},
+    media::{rtcp::RtcpPacket, TrackId},
+    session::{SessionId, SessionManager},
+    stats::StatsCollector,
     transport::TransportSession,
     SfuError,
 };
 
+/// RTCP feedback mechanism a peer can generate/consume for a codec,
+/// negotiated per-codec during `SessionInit` and stored on the session so
+/// this module can gate what it actually sends for a given track
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub enum FeedbackKind {
+    /// Generic negative acknowledgment (RFC 4585)
+    Nack,
+    /// NACK carrying a Picture Loss Indication
+    NackPli,
+    /// Full Intra Request
+    Fir,
+    /// Transport-wide congestion control feedback
+    TransportCc,
+    /// Google Reduced-size RTCP Estimation of Maximum Bitrate
+    GoogRemb,
+}
+
+/// A single RTCP feedback mechanism advertised for a codec, with an optional
+/// parameter (e.g. the `"pli"` in `a=rtcp-fb:* nack pli`)
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct FeedbackMechanism {
+    /// Mechanism kind
+    pub kind: FeedbackKind,
+    /// Mechanism parameter, if any
+    pub parameter: Option<String>,
+}
+
+/// Intersect two peers' advertised feedback mechanisms for the same codec,
+/// keeping a mechanism from `local` only when `remote` also lists its `kind`
+pub fn intersect_feedback(local: &[FeedbackMechanism], remote: &[FeedbackMechanism]) -> Vec<FeedbackMechanism> {
+    local
+        .iter()
+        .filter(|mechanism| remote.iter().any(|other| other.kind == mechanism.kind))
+        .cloned()
+        .collect()
+}
+
+/// Default minimum time between consecutive keyframe requests for the same
+/// session/track, so a sustained loss burst doesn't flood the publisher
+pub(crate) const DEFAULT_KEYFRAME_REQUEST_INTERVAL: Duration = Duration::from_millis(1000);
+
 /// Feedback message types
 #[derive(Debug, Clone)]
 pub enum FeedbackMessage {
@@ -28,6 +78,64 @@ pub enum FeedbackMessage {
     PictureLossIndication(PictureLossIndication),
     /// Bandwidth estimation
     BandwidthEstimation(BandwidthEstimation),
+    /// Request for a fresh key frame
+    RequestKeyframe(RequestKeyframe),
+    /// Connection quality score
+    ConnectionQuality(ConnectionQualityScore),
+    /// One packet's departure/arrival timestamps, reported back by a
+    /// receiver so the sender can run delay-based congestion control
+    PacketArrival(PacketArrivalReport),
+    /// Batch of per-packet transport-wide-cc arrival reports, echoing back
+    /// the sequence numbers stamped by `RtpPacketizer`'s `TRANSPORT_CC`
+    /// header extension
+    TransportCc(TransportCcFeedback),
+}
+
+/// One packet's transport-wide-cc arrival status, as reported by a receiver
+/// back to the publisher
+#[derive(Debug, Clone)]
+pub struct PacketArrivalRecord {
+    /// Transport-wide sequence number from the packet's `TRANSPORT_CC`
+    /// header extension (see `media::rtp::extension_uri::TRANSPORT_CC`)
+    pub transport_seq: u16,
+    /// Time the publisher sent this packet
+    pub departure: Instant,
+    /// Time the receiver's transport observed this packet arrive, `None` if
+    /// the packet was never received
+    pub arrival: Option<Instant>,
+    /// Size of the packet on the wire, in bytes
+    pub size_bytes: usize,
+}
+
+/// Batch of per-packet transport-wide-cc arrival reports, the feedback
+/// substrate `transport::adaptation::BandwidthAdapter`'s delay-based
+/// controller measures over; gathered over a feedback interval rather than
+/// sent one packet at a time, matching RFC draft-holmer-rmcat-transport-wide-cc-extensions
+#[derive(Debug, Clone)]
+pub struct TransportCcFeedback {
+    /// Session identifier of the track's publisher
+    pub session_id: SessionId,
+    /// Track identifier
+    pub track_id: TrackId,
+    /// Per-packet arrival records, in transport-sequence order
+    pub packets: Vec<PacketArrivalRecord>,
+}
+
+/// One received packet's departure and arrival timestamps, reported by a
+/// receiver back to the publisher so `transport::adaptation::BandwidthAdapter`
+/// can feed its delay-based rate controller
+#[derive(Debug, Clone)]
+pub struct PacketArrivalReport {
+    /// Session identifier of the track's publisher
+    pub session_id: SessionId,
+    /// Track identifier
+    pub track_id: TrackId,
+    /// Time the publisher sent this packet
+    pub departure: Instant,
+    /// Time the receiver's transport observed this packet arrive
+    pub arrival: Instant,
+    /// Size of the packet on the wire, in bytes
+    pub size_bytes: usize,
 }
 
 /// Receiver report
@@ -94,6 +202,14 @@ pub enum SimulcastControlMessage {
         /// New target bitrate
         target_bitrate: u32,
     },
+    /// Request a fresh key frame on one spatial layer, so a subscriber
+    /// switch-up pending that layer's next key frame can be applied
+    RequestKeyFrame {
+        /// Track identifier
+        track_id: TrackId,
+        /// Spatial layer the key frame is needed on
+        spatial_id: u8,
+    },
 }
 
 /// Reason for layer switching
@@ -140,42 +256,329 @@ pub enum BandwidthTrend {
     Decreasing,
 }
 
+/// Discrete connection quality score for a session/track, derived from
+/// received `ReceiverReport`s by `QualityScorer`
+#[derive(Debug, Clone)]
+pub struct ConnectionQualityScore {
+    /// Session identifier
+    pub session_id: SessionId,
+    /// Track identifier
+    pub track_id: TrackId,
+    /// EWMA-smoothed score from 1 (unusable) to 5 (excellent)
+    pub score: u8,
+    /// Direction the smoothed score has moved since the previous sample
+    pub trend: BandwidthTrend,
+}
+
+/// Request for a fresh key frame from the publisher of a track, raised by
+/// the jitter buffer on an unrecoverable gap or by a simulcast layer switch
+#[derive(Debug, Clone)]
+pub struct RequestKeyframe {
+    /// Session identifier of the track's publisher
+    pub session_id: SessionId,
+    /// Track identifier
+    pub track_id: TrackId,
+}
+
+/// Debounce policy for outgoing key frame requests, akin to a jitter
+/// buffer's `request-keyframe` property: request on loss, but no more often
+/// than once per interval per session/track so a sustained loss burst
+/// doesn't flood the publisher with PLI/FIR requests
+pub struct KeyframeRequestPolicy {
+    /// Minimum time between consecutive requests for the same session/track
+    interval: Duration,
+    /// Last time a request was allowed through, per session/track
+    last_requested: HashMap<(SessionId, TrackId), Instant>,
+}
+
+impl KeyframeRequestPolicy {
+    /// Create a new policy debouncing requests to at most once per `interval`
+    pub fn new(interval: Duration) -> Self {
+        Self {
+            interval,
+            last_requested: HashMap::new(),
+        }
+    }
+
+    /// Whether a key frame request for `session_id`/`track_id` should be sent
+    /// now, recording it as sent if so
+    pub fn should_request(&mut self, session_id: SessionId, track_id: TrackId) -> bool {
+        let now = Instant::now();
+        let key = (session_id, track_id);
+
+        let debounced = self
+            .last_requested
+            .get(&key)
+            .is_some_and(|last| now.duration_since(*last) < self.interval);
+
+        if debounced {
+            return false;
+        }
+
+        self.last_requested.insert(key, now);
+        true
+    }
+}
+
+/// Weight given to packet loss in the combined raw score, the dominant
+/// signal for perceived quality; jitter and RTT split the remainder
+const QUALITY_LOSS_WEIGHT: f32 = 0.5;
+const QUALITY_JITTER_WEIGHT: f32 = 0.3;
+const QUALITY_RTT_WEIGHT: f32 = 0.2;
+
+/// EWMA smoothing factor applied to each new raw score; at roughly one
+/// `ReceiverReport` per second this gives a multi-second settling time,
+/// enough to absorb a single bad sample without flapping the exposed score
+const QUALITY_SMOOTHING_ALPHA: f32 = 0.3;
+
+/// Smoothed score must move by more than this before a trend is reported,
+/// so noise around a stable score doesn't register as Increasing/Decreasing
+const QUALITY_TREND_EPSILON: f32 = 0.05;
+
+/// Per-session/track EWMA state backing `QualityScorer`
+struct QualityState {
+    /// Smoothed score, kept as a float between samples and rounded for display
+    smoothed: f32,
+    /// Trend last reported for this session/track
+    trend: BandwidthTrend,
+}
+
+/// Derives a discrete 1 (unusable) to 5 (excellent) connection quality score
+/// from `ReceiverReport` metrics, akin to client SDKs' connection-quality-score.
+/// Each metric (loss, jitter, RTT) maps through its own piecewise penalty,
+/// combined with a loss-weighted average, then EWMA-smoothed across reports
+/// so a single bad sample doesn't bounce the exposed score around.
+pub struct QualityScorer {
+    state: HashMap<(SessionId, TrackId), QualityState>,
+}
+
+impl QualityScorer {
+    /// Create a new scorer with no recorded history
+    pub fn new() -> Self {
+        Self {
+            state: HashMap::new(),
+        }
+    }
+
+    /// Map packet loss percentage (0-100) to a 1-5 sub-score
+    fn loss_score(packet_loss_percent: f32) -> f32 {
+        match packet_loss_percent {
+            p if p < 1.0 => 5.0,
+            p if p < 3.0 => 4.0,
+            p if p < 8.0 => 3.0,
+            p if p < 15.0 => 2.0,
+            _ => 1.0,
+        }
+    }
+
+    /// Map jitter in milliseconds to a 1-5 sub-score
+    fn jitter_score(jitter_ms: f32) -> f32 {
+        match jitter_ms {
+            j if j < 30.0 => 5.0,
+            j if j < 60.0 => 4.0,
+            j if j < 100.0 => 3.0,
+            j if j < 150.0 => 2.0,
+            _ => 1.0,
+        }
+    }
+
+    /// Map round-trip time in milliseconds to a 1-5 sub-score
+    fn rtt_score(rtt_ms: u32) -> f32 {
+        match rtt_ms {
+            r if r < 150 => 5.0,
+            r if r < 250 => 4.0,
+            r if r < 400 => 3.0,
+            r if r < 600 => 2.0,
+            _ => 1.0,
+        }
+    }
+
+    /// Feed one `ReceiverReport`'s metrics into the session/track's running
+    /// score, returning the resulting smoothed score and its trend
+    pub fn record(&mut self, report: &ReceiverReport) -> ConnectionQualityScore {
+        let loss = Self::loss_score(report.packet_loss);
+        let jitter = Self::jitter_score(report.jitter_ms);
+        let rtt = Self::rtt_score(report.rtt_ms);
+
+        // A weighted average alone would let two good metrics mask one bad
+        // one, so clamp it to the worst sub-score plus one point: the
+        // combined score tracks the average but can never stray far above
+        // the metric that's actually hurting
+        let weighted = loss * QUALITY_LOSS_WEIGHT + jitter * QUALITY_JITTER_WEIGHT + rtt * QUALITY_RTT_WEIGHT;
+        let floor = loss.min(jitter).min(rtt);
+        let raw = weighted.min(floor + 1.0).clamp(1.0, 5.0);
+
+        let key = (report.session_id, report.track_id);
+        let previous = self.state.get(&key).map(|state| state.smoothed);
+
+        let smoothed = match previous {
+            Some(previous) => QUALITY_SMOOTHING_ALPHA * raw + (1.0 - QUALITY_SMOOTHING_ALPHA) * previous,
+            None => raw,
+        };
+
+        let trend = match previous {
+            Some(previous) if smoothed > previous + QUALITY_TREND_EPSILON => BandwidthTrend::Increasing,
+            Some(previous) if smoothed < previous - QUALITY_TREND_EPSILON => BandwidthTrend::Decreasing,
+            _ => BandwidthTrend::Stable,
+        };
+
+        self.state.insert(key, QualityState { smoothed, trend });
+
+        ConnectionQualityScore {
+            session_id: report.session_id,
+            track_id: report.track_id,
+            score: smoothed.round().clamp(1.0, 5.0) as u8,
+            trend,
+        }
+    }
+
+    /// Get the session/track's current smoothed score and trend, if any
+    /// reports have been recorded for it yet
+    pub fn current(&self, session_id: SessionId, track_id: TrackId) -> Option<ConnectionQualityScore> {
+        self.state.get(&(session_id, track_id)).map(|state| ConnectionQualityScore {
+            session_id,
+            track_id,
+            score: state.smoothed.round().clamp(1.0, 5.0) as u8,
+            trend: state.trend,
+        })
+    }
+}
+
+impl Default for QualityScorer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Default for KeyframeRequestPolicy {
+    fn default() -> Self {
+        Self::new(DEFAULT_KEYFRAME_REQUEST_INTERVAL)
+    }
+}
+
 /// Feedback channel for a connection
+///
+/// Carries `FeedbackMessage`s as real RTCP packets on a RoQ flow dedicated to
+/// feedback, separate from the media flow(s) the same `TransportSession`
+/// carries. `send_feedback` encodes a message to RTCP and pushes it onto
+/// `send_flow`; `receive_feedback` reads compound RTCP packets off
+/// `receive_flow` and decodes them back, buffering any extra messages a
+/// single compound packet produced.
 pub struct FeedbackChannel {
     /// Transport session
     session: Arc<TransportSession>,
-    /// Sender for outgoing feedback messages
-    feedback_tx: mpsc::Sender<FeedbackMessage>,
-    /// Receiver for incoming feedback messages
-    feedback_rx: mpsc::Receiver<FeedbackMessage>,
+    /// RoQ flow feedback is sent on
+    send_flow: SendFlow,
+    /// RoQ flow feedback is received on, behind a lock so the channel can be
+    /// drained from a task without requiring exclusive ownership
+    receive_flow: Mutex<ReceiveFlow>,
+    /// Session id attributed to feedback reconstructed from incoming RTCP,
+    /// set once the SFU has created a session for this connection; 0 until then
+    session_id: AtomicU64,
+    /// Messages decoded from a single compound packet but not yet returned
+    pending: Mutex<VecDeque<FeedbackMessage>>,
+    /// Optional stats collector fed sender/receiver report blocks as they
+    /// arrive, set via `set_stats_collector`
+    stats_collector: RwLock<Option<Arc<dyn StatsCollector>>>,
 }
 
 impl FeedbackChannel {
-    /// Create a new feedback channel
-    pub fn new(session: Arc<TransportSession>) -> Self {
-        let (feedback_tx, feedback_rx) = mpsc::channel(100);
-        
+    /// Create a new feedback channel over `send_flow`/`receive_flow`
+    pub fn new(session: Arc<TransportSession>, send_flow: SendFlow, receive_flow: ReceiveFlow) -> Self {
         Self {
             session,
-            feedback_tx,
-            feedback_rx,
+            send_flow,
+            receive_flow: Mutex::new(receive_flow),
+            session_id: AtomicU64::new(0),
+            pending: Mutex::new(VecDeque::new()),
+            stats_collector: RwLock::new(None),
         }
     }
-    
-    /// Send a feedback message
+
+    /// Attribute feedback reconstructed from incoming RTCP to `session_id`
+    pub fn set_session_id(&self, session_id: SessionId) {
+        self.session_id.store(session_id, Ordering::Relaxed);
+    }
+
+    /// Feed sender/receiver reports seen on this channel into `stats`
+    pub async fn set_stats_collector(&self, stats: Arc<dyn StatsCollector>) {
+        *self.stats_collector.write().await = Some(stats);
+    }
+
+    /// Send a feedback message as RTCP, if it has a wire representation
     pub async fn send_feedback(&self, message: FeedbackMessage) -> Result<()> {
-        self.feedback_tx.send(message).await.map_err(|e| {
+        let Some(packet) = rtcp_codec::encode(&message) else {
+            tracing::debug!("Feedback message has no RTCP representation, dropping: {:?}", message);
+            return Ok(());
+        };
+
+        self.send_flow.send(packet.serialize()).await.map_err(|e| {
             SfuError::Other(format!("Failed to send feedback message: {}", e))
         })?;
-        
+
         Ok(())
     }
-    
-    /// Receive a feedback message
-    pub async fn receive_feedback(&mut self) -> Option<FeedbackMessage> {
-        self.feedback_rx.recv().await
+
+    /// Receive a feedback message, reading and decoding fresh RTCP packets
+    /// off `receive_flow` as needed
+    pub async fn receive_feedback(&self) -> Option<FeedbackMessage> {
+        loop {
+            if let Some(message) = self.pending.lock().await.pop_front() {
+                return Some(message);
+            }
+
+            let data = match self.receive_flow.lock().await.receive().await {
+                Ok(Some(data)) => data,
+                Ok(None) => return None,
+                Err(e) => {
+                    tracing::warn!("Failed to receive feedback packet: {}", e);
+                    return None;
+                }
+            };
+
+            let packets = match RtcpPacket::parse(&data) {
+                Ok(packets) => packets,
+                Err(e) => {
+                    tracing::warn!("Failed to parse RTCP feedback packet: {}", e);
+                    continue;
+                }
+            };
+
+            self.ingest_stats(&packets).await;
+
+            let mut decoded = rtcp_codec::decode(packets, self.session_id.load(Ordering::Relaxed));
+            if decoded.is_empty() {
+                continue;
+            }
+
+            let first = decoded.remove(0);
+            self.pending.lock().await.extend(decoded);
+            return Some(first);
+        }
+    }
+
+    /// Feed sender/receiver reports in `packets` into the stats collector,
+    /// if one has been set. Track identifiers double as RTCP SSRCs here (see
+    /// `rtcp_codec`), so the SSRC is cast back to a `TrackId` on ingest.
+    async fn ingest_stats(&self, packets: &[RtcpPacket]) {
+        let Some(stats) = self.stats_collector.read().await.clone() else {
+            return;
+        };
+
+        let session_id = self.session_id.load(Ordering::Relaxed);
+        for packet in packets {
+            let (track_id, is_publisher) = match packet {
+                RtcpPacket::SenderReport(sr) => (sr.ssrc as u64, true),
+                RtcpPacket::ReceiverReport(rr) => (rr.ssrc as u64, false),
+                _ => continue,
+            };
+
+            if let Err(e) = stats.ingest_rtcp(session_id, track_id, is_publisher, packet).await {
+                tracing::debug!("Failed to ingest RTCP into stats collector: {}", e);
+            }
+        }
     }
-    
+
     /// Get the transport session
     pub fn session(&self) -> &TransportSession {
         &self.session
@@ -187,47 +590,157 @@ impl FeedbackChannel {
 pub trait FeedbackManager: Send + Sync {
     /// Create a feedback channel for a session
     async fn create_channel(&self, session: Arc<TransportSession>) -> Result<Arc<FeedbackChannel>>;
-    
+
     /// Process feedback messages
-    async fn process_feedback(&self, channel: &mut FeedbackChannel) -> Result<()>;
-    
+    async fn process_feedback(&self, channel: &FeedbackChannel) -> Result<()>;
+
     /// Send receiver report
     async fn send_receiver_report(&self, channel: &FeedbackChannel, report: ReceiverReport) -> Result<()>;
-    
+
     /// Send simulcast control message
     async fn send_simulcast_control(
         &self,
         channel: &FeedbackChannel,
         control: SimulcastControlMessage,
     ) -> Result<()>;
-    
+
     /// Send picture loss indication
     async fn send_pli(&self, channel: &FeedbackChannel, pli: PictureLossIndication) -> Result<()>;
+
+    /// Request a fresh key frame for a track, subject to the manager's
+    /// debounce policy so a sustained loss burst doesn't flood the publisher
+    async fn request_keyframe(&self, channel: &FeedbackChannel, request: RequestKeyframe) -> Result<()>;
+
+    /// Get the session/track's current smoothed connection quality score, as
+    /// last computed from an incoming `ReceiverReport` by `process_feedback`
+    async fn get_connection_quality(&self, session_id: SessionId, track_id: TrackId) -> Result<ConnectionQualityScore>;
 }
 
 /// Default implementation of the feedback manager
-pub struct DefaultFeedbackManager;
+pub struct DefaultFeedbackManager {
+    /// Session manager, consulted to gate feedback against the mechanisms
+    /// negotiated for a track's codec during `SessionInit`
+    session_manager: Arc<dyn SessionManager>,
+    /// Debounce policy applied by `request_keyframe`
+    keyframe_policy: RwLock<KeyframeRequestPolicy>,
+    /// Congestion controller fed by `process_feedback`'s `ReceiverReport`
+    /// handling, the source of the `BandwidthEstimation` it emits in response
+    bandwidth_manager: Arc<dyn BandwidthManager>,
+    /// Connection quality scorer fed by `process_feedback`'s `ReceiverReport`
+    /// handling, the source of `get_connection_quality`
+    quality_scorer: RwLock<QualityScorer>,
+}
 
 impl DefaultFeedbackManager {
-    /// Create a new feedback manager
-    pub fn new() -> Self {
-        Self
+    /// Create a new feedback manager with the default keyframe-request
+    /// interval and its own, session-local bandwidth manager
+    pub fn new(session_manager: Arc<dyn SessionManager>) -> Self {
+        Self::with_keyframe_interval(session_manager, DEFAULT_KEYFRAME_REQUEST_INTERVAL)
+    }
+
+    /// Create a new feedback manager debouncing key frame requests to at most
+    /// once per `interval` per session/track
+    pub fn with_keyframe_interval(session_manager: Arc<dyn SessionManager>, interval: Duration) -> Self {
+        Self::with_bandwidth_manager(session_manager, interval, Arc::new(DefaultBandwidthManager::new()))
+    }
+
+    /// Create a new feedback manager driving `bandwidth_manager` from
+    /// incoming `ReceiverReport`s, shared with the media router/signaling
+    /// paths so loss- and delay-based estimates for a session stay consistent
+    pub fn with_bandwidth_manager(
+        session_manager: Arc<dyn SessionManager>,
+        interval: Duration,
+        bandwidth_manager: Arc<dyn BandwidthManager>,
+    ) -> Self {
+        Self {
+            session_manager,
+            keyframe_policy: RwLock::new(KeyframeRequestPolicy::new(interval)),
+            bandwidth_manager,
+            quality_scorer: RwLock::new(QualityScorer::new()),
+        }
+    }
+
+    /// Whether `kind` was negotiated for the codec of `session_id`'s
+    /// published `track_id`. Defaults to allowed when the track, session, or
+    /// a negotiated set for its codec can't be found, so feedback keeps
+    /// flowing for sessions that haven't been through codec negotiation yet.
+    async fn feedback_allowed(&self, session_id: SessionId, track_id: TrackId, kind: FeedbackKind) -> bool {
+        let Ok(participant) = self.session_manager.get_participant(session_id).await else {
+            return true;
+        };
+        let participant = participant.read().await;
+
+        let Some(track) = participant.published_tracks.get(&track_id) else {
+            return true;
+        };
+
+        match participant.negotiated_feedback.get(&track.codec.name) {
+            Some(kinds) => kinds.contains(&kind),
+            None => true,
+        }
     }
 }
 
 #[async_trait]
 impl FeedbackManager for DefaultFeedbackManager {
     async fn create_channel(&self, session: Arc<TransportSession>) -> Result<Arc<FeedbackChannel>> {
-        let channel = FeedbackChannel::new(session);
+        // Dedicated RoQ flows for feedback, alongside whatever media flows
+        // the session also carries
+        let send_flow = session.new_send_flow().await?;
+        let receive_flow = session.new_receive_flow().await?;
+        let channel = FeedbackChannel::new(session, send_flow, receive_flow);
         Ok(Arc::new(channel))
     }
-    
-    async fn process_feedback(&self, channel: &mut FeedbackChannel) -> Result<()> {
+
+    async fn process_feedback(&self, channel: &FeedbackChannel) -> Result<()> {
         while let Some(message) = channel.receive_feedback().await {
             match message {
                 FeedbackMessage::ReceiverReport(report) => {
                     tracing::debug!("Received receiver report: {:?}", report);
-                    // Process receiver report
+
+                    let loss_fraction = report.packet_loss / 100.0;
+                    if let Err(e) = self.bandwidth_manager.report_packet_loss(report.session_id, loss_fraction).await {
+                        tracing::debug!(
+                            "Failed to record packet loss for session {}: {}",
+                            report.session_id,
+                            e
+                        );
+                        continue;
+                    }
+
+                    let trend = self
+                        .bandwidth_manager
+                        .get_bandwidth_trend(report.session_id)
+                        .await
+                        .unwrap_or(BandwidthTrend::Stable);
+                    let available_bandwidth = self
+                        .bandwidth_manager
+                        .get_recommended_bitrate(report.session_id, report.track_id)
+                        .await
+                        .unwrap_or(report.received_bitrate);
+
+                    channel
+                        .send_feedback(FeedbackMessage::BandwidthEstimation(BandwidthEstimation {
+                            session_id: report.session_id,
+                            available_bandwidth,
+                            trend,
+                        }))
+                        .await?;
+
+                    // Derive a combined quality score from the same report,
+                    // a single actionable signal for UI indicators and
+                    // (eventually) `SwitchReason::QualityAdaptation` triggers.
+                    // Recorded locally and exposed via `get_connection_quality`
+                    // rather than sent back over `channel`: it has no RTCP
+                    // wire representation, same as `SimulcastControlMessage`.
+                    let quality = self.quality_scorer.write().await.record(&report);
+                    tracing::debug!(
+                        "Connection quality for session {} track {}: {} ({:?})",
+                        quality.session_id,
+                        quality.track_id,
+                        quality.score,
+                        quality.trend
+                    );
                 }
                 FeedbackMessage::SimulcastControl(control) => {
                     tracing::debug!("Received simulcast control: {:?}", control);
@@ -237,19 +750,48 @@ impl FeedbackManager for DefaultFeedbackManager {
                     tracing::debug!("Received PLI: {:?}", pli);
                     // Process PLI
                 }
+                FeedbackMessage::BandwidthEstimation(estimation) => {
+                    tracing::debug!(
+                        "Bandwidth estimation for session {}: {} bps ({:?})",
+                        estimation.session_id,
+                        estimation.available_bandwidth,
+                        estimation.trend
+                    );
+                    // Feeding this into `AdaptationParams.target_bitrate` is
+                    // the media router's job; see `MediaRouter::get_forwarding_decision`
+                }
+                FeedbackMessage::RequestKeyframe(request) => {
+                    tracing::info!(
+                        "Key frame requested for session {} track {}, forwarding to publisher",
+                        request.session_id,
+                        request.track_id,
+                    );
+                    // This is a placeholder - actual implementation will depend on the transport layer
+                }
+                FeedbackMessage::ConnectionQuality(quality) => {
+                    tracing::debug!(
+                        "Connection quality for session {} track {}: {} ({:?})",
+                        quality.session_id,
+                        quality.track_id,
+                        quality.score,
+                        quality.trend
+                    );
+                    // `quality_scorer` already recorded this; nothing further
+                    // to do here besides exposing it via `get_connection_quality`
+                }
                 _ => {
                     // Process other message types
                 }
             }
         }
-        
+
         Ok(())
     }
-    
+
     async fn send_receiver_report(&self, channel: &FeedbackChannel, report: ReceiverReport) -> Result<()> {
         channel.send_feedback(FeedbackMessage::ReceiverReport(report)).await
     }
-    
+
     async fn send_simulcast_control(
         &self,
         channel: &FeedbackChannel,
@@ -257,15 +799,54 @@ impl FeedbackManager for DefaultFeedbackManager {
     ) -> Result<()> {
         channel.send_feedback(FeedbackMessage::SimulcastControl(control)).await
     }
-    
+
     async fn send_pli(&self, channel: &FeedbackChannel, pli: PictureLossIndication) -> Result<()> {
+        if !self.feedback_allowed(pli.session_id, pli.track_id, FeedbackKind::NackPli).await {
+            tracing::debug!(
+                "Skipping PLI for session {} track {}: not negotiated",
+                pli.session_id,
+                pli.track_id
+            );
+            return Ok(());
+        }
+
         channel.send_feedback(FeedbackMessage::PictureLossIndication(pli)).await
     }
-}
 
-// Default implementation
-impl Default for DefaultFeedbackManager {
-    fn default() -> Self {
-        Self::new()
+    async fn request_keyframe(&self, channel: &FeedbackChannel, request: RequestKeyframe) -> Result<()> {
+        if !self.feedback_allowed(request.session_id, request.track_id, FeedbackKind::Fir).await {
+            tracing::debug!(
+                "Skipping key frame request for session {} track {}: not negotiated",
+                request.session_id,
+                request.track_id
+            );
+            return Ok(());
+        }
+
+        let should_request = self
+            .keyframe_policy
+            .write()
+            .await
+            .should_request(request.session_id, request.track_id);
+
+        if !should_request {
+            return Ok(());
+        }
+
+        channel.send_feedback(FeedbackMessage::RequestKeyframe(request)).await
+    }
+
+    async fn get_connection_quality(&self, session_id: SessionId, track_id: TrackId) -> Result<ConnectionQualityScore> {
+        self.quality_scorer
+            .read()
+            .await
+            .current(session_id, track_id)
+            .ok_or_else(|| {
+                SfuError::Other(format!(
+                    "No connection quality recorded for session {} track {}",
+                    session_id, track_id
+                ))
+                .into()
+            })
     }
 }
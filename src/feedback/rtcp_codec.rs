@@ -0,0 +1,105 @@
+// Codec bridging `FeedbackMessage` and real RTCP packets
+//
+// `FeedbackMessage` is this crate's internal representation of a feedback
+// event; `media::rtcp::RtcpPacket` is the wire format WebRTC endpoints
+// actually speak. `FeedbackChannel` uses this module to translate between
+// the two so it can push/pull real RTCP over a RoQ flow instead of an
+// in-process queue.
+//
+// Track identifiers double as RTCP SSRCs here: the transport layer does not
+// yet maintain a separate SSRC registry, so `track_id as u32` is used as the
+// wire SSRC and cast back on decode. `SimulcastControlMessage`,
+// `ConnectionQualityScore`, packet arrivals and transport-cc feedback have no
+// RTCP equivalent here and never cross the wire.
+
+use crate::media::rtcp::{
+    PictureLossIndication as WirePli, ReceiverReport as WireRr, Remb, ReportBlock, RtcpPacket,
+    SenderReport as WireSr,
+};
+use crate::session::SessionId;
+
+use super::{
+    BandwidthEstimation, BandwidthTrend, FeedbackMessage, PictureLossIndication, ReceiverReport,
+    SenderReport,
+};
+
+/// Encode a `FeedbackMessage` into the RTCP packet that carries it on the
+/// wire, if any. Returns `None` for messages with no RTCP representation.
+pub fn encode(message: &FeedbackMessage) -> Option<RtcpPacket> {
+    match message {
+        FeedbackMessage::ReceiverReport(report) => Some(RtcpPacket::ReceiverReport(WireRr {
+            ssrc: report.track_id as u32,
+            reports: vec![ReportBlock {
+                ssrc: report.track_id as u32,
+                fraction_lost: (report.packet_loss.clamp(0.0, 100.0) / 100.0 * 255.0) as u8,
+                cumulative_lost: 0,
+                extended_highest_seq: 0,
+                jitter: report.jitter_ms as u32,
+                last_sr: 0,
+                delay_since_last_sr: 0,
+            }],
+        })),
+        FeedbackMessage::SenderReport(report) => Some(RtcpPacket::SenderReport(WireSr {
+            ssrc: report.track_id as u32,
+            ntp_timestamp: 0,
+            rtp_timestamp: 0,
+            packet_count: report.packets_sent as u32,
+            octet_count: report.bytes_sent as u32,
+            reports: Vec::new(),
+        })),
+        FeedbackMessage::PictureLossIndication(pli) => Some(RtcpPacket::Pli(WirePli {
+            sender_ssrc: pli.session_id as u32,
+            media_ssrc: pli.track_id as u32,
+        })),
+        FeedbackMessage::RequestKeyframe(request) => Some(RtcpPacket::Pli(WirePli {
+            sender_ssrc: request.session_id as u32,
+            media_ssrc: request.track_id as u32,
+        })),
+        FeedbackMessage::BandwidthEstimation(estimation) => Some(RtcpPacket::Remb(Remb {
+            sender_ssrc: estimation.session_id as u32,
+            bitrate_bps: estimation.available_bandwidth as u64,
+            ssrcs: Vec::new(),
+        })),
+        FeedbackMessage::SimulcastControl(_) => None,
+        FeedbackMessage::ConnectionQuality(_) => None,
+        FeedbackMessage::PacketArrival(_) | FeedbackMessage::TransportCc(_) => None,
+    }
+}
+
+/// Decode RTCP packets received on the feedback flow back into
+/// `FeedbackMessage`s, attributing them to `session_id` (the channel's own
+/// session, since a `FeedbackChannel` carries feedback for a single connection)
+pub fn decode(packets: Vec<RtcpPacket>, session_id: SessionId) -> Vec<FeedbackMessage> {
+    packets
+        .into_iter()
+        .filter_map(|packet| match packet {
+            RtcpPacket::ReceiverReport(rr) => rr.reports.first().map(|block| {
+                FeedbackMessage::ReceiverReport(ReceiverReport {
+                    session_id,
+                    track_id: rr.ssrc as u64,
+                    packet_loss: block.packet_loss_percent(),
+                    jitter_ms: block.jitter as f32,
+                    rtt_ms: 0,
+                    received_bitrate: 0,
+                })
+            }),
+            RtcpPacket::SenderReport(sr) => Some(FeedbackMessage::SenderReport(SenderReport {
+                session_id,
+                track_id: sr.ssrc as u64,
+                packets_sent: sr.packet_count as u64,
+                bytes_sent: sr.octet_count as u64,
+                current_bitrate: 0,
+            })),
+            RtcpPacket::Pli(pli) => Some(FeedbackMessage::PictureLossIndication(PictureLossIndication {
+                session_id,
+                track_id: pli.media_ssrc as u64,
+            })),
+            RtcpPacket::Remb(remb) => Some(FeedbackMessage::BandwidthEstimation(BandwidthEstimation {
+                session_id,
+                available_bandwidth: remb.bitrate_bps.min(u32::MAX as u64) as u32,
+                trend: BandwidthTrend::Stable,
+            })),
+            RtcpPacket::Nack(_) | RtcpPacket::Fir(_) | RtcpPacket::Unknown { .. } => None,
+        })
+        .collect()
+}
@@ -0,0 +1,163 @@
+// Minimal AMF0 encode/decode
+//
+// Only the subset `rtmp::RtmpIngest` needs to read `connect`/`createStream`/
+// `publish` commands and write their `_result`/`onStatus` replies: number,
+// boolean, (short and long) string, null, strict/ECMA object, and the
+// object-end marker. Other AMF0 types (references, dates, XML, typed
+// objects) aren't used by the RTMP command channel and aren't decoded.
+
+use anyhow::Result;
+
+use crate::SfuError;
+
+const MARKER_NUMBER: u8 = 0x00;
+const MARKER_BOOLEAN: u8 = 0x01;
+const MARKER_STRING: u8 = 0x02;
+const MARKER_OBJECT: u8 = 0x03;
+const MARKER_NULL: u8 = 0x05;
+const MARKER_ECMA_ARRAY: u8 = 0x08;
+const MARKER_OBJECT_END: u8 = 0x09;
+const MARKER_LONG_STRING: u8 = 0x0c;
+
+#[derive(Debug, Clone)]
+pub enum Amf0Value {
+    Number(f64),
+    Boolean(bool),
+    String(String),
+    Object(Vec<(String, Amf0Value)>),
+    Null,
+}
+
+impl Amf0Value {
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Amf0Value::Number(n) => Some(*n),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Amf0Value::String(s) => Some(s),
+            _ => None,
+        }
+    }
+}
+
+/// Decode every AMF0 value in `buf` in sequence: an RTMP command message is
+/// a flat run of values (command name, transaction id, then each argument)
+pub fn decode_all(buf: &[u8]) -> Result<Vec<Amf0Value>> {
+    let mut values = Vec::new();
+    let mut offset = 0;
+    while offset < buf.len() {
+        let (value, consumed) = decode(&buf[offset..])?;
+        values.push(value);
+        offset += consumed;
+    }
+    Ok(values)
+}
+
+fn decode(buf: &[u8]) -> Result<(Amf0Value, usize)> {
+    let marker = *buf.first().ok_or_else(|| SfuError::Media("truncated AMF0 value".to_string()))?;
+
+    match marker {
+        MARKER_NUMBER => {
+            let bytes = take(buf, 1, 8)?;
+            Ok((Amf0Value::Number(f64::from_be_bytes(bytes.try_into().unwrap())), 9))
+        }
+        MARKER_BOOLEAN => {
+            let b = *take(buf, 1, 1)?.first().unwrap();
+            Ok((Amf0Value::Boolean(b != 0), 2))
+        }
+        MARKER_STRING => {
+            let (s, consumed) = decode_short_string(&buf[1..])?;
+            Ok((Amf0Value::String(s), 1 + consumed))
+        }
+        MARKER_LONG_STRING => {
+            let (s, consumed) = decode_long_string(&buf[1..])?;
+            Ok((Amf0Value::String(s), 1 + consumed))
+        }
+        MARKER_NULL => Ok((Amf0Value::Null, 1)),
+        MARKER_OBJECT => {
+            let (pairs, consumed) = decode_pairs(&buf[1..])?;
+            Ok((Amf0Value::Object(pairs), 1 + consumed))
+        }
+        MARKER_ECMA_ARRAY => {
+            // 4-byte approximate element count, not load-bearing for decoding
+            take(buf, 1, 4)?;
+            let (pairs, consumed) = decode_pairs(&buf[5..])?;
+            Ok((Amf0Value::Object(pairs), 5 + consumed))
+        }
+        other => Err(SfuError::Media(format!("unsupported AMF0 marker: 0x{:02x}", other)).into()),
+    }
+}
+
+fn take(buf: &[u8], offset: usize, len: usize) -> Result<&[u8]> {
+    buf.get(offset..offset + len)
+        .ok_or_else(|| SfuError::Media("truncated AMF0 value".to_string()).into())
+}
+
+fn decode_short_string(buf: &[u8]) -> Result<(String, usize)> {
+    let len = u16::from_be_bytes(take(buf, 0, 2)?.try_into().unwrap()) as usize;
+    let bytes = take(buf, 2, len)?;
+    Ok((String::from_utf8_lossy(bytes).into_owned(), 2 + len))
+}
+
+fn decode_long_string(buf: &[u8]) -> Result<(String, usize)> {
+    let len = u32::from_be_bytes(take(buf, 0, 4)?.try_into().unwrap()) as usize;
+    let bytes = take(buf, 4, len)?;
+    Ok((String::from_utf8_lossy(bytes).into_owned(), 4 + len))
+}
+
+/// Decode `(key, value)` pairs up to the object-end marker (an empty-string
+/// key followed by marker `0x09`)
+fn decode_pairs(buf: &[u8]) -> Result<(Vec<(String, Amf0Value)>, usize)> {
+    let mut pairs = Vec::new();
+    let mut offset = 0;
+
+    loop {
+        let (key, key_len) = decode_short_string(&buf[offset..])?;
+        offset += key_len;
+
+        if key.is_empty() && buf.get(offset) == Some(&MARKER_OBJECT_END) {
+            offset += 1;
+            break;
+        }
+
+        let (value, value_len) = decode(&buf[offset..])?;
+        offset += value_len;
+        pairs.push((key, value));
+    }
+
+    Ok((pairs, offset))
+}
+
+/// Encode one AMF0 value, appending to `buf`
+pub fn encode(buf: &mut Vec<u8>, value: &Amf0Value) {
+    match value {
+        Amf0Value::Number(n) => {
+            buf.push(MARKER_NUMBER);
+            buf.extend_from_slice(&n.to_be_bytes());
+        }
+        Amf0Value::Boolean(b) => {
+            buf.push(MARKER_BOOLEAN);
+            buf.push(*b as u8);
+        }
+        Amf0Value::String(s) => {
+            buf.push(MARKER_STRING);
+            buf.extend_from_slice(&(s.len() as u16).to_be_bytes());
+            buf.extend_from_slice(s.as_bytes());
+        }
+        Amf0Value::Null => buf.push(MARKER_NULL),
+        Amf0Value::Object(pairs) => {
+            buf.push(MARKER_OBJECT);
+            for (key, value) in pairs {
+                buf.extend_from_slice(&(key.len() as u16).to_be_bytes());
+                buf.extend_from_slice(key.as_bytes());
+                encode(buf, value);
+            }
+            buf.extend_from_slice(&0u16.to_be_bytes());
+            buf.push(MARKER_OBJECT_END);
+        }
+    }
+}
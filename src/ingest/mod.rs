@@ -0,0 +1,118 @@
+// Non-native ingest sources for publishers that can't speak our signaling
+// and QUIC transport directly
+//
+// `mp4` turns a fragmented MP4 byte stream - a file, or a live ffmpeg pipe
+// using `-movflags empty_moov+frag_every_frame+separate_moof+omit_tfhd_offset`
+// - into `MediaFrame`s published through a `QuicMediaManager` session.
+// `rtmp` does the same for an RTMP `publish` from OBS/ffmpeg, via `amf0`'s
+// command encoding.
+
+pub mod amf0;
+pub mod mp4;
+pub mod rtmp;
+
+use std::{collections::HashMap, io::Read, time::Duration};
+
+use anyhow::Result;
+
+use crate::{
+    media::frame::MediaFrame,
+    transport::integration::{MediaStreamConfig, QuicMediaManager, StreamDirection},
+};
+
+use self::mp4::{FragmentedMp4Source, Mp4Sample, Mp4TrackConfig};
+
+/// First dynamic RTP payload type assigned to a detected track; each
+/// subsequently detected track gets the next number up
+const FIRST_DYNAMIC_PAYLOAD_TYPE: u8 = 96;
+
+/// RTP clock rate every `QuicMediaTrack` assumes (`transport::quic::RTP_CLOCK_RATE`);
+/// sample DTS values are rescaled from the MP4 track's own timescale into this domain
+const RTP_CLOCK_RATE: u64 = 90_000;
+
+/// Read a fragmented MP4 stream and publish it into `session_id`,
+/// auto-creating one `SendOnly` stream per track declared in the init
+/// segment's `moov`, then streaming each fragment's samples with their
+/// timestamps rescaled into the RTP clock domain and paced to their own DTS
+/// deltas so playback matches a live cadence instead of bursting as fast as
+/// `reader` can be read.
+///
+/// Blocks on `reader`'s I/O between fragments; run this on a dedicated task
+/// rather than the caller's own async context.
+pub async fn ingest_fragmented_mp4<R: Read + Send + 'static>(
+    manager: &QuicMediaManager,
+    session_id: &str,
+    reader: R,
+) -> Result<()> {
+    let mut source = FragmentedMp4Source::open(reader)?;
+
+    let mut track_ids: Vec<u32> = source.tracks().keys().copied().collect();
+    track_ids.sort_unstable();
+
+    let mut stream_ids = HashMap::with_capacity(track_ids.len());
+    for (index, track_id) in track_ids.iter().enumerate() {
+        let track = source.tracks()[track_id].clone();
+        let stream_id = format!("mp4-track-{}", track_id);
+
+        manager
+            .create_stream(
+                session_id,
+                stream_id.clone(),
+                *track_id as u64,
+                MediaStreamConfig {
+                    direction: StreamDirection::SendOnly,
+                    codec_type: track.codec_type,
+                    payload_type: FIRST_DYNAMIC_PAYLOAD_TYPE + index as u8,
+                    ssrc: *track_id,
+                    min_bitrate: 0,
+                    max_bitrate: u32::MAX,
+                    layers: Vec::new(),
+                },
+            )
+            .await?;
+
+        stream_ids.insert(*track_id, stream_id);
+    }
+
+    let mut last_dts: HashMap<u32, u64> = HashMap::new();
+
+    while let Some(samples) = source.next_fragment()? {
+        for sample in samples {
+            let Some(stream_id) = stream_ids.get(&sample.track_id) else {
+                continue;
+            };
+            let track = &source.tracks()[&sample.track_id];
+
+            if let Some(&previous_dts) = last_dts.get(&sample.track_id) {
+                let gap = Duration::from_secs_f64(
+                    sample.dts.saturating_sub(previous_dts) as f64 / track.timescale as f64,
+                );
+                if !gap.is_zero() {
+                    tokio::time::sleep(gap).await;
+                }
+            }
+            last_dts.insert(sample.track_id, sample.dts);
+
+            let frame = to_media_frame(&sample, track)?;
+            manager.send_frame(session_id, stream_id, frame).await?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Convert one decoded MP4 sample into a `MediaFrame`, rescaling its DTS
+/// from the track's own `timescale` into the RTP clock domain every
+/// `QuicMediaTrack` assumes
+fn to_media_frame(sample: &Mp4Sample, track: &Mp4TrackConfig) -> Result<MediaFrame> {
+    let timestamp = (sample.dts * RTP_CLOCK_RATE / track.timescale.max(1) as u64) as u32;
+    let duration = Duration::from_secs_f64(sample.duration as f64 / track.timescale as f64);
+
+    if track.codec_type.is_audio() {
+        MediaFrame::new_audio(track.codec_type, sample.data.clone(), timestamp, duration)
+    } else if sample.is_key_frame {
+        MediaFrame::new_video_key(track.codec_type, sample.data.clone(), timestamp, duration, None, None)
+    } else {
+        MediaFrame::new_video_delta(track.codec_type, sample.data.clone(), timestamp, duration, None, None)
+    }
+}
@@ -0,0 +1,564 @@
+// Fragmented MP4 box parsing
+//
+// Reads the ISO base media file format boxes produced by an fMP4/CMAF
+// publisher (e.g. `ffmpeg -movflags
+// empty_moov+frag_every_frame+separate_moof+omit_tfhd_offset`): an `ftyp` +
+// `moov` init segment describing each track, followed by a `moof` + `mdat`
+// pair per fragment. This is the read-side counterpart to
+// `recording::mp4`'s writer; sample flags, `tfhd`/`trun` layout, and the
+// `default-base-is-moof` offset convention match what that writer produces.
+
+use std::{collections::HashMap, io::Read};
+
+use anyhow::Result;
+use bytes::Bytes;
+
+use crate::{media::codec::CodecType, SfuError};
+
+/// `trun`/`tfhd` sample flags bit indicating the sample is *not* a sync
+/// sample, i.e. not a key frame (ISO/IEC 14496-12 `sample_is_non_sync_sample`)
+const SAMPLE_IS_NON_SYNC: u32 = 0x0001_0000;
+
+/// Track configuration derived from the init segment's `moov` box
+#[derive(Debug, Clone)]
+pub struct Mp4TrackConfig {
+    /// This track's `moov`-declared track id (`tkhd.track_id`), used to
+    /// associate fragments (`tfhd.track_id`) back to the track that owns them
+    pub track_id: u32,
+    pub codec_type: CodecType,
+    /// Units per second this track's sample durations and decode times are
+    /// expressed in, read from `mdhd.timescale`
+    pub timescale: u32,
+}
+
+/// One decoded sample read from a `moof` + `mdat` fragment
+pub struct Mp4Sample {
+    pub track_id: u32,
+    pub data: Bytes,
+    /// Decode timestamp, in the track's `timescale` units, accumulated from
+    /// `tfdt.base_media_decode_time` plus each preceding sample's duration
+    pub dts: u64,
+    /// Sample duration, in the track's `timescale` units
+    pub duration: u32,
+    pub is_key_frame: bool,
+}
+
+/// One MP4 box's type and payload. The box's stream position is not stored
+/// here; callers track it to resolve `trun`'s `data_offset`
+struct Mp4Box {
+    box_type: [u8; 4],
+    payload: Vec<u8>,
+}
+
+/// Parses a fragmented MP4/CMAF byte stream into per-track configs, then
+/// samples, one fragment at a time
+pub struct FragmentedMp4Source<R: Read> {
+    reader: R,
+    tracks: HashMap<u32, Mp4TrackConfig>,
+    /// Absolute byte offset of the next unread byte, so `trun`'s
+    /// `default-base-is-moof` and `base-data-offset-present` offsets (both
+    /// relative to the stream, not to `next_fragment`'s own return value)
+    /// can be resolved against `mdat`'s payload
+    stream_pos: u64,
+}
+
+impl<R: Read> FragmentedMp4Source<R> {
+    /// Read boxes until the init segment's `moov` is found, and derive each
+    /// track's codec/timescale from it
+    pub fn open(mut reader: R) -> Result<Self> {
+        let mut stream_pos = 0u64;
+
+        let tracks = loop {
+            let Some(b) = read_box(&mut reader, &mut stream_pos)? else {
+                return Err(SfuError::Media("fMP4 stream ended before a moov box was found".to_string()).into());
+            };
+            if b.box_type == *b"moov" {
+                break parse_moov(&b.payload)?;
+            }
+            // ftyp, free, and any other top-level boxes ahead of moov are skipped
+        };
+
+        Ok(Self { reader, tracks, stream_pos })
+    }
+
+    /// Track configurations detected in the init segment, keyed by `moov` track id
+    pub fn tracks(&self) -> &HashMap<u32, Mp4TrackConfig> {
+        &self.tracks
+    }
+
+    /// Read the next `moof` + `mdat` fragment and return its samples, or
+    /// `None` at a clean end of stream
+    pub fn next_fragment(&mut self) -> Result<Option<Vec<Mp4Sample>>> {
+        let moof_offset = self.stream_pos;
+        let Some(moof) = read_box(&mut self.reader, &mut self.stream_pos)? else {
+            return Ok(None);
+        };
+        if moof.box_type != *b"moof" {
+            return Err(SfuError::Media(format!(
+                "expected moof box, found {:?}",
+                String::from_utf8_lossy(&moof.box_type)
+            ))
+            .into());
+        }
+
+        let Some(mdat) = read_box(&mut self.reader, &mut self.stream_pos)? else {
+            return Err(SfuError::Media("fMP4 stream ended between moof and mdat".to_string()).into());
+        };
+        if mdat.box_type != *b"mdat" {
+            return Err(SfuError::Media(format!(
+                "expected mdat box after moof, found {:?}",
+                String::from_utf8_lossy(&mdat.box_type)
+            ))
+            .into());
+        }
+        let mdat_payload_offset = self.stream_pos - mdat.payload.len() as u64;
+
+        parse_moof(&moof.payload, &mdat.payload, moof_offset, mdat_payload_offset, &self.tracks).map(Some)
+    }
+}
+
+/// Read one box (header + payload), advancing `pos` by the bytes consumed.
+/// `Ok(None)` signals a clean end of stream before any byte of a new box was read
+fn read_box(reader: &mut impl Read, pos: &mut u64) -> Result<Option<Mp4Box>> {
+    let mut header = [0u8; 8];
+    if !read_exact_or_eof(reader, &mut header)? {
+        return Ok(None);
+    }
+
+    let mut size = u32::from_be_bytes(header[0..4].try_into().unwrap()) as u64;
+    let box_type: [u8; 4] = header[4..8].try_into().unwrap();
+    let mut header_len = 8u64;
+
+    if size == 1 {
+        let mut largesize = [0u8; 8];
+        reader.read_exact(&mut largesize)?;
+        size = u64::from_be_bytes(largesize);
+        header_len += 8;
+    } else if size == 0 {
+        return Err(SfuError::Media(format!(
+            "{:?} box extends to end of stream, unsupported for ingest",
+            String::from_utf8_lossy(&box_type)
+        ))
+        .into());
+    }
+
+    let payload_len = size.checked_sub(header_len).ok_or_else(|| {
+        SfuError::Media(format!(
+            "{:?} box declares a size smaller than its own header",
+            String::from_utf8_lossy(&box_type)
+        ))
+    })?;
+
+    let mut payload = vec![0u8; payload_len as usize];
+    reader.read_exact(&mut payload)?;
+    *pos += header_len + payload_len;
+
+    Ok(Some(Mp4Box { box_type, payload }))
+}
+
+/// Like `Read::read_exact`, but returns `Ok(false)` instead of erroring when
+/// EOF is hit before any byte of `buf` is read, distinguishing a clean end
+/// of stream from a fragment truncated mid-box
+fn read_exact_or_eof(reader: &mut impl Read, buf: &mut [u8]) -> Result<bool> {
+    let mut read = 0;
+    while read < buf.len() {
+        match reader.read(&mut buf[read..])? {
+            0 if read == 0 => return Ok(false),
+            0 => {
+                return Err(SfuError::Io(std::io::Error::new(
+                    std::io::ErrorKind::UnexpectedEof,
+                    "truncated fMP4 box",
+                ))
+                .into())
+            }
+            n => read += n,
+        }
+    }
+    Ok(true)
+}
+
+/// Iterate the immediate child boxes of a box's already-read payload
+fn child_boxes(payload: &[u8]) -> Result<Vec<Mp4Box>> {
+    let mut boxes = Vec::new();
+    let mut cursor = std::io::Cursor::new(payload);
+    let mut pos = 0u64;
+    while (cursor.position() as usize) < payload.len() {
+        match read_box(&mut cursor, &mut pos)? {
+            Some(b) => boxes.push(b),
+            None => break,
+        }
+    }
+    Ok(boxes)
+}
+
+fn read_u32(payload: &[u8], offset: usize) -> Result<u32> {
+    payload
+        .get(offset..offset + 4)
+        .and_then(|bytes| bytes.try_into().ok())
+        .map(u32::from_be_bytes)
+        .ok_or_else(|| SfuError::Media("fMP4 box is too short for its declared fields".to_string()).into())
+}
+
+fn read_u64(payload: &[u8], offset: usize) -> Result<u64> {
+    payload
+        .get(offset..offset + 8)
+        .and_then(|bytes| bytes.try_into().ok())
+        .map(u64::from_be_bytes)
+        .ok_or_else(|| SfuError::Media("fMP4 box is too short for its declared fields".to_string()).into())
+}
+
+/// Parse a `moov` box into per-track codec/timescale configuration,
+/// skipping tracks whose sample entry isn't one of `CodecType`'s codecs
+fn parse_moov(payload: &[u8]) -> Result<HashMap<u32, Mp4TrackConfig>> {
+    let mut tracks = HashMap::new();
+
+    for b in child_boxes(payload)? {
+        if b.box_type == *b"trak" {
+            if let Some(track) = parse_trak(&b.payload)? {
+                tracks.insert(track.track_id, track);
+            }
+        }
+    }
+
+    if tracks.is_empty() {
+        return Err(SfuError::Media("moov box declared no tracks with a recognized codec".to_string()).into());
+    }
+
+    Ok(tracks)
+}
+
+fn parse_trak(payload: &[u8]) -> Result<Option<Mp4TrackConfig>> {
+    let mut track_id = None;
+    let mut mdia = None;
+
+    for b in child_boxes(payload)? {
+        match &b.box_type {
+            b"tkhd" => track_id = Some(parse_tkhd_track_id(&b.payload)?),
+            b"mdia" => mdia = Some(parse_mdia(&b.payload)?),
+            _ => {}
+        }
+    }
+
+    let Some(track_id) = track_id else {
+        return Ok(None);
+    };
+    let Some((timescale, Some(codec_type))) = mdia else {
+        // Either no mdia, or a sample entry this SFU doesn't have a codec for
+        return Ok(None);
+    };
+
+    Ok(Some(Mp4TrackConfig { track_id, codec_type, timescale }))
+}
+
+fn parse_tkhd_track_id(payload: &[u8]) -> Result<u32> {
+    if payload.is_empty() {
+        return Err(SfuError::Media("tkhd box is empty".to_string()).into());
+    }
+    let version = payload[0];
+    // flags(4) + creation_time + modification_time, both 4 or 8 bytes depending on version
+    let track_id_offset = if version == 1 { 4 + 8 + 8 } else { 4 + 4 + 4 };
+    read_u32(payload, track_id_offset)
+}
+
+fn parse_mdia(payload: &[u8]) -> Result<(u32, Option<CodecType>)> {
+    let mut timescale = None;
+    let mut codec_type = None;
+
+    for b in child_boxes(payload)? {
+        match &b.box_type {
+            b"mdhd" => timescale = Some(parse_mdhd_timescale(&b.payload)?),
+            b"minf" => codec_type = parse_minf_codec(&b.payload)?,
+            _ => {}
+        }
+    }
+
+    let timescale = timescale.ok_or_else(|| SfuError::Media("mdia box has no mdhd box".to_string()))?;
+    Ok((timescale, codec_type))
+}
+
+fn parse_mdhd_timescale(payload: &[u8]) -> Result<u32> {
+    if payload.is_empty() {
+        return Err(SfuError::Media("mdhd box is empty".to_string()).into());
+    }
+    let version = payload[0];
+    let timescale_offset = if version == 1 { 4 + 8 + 8 } else { 4 + 4 + 4 };
+    read_u32(payload, timescale_offset)
+}
+
+fn parse_minf_codec(payload: &[u8]) -> Result<Option<CodecType>> {
+    for b in child_boxes(payload)? {
+        if b.box_type == *b"stbl" {
+            for stbl_box in child_boxes(&b.payload)? {
+                if stbl_box.box_type == *b"stsd" {
+                    return parse_stsd_codec(&stbl_box.payload);
+                }
+            }
+        }
+    }
+    Ok(None)
+}
+
+/// Map a `stsd`'s single sample entry box to a `CodecType`, or `None` if
+/// it's not one this SFU handles
+fn parse_stsd_codec(payload: &[u8]) -> Result<Option<CodecType>> {
+    // version(1) + flags(3) + entry_count(4), then one sample entry box
+    if payload.len() < 8 {
+        return Err(SfuError::Media("stsd box is too short".to_string()).into());
+    }
+    let Some(entry) = child_boxes(&payload[8..])?.into_iter().next() else {
+        return Ok(None);
+    };
+
+    Ok(match &entry.box_type {
+        b"vp09" => Some(CodecType::VP9),
+        b"vp08" => Some(CodecType::VP8),
+        b"avc1" | b"avc3" => Some(CodecType::H264),
+        b"av01" => Some(CodecType::AV1),
+        b"Opus" => Some(CodecType::Opus),
+        b"mp4a" => Some(CodecType::AAC),
+        _ => None,
+    })
+}
+
+/// Fields from `tfhd` relevant to decoding `trun`'s samples
+struct Tfhd {
+    track_id: u32,
+    /// Absolute stream offset samples are anchored to, when
+    /// `base-data-offset-present` is set
+    base_data_offset: Option<u64>,
+    default_sample_duration: Option<u32>,
+    default_sample_size: Option<u32>,
+    default_sample_flags: Option<u32>,
+}
+
+const TFHD_BASE_DATA_OFFSET_PRESENT: u32 = 0x00_0001;
+const TFHD_SAMPLE_DESCRIPTION_INDEX_PRESENT: u32 = 0x00_0002;
+const TFHD_DEFAULT_SAMPLE_DURATION_PRESENT: u32 = 0x00_0008;
+const TFHD_DEFAULT_SAMPLE_SIZE_PRESENT: u32 = 0x00_0010;
+const TFHD_DEFAULT_SAMPLE_FLAGS_PRESENT: u32 = 0x00_0020;
+
+fn parse_tfhd(payload: &[u8]) -> Result<Tfhd> {
+    let flags = read_u32(payload, 0)?;
+    let mut offset = 4;
+    let track_id = read_u32(payload, offset)?;
+    offset += 4;
+
+    let base_data_offset = if flags & TFHD_BASE_DATA_OFFSET_PRESENT != 0 {
+        let v = read_u64(payload, offset)?;
+        offset += 8;
+        Some(v)
+    } else {
+        None
+    };
+    if flags & TFHD_SAMPLE_DESCRIPTION_INDEX_PRESENT != 0 {
+        offset += 4;
+    }
+    let default_sample_duration = if flags & TFHD_DEFAULT_SAMPLE_DURATION_PRESENT != 0 {
+        let v = read_u32(payload, offset)?;
+        offset += 4;
+        Some(v)
+    } else {
+        None
+    };
+    let default_sample_size = if flags & TFHD_DEFAULT_SAMPLE_SIZE_PRESENT != 0 {
+        let v = read_u32(payload, offset)?;
+        offset += 4;
+        Some(v)
+    } else {
+        None
+    };
+    let default_sample_flags = if flags & TFHD_DEFAULT_SAMPLE_FLAGS_PRESENT != 0 {
+        let v = read_u32(payload, offset)?;
+        offset += 4;
+        Some(v)
+    } else {
+        None
+    };
+
+    Ok(Tfhd {
+        track_id,
+        base_data_offset,
+        default_sample_duration,
+        default_sample_size,
+        default_sample_flags,
+    })
+}
+
+/// `tfdt.base_media_decode_time`, in the track's timescale units
+fn parse_tfdt(payload: &[u8]) -> Result<u64> {
+    if payload.is_empty() {
+        return Err(SfuError::Media("tfdt box is empty".to_string()).into());
+    }
+    if payload[0] == 1 {
+        read_u64(payload, 4)
+    } else {
+        Ok(read_u32(payload, 4)? as u64)
+    }
+}
+
+struct TrunEntry {
+    duration: Option<u32>,
+    size: Option<u32>,
+    flags: Option<u32>,
+}
+
+const TRUN_DATA_OFFSET_PRESENT: u32 = 0x00_0001;
+const TRUN_FIRST_SAMPLE_FLAGS_PRESENT: u32 = 0x00_0004;
+const TRUN_SAMPLE_DURATION_PRESENT: u32 = 0x00_0100;
+const TRUN_SAMPLE_SIZE_PRESENT: u32 = 0x00_0200;
+const TRUN_SAMPLE_FLAGS_PRESENT: u32 = 0x00_0400;
+const TRUN_SAMPLE_COMPOSITION_TIME_OFFSETS_PRESENT: u32 = 0x00_0800;
+
+/// Parsed `trun` sample entries, plus `data_offset` relative to whichever
+/// base `tfhd` establishes (see `resolve_base_offset`)
+fn parse_trun(payload: &[u8]) -> Result<(Vec<TrunEntry>, i32)> {
+    let flags = read_u32(payload, 0)?;
+    let mut offset = 4;
+    let sample_count = read_u32(payload, offset)?;
+    offset += 4;
+
+    let data_offset = if flags & TRUN_DATA_OFFSET_PRESENT != 0 {
+        let v = read_u32(payload, offset)? as i32;
+        offset += 4;
+        v
+    } else {
+        0
+    };
+
+    let first_sample_flags = if flags & TRUN_FIRST_SAMPLE_FLAGS_PRESENT != 0 {
+        let v = read_u32(payload, offset)?;
+        offset += 4;
+        Some(v)
+    } else {
+        None
+    };
+
+    let mut entries = Vec::with_capacity(sample_count as usize);
+    for i in 0..sample_count {
+        let duration = if flags & TRUN_SAMPLE_DURATION_PRESENT != 0 {
+            let v = read_u32(payload, offset)?;
+            offset += 4;
+            Some(v)
+        } else {
+            None
+        };
+        let size = if flags & TRUN_SAMPLE_SIZE_PRESENT != 0 {
+            let v = read_u32(payload, offset)?;
+            offset += 4;
+            Some(v)
+        } else {
+            None
+        };
+        let mut sample_flags = if flags & TRUN_SAMPLE_FLAGS_PRESENT != 0 {
+            let v = read_u32(payload, offset)?;
+            offset += 4;
+            Some(v)
+        } else {
+            None
+        };
+        if flags & TRUN_SAMPLE_COMPOSITION_TIME_OFFSETS_PRESENT != 0 {
+            offset += 4;
+        }
+        if i == 0 {
+            sample_flags = sample_flags.or(first_sample_flags);
+        }
+        entries.push(TrunEntry { duration, size, flags: sample_flags });
+    }
+
+    Ok((entries, data_offset))
+}
+
+/// Resolve `trun`'s `data_offset` to an absolute stream position: anchored
+/// to `tfhd.base_data_offset` when `base-data-offset-present` is set, or
+/// else to the start of the enclosing `moof` box (`default-base-is-moof`,
+/// the convention `recording::mp4::FragmentBuilder` writes)
+fn resolve_base_offset(tfhd: &Tfhd, moof_offset: u64) -> u64 {
+    tfhd.base_data_offset.unwrap_or(moof_offset)
+}
+
+fn parse_traf(
+    payload: &[u8],
+    mdat: &[u8],
+    moof_offset: u64,
+    mdat_payload_offset: u64,
+    tracks: &HashMap<u32, Mp4TrackConfig>,
+) -> Result<Vec<Mp4Sample>> {
+    let mut tfhd = None;
+    let mut base_decode_time = None;
+    let mut trun = None;
+
+    for b in child_boxes(payload)? {
+        match &b.box_type {
+            b"tfhd" => tfhd = Some(parse_tfhd(&b.payload)?),
+            b"tfdt" => base_decode_time = Some(parse_tfdt(&b.payload)?),
+            b"trun" => trun = Some(parse_trun(&b.payload)?),
+            _ => {}
+        }
+    }
+
+    let tfhd = tfhd.ok_or_else(|| SfuError::Media("traf box has no tfhd box".to_string()))?;
+    let (entries, data_offset) = trun.ok_or_else(|| SfuError::Media("traf box has no trun box".to_string()))?;
+
+    if !tracks.contains_key(&tfhd.track_id) {
+        // Fragment for a track the init segment didn't declare a codec for
+        return Ok(Vec::new());
+    }
+
+    let base_offset = resolve_base_offset(&tfhd, moof_offset);
+    let mut byte_offset: usize = (base_offset as i64)
+        .checked_add(data_offset as i64)
+        .and_then(|v| v.checked_sub(mdat_payload_offset as i64))
+        .and_then(|v| usize::try_from(v).ok())
+        .ok_or_else(|| SfuError::Media("trun data_offset resolves before the mdat box".to_string()))?;
+
+    let mut dts = base_decode_time.unwrap_or(0);
+    let mut samples = Vec::with_capacity(entries.len());
+
+    for entry in entries {
+        let duration = entry
+            .duration
+            .or(tfhd.default_sample_duration)
+            .ok_or_else(|| SfuError::Media("trun sample has no duration and tfhd declares no default".to_string()))?;
+        let size = entry
+            .size
+            .or(tfhd.default_sample_size)
+            .ok_or_else(|| SfuError::Media("trun sample has no size and tfhd declares no default".to_string()))?
+            as usize;
+        let flags = entry.flags.or(tfhd.default_sample_flags).unwrap_or(0);
+
+        let data = mdat
+            .get(byte_offset..byte_offset + size)
+            .ok_or_else(|| SfuError::Media("trun sample extends past the end of its mdat box".to_string()))?;
+
+        samples.push(Mp4Sample {
+            track_id: tfhd.track_id,
+            data: Bytes::copy_from_slice(data),
+            dts,
+            duration,
+            is_key_frame: flags & SAMPLE_IS_NON_SYNC == 0,
+        });
+
+        dts += duration as u64;
+        byte_offset += size;
+    }
+
+    Ok(samples)
+}
+
+fn parse_moof(
+    moof_payload: &[u8],
+    mdat_payload: &[u8],
+    moof_offset: u64,
+    mdat_payload_offset: u64,
+    tracks: &HashMap<u32, Mp4TrackConfig>,
+) -> Result<Vec<Mp4Sample>> {
+    let mut samples = Vec::new();
+
+    for b in child_boxes(moof_payload)? {
+        if b.box_type == *b"traf" {
+            samples.extend(parse_traf(&b.payload, mdat_payload, moof_offset, mdat_payload_offset, tracks)?);
+        }
+    }
+
+    Ok(samples)
+}
@@ -0,0 +1,589 @@
+// RTMP ingest server
+//
+// Lets tools that only speak RTMP (OBS, ffmpeg) publish directly into a
+// `QuicMediaSession`: `RtmpIngest` listens on a TCP port, performs the RTMP
+// handshake, demuxes the chunk stream, answers the `connect`/`createStream`/
+// `publish` AMF0 commands, and turns each subsequent video/audio message into
+// a `MediaFrame` forwarded through `QuicMediaManager::send_frame`.
+//
+// `QuicMediaManager::create_session` is keyed on an already-established iroh
+// `NodeId`+`Connection`, which a raw TCP publisher never has - same gap
+// `signaling::whip` documents for WHIP. A `publish` therefore selects an
+// existing session (by treating the RTMP stream key as the session id) via
+// `get_session` rather than fabricating a connection to create one; creating
+// a session still has to go through real iroh signaling first.
+
+use std::collections::HashMap;
+
+use anyhow::Result;
+use bytes::Bytes;
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+};
+
+use crate::{
+    ingest::amf0::{self, Amf0Value},
+    media::{codec::CodecType, frame::MediaFrame},
+    transport::integration::{MediaStreamConfig, QuicMediaManager, StreamDirection},
+    SfuError,
+};
+
+/// RTMP handshake version byte (unencrypted, "simple" handshake)
+const HANDSHAKE_VERSION: u8 = 3;
+/// Size of each handshake payload (C1/S1, C2/S2) after the version byte
+const HANDSHAKE_PAYLOAD_SIZE: usize = 1536;
+
+/// Chunk stream id the command channel runs on
+const COMMAND_CHUNK_STREAM_ID: u32 = 3;
+/// Message stream id `createStream` hands back and `publish`'s replies use
+const CREATED_STREAM_ID: u32 = 1;
+/// Chunk payload size assumed for peer-sent chunks until a `Set Chunk Size`
+/// protocol control message says otherwise
+const DEFAULT_CHUNK_SIZE: usize = 128;
+
+const MESSAGE_TYPE_SET_CHUNK_SIZE: u8 = 1;
+const MESSAGE_TYPE_AUDIO: u8 = 8;
+const MESSAGE_TYPE_VIDEO: u8 = 9;
+const MESSAGE_TYPE_AMF0_COMMAND: u8 = 20;
+
+/// FLV `AVCPacketType`/`AACPacketType`: codec sequence header (SPS/PPS or
+/// AudioSpecificConfig), carried once before the first frame
+const PACKET_TYPE_SEQUENCE_HEADER: u8 = 0;
+
+/// `CodecId` nibble for AVC in an FLV video tag's first byte
+const VIDEO_CODEC_ID_AVC: u8 = 7;
+/// `SoundFormat` nibble for AAC in an FLV audio tag's first byte
+const SOUND_FORMAT_AAC: u8 = 10;
+
+/// Track id/RTP payload type this ingest assigns the published video stream
+const VIDEO_TRACK_ID: u64 = 1;
+const VIDEO_PAYLOAD_TYPE: u8 = 96;
+/// Track id/RTP payload type this ingest assigns the published audio stream
+const AUDIO_TRACK_ID: u64 = 2;
+const AUDIO_PAYLOAD_TYPE: u8 = 97;
+
+/// RTP clock rate every `QuicMediaTrack` assumes (`transport::quic::RTP_CLOCK_RATE`)
+const RTP_CLOCK_RATE: u64 = 90_000;
+
+/// One connected RTMP publisher's negotiated session and per-track state
+struct Publisher {
+    /// Target session, taken from the `publish` command's stream key
+    session_id: String,
+    video_stream_id: Option<String>,
+    audio_stream_id: Option<String>,
+    /// SPS/PPS parameter sets parsed from the AVC sequence header, prepended
+    /// to each key frame since `H264Payloader` expects Annex-B framing
+    /// with in-band parameter sets rather than MP4/FLV's out-of-band AVCC ones
+    avc_parameter_sets: Vec<Bytes>,
+    last_video_timestamp_ms: Option<u32>,
+    last_audio_timestamp_ms: Option<u32>,
+}
+
+/// TCP server terminating RTMP `publish` sessions and bridging their media
+/// into `QuicMediaManager` sessions
+pub struct RtmpIngest {
+    manager: QuicMediaManager,
+}
+
+impl RtmpIngest {
+    /// Create an ingest server forwarding published media through `manager`
+    pub fn new(manager: QuicMediaManager) -> Self {
+        Self { manager }
+    }
+
+    /// Listen on `addr`, accepting one RTMP publisher connection at a time
+    /// per socket and spawning a task to run its handshake and media loop
+    pub async fn serve(self: std::sync::Arc<Self>, addr: &str) -> Result<()> {
+        let listener = TcpListener::bind(addr).await?;
+
+        loop {
+            let (socket, _peer_addr) = listener.accept().await?;
+            let ingest = self.clone();
+            tokio::spawn(async move {
+                if let Err(error) = ingest.handle_connection(socket).await {
+                    tracing::warn!(%error, "RTMP connection ended");
+                }
+            });
+        }
+    }
+
+    /// Run the handshake then the chunk/command/media loop for one connection
+    async fn handle_connection(&self, mut socket: TcpStream) -> Result<()> {
+        handshake(&mut socket).await?;
+
+        let mut demuxer = ChunkDemuxer::new();
+        let mut publisher: Option<Publisher> = None;
+
+        loop {
+            let message = demuxer.read_message(&mut socket).await?;
+
+            match message.message_type_id {
+                MESSAGE_TYPE_SET_CHUNK_SIZE => {
+                    if message.payload.len() >= 4 {
+                        let size = u32::from_be_bytes(message.payload[..4].try_into().unwrap());
+                        demuxer.read_chunk_size = size as usize;
+                    }
+                }
+                MESSAGE_TYPE_AMF0_COMMAND => {
+                    self.handle_command(&mut socket, &message.payload, &mut publisher).await?;
+                }
+                MESSAGE_TYPE_VIDEO => {
+                    if let Some(publisher) = publisher.as_mut() {
+                        self.forward_video(publisher, message.timestamp, &message.payload).await?;
+                    }
+                }
+                MESSAGE_TYPE_AUDIO => {
+                    if let Some(publisher) = publisher.as_mut() {
+                        self.forward_audio(publisher, message.timestamp, &message.payload).await?;
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Handle one AMF0 command message, replying on the command channel
+    async fn handle_command(
+        &self,
+        socket: &mut TcpStream,
+        payload: &[u8],
+        publisher: &mut Option<Publisher>,
+    ) -> Result<()> {
+        let values = amf0::decode_all(payload)?;
+        let Some(command) = values.first().and_then(Amf0Value::as_str) else {
+            return Ok(());
+        };
+
+        match command {
+            "connect" => {
+                let transaction_id = values.get(1).and_then(Amf0Value::as_f64).unwrap_or(1.0);
+                reply_connect(socket, transaction_id).await?;
+            }
+            "createStream" => {
+                let transaction_id = values.get(1).and_then(Amf0Value::as_f64).unwrap_or(0.0);
+                reply_create_stream(socket, transaction_id).await?;
+            }
+            "publish" => {
+                let stream_key = values
+                    .get(3)
+                    .and_then(Amf0Value::as_str)
+                    .ok_or_else(|| SfuError::Media("publish command missing stream key".to_string()))?;
+
+                // Selects the session the stream key names; creating one from
+                // a bare TCP connection isn't possible, see module doc comment
+                self.manager.get_session(stream_key).await?;
+
+                *publisher = Some(Publisher {
+                    session_id: stream_key.to_string(),
+                    video_stream_id: None,
+                    audio_stream_id: None,
+                    avc_parameter_sets: Vec::new(),
+                    last_video_timestamp_ms: None,
+                    last_audio_timestamp_ms: None,
+                });
+                reply_publish_start(socket, stream_key).await?;
+            }
+            // releaseStream/FCPublish and other FMLE preamble commands need
+            // no reply for publishing to proceed
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    /// Decode one FLV-style video tag and forward it as a `MediaFrame`,
+    /// converting AVCC length-prefixed NALs to the Annex-B framing
+    /// `H264Payloader` expects and caching the sequence header's SPS/PPS to
+    /// prepend ahead of each key frame
+    async fn forward_video(&self, publisher: &mut Publisher, timestamp_ms: u32, payload: &[u8]) -> Result<()> {
+        if payload.len() < 5 {
+            return Ok(());
+        }
+        let frame_type = payload[0] >> 4;
+        let codec_id = payload[0] & 0x0f;
+        if codec_id != VIDEO_CODEC_ID_AVC {
+            // Only AVC is supported; the SFU has no other video CodecType this maps to
+            return Ok(());
+        }
+        let packet_type = payload[1];
+        let is_key_frame = frame_type == 1;
+        let avcc = &payload[5..];
+
+        if packet_type == PACKET_TYPE_SEQUENCE_HEADER {
+            publisher.avc_parameter_sets = parse_avc_decoder_config(avcc)?;
+            return Ok(());
+        }
+
+        if publisher.video_stream_id.is_none() {
+            let stream_id = "video".to_string();
+            self.manager
+                .create_stream(
+                    &publisher.session_id,
+                    stream_id.clone(),
+                    VIDEO_TRACK_ID,
+                    MediaStreamConfig {
+                        direction: StreamDirection::SendOnly,
+                        codec_type: CodecType::H264,
+                        payload_type: VIDEO_PAYLOAD_TYPE,
+                        ssrc: VIDEO_TRACK_ID as u32,
+                        min_bitrate: 0,
+                        max_bitrate: u32::MAX,
+                        layers: Vec::new(),
+                    },
+                )
+                .await?;
+            publisher.video_stream_id = Some(stream_id);
+        }
+
+        let mut annex_b = Vec::with_capacity(avcc.len() + 64);
+        if is_key_frame {
+            for parameter_set in &publisher.avc_parameter_sets {
+                annex_b.extend_from_slice(&[0, 0, 0, 1]);
+                annex_b.extend_from_slice(parameter_set);
+            }
+        }
+        for nal in avcc_nals(avcc) {
+            annex_b.extend_from_slice(&[0, 0, 0, 1]);
+            annex_b.extend_from_slice(nal);
+        }
+
+        let duration_ms = timestamp_ms.saturating_sub(publisher.last_video_timestamp_ms.unwrap_or(timestamp_ms));
+        publisher.last_video_timestamp_ms = Some(timestamp_ms);
+        let rtp_timestamp = (timestamp_ms as u64 * RTP_CLOCK_RATE / 1000) as u32;
+        let duration = std::time::Duration::from_millis(duration_ms as u64);
+
+        let frame = if is_key_frame {
+            MediaFrame::new_video_key(CodecType::H264, Bytes::from(annex_b), rtp_timestamp, duration, None, None)?
+        } else {
+            MediaFrame::new_video_delta(CodecType::H264, Bytes::from(annex_b), rtp_timestamp, duration, None, None)?
+        };
+
+        self.manager
+            .send_frame(&publisher.session_id, publisher.video_stream_id.as_ref().unwrap(), frame)
+            .await
+    }
+
+    /// Decode one FLV-style audio tag and forward its raw AAC access unit as
+    /// a `MediaFrame`; `AacPayloader` adds the RFC 3016 length prefix itself,
+    /// so the tag's AudioSpecificConfig sequence header carries no media to forward
+    async fn forward_audio(&self, publisher: &mut Publisher, timestamp_ms: u32, payload: &[u8]) -> Result<()> {
+        if payload.len() < 2 {
+            return Ok(());
+        }
+        let sound_format = payload[0] >> 4;
+        if sound_format != SOUND_FORMAT_AAC {
+            // Only AAC is supported; the SFU has no CodecType for other FLV sound formats
+            return Ok(());
+        }
+        if payload[1] == PACKET_TYPE_SEQUENCE_HEADER {
+            return Ok(());
+        }
+
+        if publisher.audio_stream_id.is_none() {
+            let stream_id = "audio".to_string();
+            self.manager
+                .create_stream(
+                    &publisher.session_id,
+                    stream_id.clone(),
+                    AUDIO_TRACK_ID,
+                    MediaStreamConfig {
+                        direction: StreamDirection::SendOnly,
+                        codec_type: CodecType::AAC,
+                        payload_type: AUDIO_PAYLOAD_TYPE,
+                        ssrc: AUDIO_TRACK_ID as u32,
+                        min_bitrate: 0,
+                        max_bitrate: u32::MAX,
+                        layers: Vec::new(),
+                    },
+                )
+                .await?;
+            publisher.audio_stream_id = Some(stream_id);
+        }
+
+        let duration_ms = timestamp_ms.saturating_sub(publisher.last_audio_timestamp_ms.unwrap_or(timestamp_ms));
+        publisher.last_audio_timestamp_ms = Some(timestamp_ms);
+        let rtp_timestamp = (timestamp_ms as u64 * RTP_CLOCK_RATE / 1000) as u32;
+        let duration = std::time::Duration::from_millis(duration_ms as u64);
+
+        let frame = MediaFrame::new_audio(CodecType::AAC, Bytes::copy_from_slice(&payload[2..]), rtp_timestamp, duration)?;
+        self.manager
+            .send_frame(&publisher.session_id, publisher.audio_stream_id.as_ref().unwrap(), frame)
+            .await
+    }
+}
+
+/// Run the unencrypted RTMP handshake (C0/C1 in, S0/S1/S2 out, C2 in)
+async fn handshake(socket: &mut TcpStream) -> Result<()> {
+    let mut c0 = [0u8; 1];
+    socket.read_exact(&mut c0).await?;
+
+    let mut c1 = [0u8; HANDSHAKE_PAYLOAD_SIZE];
+    socket.read_exact(&mut c1).await?;
+
+    let mut s0_s1_s2 = Vec::with_capacity(1 + HANDSHAKE_PAYLOAD_SIZE * 2);
+    s0_s1_s2.push(HANDSHAKE_VERSION);
+    s0_s1_s2.extend(std::iter::repeat(0u8).take(HANDSHAKE_PAYLOAD_SIZE));
+    s0_s1_s2.extend_from_slice(&c1);
+    socket.write_all(&s0_s1_s2).await?;
+
+    let mut c2 = [0u8; HANDSHAKE_PAYLOAD_SIZE];
+    socket.read_exact(&mut c2).await?;
+
+    Ok(())
+}
+
+/// One fully reassembled RTMP message: a chunk stream's message header plus
+/// its payload, reassembled across as many chunks as `message_length` needs
+struct Message {
+    message_type_id: u8,
+    timestamp: u32,
+    payload: Vec<u8>,
+}
+
+/// Per-chunk-stream header state a format 1/2/3 chunk inherits from the
+/// previous chunk on the same chunk stream id
+#[derive(Clone, Default)]
+struct ChunkStreamState {
+    timestamp: u32,
+    message_length: usize,
+    message_type_id: u8,
+    message_stream_id: u32,
+    /// Payload accumulated so far for the in-progress message on this chunk stream
+    partial: Vec<u8>,
+}
+
+/// Demuxes the RTMP chunk stream into whole messages, tracking each chunk
+/// stream id's inherited header state and the negotiated inbound chunk size
+struct ChunkDemuxer {
+    streams: HashMap<u32, ChunkStreamState>,
+    read_chunk_size: usize,
+}
+
+impl ChunkDemuxer {
+    fn new() -> Self {
+        Self { streams: HashMap::new(), read_chunk_size: DEFAULT_CHUNK_SIZE }
+    }
+
+    /// Read chunks until one chunk stream's message is fully reassembled,
+    /// returning it
+    async fn read_message(&mut self, socket: &mut TcpStream) -> Result<Message> {
+        loop {
+            let (csid, fmt) = read_basic_header(socket).await?;
+            let state = self.streams.entry(csid).or_default();
+
+            match fmt {
+                0 => {
+                    state.timestamp = read_u24(socket).await?;
+                    state.message_length = read_u24(socket).await? as usize;
+                    state.message_type_id = read_u8(socket).await?;
+                    state.message_stream_id = read_u32_le(socket).await?;
+                    // A format 0 header always starts a fresh message on this
+                    // chunk stream id - drop any unfinished previous message
+                    // rather than let a shrunk `message_length` underflow below
+                    state.partial.clear();
+                }
+                1 => {
+                    let delta = read_u24(socket).await?;
+                    state.message_length = read_u24(socket).await? as usize;
+                    state.message_type_id = read_u8(socket).await?;
+                    state.timestamp = state.timestamp.wrapping_add(delta);
+                    // Same as format 0: a new `message_length` means a new message
+                    state.partial.clear();
+                }
+                2 => {
+                    let delta = read_u24(socket).await?;
+                    state.timestamp = state.timestamp.wrapping_add(delta);
+                }
+                _ => {}
+            }
+
+            let remaining = state.message_length.saturating_sub(state.partial.len());
+            let read_now = remaining.min(self.read_chunk_size);
+            let mut chunk = vec![0u8; read_now];
+            socket.read_exact(&mut chunk).await?;
+            state.partial.extend_from_slice(&chunk);
+
+            if state.partial.len() >= state.message_length {
+                let message = Message {
+                    message_type_id: state.message_type_id,
+                    timestamp: state.timestamp,
+                    payload: std::mem::take(&mut state.partial),
+                };
+                return Ok(message);
+            }
+        }
+    }
+}
+
+/// Read a 1/2/3-byte basic header, returning the chunk stream id and the
+/// 2-bit message header format
+async fn read_basic_header(socket: &mut TcpStream) -> Result<(u32, u8)> {
+    let first = read_u8(socket).await?;
+    let fmt = first >> 6;
+    let csid = match first & 0x3f {
+        0 => 64 + read_u8(socket).await? as u32,
+        1 => {
+            let low = read_u8(socket).await? as u32;
+            let high = read_u8(socket).await? as u32;
+            64 + low + high * 256
+        }
+        csid => csid as u32,
+    };
+    Ok((csid, fmt))
+}
+
+async fn read_u8(socket: &mut TcpStream) -> Result<u8> {
+    let mut buf = [0u8; 1];
+    socket.read_exact(&mut buf).await?;
+    Ok(buf[0])
+}
+
+async fn read_u24(socket: &mut TcpStream) -> Result<u32> {
+    let mut buf = [0u8; 3];
+    socket.read_exact(&mut buf).await?;
+    Ok(u32::from_be_bytes([0, buf[0], buf[1], buf[2]]))
+}
+
+async fn read_u32_le(socket: &mut TcpStream) -> Result<u32> {
+    let mut buf = [0u8; 4];
+    socket.read_exact(&mut buf).await?;
+    Ok(u32::from_le_bytes(buf))
+}
+
+/// Write one message as a single format-0 chunk followed by format-3
+/// continuation chunks, chunked to `DEFAULT_CHUNK_SIZE` - our replies are
+/// small enough this is never more than a couple of chunks
+async fn write_message(
+    socket: &mut TcpStream,
+    csid: u32,
+    message_type_id: u8,
+    message_stream_id: u32,
+    payload: &[u8],
+) -> Result<()> {
+    let mut header = Vec::with_capacity(12);
+    header.push(csid as u8 & 0x3f);
+    header.extend_from_slice(&0u32.to_be_bytes()[1..]);
+    header.extend_from_slice(&(payload.len() as u32).to_be_bytes()[1..]);
+    header.push(message_type_id);
+    header.extend_from_slice(&message_stream_id.to_le_bytes());
+    socket.write_all(&header).await?;
+
+    let mut offset = 0;
+    let mut first = true;
+    while offset < payload.len() {
+        if !first {
+            socket.write_all(&[0xc0 | (csid as u8 & 0x3f)]).await?;
+        }
+        let end = (offset + DEFAULT_CHUNK_SIZE).min(payload.len());
+        socket.write_all(&payload[offset..end]).await?;
+        offset = end;
+        first = false;
+    }
+    Ok(())
+}
+
+async fn reply_connect(socket: &mut TcpStream, transaction_id: f64) -> Result<()> {
+    let mut payload = Vec::new();
+    amf0::encode(&mut payload, &Amf0Value::String("_result".to_string()));
+    amf0::encode(&mut payload, &Amf0Value::Number(transaction_id));
+    amf0::encode(
+        &mut payload,
+        &Amf0Value::Object(vec![
+            ("fmsVer".to_string(), Amf0Value::String("FMS/3,0,1,123".to_string())),
+            ("capabilities".to_string(), Amf0Value::Number(31.0)),
+        ]),
+    );
+    amf0::encode(
+        &mut payload,
+        &Amf0Value::Object(vec![
+            ("level".to_string(), Amf0Value::String("status".to_string())),
+            ("code".to_string(), Amf0Value::String("NetConnection.Connect.Success".to_string())),
+            ("description".to_string(), Amf0Value::String("Connection succeeded.".to_string())),
+        ]),
+    );
+    write_message(socket, COMMAND_CHUNK_STREAM_ID, MESSAGE_TYPE_AMF0_COMMAND, 0, &payload).await
+}
+
+async fn reply_create_stream(socket: &mut TcpStream, transaction_id: f64) -> Result<()> {
+    let mut payload = Vec::new();
+    amf0::encode(&mut payload, &Amf0Value::String("_result".to_string()));
+    amf0::encode(&mut payload, &Amf0Value::Number(transaction_id));
+    amf0::encode(&mut payload, &Amf0Value::Null);
+    amf0::encode(&mut payload, &Amf0Value::Number(CREATED_STREAM_ID as f64));
+    write_message(socket, COMMAND_CHUNK_STREAM_ID, MESSAGE_TYPE_AMF0_COMMAND, 0, &payload).await
+}
+
+async fn reply_publish_start(socket: &mut TcpStream, stream_key: &str) -> Result<()> {
+    let mut payload = Vec::new();
+    amf0::encode(&mut payload, &Amf0Value::String("onStatus".to_string()));
+    amf0::encode(&mut payload, &Amf0Value::Number(0.0));
+    amf0::encode(&mut payload, &Amf0Value::Null);
+    amf0::encode(
+        &mut payload,
+        &Amf0Value::Object(vec![
+            ("level".to_string(), Amf0Value::String("status".to_string())),
+            ("code".to_string(), Amf0Value::String("NetStream.Publish.Start".to_string())),
+            ("description".to_string(), Amf0Value::String(format!("Publishing {}.", stream_key))),
+        ]),
+    );
+    write_message(socket, COMMAND_CHUNK_STREAM_ID, MESSAGE_TYPE_AMF0_COMMAND, CREATED_STREAM_ID, &payload).await
+}
+
+/// Split an AVCDecoderConfigurationRecord into its SPS/PPS parameter sets
+fn parse_avc_decoder_config(payload: &[u8]) -> Result<Vec<Bytes>> {
+    if payload.len() < 6 {
+        return Err(SfuError::Media("AVCDecoderConfigurationRecord is too short".to_string()).into());
+    }
+
+    let mut parameter_sets = Vec::new();
+    let mut offset = 5;
+    let num_sps = (payload[offset] & 0x1f) as usize;
+    offset += 1;
+    for _ in 0..num_sps {
+        let (set, consumed) = read_length_prefixed(payload, offset)?;
+        parameter_sets.push(set);
+        offset += consumed;
+    }
+
+    let num_pps = *payload
+        .get(offset)
+        .ok_or_else(|| SfuError::Media("truncated AVCDecoderConfigurationRecord".to_string()))? as usize;
+    offset += 1;
+    for _ in 0..num_pps {
+        let (set, consumed) = read_length_prefixed(payload, offset)?;
+        parameter_sets.push(set);
+        offset += consumed;
+    }
+
+    Ok(parameter_sets)
+}
+
+/// Read one 2-byte-length-prefixed parameter set, returning it along with
+/// how many bytes (length field included) it consumed
+fn read_length_prefixed(payload: &[u8], offset: usize) -> Result<(Bytes, usize)> {
+    let length_bytes = payload
+        .get(offset..offset + 2)
+        .ok_or_else(|| SfuError::Media("truncated parameter set length".to_string()))?;
+    let len = u16::from_be_bytes(length_bytes.try_into().unwrap()) as usize;
+    let set = payload
+        .get(offset + 2..offset + 2 + len)
+        .ok_or_else(|| SfuError::Media("truncated parameter set".to_string()))?;
+    Ok((Bytes::copy_from_slice(set), 2 + len))
+}
+
+/// Split AVCC length-prefixed NAL units into their raw byte slices
+fn avcc_nals(payload: &[u8]) -> Vec<&[u8]> {
+    let mut nals = Vec::new();
+    let mut offset = 0;
+    while offset + 4 <= payload.len() {
+        let len = u32::from_be_bytes(payload[offset..offset + 4].try_into().unwrap()) as usize;
+        offset += 4;
+        if offset + len > payload.len() {
+            break;
+        }
+        nals.push(&payload[offset..offset + len]);
+        offset += len;
+    }
+    nals
+}
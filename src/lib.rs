@@ -12,6 +12,11 @@ pub mod transport;
 pub mod signaling;
 pub mod feedback;
 pub mod simulcast;
+pub mod auth;
+pub mod recording;
+pub mod relay;
+pub mod ingest;
+pub mod clock;
 pub mod sfu;
 
 // Re-export commonly used types
@@ -39,6 +44,9 @@ pub mod error {
         #[error("Signaling error: {0}")]
         Signaling(String),
 
+        #[error("Unauthorized: {0}")]
+        Unauthorized(String),
+
         #[error("Iroh error: {0}")]
         Iroh(#[from] iroh::Error),
 
@@ -2,58 +2,247 @@
 //
 // This module implements codec-specific functionality for audio and video.
 
-use std::collections::HashMap;
+use std::{
+    collections::HashMap,
+    sync::OnceLock,
+};
 
 use anyhow::Result;
 
 use crate::SfuError;
 
 /// Codec type
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
 pub enum CodecType {
     /// Opus audio codec
     Opus,
+    /// VP8 video codec
+    VP8,
     /// VP9 video codec
     VP9,
     /// H.264 video codec
     H264,
     /// AV1 video codec
     AV1,
+    /// G.711 mu-law audio codec
+    PCMU,
+    /// G.711 a-law audio codec
+    PCMA,
+    /// AAC audio codec (MP4A-LATM framing)
+    AAC,
 }
 
 impl CodecType {
+    /// Every codec type this SFU knows about, in no particular preference order
+    pub const ALL: [CodecType; 8] = [
+        CodecType::Opus,
+        CodecType::VP8,
+        CodecType::VP9,
+        CodecType::H264,
+        CodecType::AV1,
+        CodecType::PCMU,
+        CodecType::PCMA,
+        CodecType::AAC,
+    ];
+
+    /// Capability descriptor for this codec type: which encoders, RTP
+    /// payloaders, and decoders this SFU has, and what output constraints
+    /// apply, computed once and cached for the lifetime of the process
+    pub fn descriptor(&self) -> &'static CodecDescriptor {
+        codec_registry().get(self).expect("every CodecType is in CodecType::ALL")
+    }
+
+    /// Whether this SFU can build an encoder-side `Codec` instance for this
+    /// type, e.g. to answer "can this participant receive AV1?" before
+    /// offering it during negotiation
+    pub fn has_encoder(&self) -> bool {
+        self.descriptor().has_encoder()
+    }
+
+    /// Whether this SFU can build a decoder-side `Codec` instance for this type
+    pub fn has_decoder(&self) -> bool {
+        self.descriptor().has_decoder()
+    }
+
     /// Get codec name
     pub fn name(&self) -> &'static str {
         match self {
             CodecType::Opus => "opus",
+            CodecType::VP8 => "VP8",
             CodecType::VP9 => "VP9",
             CodecType::H264 => "H264",
             CodecType::AV1 => "AV1",
+            CodecType::PCMU => "PCMU",
+            CodecType::PCMA => "PCMA",
+            CodecType::AAC => "AAC",
         }
     }
-    
+
     /// Check if codec is audio
     pub fn is_audio(&self) -> bool {
-        matches!(self, CodecType::Opus)
+        matches!(self, CodecType::Opus | CodecType::PCMU | CodecType::PCMA | CodecType::AAC)
     }
-    
+
     /// Check if codec is video
     pub fn is_video(&self) -> bool {
-        matches!(self, CodecType::VP9 | CodecType::H264 | CodecType::AV1)
+        matches!(self, CodecType::VP8 | CodecType::VP9 | CodecType::H264 | CodecType::AV1)
     }
-    
+
     /// Get codec from name
     pub fn from_name(name: &str) -> Option<Self> {
         match name.to_lowercase().as_str() {
             "opus" => Some(CodecType::Opus),
+            "vp8" => Some(CodecType::VP8),
             "vp9" => Some(CodecType::VP9),
             "h264" => Some(CodecType::H264),
             "av1" => Some(CodecType::AV1),
+            "pcmu" => Some(CodecType::PCMU),
+            "pcma" => Some(CodecType::PCMA),
+            "aac" => Some(CodecType::AAC),
             _ => None,
         }
     }
 }
 
+/// Capability constraints placed on a codec's output (resolution/bitrate/
+/// framerate ceilings), independent of any specific negotiated fmtp parameters
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct OutputFilter {
+    /// Maximum output width in pixels, if constrained
+    pub max_width: Option<u32>,
+    /// Maximum output height in pixels, if constrained
+    pub max_height: Option<u32>,
+    /// Maximum output bitrate in bps, if constrained
+    pub max_bitrate: Option<u32>,
+    /// Maximum output framerate, if constrained
+    pub max_framerate: Option<f32>,
+}
+
+/// Capability descriptor for a single codec type: what this SFU can do with
+/// it (build an encoder-side `Codec`, packetize it over RTP, build a
+/// decoder-side `Codec`) and any output constraints to respect
+#[derive(Debug, Clone)]
+pub struct CodecDescriptor {
+    /// Codec type this descriptor describes
+    pub codec_type: CodecType,
+    /// Named encoder implementations available for this codec
+    pub encoders: Vec<&'static str>,
+    /// Named RTP payloader implementations available (`media::rtp::make_payloader`)
+    pub payloaders: Vec<&'static str>,
+    /// Named decoder implementations available
+    pub decoders: Vec<&'static str>,
+    /// Output constraints applied when this codec is offered
+    pub output_filter: OutputFilter,
+}
+
+impl CodecDescriptor {
+    /// Build the descriptor for `codec_type` from this SFU's actual codec
+    /// and RTP packetization support
+    fn for_codec_type(codec_type: CodecType) -> Self {
+        // This SFU's `Codec` trait doesn't model encode/decode direction
+        // separately, so a codec this SFU can construct at all is usable
+        // both to offer (encode) and to accept (decode) that codec
+        let buildable = CodecFactory::create_codec(codec_type).is_ok();
+        let encoders = if buildable { vec!["default"] } else { Vec::new() };
+        let decoders = encoders.clone();
+
+        // RTP payloaders/depayloaders are registered for every codec except
+        // AV1, which this SFU does not yet packetize over RTP
+        let payloaders = if matches!(codec_type, CodecType::AV1) {
+            Vec::new()
+        } else {
+            vec!["default"]
+        };
+
+        Self {
+            codec_type,
+            encoders,
+            payloaders,
+            decoders,
+            output_filter: OutputFilter::default(),
+        }
+    }
+
+    /// Whether this SFU has at least one encoder implementation for the codec
+    pub fn has_encoder(&self) -> bool {
+        !self.encoders.is_empty()
+    }
+
+    /// Whether this SFU has at least one RTP payloader implementation for the codec
+    pub fn has_payloader(&self) -> bool {
+        !self.payloaders.is_empty()
+    }
+
+    /// Whether this SFU has at least one decoder implementation for the codec
+    pub fn has_decoder(&self) -> bool {
+        !self.decoders.is_empty()
+    }
+}
+
+/// Process-wide, lazily-computed registry of codec descriptors, one per
+/// `CodecType::ALL` entry
+fn codec_registry() -> &'static HashMap<CodecType, CodecDescriptor> {
+    static REGISTRY: OnceLock<HashMap<CodecType, CodecDescriptor>> = OnceLock::new();
+    REGISTRY.get_or_init(|| {
+        CodecType::ALL
+            .iter()
+            .map(|&codec_type| (codec_type, CodecDescriptor::for_codec_type(codec_type)))
+            .collect()
+    })
+}
+
+/// Video codecs in descending negotiation preference
+const VIDEO_CODEC_PREFERENCE: [CodecType; 4] = [CodecType::AV1, CodecType::VP9, CodecType::H264, CodecType::VP8];
+/// Audio codecs in descending negotiation preference
+const AUDIO_CODEC_PREFERENCE: [CodecType; 4] = [CodecType::Opus, CodecType::AAC, CodecType::PCMU, CodecType::PCMA];
+
+/// Codec resulting from negotiating a local capability set against a remote one
+#[derive(Debug, Clone)]
+pub struct NegotiatedCodec {
+    /// Codec type both sides support
+    pub codec_type: CodecType,
+    /// Default fmtp-style parameters for the negotiated codec
+    pub parameters: HashMap<String, String>,
+}
+
+/// Intersect `local` and `remote` codec capability sets and return the
+/// codecs both sides support and can actually encode/decode, in descending
+/// preference order (AV1 > VP9 > H264 > VP8 for video; Opus > AAC > PCMU >
+/// PCMA for audio), each carrying the codec's default fmtp parameters;
+/// the remote side's fmtp parameters are not consulted
+pub fn negotiate(local: &[CodecType], remote: &[CodecType]) -> Vec<NegotiatedCodec> {
+    let mut common: Vec<CodecType> = local
+        .iter()
+        .copied()
+        .filter(|codec_type| remote.contains(codec_type))
+        .filter(|codec_type| codec_type.has_encoder() && codec_type.has_decoder())
+        .collect();
+
+    common.sort_by_key(|codec_type| codec_preference_rank(*codec_type));
+    common.dedup();
+
+    common
+        .into_iter()
+        .map(|codec_type| NegotiatedCodec {
+            codec_type,
+            parameters: CodecFactory::create_codec(codec_type)
+                .map(|codec| codec.parameters())
+                .unwrap_or_default(),
+        })
+        .collect()
+}
+
+/// Negotiation preference rank for a codec type; lower sorts first
+fn codec_preference_rank(codec_type: CodecType) -> usize {
+    if let Some(pos) = VIDEO_CODEC_PREFERENCE.iter().position(|&c| c == codec_type) {
+        return pos;
+    }
+    if let Some(pos) = AUDIO_CODEC_PREFERENCE.iter().position(|&c| c == codec_type) {
+        return VIDEO_CODEC_PREFERENCE.len() + pos;
+    }
+    VIDEO_CODEC_PREFERENCE.len() + AUDIO_CODEC_PREFERENCE.len()
+}
+
 /// Codec trait
 pub trait Codec: Send + Sync {
     /// Get codec type
@@ -194,6 +383,155 @@ impl Codec for VP9Codec {
     }
 }
 
+/// VP8 video codec
+pub struct VP8Codec {
+    /// Support for simulcast
+    pub simulcast: bool,
+}
+
+impl VP8Codec {
+    /// Create a new VP8 codec with default parameters
+    pub fn new() -> Self {
+        Self { simulcast: true }
+    }
+
+    /// Create a new VP8 codec with custom parameters
+    pub fn with_params(simulcast: bool) -> Self {
+        Self { simulcast }
+    }
+}
+
+impl Codec for VP8Codec {
+    fn codec_type(&self) -> CodecType {
+        CodecType::VP8
+    }
+
+    fn parameters(&self) -> HashMap<String, String> {
+        let mut params = HashMap::new();
+        params.insert("simulcast".to_string(), if self.simulcast { "1" } else { "0" }.to_string());
+        params
+    }
+
+    fn is_compatible_with(&self, other: &dyn Codec) -> bool {
+        other.codec_type() == CodecType::VP8
+    }
+}
+
+/// H.264 video codec
+pub struct H264Codec {
+    /// Packetization mode (RFC 6184 `packetization-mode` fmtp parameter)
+    pub packetization_mode: u8,
+    /// Profile-level-id (RFC 6184 `profile-level-id` fmtp parameter)
+    pub profile_level_id: String,
+}
+
+impl H264Codec {
+    /// Create a new H.264 codec with default parameters
+    pub fn new() -> Self {
+        Self {
+            packetization_mode: 1,
+            profile_level_id: "42e01f".to_string(),
+        }
+    }
+
+    /// Create a new H.264 codec with custom parameters
+    pub fn with_params(packetization_mode: u8, profile_level_id: String) -> Self {
+        Self {
+            packetization_mode,
+            profile_level_id,
+        }
+    }
+}
+
+impl Codec for H264Codec {
+    fn codec_type(&self) -> CodecType {
+        CodecType::H264
+    }
+
+    fn parameters(&self) -> HashMap<String, String> {
+        let mut params = HashMap::new();
+        params.insert("packetization-mode".to_string(), self.packetization_mode.to_string());
+        params.insert("profile-level-id".to_string(), self.profile_level_id.clone());
+        params
+    }
+
+    fn is_compatible_with(&self, other: &dyn Codec) -> bool {
+        if other.codec_type() != CodecType::H264 {
+            return false;
+        }
+
+        // H.264 codecs are compatible if they share a packetization mode and
+        // profile-level-id, since receivers can't decode an arbitrary profile
+        let other_params = other.parameters();
+
+        if let Some(mode) = other_params.get("packetization-mode") {
+            if mode != &self.packetization_mode.to_string() {
+                return false;
+            }
+        }
+
+        if let Some(profile_level_id) = other_params.get("profile-level-id") {
+            if profile_level_id != &self.profile_level_id {
+                return false;
+            }
+        }
+
+        true
+    }
+}
+
+/// AV1 video codec
+pub struct AV1Codec {
+    /// AV1 profile (0 = Main, 1 = High, 2 = Professional)
+    pub profile: u8,
+    /// Support for simulcast
+    pub simulcast: bool,
+}
+
+impl AV1Codec {
+    /// Create a new AV1 codec with default parameters
+    pub fn new() -> Self {
+        Self {
+            profile: 0,
+            simulcast: true,
+        }
+    }
+
+    /// Create a new AV1 codec with custom parameters
+    pub fn with_params(profile: u8, simulcast: bool) -> Self {
+        Self { profile, simulcast }
+    }
+}
+
+impl Codec for AV1Codec {
+    fn codec_type(&self) -> CodecType {
+        CodecType::AV1
+    }
+
+    fn parameters(&self) -> HashMap<String, String> {
+        let mut params = HashMap::new();
+        params.insert("profile".to_string(), self.profile.to_string());
+        params.insert("simulcast".to_string(), if self.simulcast { "1" } else { "0" }.to_string());
+        params
+    }
+
+    fn is_compatible_with(&self, other: &dyn Codec) -> bool {
+        if other.codec_type() != CodecType::AV1 {
+            return false;
+        }
+
+        let other_params = other.parameters();
+
+        if let Some(profile) = other_params.get("profile") {
+            if profile != &self.profile.to_string() {
+                return false;
+            }
+        }
+
+        true
+    }
+}
+
 /// Codec factory
 pub struct CodecFactory;
 
@@ -202,7 +540,10 @@ impl CodecFactory {
     pub fn create_codec(codec_type: CodecType) -> Result<Box<dyn Codec>> {
         match codec_type {
             CodecType::Opus => Ok(Box::new(OpusCodec::new())),
+            CodecType::VP8 => Ok(Box::new(VP8Codec::new())),
             CodecType::VP9 => Ok(Box::new(VP9Codec::new())),
+            CodecType::H264 => Ok(Box::new(H264Codec::new())),
+            CodecType::AV1 => Ok(Box::new(AV1Codec::new())),
             _ => Err(SfuError::Media(format!("Unsupported codec: {:?}", codec_type)).into()),
         }
     }
@@ -246,14 +587,48 @@ impl CodecFactory {
                     .get("profile-id")
                     .map(|s| s.parse::<u8>().unwrap_or(0))
                     .unwrap_or(0);
-                
+
                 let simulcast = params
                     .get("simulcast")
                     .map(|s| s == "1")
                     .unwrap_or(true);
-                
+
                 Ok(Box::new(VP9Codec::with_params(profile_id, simulcast)))
             }
+            CodecType::VP8 => {
+                let simulcast = params
+                    .get("simulcast")
+                    .map(|s| s == "1")
+                    .unwrap_or(true);
+
+                Ok(Box::new(VP8Codec::with_params(simulcast)))
+            }
+            CodecType::H264 => {
+                let packetization_mode = params
+                    .get("packetization-mode")
+                    .map(|s| s.parse::<u8>().unwrap_or(1))
+                    .unwrap_or(1);
+
+                let profile_level_id = params
+                    .get("profile-level-id")
+                    .cloned()
+                    .unwrap_or_else(|| "42e01f".to_string());
+
+                Ok(Box::new(H264Codec::with_params(packetization_mode, profile_level_id)))
+            }
+            CodecType::AV1 => {
+                let profile = params
+                    .get("profile")
+                    .map(|s| s.parse::<u8>().unwrap_or(0))
+                    .unwrap_or(0);
+
+                let simulcast = params
+                    .get("simulcast")
+                    .map(|s| s == "1")
+                    .unwrap_or(true);
+
+                Ok(Box::new(AV1Codec::with_params(profile, simulcast)))
+            }
             _ => Err(SfuError::Media(format!("Unsupported codec: {:?}", codec_type)).into()),
         }
     }
@@ -2,13 +2,19 @@
 //
 // This module handles media frame processing for audio and video.
 
-use std::time::Duration;
+use std::{
+    collections::{BTreeMap, HashSet},
+    time::{Duration, Instant},
+};
 
 use anyhow::Result;
 use bytes::Bytes;
 
 use crate::{
-    media::codec::{Codec, CodecType},
+    media::{
+        codec::{Codec, CodecType},
+        vpx::{LayerInfo, Vp8Descriptor, Vp9Descriptor},
+    },
     SfuError,
 };
 
@@ -24,6 +30,7 @@ pub enum FrameType {
 }
 
 /// Media frame
+#[derive(Debug, Clone)]
 pub struct MediaFrame {
     /// Frame type
     pub frame_type: FrameType,
@@ -39,6 +46,9 @@ pub struct MediaFrame {
     pub spatial_layer: Option<u8>,
     /// Temporal layer index (for simulcast)
     pub temporal_layer: Option<u8>,
+    /// Monotonically increasing frame sequence number, when assigned by the
+    /// sender, used by `FrameQueue` to detect gaps between frames
+    pub sequence_number: Option<u32>,
 }
 
 impl MediaFrame {
@@ -56,9 +66,10 @@ impl MediaFrame {
             duration,
             spatial_layer: None,
             temporal_layer: None,
+            sequence_number: None,
         })
     }
-    
+
     /// Create a new video key frame
     pub fn new_video_key(
         codec_type: CodecType,
@@ -80,9 +91,10 @@ impl MediaFrame {
             duration,
             spatial_layer,
             temporal_layer,
+            sequence_number: None,
         })
     }
-    
+
     /// Create a new video delta frame
     pub fn new_video_delta(
         codec_type: CodecType,
@@ -104,9 +116,15 @@ impl MediaFrame {
             duration,
             spatial_layer,
             temporal_layer,
+            sequence_number: None,
         })
     }
-    
+
+    /// Set this frame's sequence number
+    pub fn set_sequence_number(&mut self, sequence_number: u32) {
+        self.sequence_number = Some(sequence_number);
+    }
+
     /// Check if this is an audio frame
     pub fn is_audio(&self) -> bool {
         self.frame_type == FrameType::Audio
@@ -128,32 +146,77 @@ impl MediaFrame {
     }
 }
 
+/// Picture ID, layer indices, and frame-boundary/keyframe flags extracted
+/// from a VP8 or VP9 payload descriptor, so the router/simulcast layers can
+/// make forwarding decisions without re-parsing the descriptor themselves
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FrameDescriptor {
+    /// Picture ID, when carried by the descriptor
+    pub picture_id: Option<u16>,
+    /// Spatial/temporal layer indices, when carried by the descriptor
+    pub layer: Option<LayerInfo>,
+    /// Whether this packet starts a new frame
+    pub start_of_frame: bool,
+    /// Whether this packet belongs to a key frame
+    pub is_key_frame: bool,
+}
+
 /// Media frame processor
 pub struct FrameProcessor {
     /// Codec for processing frames
     codec: Box<dyn Codec>,
+    /// Set after a key frame has been requested for this track; while set,
+    /// `should_forward` suppresses delta frames until the requested key
+    /// frame arrives
+    awaiting_keyframe: bool,
 }
 
 impl FrameProcessor {
     /// Create a new frame processor
     pub fn new(codec: Box<dyn Codec>) -> Self {
-        Self { codec }
+        Self {
+            codec,
+            awaiting_keyframe: false,
+        }
     }
-    
+
+    /// Mark this track as awaiting a requested key frame, so `should_forward`
+    /// suppresses delta frames until one arrives
+    pub fn request_keyframe(&mut self) {
+        self.awaiting_keyframe = true;
+    }
+
+    /// Whether `frame` should be forwarded to the receiver: always true for
+    /// audio or key frames, and true for delta frames only once the key frame
+    /// requested via `request_keyframe` has arrived
+    pub fn should_forward(&mut self, frame: &MediaFrame) -> bool {
+        if !frame.is_video() || frame.is_key_frame() {
+            self.awaiting_keyframe = false;
+            return true;
+        }
+
+        !self.awaiting_keyframe
+    }
+
     /// Process a media frame
     pub fn process_frame(&self, frame: &mut MediaFrame) -> Result<()> {
-        // This is a placeholder for frame processing logic
-        // In a real implementation, this would apply transformations based on the codec
-        
         match self.codec.codec_type() {
             CodecType::Opus => {
                 // Process Opus audio frame
                 // For example, adjust volume, apply filters, etc.
                 Ok(())
             }
-            CodecType::VP9 => {
-                // Process VP9 video frame
-                // For example, scale resolution, adjust quality, etc.
+            CodecType::VP9 | CodecType::VP8 => {
+                let descriptor = Self::parse_descriptor(self.codec.codec_type(), &frame.data)?;
+
+                frame.frame_type = if descriptor.is_key_frame {
+                    FrameType::VideoKey
+                } else {
+                    FrameType::VideoDelta
+                };
+                frame.spatial_layer = descriptor.layer.map(|layer| layer.spatial_id);
+                frame.temporal_layer = descriptor.layer.map(|layer| layer.temporal_id);
+
                 Ok(())
             }
             _ => {
@@ -161,65 +224,216 @@ impl FrameProcessor {
             }
         }
     }
-    
+
+    /// Parse the VP8/VP9 payload descriptor at the front of `data`
+    pub fn parse_descriptor(codec_type: CodecType, data: &[u8]) -> Result<FrameDescriptor> {
+        match codec_type {
+            CodecType::VP9 => {
+                let (descriptor, _offset) = Vp9Descriptor::parse(data)?;
+
+                Ok(FrameDescriptor {
+                    picture_id: descriptor.picture_id,
+                    layer: descriptor.layer,
+                    start_of_frame: descriptor.start_of_frame,
+                    // A VP9 frame is a key frame when it starts a new frame and
+                    // is not inter-picture predicted
+                    is_key_frame: descriptor.start_of_frame && !descriptor.inter_picture_predicted,
+                })
+            }
+            CodecType::VP8 => {
+                let (descriptor, offset) = Vp8Descriptor::parse(data)?;
+                let first_payload_byte = *data
+                    .get(offset)
+                    .ok_or_else(|| SfuError::Media("VP8 payload missing after descriptor".to_string()))?;
+
+                Ok(FrameDescriptor {
+                    picture_id: descriptor.picture_id,
+                    layer: descriptor.temporal_id.map(|(temporal_id, _)| LayerInfo {
+                        temporal_id,
+                        spatial_id: 0,
+                    }),
+                    start_of_frame: descriptor.start_of_partition && descriptor.partition_index == 0,
+                    // A VP8 key frame starts partition 0 and clears the P bit
+                    // (bit 0) of the first payload byte of that partition
+                    is_key_frame: descriptor.start_of_partition
+                        && descriptor.partition_index == 0
+                        && (first_payload_byte & 0x01) == 0,
+                })
+            }
+            _ => Err(SfuError::Media(format!("No payload descriptor for codec {:?}", codec_type)).into()),
+        }
+    }
+
     /// Get the codec used by this processor
     pub fn codec(&self) -> &dyn Codec {
         self.codec.as_ref()
     }
 }
 
-/// Media frame queue
+/// Returns `a - b` interpreted as a signed 32-bit value, so `a` is considered
+/// "after" `b` when the result is positive, tolerating 32-bit timestamp wraparound
+fn ts_diff(a: u32, b: u32) -> i32 {
+    a.wrapping_sub(b) as i32
+}
+
+/// Smoothing weight applied to each new jitter sample (RFC 3550 uses 1/16)
+const JITTER_EWMA_WEIGHT: f64 = 1.0 / 16.0;
+/// Target delay is this many multiples of the estimated jitter
+const TARGET_DELAY_JITTER_MULTIPLE: f64 = 4.0;
+/// Lower bound the adaptive target delay is clamped to
+const MIN_TARGET_DELAY: Duration = Duration::from_millis(20);
+/// Upper bound the adaptive target delay is clamped to
+const MAX_TARGET_DELAY: Duration = Duration::from_millis(500);
+
+/// A frame held in the jitter buffer, paired with its arrival time so its
+/// playout deadline can be computed against the adaptive target delay
+struct BufferedFrame {
+    frame: MediaFrame,
+    arrival: Instant,
+}
+
+/// Adaptive jitter buffer for reordering and pacing decoded media frames
+///
+/// Frames are held keyed by media `timestamp` and released once either the
+/// next frame in sequence arrives or the frame's playout deadline (arrival
+/// time plus the adaptive `target_delay`) passes. The target delay is an EWMA
+/// of observed inter-arrival jitter, in the same spirit as the RFC 3550
+/// interarrival jitter estimate used for RTCP reports. A key frame flushes
+/// the reordering window so playout can resume immediately after loss.
 pub struct FrameQueue {
-    /// Maximum queue size
+    /// Maximum number of frames to hold before forcing the oldest one out
     max_size: usize,
-    /// Frames in the queue
-    frames: Vec<MediaFrame>,
+    /// Clock rate (Hz) of the media timebase frame timestamps are expressed in
+    clock_rate: u32,
+    /// Buffered frames, keyed by media timestamp
+    frames: BTreeMap<u32, BufferedFrame>,
+    /// Next frame sequence number expected to be released, when frames carry one
+    next_sequence: Option<u32>,
+    /// Timestamp and arrival time of the last frame pushed, for the jitter estimate
+    last_arrival: Option<(u32, Instant)>,
+    /// EWMA of observed inter-arrival jitter, in media timebase ticks
+    jitter: f64,
 }
 
 impl FrameQueue {
-    /// Create a new frame queue
-    pub fn new(max_size: usize) -> Self {
+    /// Create a new frame queue buffering frames clocked at `clock_rate` Hz
+    pub fn new(max_size: usize, clock_rate: u32) -> Self {
         Self {
             max_size,
-            frames: Vec::with_capacity(max_size),
+            clock_rate,
+            frames: BTreeMap::new(),
+            next_sequence: None,
+            last_arrival: None,
+            jitter: 0.0,
         }
     }
-    
+
     /// Add a frame to the queue
+    ///
+    /// A key frame drops any older frames still buffered, since the decoder
+    /// can resume cleanly from it without waiting on frames lost before it.
     pub fn push(&mut self, frame: MediaFrame) -> Result<()> {
+        let now = Instant::now();
+        self.update_jitter(frame.timestamp, now);
+
+        if frame.is_key_frame() {
+            self.frames.retain(|&timestamp, _| ts_diff(timestamp, frame.timestamp) >= 0);
+            self.next_sequence = frame.sequence_number;
+        } else if self.next_sequence.is_none() {
+            self.next_sequence = frame.sequence_number;
+        }
+
         if self.frames.len() >= self.max_size {
-            // Remove oldest frame if queue is full
-            self.frames.remove(0);
+            if let Some(&oldest) = self.frames.keys().next() {
+                self.frames.remove(&oldest);
+            }
         }
-        
-        self.frames.push(frame);
+
+        self.frames.insert(frame.timestamp, BufferedFrame { frame, arrival: now });
         Ok(())
     }
-    
-    /// Get the next frame from the queue
+
+    /// Update the EWMA jitter estimate from this frame's arrival, following
+    /// the RFC 3550 interarrival jitter formula adapted to frame granularity
+    fn update_jitter(&mut self, timestamp: u32, arrival: Instant) {
+        if let Some((last_timestamp, last_arrival)) = self.last_arrival {
+            let arrival_delta_ticks = arrival.duration_since(last_arrival).as_secs_f64() * self.clock_rate as f64;
+            let timestamp_delta_ticks = ts_diff(timestamp, last_timestamp) as f64;
+            let d = (arrival_delta_ticks - timestamp_delta_ticks).abs();
+            self.jitter += (d - self.jitter) * JITTER_EWMA_WEIGHT;
+        }
+        self.last_arrival = Some((timestamp, arrival));
+    }
+
+    /// Adaptive delay a frame is held for before being released on its
+    /// playout deadline, derived from the current jitter estimate so the
+    /// buffer depth can be tuned against the congestion-control target
+    pub fn target_delay(&self) -> Duration {
+        let jitter_secs = self.jitter / self.clock_rate as f64;
+        Duration::from_secs_f64(jitter_secs * TARGET_DELAY_JITTER_MULTIPLE).clamp(MIN_TARGET_DELAY, MAX_TARGET_DELAY)
+    }
+
+    /// Remove and return the next frame ready for playout, if any
+    ///
+    /// A frame is ready once it is the next expected in sequence, or once its
+    /// playout deadline (arrival time plus `target_delay`) has passed.
     pub fn pop(&mut self) -> Option<MediaFrame> {
-        if self.frames.is_empty() {
-            None
-        } else {
-            Some(self.frames.remove(0))
+        let (&timestamp, buffered) = self.frames.iter().next()?;
+
+        let next_in_sequence = matches!(
+            (self.next_sequence, buffered.frame.sequence_number),
+            (Some(expected), Some(actual)) if expected == actual
+        );
+        let deadline_passed = buffered.arrival.elapsed() >= self.target_delay();
+
+        if !next_in_sequence && !deadline_passed {
+            return None;
         }
+
+        let buffered = self.frames.remove(&timestamp)?;
+        if let Some(sequence_number) = buffered.frame.sequence_number {
+            self.next_sequence = Some(sequence_number.wrapping_add(1));
+        }
+        Some(buffered.frame)
     }
-    
+
     /// Peek at the next frame without removing it
     pub fn peek(&self) -> Option<&MediaFrame> {
-        self.frames.first()
+        self.frames.values().next().map(|buffered| &buffered.frame)
     }
-    
+
+    /// Sequence numbers currently known missing (awaited but not yet
+    /// buffered), so the connection layer can issue retransmission/keyframe
+    /// requests
+    ///
+    /// Only meaningful once frames carry a `sequence_number`; returns an
+    /// empty list otherwise.
+    pub fn missing_sequences(&self) -> Vec<u32> {
+        let Some(next) = self.next_sequence else {
+            return Vec::new();
+        };
+        let present: HashSet<u32> = self
+            .frames
+            .values()
+            .filter_map(|buffered| buffered.frame.sequence_number)
+            .collect();
+        let Some(&highest) = present.iter().max() else {
+            return Vec::new();
+        };
+
+        (next..=highest).filter(|seq| !present.contains(seq)).collect()
+    }
+
     /// Get the number of frames in the queue
     pub fn len(&self) -> usize {
         self.frames.len()
     }
-    
+
     /// Check if the queue is empty
     pub fn is_empty(&self) -> bool {
         self.frames.is_empty()
     }
-    
+
     /// Clear the queue
     pub fn clear(&mut self) {
         self.frames.clear();
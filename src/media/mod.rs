@@ -4,23 +4,37 @@
 
 pub mod codec;
 pub mod rtp;
+pub mod rtcp;
 pub mod frame;
+pub mod scalability;
+pub mod trace;
+pub mod vpx;
 
 use std::{
     collections::{HashMap, HashSet},
-    sync::Arc,
+    sync::{atomic::Ordering, Arc},
     time::Instant,
 };
 
 use anyhow::Result;
 use async_trait::async_trait;
-use tokio::sync::RwLock;
+use iroh::NodeId;
+use tokio::sync::{mpsc, RwLock};
 
 use crate::{
+    bandwidth::{BandwidthManager, DefaultBandwidthManager},
+    connection::ConnectionManager,
+    relay::Broker,
     session::{SessionId, SessionManager},
+    simulcast::SimulcastManager,
+    transport::TransportSession,
     SfuError,
 };
 
+pub use scalability::ScalabilityMode;
+pub use trace::{TraceDirection, TraceEvent, TraceEventKind};
+use trace::TraceSubscription;
+
 /// Unique identifier for a media track
 pub type TrackId = u64;
 
@@ -33,6 +47,16 @@ pub enum TrackKind {
     Video,
 }
 
+impl TrackKind {
+    /// Stable lowercase name, used as a `Grants::publish_caps` key
+    pub fn name(&self) -> &'static str {
+        match self {
+            TrackKind::Audio => "audio",
+            TrackKind::Video => "video",
+        }
+    }
+}
+
 /// Media track
 pub struct MediaTrack {
     // Implementation details will be added later
@@ -56,6 +80,15 @@ impl MediaTrack {
         // Placeholder implementation
         HashMap::new()
     }
+
+    /// Parse the declared spatial/temporal layer structure from this
+    /// track's `scalability-mode` codec parameter (e.g. `L3T3`), if the
+    /// publisher declared one
+    pub fn scalability_mode(&self) -> Option<ScalabilityMode> {
+        self.codec_parameters()
+            .get("scalability-mode")
+            .and_then(|mode| ScalabilityMode::parse(mode))
+    }
 }
 
 /// Media packet with routing information
@@ -68,8 +101,17 @@ pub struct RoutableMediaPacket {
     pub packet: Vec<u8>,
     /// Packet priority
     pub priority: PacketPriority,
-    /// Packet timestamp
+    /// Time this packet arrived at the router from its publisher
     pub timestamp: Instant,
+    /// Time the publisher sent this packet, used as the departure timestamp
+    /// fed into delay-based congestion control alongside `timestamp`
+    pub send_timestamp: Instant,
+    /// Simulcast spatial layer this packet belongs to, if the publisher's
+    /// encoding pipeline tagged it; `None` for non-simulcast tracks
+    pub spatial_id: Option<u8>,
+    /// Simulcast temporal layer this packet belongs to, if the publisher's
+    /// encoding pipeline tagged it; `None` for non-simulcast tracks
+    pub temporal_id: Option<u8>,
 }
 
 /// Priority levels for media packets
@@ -85,10 +127,22 @@ pub enum PacketPriority {
     Low,
 }
 
+/// Where a track registered with the router originates
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TrackOrigin {
+    /// Published by a session connected directly to this node
+    Local,
+    /// Pulled from a remote node via the inter-node relay broker
+    Relayed(NodeId),
+}
+
 /// Forwarding decision for a media packet
 pub struct ForwardingDecision {
     /// Target subscriber session identifiers
     pub target_subscribers: Vec<SessionId>,
+    /// Peer nodes relaying this (locally-published) track onward to their
+    /// own local subscribers, per the broker's `remote_subscribers`
+    pub relay_targets: Vec<NodeId>,
     /// Whether to adapt the packet before forwarding
     pub adapt: bool,
     /// Adaptation parameters if needed
@@ -120,6 +174,26 @@ pub trait MediaRouter: Send + Sync {
 
     /// Unregister a track
     async fn unregister_track(&self, publisher_id: SessionId, track_id: TrackId) -> Result<()>;
+
+    /// Announce a locally-published track to peer SFU nodes via the relay
+    /// broker, making it available for another node's `subscribe_remote`
+    async fn announce_track(&self, track_id: TrackId, publisher_id: SessionId) -> Result<()>;
+
+    /// Subscribe to structured trace events for `track_id` (packet forwarded/
+    /// dropped, key frame seen, PLI sent, layer switched, bitrate update),
+    /// limited to `events` kinds, delivered on a bounded channel so tooling
+    /// can observe forwarding decisions at runtime without recompiling.
+    /// Replaces any existing subscription for the track.
+    async fn enable_trace(&self, track_id: TrackId, events: Vec<TraceEventKind>) -> Result<mpsc::Receiver<TraceEvent>>;
+
+    /// Remove `track_id`'s trace subscription, if any
+    async fn disable_trace(&self, track_id: TrackId);
+
+    /// Subscribe `subscriber` to a track published on a remote node, pulling
+    /// it through the relay broker and registering it here as a relayed
+    /// track so `get_forwarding_decision` routes to `subscriber` once the
+    /// broker starts forwarding its packets
+    async fn subscribe_remote(&self, node_id: NodeId, remote_track_id: TrackId, subscriber: SessionId) -> Result<()>;
 }
 
 /// Default implementation of the media router
@@ -128,6 +202,24 @@ pub struct DefaultMediaRouter {
     session_manager: Arc<dyn SessionManager>,
     /// Track registry
     tracks: Arc<RwLock<HashMap<TrackId, TrackInfo>>>,
+    /// Delay-based congestion controller fed one packet group per processed
+    /// packet, keyed by publisher session
+    bandwidth_manager: Arc<dyn BandwidthManager>,
+    /// Inter-node relay broker, attached via `set_relay` once available;
+    /// `None` means this node only forwards to local subscribers
+    broker: RwLock<Option<Arc<dyn Broker>>>,
+    /// Used to open a `TransportSession` to a relay target the first time a
+    /// packet needs forwarding to it
+    connection_manager: RwLock<Option<Arc<dyn ConnectionManager>>>,
+    /// Transport sessions opened to relay targets, keyed by peer node
+    relay_sessions: Arc<RwLock<HashMap<NodeId, Arc<TransportSession>>>>,
+    /// Per-subscriber simulcast layer allocation, attached via
+    /// `set_simulcast_manager` once available; `None` means every packet is
+    /// forwarded to every subscriber regardless of bandwidth
+    simulcast_manager: RwLock<Option<Arc<dyn SimulcastManager>>>,
+    /// Opt-in per-track trace subscriptions, attached via `enable_trace`;
+    /// a track with no entry here is traced at zero cost beyond the lookup
+    traces: RwLock<HashMap<TrackId, TraceSubscription>>,
 }
 
 /// Track information
@@ -140,24 +232,231 @@ struct TrackInfo {
     kind: TrackKind,
     /// Subscribers
     subscribers: HashSet<SessionId>,
+    /// Whether this track was published locally or pulled from a remote node
+    origin: TrackOrigin,
 }
 
 impl DefaultMediaRouter {
-    /// Create a new media router
+    /// Create a new media router with its own, session-local bandwidth manager
     pub fn new(session_manager: Arc<dyn SessionManager>) -> Self {
+        Self::with_bandwidth_manager(session_manager, Arc::new(DefaultBandwidthManager::new()))
+    }
+
+    /// Create a new media router feeding per-packet arrival feedback into
+    /// `bandwidth_manager`, shared with the feedback/signaling paths so loss-
+    /// and delay-based estimates for a session stay consistent across them
+    pub fn with_bandwidth_manager(session_manager: Arc<dyn SessionManager>, bandwidth_manager: Arc<dyn BandwidthManager>) -> Self {
         Self {
             session_manager,
             tracks: Arc::new(RwLock::new(HashMap::new())),
+            bandwidth_manager,
+            broker: RwLock::new(None),
+            connection_manager: RwLock::new(None),
+            relay_sessions: Arc::new(RwLock::new(HashMap::new())),
+            simulcast_manager: RwLock::new(None),
+            traces: RwLock::new(HashMap::new()),
         }
     }
+
+    /// Attach the inter-node relay broker and the connection manager used to
+    /// open transport sessions to relay targets. Without this, the router
+    /// only ever forwards to local subscribers and `announce_track`/
+    /// `subscribe_remote` fail.
+    pub async fn set_relay(&self, broker: Arc<dyn Broker>, connection_manager: Arc<dyn ConnectionManager>) {
+        *self.broker.write().await = Some(broker);
+        *self.connection_manager.write().await = Some(connection_manager);
+    }
+
+    /// Attach the simulcast manager used to water-fill each subscriber's
+    /// layer against its bandwidth estimate. Without this, every subscriber
+    /// gets every packet regardless of its own available bandwidth.
+    pub async fn set_simulcast_manager(&self, simulcast_manager: Arc<dyn SimulcastManager>) {
+        *self.simulcast_manager.write().await = Some(simulcast_manager);
+    }
+
+    /// Emit a trace event for `track_id` if it has an active subscription
+    /// that opted into `kind`
+    async fn trace(
+        &self,
+        track_id: TrackId,
+        kind: TraceEventKind,
+        direction: TraceDirection,
+        ssrc: Option<u32>,
+        sequence_number: Option<u16>,
+        session_id: Option<SessionId>,
+    ) {
+        let traces = self.traces.read().await;
+        let Some(subscription) = traces.get(&track_id) else {
+            return;
+        };
+
+        subscription.emit(TraceEvent {
+            track_id,
+            kind,
+            direction,
+            timestamp: Instant::now(),
+            ssrc,
+            sequence_number,
+            session_id,
+        });
+    }
+
+    /// Get or open the transport session used to forward packets to `node_id`
+    async fn relay_session(&self, node_id: NodeId) -> Result<Arc<TransportSession>> {
+        if let Some(session) = self.relay_sessions.read().await.get(&node_id) {
+            return Ok(session.clone());
+        }
+
+        let connection_manager = self.connection_manager.read().await.clone().ok_or_else(|| {
+            SfuError::Media("no connection manager configured for relay forwarding".to_string())
+        })?;
+        let connection = connection_manager.connect(iroh::NodeAddr::from(node_id)).await?;
+        let session = Arc::new(TransportSession::new(connection));
+
+        self.relay_sessions.write().await.insert(node_id, session.clone());
+        Ok(session)
+    }
+
+    /// Water-fill `subscribers` of a video track against each one's latest
+    /// bandwidth estimate, dropping this packet for a subscriber whose
+    /// allocated layer doesn't match it. Returns the filtered subscriber
+    /// list plus an `AdaptationParams` reflecting the most bandwidth-
+    /// constrained subscriber, for callers that want a single summary value.
+    async fn adapt_subscribers(
+        &self,
+        packet: &RoutableMediaPacket,
+        subscribers: Vec<SessionId>,
+    ) -> (Vec<SessionId>, bool, Option<AdaptationParams>) {
+        let Some(simulcast_manager) = self.simulcast_manager.read().await.clone() else {
+            return (subscribers, false, None);
+        };
+
+        let mut forwarded = Vec::with_capacity(subscribers.len());
+        let mut bottleneck_bitrate: Option<u32> = None;
+        let mut any_audio_only = false;
+        let mut any_switched = false;
+
+        for subscriber_id in subscribers {
+            let available_bandwidth = self
+                .bandwidth_manager
+                .get_recommended_bitrate(subscriber_id, packet.track_id)
+                .await
+                .unwrap_or(u32::MAX);
+
+            let allocation = match simulcast_manager
+                .allocate_subscriber_layer(packet.track_id, subscriber_id, available_bandwidth)
+                .await
+            {
+                Ok(allocation) => allocation,
+                // Not registered with the simulcast manager (e.g. no
+                // simulcast layers reported for this track yet); forward
+                // unconditionally rather than penalize it
+                Err(_) => {
+                    forwarded.push(subscriber_id);
+                    continue;
+                }
+            };
+
+            if let Some(message) = allocation.switch {
+                any_switched = true;
+                // This is a placeholder - actual implementation would push
+                // `message` over the subscriber's feedback channel; there is
+                // no registry mapping a subscriber session back to its
+                // `FeedbackChannel` from the media router yet
+                tracing::info!(
+                    "Layer switch for subscriber {} on track {}: {:?}",
+                    subscriber_id,
+                    packet.track_id,
+                    message
+                );
+                self.trace(
+                    packet.track_id,
+                    TraceEventKind::LayerChange,
+                    TraceDirection::Outbound,
+                    None,
+                    None,
+                    Some(subscriber_id),
+                )
+                .await;
+            }
+
+            match allocation.selection {
+                Some(selection) => {
+                    bottleneck_bitrate = Some(
+                        bottleneck_bitrate.map_or(selection.cumulative_bitrate, |current| current.min(selection.cumulative_bitrate)),
+                    );
+
+                    // A packet tagged with its spatial/temporal layer is
+                    // forwarded only if it matches this subscriber's
+                    // selection; untagged packets go to every subscriber
+                    let matches_layer = match (packet.spatial_id, packet.temporal_id) {
+                        (Some(spatial_id), Some(temporal_id)) => {
+                            spatial_id == selection.spatial_id && temporal_id == selection.temporal_id
+                        }
+                        _ => true,
+                    };
+
+                    if matches_layer {
+                        forwarded.push(subscriber_id);
+                    } else {
+                        self.trace(
+                            packet.track_id,
+                            TraceEventKind::PacketDropped,
+                            TraceDirection::Outbound,
+                            None,
+                            None,
+                            Some(subscriber_id),
+                        )
+                        .await;
+                    }
+                }
+                None => {
+                    any_audio_only = true;
+                    self.trace(
+                        packet.track_id,
+                        TraceEventKind::PacketDropped,
+                        TraceDirection::Outbound,
+                        None,
+                        None,
+                        Some(subscriber_id),
+                    )
+                    .await;
+                }
+            }
+        }
+
+        let adapt = any_switched || any_audio_only;
+        let adaptation_params = adapt.then(|| AdaptationParams {
+            target_bitrate: bottleneck_bitrate.unwrap_or(0),
+            drop_non_essential: any_audio_only,
+        });
+
+        (forwarded, adapt, adaptation_params)
+    }
 }
 
 #[async_trait]
 impl MediaRouter for DefaultMediaRouter {
     async fn process_packet(&self, packet: RoutableMediaPacket) -> Result<()> {
+        // Feed this packet's departure/arrival into the publisher's
+        // delay-based congestion controller, advancing the overuse detector
+        // and AIMD rate controller behind `get_recommended_bitrate`/
+        // `get_bandwidth_trend` for this session
+        if let Err(e) = self
+            .bandwidth_manager
+            .report_packet_group(packet.publisher_id, packet.send_timestamp, packet.timestamp, packet.packet.len())
+            .await
+        {
+            tracing::debug!(
+                "Failed to feed packet group into bandwidth estimator for session {}: {}",
+                packet.publisher_id,
+                e
+            );
+        }
+
         // Get forwarding decision
         let decision = self.get_forwarding_decision(&packet).await?;
-        
+
         // Forward the packet
         self.forward_packet(packet, decision).await
     }
@@ -171,27 +470,80 @@ impl MediaRouter for DefaultMediaRouter {
             .ok_or_else(|| SfuError::Media(format!("Track not found: {}", packet.track_id)))?;
         
         // Get subscribers
-        let target_subscribers = track_info.subscribers.iter().cloned().collect();
-        
+        let target_subscribers: Vec<SessionId> = track_info.subscribers.iter().cloned().collect();
+        let origin = track_info.origin;
+        let kind = track_info.kind;
+        drop(tracks);
+
+        // A locally-published track may also be relayed to peer nodes that
+        // have pulled it through the broker; a relayed track is never
+        // relayed onward a second hop
+        let relay_targets = match origin {
+            TrackOrigin::Local => match self.broker.read().await.clone() {
+                Some(broker) => broker.remote_subscribers(packet.track_id).await.unwrap_or_default(),
+                None => Vec::new(),
+            },
+            TrackOrigin::Relayed(_) => Vec::new(),
+        };
+
+        // Video subscribers get water-filled against their own bandwidth
+        // estimate; this also drops this packet for subscribers whose
+        // allocated layer doesn't match it
+        let (target_subscribers, adapt, adaptation_params) = if kind == TrackKind::Video {
+            self.adapt_subscribers(packet, target_subscribers).await
+        } else {
+            (target_subscribers, false, None)
+        };
+
         // Create forwarding decision
         let decision = ForwardingDecision {
             target_subscribers,
-            adapt: false,
-            adaptation_params: None,
+            relay_targets,
+            adapt,
+            adaptation_params,
         };
-        
+
         Ok(decision)
     }
 
     async fn forward_packet(&self, packet: RoutableMediaPacket, decision: ForwardingDecision) -> Result<()> {
+        let packet_len = packet.packet.len() as u64;
+        let rtp_header = rtp::RtpPacket::parse(&packet.packet).ok().map(|rtp| rtp.header);
+        let ssrc = rtp_header.as_ref().map(|header| header.ssrc);
+        let sequence_number = rtp_header.as_ref().map(|header| header.sequence_number);
+
+        if let Some(params) = &decision.adaptation_params {
+            tracing::debug!(
+                "Track {} adapting to bottleneck bitrate {} bps (drop_non_essential={})",
+                packet.track_id,
+                params.target_bitrate,
+                params.drop_non_essential
+            );
+            self.trace(packet.track_id, TraceEventKind::BitrateUpdate, TraceDirection::Outbound, ssrc, sequence_number, None)
+                .await;
+        }
+
+        // Count the packet against the publisher's track, once, regardless
+        // of how many subscribers it's forwarded to
+        if let Ok(publisher) = self.session_manager.get_participant(packet.publisher_id).await {
+            let publisher = publisher.read().await;
+            if let Some(track) = publisher.published_tracks.get(&packet.track_id) {
+                track.bytes_forwarded.fetch_add(packet_len, Ordering::Relaxed);
+                track.packets_forwarded.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+
         // For each target subscriber
         for subscriber_id in decision.target_subscribers {
             // Get the participant
             if let Ok(participant) = self.session_manager.get_participant(subscriber_id).await {
                 let participant = participant.read().await;
-                
+
                 // Check if the participant is subscribed to this track
-                if participant.subscribed_tracks.contains_key(&packet.track_id) {
+                if let Some(subscribed) = participant.subscribed_tracks.get(&packet.track_id) {
+                    subscribed.bytes_received.fetch_add(packet_len, Ordering::Relaxed);
+                    subscribed.packets_received.fetch_add(1, Ordering::Relaxed);
+
                     // Forward the packet to the participant
                     // This is a placeholder - actual implementation will depend on the transport layer
                     tracing::debug!(
@@ -199,27 +551,58 @@ impl MediaRouter for DefaultMediaRouter {
                         packet.track_id,
                         subscriber_id
                     );
+                    self.trace(
+                        packet.track_id,
+                        TraceEventKind::PacketForwarded,
+                        TraceDirection::Outbound,
+                        ssrc,
+                        sequence_number,
+                        Some(subscriber_id),
+                    )
+                    .await;
                 }
             }
         }
-        
+
+        // For each peer node relaying this track onward
+        for node_id in decision.relay_targets {
+            let session = match self.relay_session(node_id).await {
+                Ok(session) => session,
+                Err(e) => {
+                    tracing::debug!("Failed to open relay session to node {}: {}", node_id, e);
+                    continue;
+                }
+            };
+
+            // This is a placeholder - actual implementation will open (or
+            // reuse) a send flow on `session` keyed by track and push the
+            // RTP packet over it
+            tracing::debug!(
+                "Relaying packet from track {} to peer node {}",
+                packet.track_id,
+                node_id
+            );
+            let _ = session;
+        }
+
         Ok(())
     }
 
     async fn register_track(&self, publisher_id: SessionId, track_id: TrackId, kind: TrackKind) -> Result<()> {
         let mut tracks = self.tracks.write().await;
-        
+
         // Create track info
         let track_info = TrackInfo {
             track_id,
             publisher_id,
             kind,
             subscribers: HashSet::new(),
+            origin: TrackOrigin::Local,
         };
-        
+
         // Register the track
         tracks.insert(track_id, track_info);
-        
+
         Ok(())
     }
 
@@ -241,7 +624,47 @@ impl MediaRouter for DefaultMediaRouter {
         
         // Remove the track
         tracks.remove(&track_id);
-        
+
+        Ok(())
+    }
+
+    async fn announce_track(&self, track_id: TrackId, publisher_id: SessionId) -> Result<()> {
+        let broker = self.broker.read().await.clone().ok_or_else(|| {
+            SfuError::Media("no relay broker configured for announcing tracks".to_string())
+        })?;
+
+        broker.announce(track_id, publisher_id).await
+    }
+
+    async fn enable_trace(&self, track_id: TrackId, events: Vec<TraceEventKind>) -> Result<mpsc::Receiver<TraceEvent>> {
+        let (sender, receiver) = mpsc::channel(trace::TRACE_CHANNEL_CAPACITY);
+        self.traces.write().await.insert(track_id, TraceSubscription::new(events, sender));
+        Ok(receiver)
+    }
+
+    async fn disable_trace(&self, track_id: TrackId) {
+        self.traces.write().await.remove(&track_id);
+    }
+
+    async fn subscribe_remote(&self, node_id: NodeId, remote_track_id: TrackId, subscriber: SessionId) -> Result<()> {
+        let broker = self.broker.read().await.clone().ok_or_else(|| {
+            SfuError::Media("no relay broker configured for remote track subscription".to_string())
+        })?;
+
+        broker.subscribe(node_id, remote_track_id, subscriber).await?;
+
+        let mut tracks = self.tracks.write().await;
+        let track_info = tracks.entry(remote_track_id).or_insert_with(|| TrackInfo {
+            track_id: remote_track_id,
+            // No local publisher for a relayed track; `origin` is authoritative
+            publisher_id: subscriber,
+            kind: TrackKind::Video,
+            subscribers: HashSet::new(),
+            origin: TrackOrigin::Relayed(node_id),
+        });
+        track_info.origin = TrackOrigin::Relayed(node_id);
+        track_info.subscribers.insert(subscriber);
+
         Ok(())
     }
 }
@@ -0,0 +1,561 @@
+// RTCP packet parsing and building
+//
+// This module implements compound RTCP packets (Sender/Receiver Reports) so the
+// statistics collector can be fed real transport-quality data instead of relying
+// on callers to populate it directly.
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::Result;
+use bytes::{BufMut, Bytes, BytesMut};
+
+use crate::SfuError;
+
+/// RTCP packet type for Sender Report
+pub const RTCP_PT_SR: u8 = 200;
+/// RTCP packet type for Receiver Report
+pub const RTCP_PT_RR: u8 = 201;
+/// RTCP packet type for transport layer feedback (RFC 4585)
+pub const RTCP_PT_RTPFB: u8 = 205;
+/// RTCP packet type for payload-specific feedback (RFC 4585)
+pub const RTCP_PT_PSFB: u8 = 206;
+
+/// RTPFB format for Generic NACK (RFC 4585 section 6.2.1)
+pub const RTPFB_FMT_NACK: u8 = 1;
+/// PSFB format for Picture Loss Indication (RFC 4585 section 6.3.1)
+pub const PSFB_FMT_PLI: u8 = 1;
+/// PSFB format for Full Intra Request (RFC 5104 section 4.3.1)
+pub const PSFB_FMT_FIR: u8 = 4;
+/// PSFB format for Receiver Estimated Maximum Bitrate (draft-alvestrand-rmcat-remb-03)
+pub const PSFB_FMT_REMB: u8 = 15;
+
+/// Seconds between the NTP epoch (1900) and the Unix epoch (1970)
+const NTP_UNIX_EPOCH_OFFSET: u64 = 2_208_988_800;
+
+/// A single reception report block, carried by both SR and RR packets
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReportBlock {
+    /// SSRC of the source being reported on
+    pub ssrc: u32,
+    /// Fraction of packets lost since the previous report (8-bit fixed point)
+    pub fraction_lost: u8,
+    /// Cumulative number of packets lost (24-bit signed)
+    pub cumulative_lost: i32,
+    /// Extended highest sequence number received
+    pub extended_highest_seq: u32,
+    /// Interarrival jitter estimate, in RTP timestamp units
+    pub jitter: u32,
+    /// Last SR timestamp (middle 32 bits of the NTP timestamp), or 0 if none received
+    pub last_sr: u32,
+    /// Delay since last SR, in units of 1/65536 seconds
+    pub delay_since_last_sr: u32,
+}
+
+impl ReportBlock {
+    const WIRE_SIZE: usize = 24;
+
+    fn parse(data: &[u8]) -> Result<Self> {
+        if data.len() < Self::WIRE_SIZE {
+            return Err(SfuError::Media("RTCP report block too short".to_string()).into());
+        }
+
+        let ssrc = u32::from_be_bytes([data[0], data[1], data[2], data[3]]);
+        let fraction_lost = data[4];
+        let cumulative_lost = sign_extend_24(((data[5] as u32) << 16) | ((data[6] as u32) << 8) | (data[7] as u32));
+        let extended_highest_seq = u32::from_be_bytes([data[8], data[9], data[10], data[11]]);
+        let jitter = u32::from_be_bytes([data[12], data[13], data[14], data[15]]);
+        let last_sr = u32::from_be_bytes([data[16], data[17], data[18], data[19]]);
+        let delay_since_last_sr = u32::from_be_bytes([data[20], data[21], data[22], data[23]]);
+
+        Ok(Self {
+            ssrc,
+            fraction_lost,
+            cumulative_lost,
+            extended_highest_seq,
+            jitter,
+            last_sr,
+            delay_since_last_sr,
+        })
+    }
+
+    fn serialize(&self, buf: &mut BytesMut) {
+        buf.put_u32(self.ssrc);
+        buf.put_u8(self.fraction_lost);
+        let cumulative = (self.cumulative_lost as u32) & 0x00FF_FFFF;
+        buf.put_u8((cumulative >> 16) as u8);
+        buf.put_u8((cumulative >> 8) as u8);
+        buf.put_u8(cumulative as u8);
+        buf.put_u32(self.extended_highest_seq);
+        buf.put_u32(self.jitter);
+        buf.put_u32(self.last_sr);
+        buf.put_u32(self.delay_since_last_sr);
+    }
+
+    /// Packet loss percentage implied by the fraction-lost field
+    pub fn packet_loss_percent(&self) -> f32 {
+        self.fraction_lost as f32 / 256.0 * 100.0
+    }
+
+    /// Compute round-trip time in milliseconds given the current NTP mid-32 timestamp
+    ///
+    /// Returns `None` if no SR has ever been received (`last_sr == 0`).
+    pub fn round_trip_time_ms(&self, now_ntp_mid32: u32) -> Option<u32> {
+        if self.last_sr == 0 {
+            return None;
+        }
+
+        let rtt_fixed = now_ntp_mid32.wrapping_sub(self.last_sr).wrapping_sub(self.delay_since_last_sr);
+        Some(((rtt_fixed as u64) * 1000 / 65536) as u32)
+    }
+}
+
+fn sign_extend_24(value: u32) -> i32 {
+    if value & 0x0080_0000 != 0 {
+        (value | 0xFF00_0000) as i32
+    } else {
+        value as i32
+    }
+}
+
+/// Sender Report (RTCP PT=200)
+#[derive(Debug, Clone)]
+pub struct SenderReport {
+    /// SSRC of the sender
+    pub ssrc: u32,
+    /// 64-bit NTP timestamp of when this report was sent
+    pub ntp_timestamp: u64,
+    /// RTP timestamp corresponding to the NTP timestamp
+    pub rtp_timestamp: u32,
+    /// Number of packets sent since starting transmission
+    pub packet_count: u32,
+    /// Number of payload octets sent since starting transmission
+    pub octet_count: u32,
+    /// Reception report blocks
+    pub reports: Vec<ReportBlock>,
+}
+
+/// Receiver Report (RTCP PT=201)
+#[derive(Debug, Clone)]
+pub struct ReceiverReport {
+    /// SSRC of the report originator
+    pub ssrc: u32,
+    /// Reception report blocks
+    pub reports: Vec<ReportBlock>,
+}
+
+/// A single lost-packet group carried by a Generic NACK FCI entry (RFC 4585 section 6.2.1)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GenericNack {
+    /// Sequence number of the first packet lost in this group
+    pub pid: u16,
+    /// Bitmask of further lost packets following `pid`: bit `i` set means `pid + i + 1` is also lost
+    pub blp: u16,
+}
+
+/// Generic NACK feedback (RTCP RTPFB, PT=205, FMT=1)
+#[derive(Debug, Clone)]
+pub struct Nack {
+    /// SSRC of the packet sender (the endpoint generating this feedback)
+    pub sender_ssrc: u32,
+    /// SSRC of the media source the NACK refers to
+    pub media_ssrc: u32,
+    /// Lost-packet groups
+    pub nacks: Vec<GenericNack>,
+}
+
+impl Nack {
+    /// Build the NACK groups covering a set of missing sequence numbers
+    ///
+    /// Consecutive missing sequence numbers are folded into as few groups as
+    /// possible, each covering up to 17 sequence numbers (1 PID + 16 BLP bits).
+    pub fn from_missing_sequences(sender_ssrc: u32, media_ssrc: u32, missing: &[u16]) -> Self {
+        let mut nacks = Vec::new();
+        let mut iter = missing.iter().copied().peekable();
+
+        while let Some(pid) = iter.next() {
+            let mut blp = 0u16;
+
+            while let Some(&next) = iter.peek() {
+                let gap = next.wrapping_sub(pid).wrapping_sub(1);
+                if gap >= 16 {
+                    break;
+                }
+                blp |= 1 << gap;
+                iter.next();
+            }
+
+            nacks.push(GenericNack { pid, blp });
+        }
+
+        Self {
+            sender_ssrc,
+            media_ssrc,
+            nacks,
+        }
+    }
+}
+
+/// Picture Loss Indication (RTCP PSFB, PT=206, FMT=1): the decoder lost the
+/// picture context and needs a new key frame
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PictureLossIndication {
+    /// SSRC of the packet sender
+    pub sender_ssrc: u32,
+    /// SSRC of the media source
+    pub media_ssrc: u32,
+}
+
+/// Full Intra Request (RTCP PSFB, PT=206, FMT=4): a stronger key frame request
+/// than PLI, carrying a sequence number so repeated requests can be deduplicated
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FullIntraRequest {
+    /// SSRC of the packet sender
+    pub sender_ssrc: u32,
+    /// SSRC of the media source that should send a new key frame
+    pub media_ssrc: u32,
+    /// FIR command sequence number, incremented on each new request
+    pub seq_nr: u8,
+}
+
+/// Receiver Estimated Maximum Bitrate (RTCP PSFB, PT=206, FMT=15,
+/// draft-alvestrand-rmcat-remb-03): the wire form of a
+/// `feedback::BandwidthEstimation`
+#[derive(Debug, Clone)]
+pub struct Remb {
+    /// SSRC of the packet sender (the endpoint generating this feedback)
+    pub sender_ssrc: u32,
+    /// Estimated maximum receive bitrate, in bits per second
+    pub bitrate_bps: u64,
+    /// SSRCs this estimate applies to
+    pub ssrcs: Vec<u32>,
+}
+
+impl Remb {
+    /// Encode a bitrate as the 6-bit exponent / 18-bit mantissa pair REMB
+    /// carries on the wire
+    fn encode_bitrate(bitrate_bps: u64) -> (u8, u32) {
+        let mut exponent: u8 = 0;
+        let mut mantissa = bitrate_bps;
+        while mantissa > 0x3_FFFF && exponent < 63 {
+            mantissa >>= 1;
+            exponent += 1;
+        }
+        (exponent, mantissa as u32)
+    }
+}
+
+/// A parsed RTCP packet within a compound packet
+#[derive(Debug, Clone)]
+pub enum RtcpPacket {
+    /// Sender report
+    SenderReport(SenderReport),
+    /// Receiver report
+    ReceiverReport(ReceiverReport),
+    /// Generic NACK feedback
+    Nack(Nack),
+    /// Picture loss indication
+    Pli(PictureLossIndication),
+    /// Full intra request
+    Fir(FullIntraRequest),
+    /// Receiver estimated maximum bitrate
+    Remb(Remb),
+    /// A packet type this module does not (yet) interpret
+    Unknown {
+        /// RTCP payload type
+        payload_type: u8,
+        /// Raw packet bytes, including the header
+        data: Bytes,
+    },
+}
+
+impl RtcpPacket {
+    /// Parse a (possibly compound) RTCP packet into its constituent packets
+    pub fn parse(data: &[u8]) -> Result<Vec<Self>> {
+        let mut packets = Vec::new();
+        let mut offset = 0;
+
+        while offset < data.len() {
+            if offset + 4 > data.len() {
+                return Err(SfuError::Media("RTCP packet too short for header".to_string()).into());
+            }
+
+            let version = (data[offset] >> 6) & 0x03;
+            if version != 2 {
+                return Err(SfuError::Media(format!("Unsupported RTCP version: {}", version)).into());
+            }
+            // Lower 5 bits: report count for SR/RR, FMT for RTPFB/PSFB feedback packets
+            let report_count = data[offset] & 0x1F;
+            let fmt = report_count;
+            let payload_type = data[offset + 1];
+            let length_words = u16::from_be_bytes([data[offset + 2], data[offset + 3]]) as usize;
+            let packet_len = (length_words + 1) * 4;
+
+            if offset + packet_len > data.len() {
+                return Err(SfuError::Media("RTCP packet length exceeds buffer".to_string()).into());
+            }
+
+            let packet_data = &data[offset..offset + packet_len];
+
+            let parsed = match payload_type {
+                RTCP_PT_SR => Self::parse_sender_report(packet_data, report_count)?,
+                RTCP_PT_RR => Self::parse_receiver_report(packet_data, report_count)?,
+                RTCP_PT_RTPFB if fmt == RTPFB_FMT_NACK => Self::parse_nack(packet_data)?,
+                RTCP_PT_PSFB if fmt == PSFB_FMT_PLI => Self::parse_pli(packet_data)?,
+                RTCP_PT_PSFB if fmt == PSFB_FMT_FIR => Self::parse_fir(packet_data)?,
+                RTCP_PT_PSFB if fmt == PSFB_FMT_REMB => Self::parse_remb(packet_data)?,
+                _ => RtcpPacket::Unknown {
+                    payload_type,
+                    data: Bytes::copy_from_slice(packet_data),
+                },
+            };
+
+            packets.push(parsed);
+            offset += packet_len;
+        }
+
+        Ok(packets)
+    }
+
+    fn parse_sender_report(data: &[u8], report_count: u8) -> Result<Self> {
+        if data.len() < 28 {
+            return Err(SfuError::Media("RTCP SR too short".to_string()).into());
+        }
+
+        let ssrc = u32::from_be_bytes([data[4], data[5], data[6], data[7]]);
+        let ntp_msw = u32::from_be_bytes([data[8], data[9], data[10], data[11]]);
+        let ntp_lsw = u32::from_be_bytes([data[12], data[13], data[14], data[15]]);
+        let ntp_timestamp = ((ntp_msw as u64) << 32) | (ntp_lsw as u64);
+        let rtp_timestamp = u32::from_be_bytes([data[16], data[17], data[18], data[19]]);
+        let packet_count = u32::from_be_bytes([data[20], data[21], data[22], data[23]]);
+        let octet_count = u32::from_be_bytes([data[24], data[25], data[26], data[27]]);
+
+        let mut reports = Vec::with_capacity(report_count as usize);
+        let mut offset = 28;
+        for _ in 0..report_count {
+            reports.push(ReportBlock::parse(&data[offset..])?);
+            offset += ReportBlock::WIRE_SIZE;
+        }
+
+        Ok(RtcpPacket::SenderReport(SenderReport {
+            ssrc,
+            ntp_timestamp,
+            rtp_timestamp,
+            packet_count,
+            octet_count,
+            reports,
+        }))
+    }
+
+    fn parse_receiver_report(data: &[u8], report_count: u8) -> Result<Self> {
+        if data.len() < 8 {
+            return Err(SfuError::Media("RTCP RR too short".to_string()).into());
+        }
+
+        let ssrc = u32::from_be_bytes([data[4], data[5], data[6], data[7]]);
+
+        let mut reports = Vec::with_capacity(report_count as usize);
+        let mut offset = 8;
+        for _ in 0..report_count {
+            reports.push(ReportBlock::parse(&data[offset..])?);
+            offset += ReportBlock::WIRE_SIZE;
+        }
+
+        Ok(RtcpPacket::ReceiverReport(ReceiverReport { ssrc, reports }))
+    }
+
+    fn parse_nack(data: &[u8]) -> Result<Self> {
+        if data.len() < 12 {
+            return Err(SfuError::Media("RTCP NACK too short".to_string()).into());
+        }
+
+        let sender_ssrc = u32::from_be_bytes([data[4], data[5], data[6], data[7]]);
+        let media_ssrc = u32::from_be_bytes([data[8], data[9], data[10], data[11]]);
+
+        let mut nacks = Vec::new();
+        let mut offset = 12;
+        while offset + 4 <= data.len() {
+            let pid = u16::from_be_bytes([data[offset], data[offset + 1]]);
+            let blp = u16::from_be_bytes([data[offset + 2], data[offset + 3]]);
+            nacks.push(GenericNack { pid, blp });
+            offset += 4;
+        }
+
+        Ok(RtcpPacket::Nack(Nack {
+            sender_ssrc,
+            media_ssrc,
+            nacks,
+        }))
+    }
+
+    fn parse_pli(data: &[u8]) -> Result<Self> {
+        if data.len() < 12 {
+            return Err(SfuError::Media("RTCP PLI too short".to_string()).into());
+        }
+
+        let sender_ssrc = u32::from_be_bytes([data[4], data[5], data[6], data[7]]);
+        let media_ssrc = u32::from_be_bytes([data[8], data[9], data[10], data[11]]);
+
+        Ok(RtcpPacket::Pli(PictureLossIndication {
+            sender_ssrc,
+            media_ssrc,
+        }))
+    }
+
+    fn parse_fir(data: &[u8]) -> Result<Self> {
+        // Header carries "SSRC of media source" as 0 (unused); the FCI entry
+        // below carries the SSRC of the media sender the request targets.
+        if data.len() < 20 {
+            return Err(SfuError::Media("RTCP FIR too short".to_string()).into());
+        }
+
+        let sender_ssrc = u32::from_be_bytes([data[4], data[5], data[6], data[7]]);
+        let media_ssrc = u32::from_be_bytes([data[12], data[13], data[14], data[15]]);
+        let seq_nr = data[16];
+
+        Ok(RtcpPacket::Fir(FullIntraRequest {
+            sender_ssrc,
+            media_ssrc,
+            seq_nr,
+        }))
+    }
+
+    fn parse_remb(data: &[u8]) -> Result<Self> {
+        if data.len() < 20 {
+            return Err(SfuError::Media("RTCP REMB too short".to_string()).into());
+        }
+
+        let sender_ssrc = u32::from_be_bytes([data[4], data[5], data[6], data[7]]);
+        // data[8..12] is "SSRC of media source", unused (always 0)
+        if &data[12..16] != b"REMB" {
+            return Err(SfuError::Media("RTCP REMB missing unique identifier".to_string()).into());
+        }
+
+        let num_ssrc = data[16] as usize;
+        let exponent = (data[17] >> 2) & 0x3F;
+        let mantissa = (((data[17] & 0x03) as u32) << 16) | ((data[18] as u32) << 8) | (data[19] as u32);
+        let bitrate_bps = (mantissa as u64) << exponent;
+
+        let mut ssrcs = Vec::with_capacity(num_ssrc);
+        let mut offset = 20;
+        for _ in 0..num_ssrc {
+            if offset + 4 > data.len() {
+                break;
+            }
+            ssrcs.push(u32::from_be_bytes([data[offset], data[offset + 1], data[offset + 2], data[offset + 3]]));
+            offset += 4;
+        }
+
+        Ok(RtcpPacket::Remb(Remb { sender_ssrc, bitrate_bps, ssrcs }))
+    }
+
+    /// Serialize a single RTCP packet
+    pub fn serialize(&self) -> Bytes {
+        let mut buf = BytesMut::new();
+
+        match self {
+            RtcpPacket::SenderReport(sr) => {
+                let report_count = sr.reports.len() as u8;
+                let length_words = (6 + sr.reports.len() * ReportBlock::WIRE_SIZE / 4) as u16;
+
+                buf.put_u8(0x80 | (report_count & 0x1F));
+                buf.put_u8(RTCP_PT_SR);
+                buf.put_u16(length_words);
+                buf.put_u32(sr.ssrc);
+                buf.put_u32((sr.ntp_timestamp >> 32) as u32);
+                buf.put_u32(sr.ntp_timestamp as u32);
+                buf.put_u32(sr.rtp_timestamp);
+                buf.put_u32(sr.packet_count);
+                buf.put_u32(sr.octet_count);
+                for report in &sr.reports {
+                    report.serialize(&mut buf);
+                }
+            }
+            RtcpPacket::ReceiverReport(rr) => {
+                let report_count = rr.reports.len() as u8;
+                let length_words = (1 + rr.reports.len() * ReportBlock::WIRE_SIZE / 4) as u16;
+
+                buf.put_u8(0x80 | (report_count & 0x1F));
+                buf.put_u8(RTCP_PT_RR);
+                buf.put_u16(length_words);
+                buf.put_u32(rr.ssrc);
+                for report in &rr.reports {
+                    report.serialize(&mut buf);
+                }
+            }
+            RtcpPacket::Nack(nack) => {
+                let length_words = (2 + nack.nacks.len()) as u16;
+
+                buf.put_u8(0x80 | RTPFB_FMT_NACK);
+                buf.put_u8(RTCP_PT_RTPFB);
+                buf.put_u16(length_words);
+                buf.put_u32(nack.sender_ssrc);
+                buf.put_u32(nack.media_ssrc);
+                for entry in &nack.nacks {
+                    buf.put_u16(entry.pid);
+                    buf.put_u16(entry.blp);
+                }
+            }
+            RtcpPacket::Pli(pli) => {
+                buf.put_u8(0x80 | PSFB_FMT_PLI);
+                buf.put_u8(RTCP_PT_PSFB);
+                buf.put_u16(2);
+                buf.put_u32(pli.sender_ssrc);
+                buf.put_u32(pli.media_ssrc);
+            }
+            RtcpPacket::Fir(fir) => {
+                buf.put_u8(0x80 | PSFB_FMT_FIR);
+                buf.put_u8(RTCP_PT_PSFB);
+                buf.put_u16(4);
+                buf.put_u32(fir.sender_ssrc);
+                buf.put_u32(0); // SSRC of media source: unused for FIR
+                buf.put_u32(fir.media_ssrc);
+                buf.put_u8(fir.seq_nr);
+                buf.put_u8(0);
+                buf.put_u16(0);
+            }
+            RtcpPacket::Remb(remb) => {
+                let length_words = (4 + remb.ssrcs.len()) as u16;
+                let (exponent, mantissa) = Remb::encode_bitrate(remb.bitrate_bps);
+
+                buf.put_u8(0x80 | PSFB_FMT_REMB);
+                buf.put_u8(RTCP_PT_PSFB);
+                buf.put_u16(length_words);
+                buf.put_u32(remb.sender_ssrc);
+                buf.put_u32(0); // SSRC of media source: unused for REMB
+                buf.put_slice(b"REMB");
+                buf.put_u8(remb.ssrcs.len() as u8);
+                buf.put_u8((exponent << 2) | ((mantissa >> 16) as u8 & 0x03));
+                buf.put_u8((mantissa >> 8) as u8);
+                buf.put_u8(mantissa as u8);
+                for ssrc in &remb.ssrcs {
+                    buf.put_u32(*ssrc);
+                }
+            }
+            RtcpPacket::Unknown { data, .. } => {
+                buf.put_slice(data);
+            }
+        }
+
+        buf.freeze()
+    }
+
+    /// Serialize a compound RTCP packet from several RTCP packets
+    pub fn serialize_compound(packets: &[Self]) -> Bytes {
+        let mut buf = BytesMut::new();
+        for packet in packets {
+            buf.put(packet.serialize());
+        }
+        buf.freeze()
+    }
+}
+
+/// Get the current time as the middle 32 bits of a 64-bit NTP timestamp
+///
+/// This is the representation used by the `last_sr`/`delay_since_last_sr` RTT calculation.
+pub fn ntp_now_mid32() -> u32 {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+    let ntp_seconds = now.as_secs() + NTP_UNIX_EPOCH_OFFSET;
+    let ntp_fraction = ((now.subsec_nanos() as u64) << 32) / 1_000_000_000;
+    let ntp_timestamp = (ntp_seconds << 32) | ntp_fraction;
+    (ntp_timestamp >> 16) as u32
+}
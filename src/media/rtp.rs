@@ -2,13 +2,19 @@
 //
 // This module handles RTP packet encoding and decoding for media transport.
 
-use std::sync::Arc;
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::Arc,
+};
 
 use anyhow::Result;
 use bytes::{Bytes, BytesMut, BufMut};
 
 use crate::{
-    media::codec::{Codec, CodecType},
+    media::{
+        codec::{Codec, CodecType},
+        vpx::{LayerInfo, Vp8Descriptor, Vp9Descriptor},
+    },
     SfuError,
 };
 
@@ -46,12 +52,152 @@ pub struct RtpHeader {
     pub extension_data: Option<RtpExtension>,
 }
 
-/// RTP extension
+/// RFC 8285 one-byte header extension profile
+pub const EXTENSION_PROFILE_ONE_BYTE: u16 = 0xBEDE;
+/// RFC 8285 two-byte header extension profile (low nibble carries appbits)
+pub const EXTENSION_PROFILE_TWO_BYTE: u16 = 0x1000;
+/// Mask to detect the two-byte profile regardless of the appbits nibble
+const EXTENSION_PROFILE_TWO_BYTE_MASK: u16 = 0xFFF0;
+
+/// Typed view of the RTP header extension elements, keyed by their local identifier
+pub type HeaderExtensions = HashMap<u8, Bytes>;
+
+/// Well-known extension URIs recognized by this crate
+pub mod extension_uri {
+    /// Audio level indication (RFC 6464)
+    pub const SSRC_AUDIO_LEVEL: &str = "urn:ietf:params:rtp-hdrext:ssrc-audio-level";
+    /// Absolute send time
+    pub const ABS_SEND_TIME: &str = "http://www.webrtc.org/experiments/rtp-hdrext/abs-send-time";
+    /// Transport-wide congestion control sequence number
+    pub const TRANSPORT_CC: &str = "http://www.ietf.org/id/draft-holmer-rmcat-transport-wide-cc-extensions-01";
+}
+
+/// Negotiated mapping between extension local identifiers and their URIs
+#[derive(Debug, Clone, Default)]
+pub struct ExtensionMap {
+    uri_to_id: HashMap<String, u8>,
+    id_to_uri: HashMap<u8, String>,
+}
+
+impl ExtensionMap {
+    /// Create an empty extension map
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a negotiated local identifier for an extension URI
+    pub fn register(&mut self, id: u8, uri: impl Into<String>) {
+        let uri = uri.into();
+        self.id_to_uri.insert(id, uri.clone());
+        self.uri_to_id.insert(uri, id);
+    }
+
+    /// Look up the local identifier negotiated for a URI
+    pub fn id_for_uri(&self, uri: &str) -> Option<u8> {
+        self.uri_to_id.get(uri).copied()
+    }
+
+    /// Look up the URI registered for a local identifier
+    pub fn uri_for_id(&self, id: u8) -> Option<&str> {
+        self.id_to_uri.get(&id).map(String::as_str)
+    }
+}
+
+/// RTP extension block
+#[derive(Clone)]
 pub struct RtpExtension {
     /// Extension profile
     pub profile: u16,
-    /// Extension data
+    /// Raw extension data (the profile-specific encoding of `extensions`)
     pub data: Bytes,
+    /// Decoded extension elements, keyed by local identifier
+    ///
+    /// Populated when `profile` is a recognized one-byte (`0xBEDE`) or two-byte
+    /// (`0x1000`-`0x100F`) profile; empty otherwise.
+    pub extensions: HeaderExtensions,
+}
+
+/// Parse RFC 8285 one-byte header extension elements from a profile's data block
+fn parse_one_byte_extensions(data: &[u8]) -> HeaderExtensions {
+    let mut extensions = HashMap::new();
+    let mut offset = 0;
+
+    while offset < data.len() {
+        let byte = data[offset];
+
+        // Padding
+        if byte == 0x00 {
+            offset += 1;
+            continue;
+        }
+
+        let id = byte >> 4;
+        // ID 15 is reserved to stop parsing further extension elements
+        if id == 15 {
+            break;
+        }
+
+        let len = (byte & 0x0F) as usize + 1;
+        offset += 1;
+
+        if offset + len > data.len() {
+            break;
+        }
+
+        extensions.insert(id, Bytes::copy_from_slice(&data[offset..offset + len]));
+        offset += len;
+    }
+
+    extensions
+}
+
+/// Parse RFC 8285 two-byte header extension elements from a profile's data block
+fn parse_two_byte_extensions(data: &[u8]) -> HeaderExtensions {
+    let mut extensions = HashMap::new();
+    let mut offset = 0;
+
+    while offset + 2 <= data.len() {
+        let id = data[offset];
+        let len = data[offset + 1] as usize;
+        offset += 2;
+
+        // Padding element
+        if id == 0 {
+            continue;
+        }
+
+        if offset + len > data.len() {
+            break;
+        }
+
+        extensions.insert(id, Bytes::copy_from_slice(&data[offset..offset + len]));
+        offset += len;
+    }
+
+    extensions
+}
+
+/// Build an RFC 8285 one-byte extension data block (padded to a 4-byte boundary)
+fn build_one_byte_extensions(extensions: &HeaderExtensions) -> Bytes {
+    let mut buf = BytesMut::new();
+
+    let mut ids: Vec<&u8> = extensions.keys().collect();
+    ids.sort();
+
+    for id in ids {
+        let value = &extensions[id];
+        debug_assert!(*id >= 1 && *id <= 14, "one-byte extension IDs must be 1-14");
+        debug_assert!(!value.is_empty() && value.len() <= 16, "one-byte extension length must be 1-16");
+
+        buf.put_u8((id << 4) | ((value.len() - 1) as u8 & 0x0F));
+        buf.put_slice(value);
+    }
+
+    while buf.len() % 4 != 0 {
+        buf.put_u8(0x00);
+    }
+
+    buf.freeze()
 }
 
 impl RtpPacket {
@@ -109,11 +255,19 @@ impl RtpPacket {
             }
             
             let ext_data = Bytes::copy_from_slice(&data[offset..offset + ext_size]);
+            let extensions = if profile == EXTENSION_PROFILE_ONE_BYTE {
+                parse_one_byte_extensions(&ext_data)
+            } else if profile & EXTENSION_PROFILE_TWO_BYTE_MASK == EXTENSION_PROFILE_TWO_BYTE {
+                parse_two_byte_extensions(&ext_data)
+            } else {
+                HashMap::new()
+            };
             extension_data = Some(RtpExtension {
                 profile,
                 data: ext_data,
+                extensions,
             });
-            
+
             offset += ext_size;
         }
         
@@ -180,6 +334,400 @@ impl RtpPacket {
     }
 }
 
+/// Codec-specific splitting and marker-bit policy used by `RtpPacketizer`
+///
+/// One implementation per codec keeps the fragmentation rules (single packet,
+/// STAP-A/FU-A, raw framing, ...) out of a central match in the packetizer.
+pub trait Payloader: Send + Sync {
+    /// Split a single media frame into the RTP payloads that carry it, in order
+    fn payload(&mut self, max_payload_size: usize, frame: &[u8]) -> Result<Vec<Bytes>>;
+
+    /// Whether the marker bit should be set on the packet at `index` out of the
+    /// `count` packets produced for one frame
+    fn marker(&self, index: usize, count: usize) -> bool;
+
+    /// Queue the spatial/temporal layer indices to tag onto the packets produced
+    /// by the next call to `payload`, for codecs whose payload descriptor
+    /// carries layer indices (VP8/VP9). No-op for codecs without layering.
+    fn set_layer(&mut self, _layer: LayerInfo) {}
+}
+
+/// Build the payloader for a codec
+fn make_payloader(codec_type: CodecType) -> Box<dyn Payloader> {
+    match codec_type {
+        CodecType::Opus => Box::new(OpusPayloader),
+        CodecType::VP8 => Box::new(Vp8Payloader::default()),
+        CodecType::VP9 => Box::new(Vp9Payloader::default()),
+        CodecType::H264 => Box::new(H264Payloader),
+        CodecType::PCMU | CodecType::PCMA => Box::new(PcmPayloader),
+        CodecType::AAC => Box::new(AacPayloader),
+        CodecType::AV1 => Box::new(UnsupportedPayloader),
+    }
+}
+
+/// Payloader for AV1, which this SFU does not packetize over RTP: every
+/// frame is rejected with an error
+struct UnsupportedPayloader;
+
+impl Payloader for UnsupportedPayloader {
+    fn payload(&mut self, _max_payload_size: usize, _frame: &[u8]) -> Result<Vec<Bytes>> {
+        Err(crate::SfuError::Media("Unsupported codec for packetization: AV1".to_string()).into())
+    }
+
+    fn marker(&self, _index: usize, _count: usize) -> bool {
+        true
+    }
+}
+
+/// Opus payloader: each frame is carried by exactly one RTP packet
+struct OpusPayloader;
+
+impl Payloader for OpusPayloader {
+    fn payload(&mut self, _max_payload_size: usize, frame: &[u8]) -> Result<Vec<Bytes>> {
+        Ok(vec![Bytes::copy_from_slice(frame)])
+    }
+
+    fn marker(&self, _index: usize, _count: usize) -> bool {
+        true
+    }
+}
+
+/// VP9 payloader, emitting the full draft-ietf-payload-vp9 descriptor (picture ID,
+/// layer indices, and TL0PICIDX) ahead of each fragment. Always emits in
+/// non-flexible mode; `RtpDepacketizer`/`Vp9Depayloader` parse both modes.
+#[derive(Default)]
+struct Vp9Payloader {
+    /// 15-bit picture ID, incremented once per frame
+    next_picture_id: u16,
+    /// TL0PICIDX, incremented whenever a base (temporal_id == 0) layer frame is sent
+    tl0_pic_idx: u8,
+    /// Layer indices queued via `set_layer` for the next frame
+    pending_layer: Option<LayerInfo>,
+}
+
+impl Payloader for Vp9Payloader {
+    fn payload(&mut self, max_payload_size: usize, frame: &[u8]) -> Result<Vec<Bytes>> {
+        // Mirrors the key-frame heuristic used elsewhere in the crate: VP9's
+        // first payload byte's low bit is clear on intra (key) frames.
+        let is_key_frame = frame.first().map_or(true, |b| b & 0x01 == 0);
+
+        let picture_id = self.next_picture_id;
+        self.next_picture_id = (self.next_picture_id + 1) & 0x7FFF;
+
+        let layer = self.pending_layer.take();
+        if layer.map_or(true, |l| l.temporal_id == 0) {
+            self.tl0_pic_idx = self.tl0_pic_idx.wrapping_add(1);
+        }
+
+        // Reserve headroom for the descriptor (flags + 2-byte picture ID +
+        // layer indices + TL0PICIDX) so fragments still fit within the MTU.
+        let chunk_size = max_payload_size.saturating_sub(5).max(1);
+
+        let mut payloads = Vec::new();
+        let mut offset = 0;
+
+        while offset < frame.len() {
+            let remaining = frame.len() - offset;
+            let payload_size = remaining.min(chunk_size);
+            let is_first = offset == 0;
+            let is_last = offset + payload_size >= frame.len();
+
+            let descriptor = Vp9Descriptor {
+                start_of_frame: is_first,
+                end_of_frame: is_last,
+                inter_picture_predicted: !is_key_frame,
+                flexible_mode: false,
+                picture_id: Some(picture_id),
+                layer,
+                tl0_pic_idx: layer.map(|_| self.tl0_pic_idx),
+                p_diffs: Vec::new(),
+            };
+            let descriptor_bytes = descriptor.serialize();
+
+            let mut payload = BytesMut::with_capacity(descriptor_bytes.len() + payload_size);
+            payload.put_slice(&descriptor_bytes);
+            payload.put_slice(&frame[offset..offset + payload_size]);
+            payloads.push(payload.freeze());
+
+            offset += payload_size;
+        }
+
+        Ok(payloads)
+    }
+
+    fn marker(&self, index: usize, count: usize) -> bool {
+        index + 1 == count
+    }
+
+    fn set_layer(&mut self, layer: LayerInfo) {
+        self.pending_layer = Some(layer);
+    }
+}
+
+/// VP8 payloader (RFC 7741), emitting the full payload descriptor (picture ID
+/// and, when a layer is queued via `set_layer`, TL0PICIDX/TID) ahead of each
+/// fragment. A frame is always carried as partition index 0.
+#[derive(Default)]
+struct Vp8Payloader {
+    /// 15-bit picture ID, incremented once per frame
+    next_picture_id: u16,
+    /// TL0PICIDX, incremented whenever a base (temporal_id == 0) layer frame is sent
+    tl0_pic_idx: u8,
+    /// Layer index queued via `set_layer` for the next frame
+    pending_layer: Option<LayerInfo>,
+}
+
+impl Payloader for Vp8Payloader {
+    fn payload(&mut self, max_payload_size: usize, frame: &[u8]) -> Result<Vec<Bytes>> {
+        let picture_id = self.next_picture_id;
+        self.next_picture_id = (self.next_picture_id + 1) & 0x7FFF;
+
+        let layer = self.pending_layer.take();
+        if layer.map_or(true, |l| l.temporal_id == 0) {
+            self.tl0_pic_idx = self.tl0_pic_idx.wrapping_add(1);
+        }
+
+        // Reserve headroom for the descriptor (required first byte + extended
+        // bits + 2-byte picture ID + TL0PICIDX + TID byte) so fragments still
+        // fit within the MTU.
+        let chunk_size = max_payload_size.saturating_sub(6).max(1);
+
+        let mut payloads = Vec::new();
+        let mut offset = 0;
+
+        while offset < frame.len() {
+            let remaining = frame.len() - offset;
+            let payload_size = remaining.min(chunk_size);
+            let is_first = offset == 0;
+
+            let descriptor = Vp8Descriptor {
+                start_of_partition: is_first,
+                non_reference: false,
+                partition_index: 0,
+                picture_id: Some(picture_id),
+                tl0_pic_idx: layer.map(|_| self.tl0_pic_idx),
+                temporal_id: layer.map(|l| (l.temporal_id, false)),
+                key_idx: None,
+            };
+            let descriptor_bytes = descriptor.serialize();
+
+            let mut payload = BytesMut::with_capacity(descriptor_bytes.len() + payload_size);
+            payload.put_slice(&descriptor_bytes);
+            payload.put_slice(&frame[offset..offset + payload_size]);
+            payloads.push(payload.freeze());
+
+            offset += payload_size;
+        }
+
+        Ok(payloads)
+    }
+
+    fn marker(&self, index: usize, count: usize) -> bool {
+        index + 1 == count
+    }
+
+    fn set_layer(&mut self, layer: LayerInfo) {
+        self.pending_layer = Some(layer);
+    }
+}
+
+/// RFC 6184 NAL unit type used for aggregating several small NALs into one packet
+const H264_NALU_TYPE_STAP_A: u8 = 24;
+/// RFC 6184 NAL unit type used for fragmenting one NAL across several packets
+const H264_NALU_TYPE_FU_A: u8 = 28;
+
+/// Split an Annex-B byte stream (NAL units separated by 3- or 4-byte start codes)
+/// into its constituent NAL units, excluding the start codes themselves
+fn split_annex_b(frame: &[u8]) -> Vec<&[u8]> {
+    let mut nals = Vec::new();
+    let mut start: Option<usize> = None;
+    let mut i = 0;
+
+    while i < frame.len() {
+        if let Some(code_len) = start_code_len(&frame[i..]) {
+            if let Some(nal_start) = start {
+                nals.push(&frame[nal_start..i]);
+            }
+            i += code_len;
+            start = Some(i);
+        } else {
+            i += 1;
+        }
+    }
+
+    if let Some(nal_start) = start {
+        nals.push(&frame[nal_start..]);
+    }
+
+    nals
+}
+
+/// Length of the Annex-B start code at the beginning of `data`, if any
+fn start_code_len(data: &[u8]) -> Option<usize> {
+    if data.len() >= 4 && data[0] == 0 && data[1] == 0 && data[2] == 0 && data[3] == 1 {
+        Some(4)
+    } else if data.len() >= 3 && data[0] == 0 && data[1] == 0 && data[2] == 1 {
+        Some(3)
+    } else {
+        None
+    }
+}
+
+/// Fragment a single NAL unit into FU-A packets (RFC 6184 section 5.8)
+fn fragment_fu_a(nal: &[u8], max_payload_size: usize) -> Vec<Bytes> {
+    let indicator_nri = nal[0] & 0x60;
+    let nal_type = nal[0] & 0x1F;
+    let payload = &nal[1..];
+    let chunk_size = max_payload_size.saturating_sub(2).max(1);
+
+    let mut fragments = Vec::new();
+    let mut offset = 0;
+
+    while offset < payload.len() {
+        let end = (offset + chunk_size).min(payload.len());
+        let is_first = offset == 0;
+        let is_last = end == payload.len();
+
+        let mut buf = BytesMut::with_capacity(2 + (end - offset));
+        buf.put_u8(H264_NALU_TYPE_FU_A | indicator_nri);
+        buf.put_u8(((is_first as u8) << 7) | ((is_last as u8) << 6) | nal_type);
+        buf.put_slice(&payload[offset..end]);
+        fragments.push(buf.freeze());
+
+        offset = end;
+    }
+
+    fragments
+}
+
+/// H.264 payloader (RFC 6184): single NAL unit packets, STAP-A aggregation of
+/// consecutive small NALs, and FU-A fragmentation of NALs larger than one packet
+struct H264Payloader;
+
+impl Payloader for H264Payloader {
+    fn payload(&mut self, max_payload_size: usize, frame: &[u8]) -> Result<Vec<Bytes>> {
+        let nals: Vec<&[u8]> = split_annex_b(frame).into_iter().filter(|n| !n.is_empty()).collect();
+        let mut payloads = Vec::new();
+        let mut i = 0;
+
+        while i < nals.len() {
+            let nal = nals[i];
+
+            if nal.len() > max_payload_size {
+                payloads.extend(fragment_fu_a(nal, max_payload_size));
+                i += 1;
+                continue;
+            }
+
+            // Greedily aggregate consecutive small NALs into one STAP-A packet
+            let mut group = vec![nal];
+            let mut group_size = 1 + 2 + nal.len();
+            let mut j = i + 1;
+
+            while j < nals.len() {
+                let next = nals[j];
+                let added = 2 + next.len();
+                if next.len() > max_payload_size || group_size + added > max_payload_size {
+                    break;
+                }
+                group.push(next);
+                group_size += added;
+                j += 1;
+            }
+
+            if group.len() == 1 {
+                payloads.push(Bytes::copy_from_slice(nal));
+            } else {
+                let nri = group.iter().map(|n| n[0] & 0x60).max().unwrap_or(0);
+                let mut buf = BytesMut::with_capacity(group_size);
+                buf.put_u8(H264_NALU_TYPE_STAP_A | nri);
+                for n in &group {
+                    buf.put_u16(n.len() as u16);
+                    buf.put_slice(n);
+                }
+                payloads.push(buf.freeze());
+            }
+
+            i = j;
+        }
+
+        Ok(payloads)
+    }
+
+    fn marker(&self, index: usize, count: usize) -> bool {
+        index + 1 == count
+    }
+}
+
+/// G.711 payloader (PCMU/PCMA, fixed payload types 0 and 8, 8000 Hz clock):
+/// raw samples carried as-is, split only if a frame exceeds one packet
+struct PcmPayloader;
+
+impl Payloader for PcmPayloader {
+    fn payload(&mut self, max_payload_size: usize, frame: &[u8]) -> Result<Vec<Bytes>> {
+        Ok(frame
+            .chunks(max_payload_size.max(1))
+            .map(Bytes::copy_from_slice)
+            .collect())
+    }
+
+    fn marker(&self, _index: usize, _count: usize) -> bool {
+        true
+    }
+}
+
+/// Prepend an RFC 3016 LATM PayloadLengthInfo prefix (a run of `0xFF` continuation
+/// bytes, one per full 255 octets, followed by the remainder) to a raw AAC access unit
+fn latm_wrap(frame: &[u8]) -> Bytes {
+    let mut buf = BytesMut::with_capacity(frame.len() / 255 + 1 + frame.len());
+    let mut remaining = frame.len();
+
+    while remaining >= 255 {
+        buf.put_u8(0xFF);
+        remaining -= 255;
+    }
+    buf.put_u8(remaining as u8);
+    buf.put_slice(frame);
+
+    buf.freeze()
+}
+
+/// Strip the RFC 3016 LATM PayloadLengthInfo prefix, returning the raw AAC access unit
+fn latm_unwrap(data: &[u8]) -> Result<Bytes> {
+    let mut len = 0usize;
+    let mut offset = 0;
+
+    loop {
+        let byte = *data
+            .get(offset)
+            .ok_or_else(|| SfuError::Media("Truncated LATM length prefix".to_string()))?;
+        offset += 1;
+        len += byte as usize;
+        if byte != 0xFF {
+            break;
+        }
+    }
+
+    if offset + len > data.len() {
+        return Err(SfuError::Media("LATM payload shorter than declared length".to_string()).into());
+    }
+
+    Ok(Bytes::copy_from_slice(&data[offset..offset + len]))
+}
+
+/// AAC payloader using MP4A-LATM framing (RFC 3016): one AudioMuxElement per packet
+struct AacPayloader;
+
+impl Payloader for AacPayloader {
+    fn payload(&mut self, _max_payload_size: usize, frame: &[u8]) -> Result<Vec<Bytes>> {
+        Ok(vec![latm_wrap(frame)])
+    }
+
+    fn marker(&self, _index: usize, _count: usize) -> bool {
+        true
+    }
+}
+
 /// RTP packetizer for media frames
 pub struct RtpPacketizer {
     /// Codec type
@@ -194,6 +742,16 @@ pub struct RtpPacketizer {
     timestamp: u32,
     /// Maximum payload size
     max_payload_size: usize,
+    /// Negotiated extension ID to URI mapping
+    extension_map: ExtensionMap,
+    /// Extension values queued to attach to the next packetized frame's packets
+    pending_extensions: HeaderExtensions,
+    /// Next transport-wide sequence number stamped via the `TRANSPORT_CC`
+    /// extension, if registered; lets receivers echo back per-packet
+    /// arrival times for `feedback::TransportCcFeedback`
+    transport_cc_seq: u16,
+    /// Codec-specific splitting and marker-bit policy
+    payloader: Box<dyn Payloader>,
 }
 
 impl RtpPacketizer {
@@ -206,163 +764,571 @@ impl RtpPacketizer {
             sequence_number: 0,
             timestamp: 0,
             max_payload_size: 1200, // Default to 1200 bytes for QUIC
+            extension_map: ExtensionMap::new(),
+            pending_extensions: HashMap::new(),
+            transport_cc_seq: 0,
+            payloader: make_payloader(codec_type),
         }
     }
-    
+
+    /// Get the codec type
+    pub fn codec_type(&self) -> CodecType {
+        self.codec_type
+    }
+
     /// Set the maximum payload size
     pub fn set_max_payload_size(&mut self, size: usize) {
         self.max_payload_size = size;
     }
-    
+
+    /// Register a negotiated extension URI under a local identifier
+    pub fn register_extension(&mut self, id: u8, uri: impl Into<String>) {
+        self.extension_map.register(id, uri);
+    }
+
+    /// Queue the spatial/temporal layer indices to tag onto the packets produced
+    /// by the next call to `packetize`, for codecs whose payload descriptor
+    /// carries layer indices (VP8/VP9). No-op for codecs without layering.
+    pub fn set_layer(&mut self, layer: LayerInfo) {
+        self.payloader.set_layer(layer);
+    }
+
+    /// Queue a header extension value (by URI) to be attached to the packets
+    /// produced by the next call to `packetize`
+    pub fn set_extension_value(&mut self, uri: &str, value: Bytes) -> Result<()> {
+        let id = self
+            .extension_map
+            .id_for_uri(uri)
+            .ok_or_else(|| SfuError::Media(format!("Extension not registered: {}", uri)))?;
+        self.pending_extensions.insert(id, value);
+        Ok(())
+    }
+
+    /// Build the `RtpExtension` to attach to one outgoing packet, mixing the
+    /// queued pending values with a fresh `TRANSPORT_CC` sequence number if
+    /// that extension was negotiated, incrementing the counter for next time
+    fn build_extension(&mut self) -> Option<RtpExtension> {
+        let mut extensions = self.pending_extensions.clone();
+
+        if let Some(id) = self.extension_map.id_for_uri(extension_uri::TRANSPORT_CC) {
+            extensions.insert(id, Bytes::copy_from_slice(&self.transport_cc_seq.to_be_bytes()));
+            self.transport_cc_seq = self.transport_cc_seq.wrapping_add(1);
+        }
+
+        if extensions.is_empty() {
+            return None;
+        }
+
+        let data = build_one_byte_extensions(&extensions);
+        Some(RtpExtension {
+            profile: EXTENSION_PROFILE_ONE_BYTE,
+            data,
+            extensions,
+        })
+    }
+
     /// Packetize a media frame into RTP packets
     pub fn packetize(&mut self, frame: &[u8], timestamp: u32) -> Result<Vec<RtpPacket>> {
         // Update timestamp
         self.timestamp = timestamp;
-        
-        // Split frame into packets
-        let mut packets = Vec::new();
-        
-        match self.codec_type {
-            CodecType::Opus => {
-                // Opus frames are sent as single RTP packets
-                let header = RtpHeader {
-                    version: 2,
-                    padding: false,
-                    extension: false,
-                    csrc_count: 0,
-                    marker: true,
-                    payload_type: self.payload_type,
-                    sequence_number: self.sequence_number,
-                    timestamp: self.timestamp,
-                    ssrc: self.ssrc,
-                    csrc: Vec::new(),
-                    extension_data: None,
-                };
-                
-                let packet = RtpPacket::new(header, Bytes::copy_from_slice(frame));
-                packets.push(packet);
-                
-                // Increment sequence number
-                self.sequence_number = self.sequence_number.wrapping_add(1);
+
+        let payloads = self.payloader.payload(self.max_payload_size, frame)?;
+        let count = payloads.len();
+        let mut packets = Vec::with_capacity(count);
+
+        for (index, payload) in payloads.into_iter().enumerate() {
+            // Each packet gets its own `TRANSPORT_CC` sequence number, so
+            // this is built per packet rather than once for the whole frame
+            let extension_data = self.build_extension();
+
+            let header = RtpHeader {
+                version: 2,
+                padding: false,
+                extension: extension_data.is_some(),
+                csrc_count: 0,
+                marker: self.payloader.marker(index, count),
+                payload_type: self.payload_type,
+                sequence_number: self.sequence_number,
+                timestamp: self.timestamp,
+                ssrc: self.ssrc,
+                csrc: Vec::new(),
+                extension_data,
+            };
+
+            packets.push(RtpPacket::new(header, payload));
+            self.sequence_number = self.sequence_number.wrapping_add(1);
+        }
+
+        self.pending_extensions.clear();
+
+        Ok(packets)
+    }
+}
+
+/// Default number of packets a `JitterBuffer` will hold before skipping ahead
+const DEFAULT_JITTER_BUFFER_DEPTH: usize = 64;
+
+/// Compare two 16-bit RTP sequence numbers across wraparound
+///
+/// Returns `a - b` interpreted as a signed 16-bit value, so `a` is considered
+/// "after" `b` when the result is positive.
+fn seq_diff(a: u16, b: u16) -> i16 {
+    a.wrapping_sub(b) as i16
+}
+
+/// A sequence-number-ordered jitter buffer with reordering and wraparound handling
+///
+/// Packets are held keyed by sequence number and released in order once the
+/// buffer becomes contiguous from the next expected sequence number. A gap that
+/// persists beyond `max_depth` packets is skipped rather than stalling forever.
+pub struct JitterBuffer {
+    /// Buffered packets, keyed by their 16-bit RTP sequence number
+    packets: BTreeMap<u16, RtpPacket>,
+    /// Maximum number of packets to hold before skipping a persistent gap
+    max_depth: usize,
+    /// Next sequence number expected to be released
+    next_seq: Option<u16>,
+    /// Whether the most recent `drain_ready` call skipped an unrecoverable gap
+    skipped_gap: bool,
+}
+
+impl JitterBuffer {
+    /// Create a new jitter buffer holding up to `max_depth` packets
+    pub fn new(max_depth: usize) -> Self {
+        Self {
+            packets: BTreeMap::new(),
+            max_depth,
+            next_seq: None,
+            skipped_gap: false,
+        }
+    }
+
+    /// Insert a received packet into the buffer
+    pub fn insert(&mut self, packet: RtpPacket) {
+        let seq = packet.header.sequence_number;
+
+        // Drop packets that arrive after we've already released or skipped past them
+        if let Some(next) = self.next_seq {
+            if seq_diff(seq, next) < 0 {
+                return;
             }
-            CodecType::VP9 => {
-                // VP9 frames may need to be split into multiple packets
-                let mut offset = 0;
-                let mut is_first = true;
-                let mut is_last = false;
-                
-                while offset < frame.len() {
-                    let remaining = frame.len() - offset;
-                    let payload_size = remaining.min(self.max_payload_size);
-                    is_last = offset + payload_size >= frame.len();
-                    
-                    // Create VP9 payload descriptor
-                    // This is a simplified version; a real implementation would include more fields
-                    let mut descriptor = BytesMut::with_capacity(1);
-                    let descriptor_byte = ((is_first as u8) << 7) | ((is_last as u8) << 6);
-                    descriptor.put_u8(descriptor_byte);
-                    
-                    // Create payload with descriptor and frame data
-                    let mut payload = BytesMut::with_capacity(descriptor.len() + payload_size);
-                    payload.put_slice(&descriptor);
-                    payload.put_slice(&frame[offset..offset + payload_size]);
-                    
-                    // Create RTP header
-                    let header = RtpHeader {
-                        version: 2,
-                        padding: false,
-                        extension: false,
-                        csrc_count: 0,
-                        marker: is_last,
-                        payload_type: self.payload_type,
-                        sequence_number: self.sequence_number,
-                        timestamp: self.timestamp,
-                        ssrc: self.ssrc,
-                        csrc: Vec::new(),
-                        extension_data: None,
-                    };
-                    
-                    // Create RTP packet
-                    let packet = RtpPacket::new(header, payload.freeze());
-                    packets.push(packet);
-                    
-                    // Update for next packet
-                    offset += payload_size;
-                    is_first = false;
-                    self.sequence_number = self.sequence_number.wrapping_add(1);
+        }
+
+        self.packets.insert(seq, packet);
+    }
+
+    /// Drain all packets that are currently ready for in-order delivery
+    ///
+    /// If a gap persists long enough that the buffer exceeds `max_depth`, the
+    /// missing packets are declared lost and delivery skips ahead to the next
+    /// packet actually present, rather than stalling indefinitely.
+    pub fn drain_ready(&mut self) -> Vec<RtpPacket> {
+        let mut ready = Vec::new();
+
+        if self.next_seq.is_none() {
+            self.next_seq = self.packets.keys().next().copied();
+        }
+
+        loop {
+            let Some(next) = self.next_seq else {
+                break;
+            };
+
+            if let Some(packet) = self.packets.remove(&next) {
+                ready.push(packet);
+                self.next_seq = Some(next.wrapping_add(1));
+                continue;
+            }
+
+            if self.packets.len() >= self.max_depth {
+                if let Some(&lowest) = self.packets.keys().next() {
+                    self.next_seq = Some(lowest);
+                    self.skipped_gap = true;
+                    continue;
+                }
+            }
+
+            break;
+        }
+
+        ready
+    }
+
+    /// Whether the most recent `drain_ready` call skipped an unrecoverable gap,
+    /// i.e. declared packets lost rather than waiting for them to arrive
+    pub fn take_skipped_gap(&mut self) -> bool {
+        std::mem::take(&mut self.skipped_gap)
+    }
+
+    /// Sequence numbers that are currently known missing (awaited but not yet
+    /// buffered), so callers can schedule retransmission requests
+    pub fn missing_sequences(&self) -> Vec<u16> {
+        let (Some(next), Some(&highest)) = (self.next_seq, self.packets.keys().last()) else {
+            return Vec::new();
+        };
+
+        let mut missing = Vec::new();
+        let mut seq = next;
+
+        while seq_diff(seq, highest) <= 0 {
+            if !self.packets.contains_key(&seq) {
+                missing.push(seq);
+            }
+            if seq == highest {
+                break;
+            }
+            seq = seq.wrapping_add(1);
+        }
+
+        missing
+    }
+
+    /// Number of packets currently buffered
+    pub fn len(&self) -> usize {
+        self.packets.len()
+    }
+
+    /// Whether the buffer currently holds no packets
+    pub fn is_empty(&self) -> bool {
+        self.packets.is_empty()
+    }
+}
+
+/// Codec-specific reassembly used by `RtpDepacketizer`
+///
+/// One implementation per codec keeps the reassembly rules out of a central
+/// match in the depacketizer. Packets are handed to a `Depayloader` in
+/// sequence-number order (the `RtpDepacketizer` owns the jitter buffer that
+/// guarantees this).
+pub trait Depayloader: Send + Sync {
+    /// Feed one in-order RTP packet into the reassembly state machine,
+    /// returning a fully reassembled frame once one becomes available
+    fn depayload(&mut self, packet: &RtpPacket) -> Result<Option<Vec<u8>>>;
+
+    /// Spatial/temporal layer of the frame most recently returned by `depayload`,
+    /// for codecs whose payload descriptor carries layer indices (VP8/VP9).
+    /// `None` for codecs without layering, or before any layer has been observed.
+    fn current_layer(&self) -> Option<LayerInfo> {
+        None
+    }
+
+    /// Whether the frame most recently returned by `depayload` was a key
+    /// frame, for codecs whose payload descriptor carries that information
+    /// (VP9). `false` for codecs that don't expose it, or before any frame
+    /// has been observed.
+    fn current_is_key_frame(&self) -> bool {
+        false
+    }
+}
+
+/// Build the depayloader for a codec
+fn make_depayloader(codec_type: CodecType) -> Box<dyn Depayloader> {
+    match codec_type {
+        CodecType::Opus => Box::new(OpusDepayloader),
+        CodecType::VP8 => Box::new(Vp8Depayloader::default()),
+        CodecType::VP9 => Box::new(Vp9Depayloader::default()),
+        CodecType::H264 => Box::new(H264Depayloader::default()),
+        CodecType::PCMU | CodecType::PCMA => Box::new(PcmDepayloader),
+        CodecType::AAC => Box::new(AacDepayloader),
+        CodecType::AV1 => Box::new(UnsupportedDepayloader),
+    }
+}
+
+/// Depayloader for AV1, which this SFU does not depacketize from RTP: every
+/// packet is rejected with an error
+struct UnsupportedDepayloader;
+
+impl Depayloader for UnsupportedDepayloader {
+    fn depayload(&mut self, _packet: &RtpPacket) -> Result<Option<Vec<u8>>> {
+        Err(crate::SfuError::Media("Unsupported codec for depacketization: AV1".to_string()).into())
+    }
+}
+
+/// Opus depayloader: each RTP packet carries exactly one frame
+struct OpusDepayloader;
+
+impl Depayloader for OpusDepayloader {
+    fn depayload(&mut self, packet: &RtpPacket) -> Result<Option<Vec<u8>>> {
+        Ok(Some(packet.payload.to_vec()))
+    }
+}
+
+/// VP9 depayloader, parsing the full draft-ietf-payload-vp9 descriptor on each
+/// fragment to find the payload boundary and expose the frame's layer indices
+#[derive(Default)]
+struct Vp9Depayloader {
+    /// Codec payload bytes reassembled so far for the frame in progress
+    frame_buffer: BytesMut,
+    /// Layer indices parsed from the descriptor of the frame last completed
+    current_layer: Option<LayerInfo>,
+    /// Whether the frame last completed was a key frame: its first packet had
+    /// the start-of-frame bit set and the no-inter-prediction (`P`) bit clear
+    current_is_key_frame: bool,
+}
+
+impl Depayloader for Vp9Depayloader {
+    fn depayload(&mut self, packet: &RtpPacket) -> Result<Option<Vec<u8>>> {
+        let (descriptor, offset) = Vp9Descriptor::parse(&packet.payload)?;
+
+        if descriptor.start_of_frame {
+            self.frame_buffer.clear();
+            self.current_is_key_frame = !descriptor.inter_picture_predicted;
+        }
+
+        self.frame_buffer.put_slice(&packet.payload[offset..]);
+
+        if descriptor.layer.is_some() {
+            self.current_layer = descriptor.layer;
+        }
+
+        if descriptor.end_of_frame || packet.header.marker {
+            Ok(Some(std::mem::take(&mut self.frame_buffer).to_vec()))
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn current_layer(&self) -> Option<LayerInfo> {
+        self.current_layer
+    }
+
+    fn current_is_key_frame(&self) -> bool {
+        self.current_is_key_frame
+    }
+}
+
+/// VP8 depayloader (RFC 7741), parsing the payload descriptor on each fragment
+/// to find the payload boundary and expose the frame's temporal layer
+#[derive(Default)]
+struct Vp8Depayloader {
+    /// Codec payload bytes reassembled so far for the frame in progress
+    frame_buffer: BytesMut,
+    /// Layer index parsed from the descriptor of the frame last completed
+    current_layer: Option<LayerInfo>,
+}
+
+impl Depayloader for Vp8Depayloader {
+    fn depayload(&mut self, packet: &RtpPacket) -> Result<Option<Vec<u8>>> {
+        let (descriptor, offset) = Vp8Descriptor::parse(&packet.payload)?;
+
+        if descriptor.start_of_partition && descriptor.partition_index == 0 {
+            self.frame_buffer.clear();
+        }
+
+        self.frame_buffer.put_slice(&packet.payload[offset..]);
+
+        if let Some((temporal_id, _)) = descriptor.temporal_id {
+            self.current_layer = Some(LayerInfo {
+                temporal_id,
+                spatial_id: 0,
+            });
+        }
+
+        if packet.header.marker {
+            Ok(Some(std::mem::take(&mut self.frame_buffer).to_vec()))
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn current_layer(&self) -> Option<LayerInfo> {
+        self.current_layer
+    }
+}
+
+/// H.264 depayloader (RFC 6184): reassembles single-NAL, STAP-A, and FU-A
+/// packets back into an Annex-B start-code-prefixed access unit
+#[derive(Default)]
+struct H264Depayloader {
+    /// NAL units reassembled so far for the access unit in progress, each
+    /// prefixed with a 4-byte Annex-B start code
+    access_unit: BytesMut,
+    /// Payload accumulated across FU-A fragments for the NAL currently in progress
+    fu_buffer: BytesMut,
+    /// Reconstructed NAL header byte while a FU-A fragment sequence is in progress
+    fu_header: Option<u8>,
+}
+
+impl H264Depayloader {
+    fn push_nal(&mut self, nal: &[u8]) {
+        self.access_unit.put_slice(&[0, 0, 0, 1]);
+        self.access_unit.put_slice(nal);
+    }
+}
+
+impl Depayloader for H264Depayloader {
+    fn depayload(&mut self, packet: &RtpPacket) -> Result<Option<Vec<u8>>> {
+        let payload = &packet.payload;
+        if payload.is_empty() {
+            return Err(SfuError::Media("Empty H.264 RTP payload".to_string()).into());
+        }
+
+        let nal_type = payload[0] & 0x1F;
+
+        match nal_type {
+            1..=23 => {
+                self.push_nal(payload);
+            }
+            t if t == H264_NALU_TYPE_STAP_A => {
+                let mut offset = 1;
+                while offset + 2 <= payload.len() {
+                    let len = u16::from_be_bytes([payload[offset], payload[offset + 1]]) as usize;
+                    offset += 2;
+                    if offset + len > payload.len() {
+                        break;
+                    }
+                    self.push_nal(&payload[offset..offset + len]);
+                    offset += len;
+                }
+            }
+            t if t == H264_NALU_TYPE_FU_A => {
+                if payload.len() < 2 {
+                    return Err(SfuError::Media("FU-A payload too short".to_string()).into());
+                }
+
+                let indicator = payload[0];
+                let fu_header = payload[1];
+                let is_first = fu_header & 0x80 != 0;
+                let is_last = fu_header & 0x40 != 0;
+                let original_nal_type = fu_header & 0x1F;
+
+                if is_first {
+                    self.fu_buffer.clear();
+                    self.fu_header = Some((indicator & 0x60) | original_nal_type);
+                }
+
+                self.fu_buffer.put_slice(&payload[2..]);
+
+                if is_last {
+                    if let Some(header) = self.fu_header.take() {
+                        let mut nal = BytesMut::with_capacity(1 + self.fu_buffer.len());
+                        nal.put_u8(header);
+                        nal.put_slice(&self.fu_buffer);
+                        self.push_nal(&nal);
+                    }
+                    self.fu_buffer.clear();
                 }
             }
             _ => {
-                return Err(SfuError::Media(format!("Unsupported codec for packetization: {:?}", self.codec_type)).into());
+                return Err(SfuError::Media(format!("Unsupported H.264 NAL unit type: {}", nal_type)).into());
             }
         }
-        
-        Ok(packets)
+
+        if packet.header.marker {
+            Ok(Some(std::mem::take(&mut self.access_unit).to_vec()))
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+/// G.711 depayloader (PCMU/PCMA): raw samples carried as-is
+struct PcmDepayloader;
+
+impl Depayloader for PcmDepayloader {
+    fn depayload(&mut self, packet: &RtpPacket) -> Result<Option<Vec<u8>>> {
+        Ok(Some(packet.payload.to_vec()))
+    }
+}
+
+/// AAC depayloader using MP4A-LATM framing (RFC 3016): one AudioMuxElement per packet
+struct AacDepayloader;
+
+impl Depayloader for AacDepayloader {
+    fn depayload(&mut self, packet: &RtpPacket) -> Result<Option<Vec<u8>>> {
+        Ok(Some(latm_unwrap(&packet.payload)?.to_vec()))
     }
 }
 
+/// Feedback the session layer should send upstream in response to loss
+/// detected while processing an RTP packet, analogous to the request-keyframe
+/// behavior built into established VP8/VP9 depayloaders
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FeedbackAction {
+    /// Missing sequence numbers that can still be recovered by retransmission
+    Nack(Vec<u16>),
+    /// An unrecoverable gap occurred on a video track; a new key frame is needed
+    RequestKeyFrame,
+}
+
 /// RTP depacketizer for media frames
 pub struct RtpDepacketizer {
-    /// Codec type
+    /// Codec being depacketized
     codec_type: CodecType,
-    /// Expected sequence number
-    expected_seq: u16,
-    /// Packet buffer for reassembly
-    packet_buffer: Vec<RtpPacket>,
+    /// Reordering jitter buffer feeding the codec-specific reassembly below
+    jitter_buffer: JitterBuffer,
+    /// Codec-specific reassembly state machine
+    depayloader: Box<dyn Depayloader>,
+    /// Whether an unrecoverable gap on a video track should request a key frame
+    request_keyframe_on_loss: bool,
 }
 
 impl RtpDepacketizer {
-    /// Create a new RTP depacketizer
+    /// Create a new RTP depacketizer with the default jitter buffer depth
     pub fn new(codec_type: CodecType) -> Self {
+        Self::with_depth(codec_type, DEFAULT_JITTER_BUFFER_DEPTH)
+    }
+
+    /// Create a new RTP depacketizer with a specific jitter buffer depth
+    pub fn with_depth(codec_type: CodecType, depth: usize) -> Self {
         Self {
             codec_type,
-            expected_seq: 0,
-            packet_buffer: Vec::new(),
+            jitter_buffer: JitterBuffer::new(depth),
+            depayloader: make_depayloader(codec_type),
+            request_keyframe_on_loss: false,
         }
     }
-    
-    /// Process an RTP packet and try to reassemble a media frame
-    pub fn process_packet(&mut self, packet: RtpPacket) -> Result<Option<Vec<u8>>> {
-        match self.codec_type {
-            CodecType::Opus => {
-                // Opus frames are contained in single RTP packets
-                // Just extract the payload
-                Ok(Some(packet.payload.to_vec()))
-            }
-            CodecType::VP9 => {
-                // Check if this is the start of a new frame
-                if packet.payload.len() > 0 && (packet.payload[0] & 0x80) != 0 {
-                    // Clear buffer if we're starting a new frame
-                    self.packet_buffer.clear();
-                }
-                
-                // Add packet to buffer
-                self.packet_buffer.push(packet);
-                
-                // Check if we have a complete frame
-                if self.packet_buffer.last().map_or(false, |p| p.header.marker) {
-                    // Reassemble frame
-                    let mut frame = Vec::new();
-                    
-                    for packet in &self.packet_buffer {
-                        if packet.payload.len() > 1 {
-                            // Skip VP9 payload descriptor (simplified)
-                            frame.extend_from_slice(&packet.payload[1..]);
-                        }
-                    }
-                    
-                    // Clear buffer
-                    self.packet_buffer.clear();
-                    
-                    Ok(Some(frame))
-                } else {
-                    // Frame not complete yet
-                    Ok(None)
-                }
-            }
-            _ => {
-                Err(SfuError::Media(format!("Unsupported codec for depacketization: {:?}", self.codec_type)).into())
+
+    /// Enable or disable requesting a key frame when an unrecoverable gap is
+    /// detected on a video track (has no effect on audio tracks)
+    pub fn set_request_keyframe_on_loss(&mut self, enabled: bool) {
+        self.request_keyframe_on_loss = enabled;
+    }
+
+    /// Sequence numbers currently missing from the jitter buffer
+    pub fn missing_sequences(&self) -> Vec<u16> {
+        self.jitter_buffer.missing_sequences()
+    }
+
+    /// Spatial/temporal layer of the frame most recently reassembled, for
+    /// codecs whose payload descriptor carries layer indices (VP8/VP9)
+    pub fn current_layer(&self) -> Option<LayerInfo> {
+        self.depayloader.current_layer()
+    }
+
+    /// Whether the frame most recently reassembled was a key frame, for
+    /// codecs whose payload descriptor carries that information (VP9)
+    pub fn current_is_key_frame(&self) -> bool {
+        self.depayloader.current_is_key_frame()
+    }
+
+    /// Process an RTP packet, reordering it through the jitter buffer, and
+    /// return any media frames that became ready for reassembly as a result,
+    /// along with any feedback actions the loss implies
+    pub fn process_packet(&mut self, packet: RtpPacket) -> Result<(Vec<Vec<u8>>, Vec<FeedbackAction>)> {
+        self.jitter_buffer.insert(packet);
+
+        let mut frames = Vec::new();
+        for packet in self.jitter_buffer.drain_ready() {
+            if let Some(frame) = self.depayloader.depayload(&packet)? {
+                frames.push(frame);
             }
         }
+
+        let mut actions = Vec::new();
+
+        let missing = self.jitter_buffer.missing_sequences();
+        if !missing.is_empty() {
+            actions.push(FeedbackAction::Nack(missing));
+        }
+
+        if self.request_keyframe_on_loss
+            && self.codec_type.is_video()
+            && self.jitter_buffer.take_skipped_gap()
+        {
+            actions.push(FeedbackAction::RequestKeyFrame);
+        }
+
+        Ok((frames, actions))
     }
 }
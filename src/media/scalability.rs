@@ -0,0 +1,65 @@
+// Scalability-mode parsing for simulcast/SVC video tracks
+//
+// WebRTC/mediasoup describe a video track's declared spatial/temporal layer
+// structure with a compact string like `L3T3` (3 spatial x 3 temporal, full
+// SVC: every spatial layer predicts from the one below it) or `L3T3_KEY`
+// (K-SVC: spatial layers are only inter-predicted at keyframes) or `S3T3`
+// (simulcast: independently-encoded spatial layers, no inter-layer
+// prediction at all). This module parses that string into a declared layer
+// range so the simulcast manager can reject `ActivateLayers` requests for
+// indices the publisher never declared.
+
+/// A publisher's declared spatial/temporal layer structure, parsed from an
+/// SDP `scalability-mode` fmtp parameter (e.g. `L3T3`, `L1T3`, `S2T3_KEY`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ScalabilityMode {
+    /// Number of spatial layers declared
+    pub spatial_layers: u8,
+    /// Number of temporal layers declared per spatial layer
+    pub temporal_layers: u8,
+    /// Whether spatial layers are only inter-predicted at keyframes (K-SVC),
+    /// as opposed to continuously inter-predicted (full SVC) or not at all
+    /// (simulcast, see `simulcast`)
+    pub ksvc: bool,
+    /// Whether spatial layers are independently-encoded simulcast streams
+    /// (an `S`-prefixed mode) rather than a scalable `L`-prefixed encoding
+    pub simulcast: bool,
+}
+
+impl ScalabilityMode {
+    /// Parse a scalability-mode string of the form `L<spatial>T<temporal>`
+    /// or `S<spatial>T<temporal>`, with an optional trailing `_KEY`.
+    /// Returns `None` if `mode` doesn't match this grammar.
+    pub fn parse(mode: &str) -> Option<Self> {
+        let (body, ksvc) = match mode.strip_suffix("_KEY") {
+            Some(body) => (body, true),
+            None => (mode, false),
+        };
+
+        let (rest, simulcast) = match body.strip_prefix('L') {
+            Some(rest) => (rest, false),
+            None => (body.strip_prefix('S')?, true),
+        };
+
+        let (spatial_str, temporal_str) = rest.split_once('T')?;
+        let spatial_layers: u8 = spatial_str.parse().ok()?;
+        let temporal_layers: u8 = temporal_str.parse().ok()?;
+
+        if spatial_layers == 0 || temporal_layers == 0 {
+            return None;
+        }
+
+        Some(Self {
+            spatial_layers,
+            temporal_layers,
+            ksvc,
+            simulcast,
+        })
+    }
+
+    /// Whether `spatial_id`/`temporal_id` fall within this mode's declared
+    /// layer range, i.e. could legally be requested by `ActivateLayers`
+    pub fn contains(&self, spatial_id: u8, temporal_id: u8) -> bool {
+        spatial_id < self.spatial_layers && temporal_id < self.temporal_layers
+    }
+}
@@ -0,0 +1,95 @@
+// Per-track RTP trace events for debugging
+//
+// Modeled on mediasoup's consumer/producer trace events: opt-in, per-track,
+// and delivered as structured events rather than raw packets, so tooling can
+// diagnose a freeze or layer-switch storm in production without recompiling.
+// Disabled by default; `MediaRouter::enable_trace` attaches a bounded
+// channel for one track, `disable_trace` detaches it. A track with no
+// subscription pays only a `HashMap` lookup per packet.
+
+use std::{collections::HashSet, time::Instant};
+
+use tokio::sync::mpsc;
+
+use crate::{media::TrackId, session::SessionId};
+
+/// Capacity of a trace subscription's channel. A subscriber that can't keep
+/// up loses each new event that arrives while the channel is full, rather
+/// than applying backpressure to
+/// the forwarding path that produces them.
+pub const TRACE_CHANNEL_CAPACITY: usize = 256;
+
+/// Kinds of trace events a subscription can opt into
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TraceEventKind {
+    /// A packet was forwarded to a subscriber
+    PacketForwarded,
+    /// A packet was not forwarded to a subscriber it would otherwise go to
+    /// (e.g. simulcast layer mismatch, audio-only fallback)
+    PacketDropped,
+    /// A key frame was observed on the track
+    Keyframe,
+    /// A PLI was sent requesting a key frame
+    Pli,
+    /// A subscriber's simulcast/SVC layer changed
+    LayerChange,
+    /// A bitrate adaptation target changed
+    BitrateUpdate,
+}
+
+/// Direction of a traced event relative to this SFU
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TraceDirection {
+    /// From a publisher into this SFU
+    Inbound,
+    /// From this SFU to a subscriber
+    Outbound,
+}
+
+/// One structured trace event emitted for a track with an active subscription
+#[derive(Debug, Clone)]
+pub struct TraceEvent {
+    /// Track this event concerns
+    pub track_id: TrackId,
+    /// Kind of event
+    pub kind: TraceEventKind,
+    /// Direction relative to this SFU
+    pub direction: TraceDirection,
+    /// Time the event was emitted
+    pub timestamp: Instant,
+    /// RTP SSRC of the packet this event concerns, if known
+    pub ssrc: Option<u32>,
+    /// RTP sequence number of the packet this event concerns, if known
+    pub sequence_number: Option<u16>,
+    /// Subscriber this event concerns, for outbound per-subscriber events
+    pub session_id: Option<SessionId>,
+}
+
+/// A track's active trace subscription: which event kinds to emit, and the
+/// channel to emit them on
+pub struct TraceSubscription {
+    events: HashSet<TraceEventKind>,
+    sender: mpsc::Sender<TraceEvent>,
+}
+
+impl TraceSubscription {
+    /// Subscribe to `events` on `sender`
+    pub fn new(events: Vec<TraceEventKind>, sender: mpsc::Sender<TraceEvent>) -> Self {
+        Self {
+            events: events.into_iter().collect(),
+            sender,
+        }
+    }
+
+    /// Emit `event` if its kind was subscribed to, dropping it silently if
+    /// the channel is full rather than blocking the forwarding path
+    pub fn emit(&self, event: TraceEvent) {
+        if !self.events.contains(&event.kind) {
+            return;
+        }
+
+        if let Err(e) = self.sender.try_send(event) {
+            tracing::debug!("Dropping trace event for track: {}", e);
+        }
+    }
+}
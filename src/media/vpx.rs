@@ -0,0 +1,367 @@
+// VP8/VP9 RTP payload descriptor parsing and building
+//
+// This module implements the payload descriptors carried at the front of each
+// VP8 (RFC 7741) and VP9 (draft-ietf-payload-vp9) RTP packet, so the RTP
+// payloader/depayloader can fragment and reassemble frames without losing the
+// picture ID, layer indices, or reference information real streams rely on.
+
+use anyhow::Result;
+use bytes::{BufMut, Bytes, BytesMut};
+
+use crate::SfuError;
+
+/// Spatial/temporal layer indices carried by a VP9 or VP8 payload descriptor
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LayerInfo {
+    /// Temporal layer index
+    pub temporal_id: u8,
+    /// Spatial layer index (always 0 for VP8, which has no spatial scalability)
+    pub spatial_id: u8,
+}
+
+/// Parsed VP9 payload descriptor (draft-ietf-payload-vp9)
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Vp9Descriptor {
+    /// Start of a VP9 frame
+    pub start_of_frame: bool,
+    /// End of a VP9 frame
+    pub end_of_frame: bool,
+    /// Inter-picture predicted frame (not a key frame)
+    pub inter_picture_predicted: bool,
+    /// Flexible mode in use (P_DIFF references instead of TL0PICIDX)
+    pub flexible_mode: bool,
+    /// Picture ID, 7 or 15 bits depending on the M bit
+    pub picture_id: Option<u16>,
+    /// Temporal/spatial layer indices, when the L bit is set
+    pub layer: Option<LayerInfo>,
+    /// Temporal layer zero index, present in non-flexible mode alongside layer indices
+    pub tl0_pic_idx: Option<u8>,
+    /// Flexible-mode reference picture diffs (P_DIFF), present when `flexible_mode` is set
+    pub p_diffs: Vec<u8>,
+}
+
+impl Vp9Descriptor {
+    /// Parse a VP9 payload descriptor from the front of an RTP payload
+    ///
+    /// Returns the parsed descriptor and the number of bytes it occupied, so the
+    /// caller can skip straight to the VP9 payload data.
+    pub fn parse(data: &[u8]) -> Result<(Self, usize)> {
+        let first = *data
+            .first()
+            .ok_or_else(|| SfuError::Media("Empty VP9 payload".to_string()))?;
+
+        let has_picture_id = first & 0x80 != 0;
+        let inter_picture_predicted = first & 0x40 != 0;
+        let has_layer_indices = first & 0x20 != 0;
+        let flexible_mode = first & 0x10 != 0;
+        let start_of_frame = first & 0x08 != 0;
+        let end_of_frame = first & 0x04 != 0;
+        let has_scalability_structure = first & 0x02 != 0;
+
+        let mut offset = 1;
+        let mut descriptor = Self {
+            start_of_frame,
+            end_of_frame,
+            inter_picture_predicted,
+            flexible_mode,
+            ..Default::default()
+        };
+
+        if has_picture_id {
+            let byte = *data
+                .get(offset)
+                .ok_or_else(|| SfuError::Media("VP9 descriptor truncated at picture ID".to_string()))?;
+            offset += 1;
+
+            if byte & 0x80 != 0 {
+                let low = *data
+                    .get(offset)
+                    .ok_or_else(|| SfuError::Media("VP9 descriptor truncated at extended picture ID".to_string()))?;
+                offset += 1;
+                descriptor.picture_id = Some((((byte & 0x7F) as u16) << 8) | low as u16);
+            } else {
+                descriptor.picture_id = Some((byte & 0x7F) as u16);
+            }
+        }
+
+        if has_layer_indices {
+            let byte = *data
+                .get(offset)
+                .ok_or_else(|| SfuError::Media("VP9 descriptor truncated at layer indices".to_string()))?;
+            offset += 1;
+
+            descriptor.layer = Some(LayerInfo {
+                temporal_id: byte >> 5,
+                spatial_id: (byte >> 1) & 0x07,
+            });
+
+            if !flexible_mode {
+                let tl0 = *data
+                    .get(offset)
+                    .ok_or_else(|| SfuError::Media("VP9 descriptor truncated at TL0PICIDX".to_string()))?;
+                offset += 1;
+                descriptor.tl0_pic_idx = Some(tl0);
+            }
+        }
+
+        if flexible_mode {
+            loop {
+                let byte = *data
+                    .get(offset)
+                    .ok_or_else(|| SfuError::Media("VP9 descriptor truncated at P_DIFF".to_string()))?;
+                offset += 1;
+                descriptor.p_diffs.push(byte >> 1);
+                if byte & 0x01 == 0 {
+                    break;
+                }
+            }
+        }
+
+        if has_scalability_structure {
+            offset += Self::scalability_structure_len(&data[offset..])?;
+        }
+
+        Ok((descriptor, offset))
+    }
+
+    /// Length in bytes of the scalability structure (SS) block starting at `data`
+    fn scalability_structure_len(data: &[u8]) -> Result<usize> {
+        let first = *data
+            .first()
+            .ok_or_else(|| SfuError::Media("VP9 descriptor truncated at scalability structure".to_string()))?;
+
+        let n_s = (first >> 5) + 1;
+        let has_resolutions = first & 0x10 != 0;
+        let has_pic_group = first & 0x08 != 0;
+
+        let mut offset = 1;
+        if has_resolutions {
+            offset += n_s as usize * 4;
+        }
+
+        if has_pic_group {
+            let n_g = *data
+                .get(offset)
+                .ok_or_else(|| SfuError::Media("VP9 SS truncated at N_G".to_string()))?;
+            offset += 1;
+
+            for _ in 0..n_g {
+                let g_byte = *data
+                    .get(offset)
+                    .ok_or_else(|| SfuError::Media("VP9 SS truncated at picture group entry".to_string()))?;
+                offset += 1;
+                let r = (g_byte >> 2) & 0x03;
+                offset += r as usize;
+            }
+        }
+
+        Ok(offset)
+    }
+
+    /// Serialize this descriptor to its wire representation
+    pub fn serialize(&self) -> Bytes {
+        let mut buf = BytesMut::with_capacity(3);
+
+        let mut first = 0u8;
+        if self.picture_id.is_some() {
+            first |= 0x80;
+        }
+        if self.inter_picture_predicted {
+            first |= 0x40;
+        }
+        if self.layer.is_some() {
+            first |= 0x20;
+        }
+        if self.flexible_mode {
+            first |= 0x10;
+        }
+        if self.start_of_frame {
+            first |= 0x08;
+        }
+        if self.end_of_frame {
+            first |= 0x04;
+        }
+        buf.put_u8(first);
+
+        if let Some(pid) = self.picture_id {
+            if pid > 0x7F {
+                buf.put_u8(0x80 | ((pid >> 8) as u8 & 0x7F));
+                buf.put_u8(pid as u8);
+            } else {
+                buf.put_u8(pid as u8);
+            }
+        }
+
+        if let Some(layer) = self.layer {
+            buf.put_u8((layer.temporal_id << 5) | (layer.spatial_id << 1));
+
+            if !self.flexible_mode {
+                buf.put_u8(self.tl0_pic_idx.unwrap_or(0));
+            }
+        }
+
+        if self.flexible_mode {
+            let last = self.p_diffs.len().saturating_sub(1);
+            for (i, p_diff) in self.p_diffs.iter().enumerate() {
+                let continuation = if i == last { 0 } else { 0x01 };
+                buf.put_u8((p_diff << 1) | continuation);
+            }
+        }
+
+        buf.freeze()
+    }
+}
+
+/// Parsed VP8 payload descriptor (RFC 7741)
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Vp8Descriptor {
+    /// Start of a VP8 partition
+    pub start_of_partition: bool,
+    /// Non-reference frame (can be discarded without affecting other frames)
+    pub non_reference: bool,
+    /// Partition index the packet belongs to
+    pub partition_index: u8,
+    /// Picture ID, 7 or 15 bits depending on the M bit, when present
+    pub picture_id: Option<u16>,
+    /// TL0PICIDX, when present
+    pub tl0_pic_idx: Option<u8>,
+    /// Temporal layer index and layer-sync bit, when present
+    pub temporal_id: Option<(u8, bool)>,
+    /// Keyframe sync index for temporal/spatial prediction, when present
+    pub key_idx: Option<u8>,
+}
+
+impl Vp8Descriptor {
+    /// Parse a VP8 payload descriptor from the front of an RTP payload
+    ///
+    /// Returns the parsed descriptor and the number of bytes it occupied.
+    pub fn parse(data: &[u8]) -> Result<(Self, usize)> {
+        let first = *data
+            .first()
+            .ok_or_else(|| SfuError::Media("Empty VP8 payload".to_string()))?;
+
+        let has_extended_bits = first & 0x80 != 0;
+        let non_reference = first & 0x20 != 0;
+        let start_of_partition = first & 0x10 != 0;
+        let partition_index = first & 0x07;
+
+        let mut descriptor = Self {
+            start_of_partition,
+            non_reference,
+            partition_index,
+            ..Default::default()
+        };
+
+        let mut offset = 1;
+        if !has_extended_bits {
+            return Ok((descriptor, offset));
+        }
+
+        let ext = *data
+            .get(offset)
+            .ok_or_else(|| SfuError::Media("VP8 descriptor truncated at extended bits".to_string()))?;
+        offset += 1;
+
+        let has_picture_id = ext & 0x80 != 0;
+        let has_tl0_pic_idx = ext & 0x40 != 0;
+        let has_temporal_id = ext & 0x20 != 0;
+        let has_key_idx = ext & 0x10 != 0;
+
+        if has_picture_id {
+            let byte = *data
+                .get(offset)
+                .ok_or_else(|| SfuError::Media("VP8 descriptor truncated at picture ID".to_string()))?;
+            offset += 1;
+
+            if byte & 0x80 != 0 {
+                let low = *data
+                    .get(offset)
+                    .ok_or_else(|| SfuError::Media("VP8 descriptor truncated at extended picture ID".to_string()))?;
+                offset += 1;
+                descriptor.picture_id = Some((((byte & 0x7F) as u16) << 8) | low as u16);
+            } else {
+                descriptor.picture_id = Some((byte & 0x7F) as u16);
+            }
+        }
+
+        if has_tl0_pic_idx {
+            let byte = *data
+                .get(offset)
+                .ok_or_else(|| SfuError::Media("VP8 descriptor truncated at TL0PICIDX".to_string()))?;
+            offset += 1;
+            descriptor.tl0_pic_idx = Some(byte);
+        }
+
+        if has_temporal_id || has_key_idx {
+            let byte = *data
+                .get(offset)
+                .ok_or_else(|| SfuError::Media("VP8 descriptor truncated at TID/KEYIDX".to_string()))?;
+            offset += 1;
+
+            if has_temporal_id {
+                descriptor.temporal_id = Some((byte >> 6, byte & 0x20 != 0));
+            }
+            if has_key_idx {
+                descriptor.key_idx = Some(byte & 0x1F);
+            }
+        }
+
+        Ok((descriptor, offset))
+    }
+
+    /// Serialize this descriptor to its wire representation
+    pub fn serialize(&self) -> Bytes {
+        let has_extended_bits =
+            self.picture_id.is_some() || self.tl0_pic_idx.is_some() || self.temporal_id.is_some() || self.key_idx.is_some();
+
+        let mut buf = BytesMut::with_capacity(5);
+
+        let mut first = (self.partition_index & 0x07)
+            | ((self.start_of_partition as u8) << 4)
+            | ((self.non_reference as u8) << 5);
+        if has_extended_bits {
+            first |= 0x80;
+        }
+        buf.put_u8(first);
+
+        if !has_extended_bits {
+            return buf.freeze();
+        }
+
+        let mut ext = 0u8;
+        if self.picture_id.is_some() {
+            ext |= 0x80;
+        }
+        if self.tl0_pic_idx.is_some() {
+            ext |= 0x40;
+        }
+        if self.temporal_id.is_some() {
+            ext |= 0x20;
+        }
+        if self.key_idx.is_some() {
+            ext |= 0x10;
+        }
+        buf.put_u8(ext);
+
+        if let Some(pid) = self.picture_id {
+            if pid > 0x7F {
+                buf.put_u8(0x80 | ((pid >> 8) as u8 & 0x7F));
+                buf.put_u8(pid as u8);
+            } else {
+                buf.put_u8(pid as u8);
+            }
+        }
+
+        if let Some(tl0) = self.tl0_pic_idx {
+            buf.put_u8(tl0);
+        }
+
+        if self.temporal_id.is_some() || self.key_idx.is_some() {
+            let (tid, layer_sync) = self.temporal_id.unwrap_or((0, false));
+            let key_idx = self.key_idx.unwrap_or(0);
+            buf.put_u8((tid << 6) | ((layer_sync as u8) << 5) | (key_idx & 0x1F));
+        }
+
+        buf.freeze()
+    }
+}
@@ -0,0 +1,224 @@
+// Recording module for the SFU
+//
+// This module persists published tracks to disk as fragmented MP4 files, so
+// rooms can be archived and played back without the SFU.
+
+pub mod mp4;
+
+use std::{collections::HashMap, path::PathBuf, sync::Arc, time::Duration};
+
+use anyhow::Result;
+use async_trait::async_trait;
+use bytes::Bytes;
+use tokio::{
+    fs::File,
+    io::AsyncWriteExt,
+    sync::{mpsc, RwLock},
+};
+
+use crate::{
+    media::{TrackId, TrackKind},
+    session::CodecInfo,
+    SfuError,
+};
+
+use self::mp4::{FragmentBuilder, SampleEntryKind};
+
+/// How much media to buffer into a single `moof`/`mdat` fragment before
+/// flushing it to disk, if `RecordingConfig` doesn't override it
+const DEFAULT_FRAGMENT_DURATION: Duration = Duration::from_secs(2);
+
+/// Capacity of the per-track channel buffering samples ahead of disk writes,
+/// so a slow flush doesn't block the forwarding path that calls `write_sample`
+const SAMPLE_CHANNEL_CAPACITY: usize = 512;
+
+/// A single encoded access unit to be written into the recording as one
+/// fragmented MP4 sample
+#[derive(Debug, Clone)]
+pub struct Mp4Sample {
+    /// Encoded sample data, one NAL/OBU/frame per the track's codec
+    pub data: Bytes,
+    /// Sample duration, in `mp4::TIMESCALE` units
+    pub duration: u32,
+    /// Whether this sample is a sync sample (key frame) a player can seek to
+    pub is_keyframe: bool,
+}
+
+/// Configuration for fragmented MP4 recording
+#[derive(Clone)]
+pub struct RecordingConfig {
+    /// Directory recordings are written into, one file per track
+    pub output_dir: PathBuf,
+    /// How much media to buffer before flushing a fragment
+    pub fragment_duration: Duration,
+}
+
+impl Default for RecordingConfig {
+    fn default() -> Self {
+        Self {
+            output_dir: PathBuf::from("recordings"),
+            fragment_duration: DEFAULT_FRAGMENT_DURATION,
+        }
+    }
+}
+
+/// Persists published tracks to disk as fragmented MP4
+#[async_trait]
+pub trait Recorder: Send + Sync {
+    /// Start recording `track_id`, writing its initialization segment
+    /// (`ftyp` + `moov`) immediately
+    async fn start(&self, track_id: TrackId, kind: TrackKind, codec: &CodecInfo) -> Result<()>;
+
+    /// Buffer a sample for `track_id`, to be flushed in its next fragment
+    async fn write_sample(&self, track_id: TrackId, sample: Mp4Sample) -> Result<()>;
+
+    /// Flush any buffered samples and stop recording `track_id`
+    async fn finalize(&self, track_id: TrackId) -> Result<()>;
+}
+
+/// Commands sent to a track's background writer task
+enum RecorderCommand {
+    /// Buffer a sample into the current fragment
+    Sample(Mp4Sample),
+    /// Flush the current fragment and stop the writer task
+    Finalize,
+}
+
+/// Handle to a track's background writer task
+struct TrackRecording {
+    command_tx: mpsc::Sender<RecorderCommand>,
+}
+
+/// Default implementation of the recorder: one file per track under
+/// `RecordingConfig::output_dir`, written by a dedicated background task per
+/// track so `write_sample` never blocks on disk I/O
+pub struct DefaultRecorder {
+    config: RecordingConfig,
+    tracks: Arc<RwLock<HashMap<TrackId, TrackRecording>>>,
+}
+
+impl DefaultRecorder {
+    /// Create a new recorder writing under `config.output_dir`
+    pub fn new(config: RecordingConfig) -> Self {
+        Self {
+            config,
+            tracks: Arc::new(RwLock::new(HashMap::new())),
+        }
+    }
+}
+
+#[async_trait]
+impl Recorder for DefaultRecorder {
+    async fn start(&self, track_id: TrackId, kind: TrackKind, codec: &CodecInfo) -> Result<()> {
+        let sample_entry = SampleEntryKind::for_codec_name(&codec.name).ok_or_else(|| {
+            SfuError::Media(format!("no fragmented MP4 mapping for codec: {}", codec.name))
+        })?;
+
+        tokio::fs::create_dir_all(&self.config.output_dir)
+            .await
+            .map_err(|e| SfuError::Other(format!("failed to create recording directory: {}", e)))?;
+
+        let path = self.config.output_dir.join(format!("track-{}.mp4", track_id));
+        let mut file = File::create(&path)
+            .await
+            .map_err(|e| SfuError::Other(format!("failed to create recording file {:?}: {}", path, e)))?;
+
+        let profile_level_id = codec.parameters.get("profile-level-id").map(String::as_str);
+        let init_segment = mp4::init_segment(track_id as u32, sample_entry, profile_level_id);
+        file.write_all(&init_segment)
+            .await
+            .map_err(|e| SfuError::Other(format!("failed to write init segment for track {}: {}", track_id, e)))?;
+
+        let (command_tx, command_rx) = mpsc::channel(SAMPLE_CHANNEL_CAPACITY);
+        let fragment_duration = self.config.fragment_duration;
+        tokio::spawn(run_track_writer(track_id, file, fragment_duration, command_rx));
+
+        let mut tracks = self.tracks.write().await;
+        tracks.insert(track_id, TrackRecording { command_tx });
+
+        tracing::info!("Recording started for {:?} track {} at {:?}", kind, track_id, path);
+
+        Ok(())
+    }
+
+    async fn write_sample(&self, track_id: TrackId, sample: Mp4Sample) -> Result<()> {
+        let tracks = self.tracks.read().await;
+        let track = tracks
+            .get(&track_id)
+            .ok_or_else(|| SfuError::Media(format!("recording not started for track: {}", track_id)))?;
+
+        track
+            .command_tx
+            .send(RecorderCommand::Sample(sample))
+            .await
+            .map_err(|e| SfuError::Other(format!("failed to queue sample for track {}: {}", track_id, e)))?;
+
+        Ok(())
+    }
+
+    async fn finalize(&self, track_id: TrackId) -> Result<()> {
+        let track = {
+            let mut tracks = self.tracks.write().await;
+            tracks.remove(&track_id)
+        };
+
+        let Some(track) = track else {
+            return Ok(());
+        };
+
+        // Best-effort: the writer task flushes and exits even if this send
+        // fails because the task already stopped on its own
+        let _ = track.command_tx.send(RecorderCommand::Finalize).await;
+
+        Ok(())
+    }
+}
+
+/// Background task owning a track's recording file: drains buffered samples
+/// into fragments and flushes one to disk every `fragment_duration`
+async fn run_track_writer(
+    track_id: TrackId,
+    mut file: File,
+    fragment_duration: Duration,
+    mut command_rx: mpsc::Receiver<RecorderCommand>,
+) {
+    let mut fragment = FragmentBuilder::new(track_id as u32);
+    let flush_threshold = (fragment_duration.as_secs_f64() * mp4::TIMESCALE as f64) as u64;
+
+    while let Some(command) = command_rx.recv().await {
+        match command {
+            RecorderCommand::Sample(sample) => {
+                fragment.push_sample(sample);
+
+                if fragment.buffered_duration() >= flush_threshold {
+                    if let Err(e) = flush_fragment(&mut file, &mut fragment).await {
+                        tracing::error!("Failed to flush recording fragment for track {}: {}", track_id, e);
+                    }
+                }
+            }
+            RecorderCommand::Finalize => break,
+        }
+    }
+
+    if let Err(e) = flush_fragment(&mut file, &mut fragment).await {
+        tracing::error!("Failed to flush final recording fragment for track {}: {}", track_id, e);
+    }
+
+    if let Err(e) = file.flush().await {
+        tracing::error!("Failed to flush recording file for track {}: {}", track_id, e);
+    }
+}
+
+/// Take whatever is buffered in `fragment` and append it to `file` as a
+/// `moof`/`mdat` pair, doing nothing if nothing is buffered
+async fn flush_fragment(file: &mut File, fragment: &mut FragmentBuilder) -> Result<()> {
+    let Some(bytes) = fragment.take_fragment() else {
+        return Ok(());
+    };
+
+    file.write_all(&bytes)
+        .await
+        .map_err(|e| SfuError::Other(format!("failed to write recording fragment: {}", e)))?;
+
+    Ok(())
+}
@@ -0,0 +1,524 @@
+// Fragmented MP4 box construction
+//
+// Builds the ISO base media file format boxes used by a fragmented MP4
+// ("fmp4") recording: an initialization segment (ftyp + moov), written once
+// per track, followed by a moof + mdat pair per flushed fragment.
+
+use bytes::{BufMut, Bytes, BytesMut};
+
+use super::Mp4Sample;
+
+/// Timescale (units per second) used for every track this recorder writes
+pub const TIMESCALE: u32 = 90_000;
+
+/// Codec-specific sample entry this recorder knows how to write into `stsd`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SampleEntryKind {
+    /// Opus audio, boxed as an `Opus` sample entry with a `dOps` config box
+    Opus,
+    /// AAC audio, boxed as an `mp4a` sample entry with an `esds` config box
+    Aac,
+    /// H.264 video, boxed as an `avc1` sample entry with an `avcC` config box
+    H264,
+    /// VP9 video, boxed as a `vp09` sample entry with a `vpcC` config box
+    Vp9,
+    /// AV1 video, boxed as an `av01` sample entry with an `av1C` config box
+    Av1,
+}
+
+impl SampleEntryKind {
+    /// Resolve the MP4 sample entry for a negotiated codec name, or `None`
+    /// if this recorder has no fragmented MP4 mapping for it
+    pub fn for_codec_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "opus" => Some(Self::Opus),
+            "aac" => Some(Self::Aac),
+            "h264" => Some(Self::H264),
+            "vp9" => Some(Self::Vp9),
+            "av1" => Some(Self::Av1),
+            _ => None,
+        }
+    }
+
+    /// Whether this sample entry belongs to a video track
+    pub fn is_video(&self) -> bool {
+        matches!(self, Self::H264 | Self::Vp9 | Self::Av1)
+    }
+
+    /// ISO handler type for `hdlr` (`"vide"`/`"soun"`)
+    fn handler_type(&self) -> &'static [u8; 4] {
+        if self.is_video() {
+            b"vide"
+        } else {
+            b"soun"
+        }
+    }
+}
+
+/// Write `box_type` with a length-prefixed body produced by `body`, patching
+/// the size field in place once the body has been written
+fn write_box(buf: &mut BytesMut, box_type: &[u8; 4], body: impl FnOnce(&mut BytesMut)) {
+    let start = buf.len();
+    buf.put_u32(0);
+    buf.put_slice(box_type);
+    body(buf);
+    let size = (buf.len() - start) as u32;
+    buf[start..start + 4].copy_from_slice(&size.to_be_bytes());
+}
+
+/// Fixed-point 16.16 encoding used by several legacy QuickTime-derived fields
+fn fixed16_16(value: u16) -> u32 {
+    (value as u32) << 16
+}
+
+/// Null-terminated compressorname field of a `VisualSampleEntry` (32 bytes,
+/// first byte is the Pascal-string length)
+fn compressorname(buf: &mut BytesMut) {
+    buf.put_bytes(0, 32);
+}
+
+fn ftyp(buf: &mut BytesMut) {
+    write_box(buf, b"ftyp", |buf| {
+        buf.put_slice(b"isom");
+        buf.put_u32(0);
+        buf.put_slice(b"isom");
+        buf.put_slice(b"iso6");
+        buf.put_slice(b"mp41");
+    });
+}
+
+fn mvhd(buf: &mut BytesMut, next_track_id: u32) {
+    write_box(buf, b"mvhd", |buf| {
+        buf.put_u32(0); // version/flags
+        buf.put_u32(0); // creation_time
+        buf.put_u32(0); // modification_time
+        buf.put_u32(1000); // timescale
+        buf.put_u32(0); // duration, unknown for a fragmented recording
+        buf.put_u32(0x00010000); // rate, 1.0
+        buf.put_u16(0x0100); // volume, 1.0
+        buf.put_u16(0); // reserved
+        buf.put_u64(0); // reserved
+        for v in [0x00010000u32, 0, 0, 0, 0x00010000, 0, 0, 0, 0x40000000] {
+            buf.put_u32(v); // unity transformation matrix
+        }
+        buf.put_bytes(0, 24); // pre_defined
+        buf.put_u32(next_track_id);
+    });
+}
+
+fn tkhd(buf: &mut BytesMut, track_id: u32, kind: SampleEntryKind) {
+    write_box(buf, b"tkhd", |buf| {
+        buf.put_u8(0); // version
+        buf.put_u8(0);
+        buf.put_u8(0);
+        buf.put_u8(0x07); // flags: enabled, in movie, in preview
+        buf.put_u32(0); // creation_time
+        buf.put_u32(0); // modification_time
+        buf.put_u32(track_id);
+        buf.put_u32(0); // reserved
+        buf.put_u32(0); // duration, unknown for a fragmented recording
+        buf.put_u64(0); // reserved
+        buf.put_u16(0); // layer
+        buf.put_u16(0); // alternate_group
+        buf.put_u16(if kind.is_video() { 0 } else { 0x0100 }); // volume
+        buf.put_u16(0); // reserved
+        for v in [0x00010000u32, 0, 0, 0, 0x00010000, 0, 0, 0, 0x40000000] {
+            buf.put_u32(v); // unity transformation matrix
+        }
+        buf.put_u32(if kind.is_video() { fixed16_16(1280) } else { 0 }); // width
+        buf.put_u32(if kind.is_video() { fixed16_16(720) } else { 0 }); // height
+    });
+}
+
+fn mdhd(buf: &mut BytesMut) {
+    write_box(buf, b"mdhd", |buf| {
+        buf.put_u32(0); // version/flags
+        buf.put_u32(0); // creation_time
+        buf.put_u32(0); // modification_time
+        buf.put_u32(TIMESCALE);
+        buf.put_u32(0); // duration, unknown for a fragmented recording
+        buf.put_u16(0x55c4); // language, "und"
+        buf.put_u16(0); // pre_defined
+    });
+}
+
+fn hdlr(buf: &mut BytesMut, kind: SampleEntryKind) {
+    write_box(buf, b"hdlr", |buf| {
+        buf.put_u32(0); // version/flags
+        buf.put_u32(0); // pre_defined
+        buf.put_slice(kind.handler_type());
+        buf.put_bytes(0, 12); // reserved
+        let name: &[u8] = if kind.is_video() { b"VideoHandler\0" } else { b"SoundHandler\0" };
+        buf.put_slice(name);
+    });
+}
+
+fn dinf(buf: &mut BytesMut) {
+    write_box(buf, b"dinf", |buf| {
+        write_box(buf, b"dref", |buf| {
+            buf.put_u32(0); // version/flags
+            buf.put_u32(1); // entry_count
+            write_box(buf, b"url ", |buf| {
+                buf.put_u32(0x00000001); // flags: media data is in this file
+            });
+        });
+    });
+}
+
+fn opus_sample_entry(buf: &mut BytesMut) {
+    write_box(buf, b"Opus", |buf| {
+        buf.put_bytes(0, 6); // reserved
+        buf.put_u16(1); // data_reference_index
+        buf.put_u64(0); // reserved
+        buf.put_u16(2); // channelcount
+        buf.put_u16(16); // samplesize
+        buf.put_u16(0); // pre_defined
+        buf.put_u16(0); // reserved
+        buf.put_u32(fixed16_16(48_000)); // samplerate
+        write_box(buf, b"dOps", |buf| {
+            buf.put_u8(0); // version
+            buf.put_u8(2); // OutputChannelCount
+            buf.put_u16(3_840); // PreSkip, 80ms at 48kHz
+            buf.put_u32(48_000); // InputSampleRate
+            buf.put_i16(0); // OutputGain
+            buf.put_u8(0); // ChannelMappingFamily
+        });
+    });
+}
+
+fn aac_sample_entry(buf: &mut BytesMut) {
+    write_box(buf, b"mp4a", |buf| {
+        buf.put_bytes(0, 6); // reserved
+        buf.put_u16(1); // data_reference_index
+        buf.put_u32(0); // reserved
+        buf.put_u32(0); // reserved
+        buf.put_u16(2); // channelcount
+        buf.put_u16(16); // samplesize
+        buf.put_u16(0); // pre_defined
+        buf.put_u16(0); // reserved
+        buf.put_u32(fixed16_16(48_000)); // samplerate
+        write_box(buf, b"esds", |buf| {
+            buf.put_u32(0); // version/flags
+            // ES_Descriptor, minimal: AAC-LC, 48kHz, stereo AudioSpecificConfig
+            buf.put_u8(0x03); // ES_DescrTag
+            buf.put_u8(25); // descriptor length
+            buf.put_u16(0); // ES_ID
+            buf.put_u8(0); // flags
+            buf.put_u8(0x04); // DecoderConfigDescrTag
+            buf.put_u8(17); // descriptor length
+            buf.put_u8(0x40); // objectTypeIndication: Audio ISO/IEC 14496-3
+            buf.put_u8(0x15); // streamType: audio, upstream flag unset
+            buf.put_bytes(0, 3); // bufferSizeDB
+            buf.put_u32(0); // maxBitrate
+            buf.put_u32(0); // avgBitrate
+            buf.put_u8(0x05); // DecSpecificInfoTag
+            buf.put_u8(2); // descriptor length
+            buf.put_slice(&[0x11, 0x90]); // AudioSpecificConfig: AAC-LC, 48kHz, stereo
+            buf.put_u8(0x06); // SLConfigDescrTag
+            buf.put_u8(1); // descriptor length
+            buf.put_u8(0x02); // predefined
+        });
+    });
+}
+
+fn visual_sample_entry(buf: &mut BytesMut, box_type: &[u8; 4], config: impl FnOnce(&mut BytesMut)) {
+    write_box(buf, box_type, |buf| {
+        buf.put_bytes(0, 6); // reserved
+        buf.put_u16(1); // data_reference_index
+        buf.put_u16(0); // pre_defined
+        buf.put_u16(0); // reserved
+        buf.put_bytes(0, 12); // pre_defined
+        buf.put_u16(1280); // width
+        buf.put_u16(720); // height
+        buf.put_u32(fixed16_16(72)); // horizresolution, 72dpi
+        buf.put_u32(fixed16_16(72)); // vertresolution, 72dpi
+        buf.put_u32(0); // reserved
+        buf.put_u16(1); // frame_count
+        compressorname(buf);
+        buf.put_u16(0x0018); // depth
+        buf.put_i16(-1); // pre_defined
+        config(buf);
+    });
+}
+
+/// Decode a hex-digit pair into a byte, or `None` if either digit is invalid
+fn hex_byte(hi: u8, lo: u8) -> Option<u8> {
+    let hi = (hi as char).to_digit(16)?;
+    let lo = (lo as char).to_digit(16)?;
+    Some(((hi << 4) | lo) as u8)
+}
+
+/// Parse a RFC 6184 `profile-level-id` fmtp value (3 hex-encoded bytes) into
+/// `(profile_indication, profile_compatibility, level_indication)`, falling
+/// back to Constrained Baseline level 3.1 if it's missing or malformed
+fn parse_profile_level_id(profile_level_id: Option<&str>) -> (u8, u8, u8) {
+    let bytes = profile_level_id.filter(|hex| hex.len() == 6).and_then(|hex| {
+        let hex = hex.as_bytes();
+        let b0 = hex_byte(hex[0], hex[1])?;
+        let b1 = hex_byte(hex[2], hex[3])?;
+        let b2 = hex_byte(hex[4], hex[5])?;
+        Some((b0, b1, b2))
+    });
+
+    bytes.unwrap_or((0x42, 0xe0, 0x1f))
+}
+
+fn h264_sample_entry(buf: &mut BytesMut, profile_level_id: Option<&str>) {
+    let (profile, compat, level) = parse_profile_level_id(profile_level_id);
+
+    visual_sample_entry(buf, b"avc1", |buf| {
+        write_box(buf, b"avcC", |buf| {
+            buf.put_u8(1); // configurationVersion
+            buf.put_u8(profile);
+            buf.put_u8(compat);
+            buf.put_u8(level);
+            buf.put_u8(0xff); // reserved (6 bits) | lengthSizeMinusOne=3, i.e. 4-byte NAL lengths
+            buf.put_u8(0xe0); // reserved (3 bits) | numOfSequenceParameterSets=0
+            buf.put_u8(0); // numOfPictureParameterSets=0
+        });
+    });
+}
+
+fn vp9_sample_entry(buf: &mut BytesMut) {
+    visual_sample_entry(buf, b"vp09", |buf| {
+        write_box(buf, b"vpcC", |buf| {
+            buf.put_u8(1); // version
+            buf.put_u8(0); // flags (24 bits, high byte)
+            buf.put_u16(0);
+            buf.put_u8(0); // profile
+            buf.put_u8(0); // level
+            buf.put_u8(0x80); // bitDepth=8 (4 bits) | chromaSubsampling=0 (3 bits) | videoFullRangeFlag=0
+            buf.put_u8(1); // colourPrimaries, BT.709
+            buf.put_u8(1); // transferCharacteristics, BT.709
+            buf.put_u8(1); // matrixCoefficients, BT.709
+            buf.put_u16(0); // codecIntializationDataSize
+        });
+    });
+}
+
+fn av1_sample_entry(buf: &mut BytesMut) {
+    visual_sample_entry(buf, b"av01", |buf| {
+        write_box(buf, b"av1C", |buf| {
+            buf.put_u8(0x81); // marker=1, version=1
+            buf.put_u8(0); // seq_profile (3 bits) | seq_level_idx_0 (5 bits)
+            buf.put_u8(0); // seq_tier_0, flags
+            buf.put_u8(0); // reserved, no config OBUs stored here
+        });
+    });
+}
+
+fn stsd(buf: &mut BytesMut, kind: SampleEntryKind, profile_level_id: Option<&str>) {
+    write_box(buf, b"stsd", |buf| {
+        buf.put_u32(0); // version/flags
+        buf.put_u32(1); // entry_count
+        match kind {
+            SampleEntryKind::Opus => opus_sample_entry(buf),
+            SampleEntryKind::Aac => aac_sample_entry(buf),
+            SampleEntryKind::H264 => h264_sample_entry(buf, profile_level_id),
+            SampleEntryKind::Vp9 => vp9_sample_entry(buf),
+            SampleEntryKind::Av1 => av1_sample_entry(buf),
+        }
+    });
+}
+
+fn stbl(buf: &mut BytesMut, kind: SampleEntryKind, profile_level_id: Option<&str>) {
+    write_box(buf, b"stbl", |buf| {
+        stsd(buf, kind, profile_level_id);
+        write_box(buf, b"stts", |buf| {
+            buf.put_u32(0); // version/flags
+            buf.put_u32(0); // entry_count, samples only ever appear in moof/trun
+        });
+        write_box(buf, b"stsc", |buf| {
+            buf.put_u32(0);
+            buf.put_u32(0);
+        });
+        write_box(buf, b"stsz", |buf| {
+            buf.put_u32(0);
+            buf.put_u32(0); // sample_size
+            buf.put_u32(0); // sample_count
+        });
+        write_box(buf, b"stco", |buf| {
+            buf.put_u32(0);
+            buf.put_u32(0);
+        });
+    });
+}
+
+fn minf(buf: &mut BytesMut, kind: SampleEntryKind, profile_level_id: Option<&str>) {
+    write_box(buf, b"minf", |buf| {
+        if kind.is_video() {
+            write_box(buf, b"vmhd", |buf| {
+                buf.put_u32(1); // version/flags: flags=1
+                buf.put_u64(0); // graphicsmode + opcolor
+            });
+        } else {
+            write_box(buf, b"smhd", |buf| {
+                buf.put_u32(0); // version/flags
+                buf.put_u16(0); // balance
+                buf.put_u16(0); // reserved
+            });
+        }
+        dinf(buf);
+        stbl(buf, kind, profile_level_id);
+    });
+}
+
+fn mdia(buf: &mut BytesMut, kind: SampleEntryKind, profile_level_id: Option<&str>) {
+    write_box(buf, b"mdia", |buf| {
+        mdhd(buf);
+        hdlr(buf, kind);
+        minf(buf, kind, profile_level_id);
+    });
+}
+
+fn trak(buf: &mut BytesMut, track_id: u32, kind: SampleEntryKind, profile_level_id: Option<&str>) {
+    write_box(buf, b"trak", |buf| {
+        tkhd(buf, track_id, kind);
+        mdia(buf, kind, profile_level_id);
+    });
+}
+
+fn trex(buf: &mut BytesMut, track_id: u32) {
+    write_box(buf, b"trex", |buf| {
+        buf.put_u32(0); // version/flags
+        buf.put_u32(track_id);
+        buf.put_u32(1); // default_sample_description_index
+        buf.put_u32(0); // default_sample_duration
+        buf.put_u32(0); // default_sample_size
+        buf.put_u32(0); // default_sample_flags
+    });
+}
+
+/// Build the initialization segment (`ftyp` + `moov`) written once at the
+/// start of a track's recording, ahead of any `moof`/`mdat` fragments
+pub fn init_segment(track_id: u32, kind: SampleEntryKind, profile_level_id: Option<&str>) -> Bytes {
+    let mut buf = BytesMut::new();
+    ftyp(&mut buf);
+    write_box(&mut buf, b"moov", |buf| {
+        mvhd(buf, track_id + 1);
+        trak(buf, track_id, kind, profile_level_id);
+        write_box(buf, b"mvex", |buf| trex(buf, track_id));
+    });
+    buf.freeze()
+}
+
+/// `trun` sample flags: byte layout per ISO/IEC 14496-12, set for a
+/// non-sync (non-keyframe) sample that depends on another sample
+const SAMPLE_FLAGS_NON_SYNC: u32 = 0x0001_0000;
+/// `trun` sample flags for a sync sample (keyframe), independent of others
+const SAMPLE_FLAGS_SYNC: u32 = 0x0200_0000;
+
+/// Builds one fragment (`moof` + `mdat`) worth of samples for a track,
+/// accumulating them until the recorder's flush policy decides to emit it
+pub struct FragmentBuilder {
+    track_id: u32,
+    sequence_number: u32,
+    samples: Vec<Mp4Sample>,
+}
+
+impl FragmentBuilder {
+    /// Create a new fragment builder for `track_id`, numbering its first
+    /// emitted fragment `1` as required by `mfhd`
+    pub fn new(track_id: u32) -> Self {
+        Self {
+            track_id,
+            sequence_number: 0,
+            samples: Vec::new(),
+        }
+    }
+
+    /// Buffer a sample into the fragment currently being built
+    pub fn push_sample(&mut self, sample: Mp4Sample) {
+        self.samples.push(sample);
+    }
+
+    /// Whether any samples are buffered for the next fragment
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+
+    /// Total duration, in the track's timescale units, of the buffered samples
+    pub fn buffered_duration(&self) -> u64 {
+        self.samples.iter().map(|s| s.duration as u64).sum()
+    }
+
+    /// Take the buffered samples and encode them as a `moof` + `mdat` pair,
+    /// or `None` if nothing is buffered
+    pub fn take_fragment(&mut self) -> Option<Bytes> {
+        if self.samples.is_empty() {
+            return None;
+        }
+
+        self.sequence_number += 1;
+        let samples = std::mem::take(&mut self.samples);
+
+        let mut buf = BytesMut::new();
+        // `data_offset` in `trun` is relative to the start of `moof`; it's
+        // only known once `moof`'s size is fixed, so it's written as a
+        // placeholder here and patched in after `moof` is complete
+        let moof_start = buf.len();
+        write_box(&mut buf, b"moof", |buf| {
+            write_box(buf, b"mfhd", |buf| {
+                buf.put_u32(0); // version/flags
+                buf.put_u32(self.sequence_number);
+            });
+            write_box(buf, b"traf", |buf| {
+                write_box(buf, b"tfhd", |buf| {
+                    buf.put_u32(0x02_0000); // flags: default-base-is-moof
+                    buf.put_u32(self.track_id);
+                });
+                write_box(buf, b"tfdt", |buf| {
+                    buf.put_u32(1); // version 1: 64-bit base_media_decode_time
+                    buf.put_u64(0); // base_media_decode_time, per-track running clock not tracked here
+                });
+                write_box(buf, b"trun", |buf| {
+                    // flags: data-offset-present, sample-duration/size/flags present
+                    buf.put_u32(0x00_0701);
+                    buf.put_u32(samples.len() as u32);
+                    buf.put_i32(0); // data_offset, patched below once moof's total size is known
+                    for sample in &samples {
+                        buf.put_u32(sample.duration);
+                        buf.put_u32(sample.data.len() as u32);
+                        buf.put_u32(if sample.is_keyframe {
+                            SAMPLE_FLAGS_SYNC
+                        } else {
+                            SAMPLE_FLAGS_NON_SYNC
+                        });
+                    }
+                });
+            });
+        });
+        let moof_len = buf.len() - moof_start;
+
+        let mut mdat_body_len = 0usize;
+        for sample in &samples {
+            mdat_body_len += sample.data.len();
+        }
+        let data_offset = (moof_len + 8) as i32; // moof size + mdat header
+
+        // `trun`'s data_offset can only be computed once `moof`'s total size
+        // is known, so patch it in after the fact rather than threading the
+        // position out of the nested `write_box` closures above
+        if let Some(pos) = find_trun_data_offset(&buf) {
+            buf[pos..pos + 4].copy_from_slice(&data_offset.to_be_bytes());
+        }
+
+        write_box(&mut buf, b"mdat", |buf| {
+            buf.reserve(mdat_body_len);
+            for sample in &samples {
+                buf.put_slice(&sample.data);
+            }
+        });
+
+        Some(buf.freeze())
+    }
+}
+
+/// Locate the `data_offset` field inside the `trun` box written by
+/// `FragmentBuilder::take_fragment`, identified by the box header bytes
+/// rather than a captured offset so the patch survives box-size rewrites
+fn find_trun_data_offset(buf: &[u8]) -> Option<usize> {
+    let pos = buf.windows(4).position(|window| window == b"trun")?;
+    // trun box: [size(4) type(4)] flags(4) sample_count(4) data_offset(4) ...
+    Some(pos + 4 + 4 + 4)
+}
@@ -0,0 +1,191 @@
+// Inter-node relay module for the SFU
+//
+// This module lets tracks published on one SFU node be subscribed to from
+// another. It's modeled as a pub/sub broadcast registry: a node `announce`s
+// a locally-published track to its peers, and a remote node `subscribe`s to
+// pull it over an iroh connection. A single upstream pull fans out to every
+// local subscriber of that remote track.
+
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+};
+
+use anyhow::Result;
+use async_trait::async_trait;
+use iroh::NodeId;
+use serde::{Deserialize, Serialize};
+use tokio::sync::RwLock;
+
+use crate::{connection::ConnectionManager, media::TrackId, session::SessionId};
+
+/// Announce/unannounce/subscribe protocol messages exchanged between broker
+/// instances so a remote node can discover and pull tracks published here
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum RelayMessage {
+    /// A track has been published locally and is available to remote brokers
+    Announce {
+        /// Track identifier, scoped to the announcing node
+        track_id: TrackId,
+        /// Session that published the track
+        source: SessionId,
+    },
+    /// A previously announced track is no longer available
+    Unannounce {
+        /// Track identifier, scoped to the announcing node
+        track_id: TrackId,
+    },
+    /// Pull request for an announced track
+    Subscribe {
+        /// Track identifier, scoped to the node receiving this message
+        track_id: TrackId,
+    },
+    /// Release of a previous pull request
+    Unsubscribe {
+        /// Track identifier, scoped to the node receiving this message
+        track_id: TrackId,
+    },
+}
+
+/// Local fan-out state for a track pulled from a remote node: one upstream
+/// pull feeds every local session subscribed to it
+struct RelayedTrack {
+    /// Local sessions forwarding this relayed track to their own subscribers
+    subscribers: HashSet<SessionId>,
+}
+
+/// Inter-node relay/broker: announces locally-published tracks to peer SFU
+/// nodes and lets this node pull tracks announced by them
+#[async_trait]
+pub trait Broker: Send + Sync {
+    /// Announce a locally-published track as available to remote subscribers
+    async fn announce(&self, track_id: TrackId, source: SessionId) -> Result<()>;
+
+    /// Withdraw a previously announced track
+    async fn unannounce(&self, track_id: TrackId) -> Result<()>;
+
+    /// Subscribe `subscriber` to `remote_track_id` published on `node_id`,
+    /// pulling it over a connection to that node the first time any local
+    /// subscriber needs it
+    async fn subscribe(&self, node_id: NodeId, remote_track_id: TrackId, subscriber: SessionId) -> Result<()>;
+
+    /// Unsubscribe `subscriber` from a remotely-sourced track, tearing down
+    /// the upstream pull once its last local subscriber leaves
+    async fn unsubscribe(&self, node_id: NodeId, remote_track_id: TrackId, subscriber: SessionId) -> Result<()>;
+
+    /// Remote nodes currently pulling a locally-announced track, if any have
+    /// been recorded by an inbound `RelayMessage::Subscribe`. Always empty
+    /// until that wire handshake is implemented (see `subscribe`'s placeholder note).
+    async fn remote_subscribers(&self, track_id: TrackId) -> Result<Vec<NodeId>>;
+}
+
+/// Default implementation of the inter-node relay/broker
+pub struct DefaultBroker {
+    /// Used to open a connection to a remote node on the first pull of one
+    /// of its tracks
+    connection_manager: Arc<dyn ConnectionManager>,
+    /// Tracks published locally and announced to remote brokers, mapped to
+    /// the local session that published them
+    announced: Arc<RwLock<HashMap<TrackId, SessionId>>>,
+    /// Tracks pulled from remote nodes, keyed by the announcing node and its
+    /// track identifier
+    relayed: Arc<RwLock<HashMap<(NodeId, TrackId), RelayedTrack>>>,
+}
+
+impl DefaultBroker {
+    /// Create a new broker that opens relay connections via `connection_manager`
+    pub fn new(connection_manager: Arc<dyn ConnectionManager>) -> Self {
+        Self {
+            connection_manager,
+            announced: Arc::new(RwLock::new(HashMap::new())),
+            relayed: Arc::new(RwLock::new(HashMap::new())),
+        }
+    }
+}
+
+#[async_trait]
+impl Broker for DefaultBroker {
+    async fn announce(&self, track_id: TrackId, source: SessionId) -> Result<()> {
+        let mut announced = self.announced.write().await;
+        announced.insert(track_id, source);
+
+        // This is a placeholder - actual implementation would broadcast
+        // `RelayMessage::Announce` to every peer broker this node gossips with
+        tracing::debug!("Announced track {} from session {} to peer brokers", track_id, source);
+
+        Ok(())
+    }
+
+    async fn unannounce(&self, track_id: TrackId) -> Result<()> {
+        let mut announced = self.announced.write().await;
+        announced.remove(&track_id);
+
+        // This is a placeholder - actual implementation would broadcast
+        // `RelayMessage::Unannounce` to every peer broker this node gossips with
+        tracing::debug!("Unannounced track {}", track_id);
+
+        Ok(())
+    }
+
+    async fn subscribe(&self, node_id: NodeId, remote_track_id: TrackId, subscriber: SessionId) -> Result<()> {
+        let mut relayed = self.relayed.write().await;
+        let key = (node_id, remote_track_id);
+
+        if let Some(track) = relayed.get_mut(&key) {
+            track.subscribers.insert(subscriber);
+            return Ok(());
+        }
+
+        // First local subscriber for this remote track: open a connection to
+        // its node and pull it upstream. This is a placeholder - actual
+        // implementation would send `RelayMessage::Subscribe` over the
+        // connection and forward the resulting iroh_roq receive flow's
+        // packets to every session in `subscribers`.
+        let connection = self
+            .connection_manager
+            .connect(iroh::NodeAddr::from(node_id))
+            .await?;
+        tracing::info!(
+            "Pulling track {} from node {} for first local subscriber {}",
+            remote_track_id,
+            connection.remote_node_id(),
+            subscriber,
+        );
+
+        let mut subscribers = HashSet::new();
+        subscribers.insert(subscriber);
+        relayed.insert(key, RelayedTrack { subscribers });
+
+        Ok(())
+    }
+
+    async fn unsubscribe(&self, node_id: NodeId, remote_track_id: TrackId, subscriber: SessionId) -> Result<()> {
+        let mut relayed = self.relayed.write().await;
+        let key = (node_id, remote_track_id);
+
+        let Some(track) = relayed.get_mut(&key) else {
+            return Ok(());
+        };
+
+        track.subscribers.remove(&subscriber);
+
+        if track.subscribers.is_empty() {
+            relayed.remove(&key);
+            tracing::info!(
+                "Last local subscriber left track {} from node {}, stopping upstream pull",
+                remote_track_id,
+                node_id,
+            );
+        }
+
+        Ok(())
+    }
+
+    async fn remote_subscribers(&self, track_id: TrackId) -> Result<Vec<NodeId>> {
+        let _ = track_id;
+        // Placeholder - no real wire handshake receives a peer's
+        // `RelayMessage::Subscribe` yet, so this node doesn't yet know which
+        // remote nodes have pulled a given announced track
+        Ok(Vec::new())
+    }
+}
@@ -0,0 +1,98 @@
+// Adaptive bitrate layer selection for simulcast/SVC video tracks
+//
+// Per-subscription state tracking which layer of a published video track to
+// forward, driven by the subscriber's estimated download bandwidth.
+
+use crate::simulcast::LayerId;
+
+/// Safety margin applied to a subscriber's estimated download bandwidth
+/// before it is compared against layer bitrates, so the selection leaves
+/// headroom rather than targeting the estimate exactly
+const SAFETY_FACTOR: f32 = 0.9;
+
+/// Consecutive bandwidth samples a layer must stay below its floor (to
+/// downgrade) or above the next layer's target (to upgrade) before the
+/// selection actually changes, preventing oscillation between layers
+const HYSTERESIS_SAMPLES: u32 = 3;
+
+/// One simulcast/SVC layer a published video track can be forwarded at
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TrackLayer {
+    /// Spatial layer index (0 = lowest)
+    pub spatial_id: LayerId,
+    /// Target bitrate for this layer in bps
+    pub target_bitrate: u32,
+}
+
+/// Per-subscription adaptive bitrate state: the currently selected layer and
+/// the hysteresis counters guarding against flapping between layers
+pub struct AbrState {
+    /// Layer currently selected for forwarding
+    selected_layer: LayerId,
+    /// Consecutive samples where the budget fell below the selected layer's floor
+    below_floor_count: u32,
+    /// Consecutive samples where the budget stayed above the next layer's target
+    above_next_count: u32,
+}
+
+impl AbrState {
+    /// Create a new ABR state, initially selecting the lowest layer
+    pub fn new() -> Self {
+        Self {
+            selected_layer: 0,
+            below_floor_count: 0,
+            above_next_count: 0,
+        }
+    }
+
+    /// Currently selected layer
+    pub fn selected_layer(&self) -> LayerId {
+        self.selected_layer
+    }
+
+    /// Re-run layer selection given `layers` (ascending spatial order) and
+    /// the subscriber's latest `download_bandwidth` estimate, applying
+    /// hysteresis so the selection only moves after `HYSTERESIS_SAMPLES`
+    /// consecutive samples support the move
+    pub fn update(&mut self, layers: &[TrackLayer], download_bandwidth: u32) {
+        if layers.is_empty() {
+            return;
+        }
+
+        let budget = (download_bandwidth as f32 * SAFETY_FACTOR) as u32;
+
+        let current_index = layers
+            .iter()
+            .position(|layer| layer.spatial_id == self.selected_layer)
+            .unwrap_or(0);
+        let current = &layers[current_index];
+        let next = layers.get(current_index + 1);
+
+        if budget < current.target_bitrate {
+            self.below_floor_count += 1;
+            self.above_next_count = 0;
+        } else if next.is_some_and(|next| budget >= next.target_bitrate) {
+            self.above_next_count += 1;
+            self.below_floor_count = 0;
+        } else {
+            self.below_floor_count = 0;
+            self.above_next_count = 0;
+        }
+
+        if self.below_floor_count >= HYSTERESIS_SAMPLES && current_index > 0 {
+            self.selected_layer = layers[current_index - 1].spatial_id;
+            self.below_floor_count = 0;
+        } else if self.above_next_count >= HYSTERESIS_SAMPLES {
+            if let Some(next) = next {
+                self.selected_layer = next.spatial_id;
+            }
+            self.above_next_count = 0;
+        }
+    }
+}
+
+impl Default for AbrState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -2,10 +2,16 @@
 //
 // This module handles participant sessions and their associated streams.
 
+pub mod abr;
+pub mod stats;
+
 use std::{
     collections::{HashMap, HashSet},
-    sync::{Arc, Mutex},
-    time::Instant,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
 };
 
 use anyhow::Result;
@@ -13,11 +19,25 @@ use async_trait::async_trait;
 use tokio::sync::RwLock;
 
 use crate::{
+    auth::{Grants, TokenVerifier},
+    clock::{Clock, SystemClock},
     connection::RtcConnection,
-    media::{MediaTrack, TrackId, TrackKind},
+    feedback::FeedbackKind,
+    media::{codec::CodecType, MediaTrack, ScalabilityMode, TrackId, TrackKind},
+    recording::Recorder,
+    relay::Broker,
+    session::{
+        abr::{AbrState, TrackLayer},
+        stats::{PublishedTrackSnapshot, RoomStatsSnapshot, SessionSnapshot, StatsSink, SubscribedTrackSnapshot},
+    },
+    simulcast::LayerId,
+    stats::StatsCollector,
     SfuError,
 };
 
+/// How long a bandwidth estimate is trusted before it's considered stale
+const BANDWIDTH_ESTIMATE_MAX_AGE: Duration = Duration::from_secs(5);
+
 /// Unique identifier for a participant session
 pub type SessionId = u64;
 
@@ -37,6 +57,23 @@ pub struct Participant {
     pub bandwidth: BandwidthInfo,
     /// Last activity timestamp
     pub last_activity: Instant,
+    /// Capabilities granted to this session by its verified token
+    pub grants: Grants,
+    /// RTCP feedback mechanisms negotiated per codec during `SessionInit`,
+    /// keyed by codec name. Consulted by the `feedback` module to gate
+    /// whether it generates PLI/NACK/transport-cc for a given track.
+    pub negotiated_feedback: HashMap<String, HashSet<FeedbackKind>>,
+}
+
+impl Participant {
+    /// Currently selected ABR layer for a subscribed video track, or the
+    /// lowest layer if the track isn't subscribed or has no selection yet
+    pub fn selected_layer(&self, track_id: TrackId) -> LayerId {
+        self.subscribed_tracks
+            .get(&track_id)
+            .map(|track| track.abr.selected_layer())
+            .unwrap_or(0)
+    }
 }
 
 /// Information about a track published by a participant
@@ -51,18 +88,46 @@ pub struct PublishedTrack {
     pub codec: CodecInfo,
     /// Current bitrate
     pub current_bitrate: u32,
+    /// Target bitrate, as last set for this track
+    pub target_bitrate: u32,
     /// Subscribers to this track
     pub subscribers: HashSet<SessionId>,
+    /// Simulcast/SVC layers available for this track, ascending by spatial_id
+    pub layers: Vec<TrackLayer>,
+    /// Declared spatial/temporal layer structure from the publisher's
+    /// `scalability-mode` codec parameter, if it declared one. Bounds which
+    /// `ActivateLayers` spatial/temporal indices the simulcast manager accepts.
+    pub scalability_mode: Option<ScalabilityMode>,
+    /// Total bytes forwarded to subscribers since this track was published
+    pub bytes_forwarded: AtomicU64,
+    /// Total packets forwarded to subscribers since this track was published
+    pub packets_forwarded: AtomicU64,
+}
+
+/// Where a subscribed track's publisher lives: a session on this node, or a
+/// session on a remote node reached through the inter-node relay/broker
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PublisherLocation {
+    /// Published by a session on this node
+    Local(SessionId),
+    /// Published by a session on a remote node, pulled through the `Broker`
+    Remote(iroh::NodeId, SessionId),
 }
 
 /// Information about a track subscribed to by a participant
 pub struct SubscribedTrack {
     /// Unique track identifier
     pub track_id: TrackId,
-    /// Publisher of this track
-    pub publisher_id: SessionId,
+    /// Where this track's publisher lives
+    pub publisher: PublisherLocation,
     /// Media receiver
     pub receiver: MediaTrackReceiver,
+    /// Adaptive bitrate layer selection state for this subscription
+    pub abr: AbrState,
+    /// Total bytes forwarded to this subscriber since it subscribed
+    pub bytes_received: AtomicU64,
+    /// Total packets forwarded to this subscriber since it subscribed
+    pub packets_received: AtomicU64,
 }
 
 /// Bandwidth information for a participant
@@ -75,7 +140,16 @@ pub struct BandwidthInfo {
     pub last_update: Instant,
 }
 
+impl BandwidthInfo {
+    /// Whether the current estimate is older than `BANDWIDTH_ESTIMATE_MAX_AGE`
+    /// as of `now`, and so should no longer be trusted for layer selection
+    pub fn is_stale(&self, now: Instant) -> bool {
+        now.duration_since(self.last_update) > BANDWIDTH_ESTIMATE_MAX_AGE
+    }
+}
+
 /// Codec information
+#[derive(Clone)]
 pub struct CodecInfo {
     /// Codec name
     pub name: String,
@@ -91,8 +165,14 @@ pub struct MediaTrackReceiver {
 /// Session manager trait
 #[async_trait]
 pub trait SessionManager: Send + Sync {
-    /// Create a new session for a participant
-    async fn create_session(&self, node_id: iroh::NodeId, connection: RtcConnection) -> Result<SessionId>;
+    /// Create a new session for a participant, verifying `token` into the
+    /// `Grants` enforced by `register_published_track`/`register_subscribed_track`
+    async fn create_session(
+        &self,
+        node_id: iroh::NodeId,
+        connection: RtcConnection,
+        token: &str,
+    ) -> Result<SessionId>;
 
     /// Get a participant by session ID
     async fn get_participant(&self, session_id: SessionId) -> Result<Arc<RwLock<Participant>>>;
@@ -107,11 +187,12 @@ pub trait SessionManager: Send + Sync {
         track: MediaTrack,
     ) -> Result<TrackId>;
 
-    /// Register a subscribed track
+    /// Register a subscribed track, transparently setting up cross-node
+    /// forwarding through the `Broker` when `publisher` is `Remote`
     async fn register_subscribed_track(
         &self,
         subscriber_id: SessionId,
-        publisher_id: SessionId,
+        publisher: PublisherLocation,
         track_id: TrackId,
     ) -> Result<()>;
 
@@ -130,6 +211,37 @@ pub trait SessionManager: Send + Sync {
 
     /// Get all subscribed tracks for a session
     async fn get_subscribed_tracks(&self, session_id: SessionId) -> Result<Vec<TrackId>>;
+
+    /// Record a fresh upload/download bandwidth estimate for a session,
+    /// aging out the previous sample, and re-run ABR layer selection for
+    /// every video track this session subscribes to
+    async fn update_bandwidth(&self, session_id: SessionId, upload: u32, download: u32) -> Result<()>;
+
+    /// Set the simulcast/SVC layers (ascending by spatial_id) available for
+    /// a published video track, consumed by subscribers' ABR selection
+    async fn set_track_layers(
+        &self,
+        publisher_id: SessionId,
+        track_id: TrackId,
+        layers: Vec<TrackLayer>,
+    ) -> Result<()>;
+
+    /// Store the per-codec RTCP feedback mechanisms negotiated for a session
+    /// during `SessionInit`, so `feedback` can gate what it generates per
+    /// track against what was actually agreed
+    async fn set_negotiated_feedback(
+        &self,
+        session_id: SessionId,
+        feedback: HashMap<String, HashSet<FeedbackKind>>,
+    ) -> Result<()>;
+
+    /// Build a live snapshot of every session's and track's counters,
+    /// ready to be serialized to JSON for scraping
+    async fn stats_snapshot(&self) -> Result<RoomStatsSnapshot>;
+
+    /// Build a stats snapshot and push it to `sink`, for deployments that
+    /// want to forward it to their own metrics backend on a timer
+    async fn emit_stats(&self, sink: &dyn StatsSink) -> Result<()>;
 }
 
 /// Default implementation of the session manager
@@ -137,18 +249,56 @@ pub struct DefaultSessionManager {
     participants: Arc<RwLock<HashMap<SessionId, Arc<RwLock<Participant>>>>>,
     next_session_id: Arc<Mutex<SessionId>>,
     next_track_id: Arc<Mutex<TrackId>>,
+    token_verifier: Arc<dyn TokenVerifier>,
+    /// Optional recorder notified of newly published tracks, set via `set_recorder`
+    recorder: RwLock<Option<Arc<dyn Recorder>>>,
+    /// Optional inter-node broker used to pull tracks published on remote
+    /// nodes, set via `set_broker`
+    broker: RwLock<Option<Arc<dyn Broker>>>,
+    /// Optional stats collector read from in `stats_snapshot` for
+    /// RTCP-derived figures (currently just `packet_loss_percent`) this
+    /// manager has no other source for, set via `set_stats_collector`
+    stats_collector: RwLock<Option<Arc<dyn StatsCollector>>>,
+    /// Source of the current time for activity and bandwidth timestamps
+    clock: Arc<dyn Clock>,
 }
 
 impl DefaultSessionManager {
-    /// Create a new session manager
-    pub fn new() -> Self {
+    /// Create a new session manager that verifies session tokens with `token_verifier`
+    pub fn new(token_verifier: Arc<dyn TokenVerifier>) -> Self {
+        Self::with_clock(token_verifier, Arc::new(SystemClock))
+    }
+
+    /// Create a new session manager reading the time from `clock`, so tests
+    /// can advance it deterministically instead of sleeping for real
+    pub fn with_clock(token_verifier: Arc<dyn TokenVerifier>, clock: Arc<dyn Clock>) -> Self {
         Self {
             participants: Arc::new(RwLock::new(HashMap::new())),
             next_session_id: Arc::new(Mutex::new(1)),
             next_track_id: Arc::new(Mutex::new(1)),
+            token_verifier,
+            recorder: RwLock::new(None),
+            broker: RwLock::new(None),
+            stats_collector: RwLock::new(None),
+            clock,
         }
     }
 
+    /// Attach a recorder to be notified of every subsequently published track
+    pub async fn set_recorder(&self, recorder: Arc<dyn Recorder>) {
+        *self.recorder.write().await = Some(recorder);
+    }
+
+    /// Attach a broker used to pull tracks published on remote nodes
+    pub async fn set_broker(&self, broker: Arc<dyn Broker>) {
+        *self.broker.write().await = Some(broker);
+    }
+
+    /// Attach a stats collector queried by `stats_snapshot` for RTCP-derived figures
+    pub async fn set_stats_collector(&self, stats_collector: Arc<dyn StatsCollector>) {
+        *self.stats_collector.write().await = Some(stats_collector);
+    }
+
     /// Generate a new session ID
     fn generate_session_id(&self) -> SessionId {
         let mut id = self.next_session_id.lock().unwrap();
@@ -168,9 +318,16 @@ impl DefaultSessionManager {
 
 #[async_trait]
 impl SessionManager for DefaultSessionManager {
-    async fn create_session(&self, node_id: iroh::NodeId, connection: RtcConnection) -> Result<SessionId> {
+    async fn create_session(
+        &self,
+        node_id: iroh::NodeId,
+        connection: RtcConnection,
+        token: &str,
+    ) -> Result<SessionId> {
+        let grants = self.token_verifier.verify(token)?;
+
         let session_id = self.generate_session_id();
-        
+
         let participant = Participant {
             session_id,
             node_id,
@@ -180,14 +337,16 @@ impl SessionManager for DefaultSessionManager {
             bandwidth: BandwidthInfo {
                 upload_bandwidth: 0,
                 download_bandwidth: 0,
-                last_update: Instant::now(),
+                last_update: self.clock.now(),
             },
-            last_activity: Instant::now(),
+            last_activity: self.clock.now(),
+            grants,
+            negotiated_feedback: HashMap::new(),
         };
-        
+
         let mut participants = self.participants.write().await;
         participants.insert(session_id, Arc::new(RwLock::new(participant)));
-        
+
         Ok(session_id)
     }
 
@@ -216,61 +375,140 @@ impl SessionManager for DefaultSessionManager {
         track: MediaTrack,
     ) -> Result<TrackId> {
         let participant = self.get_participant(session_id).await?;
-        let track_id = self.generate_track_id();
-        
         let mut participant = participant.write().await;
-        
+
+        if !participant.grants.can_publish {
+            return Err(SfuError::Unauthorized(format!(
+                "session {} is not granted publish access",
+                session_id
+            ))
+            .into());
+        }
+
+        let kind = track.kind();
+        let codec_type = CodecType::from_name(track.codec_name());
+        if !codec_type.is_some_and(|codec_type| participant.grants.allows_codec(codec_type)) {
+            return Err(SfuError::Unauthorized(format!(
+                "session {} is not granted publish access for codec {}",
+                session_id,
+                track.codec_name()
+            ))
+            .into());
+        }
+
+        if let Some(cap) = participant.grants.publish_cap(kind.name()) {
+            let published_of_kind = participant
+                .published_tracks
+                .values()
+                .filter(|t| t.kind == kind)
+                .count() as u32;
+            if published_of_kind >= cap {
+                return Err(SfuError::Unauthorized(format!(
+                    "session {} has reached its publish cap for {} tracks",
+                    session_id,
+                    kind.name()
+                ))
+                .into());
+            }
+        }
+
+        let track_id = self.generate_track_id();
+        let scalability_mode = track.scalability_mode();
+        let codec = CodecInfo {
+            name: track.codec_name().to_string(),
+            parameters: track.codec_parameters(),
+        };
+
         let published_track = PublishedTrack {
             track_id,
             publisher_id: session_id,
-            kind: track.kind(),
-            codec: CodecInfo {
-                name: track.codec_name().to_string(),
-                parameters: track.codec_parameters(),
-            },
+            kind,
+            codec: codec.clone(),
             current_bitrate: 0,
+            target_bitrate: 0,
             subscribers: HashSet::new(),
+            layers: Vec::new(),
+            scalability_mode,
+            bytes_forwarded: AtomicU64::new(0),
+            packets_forwarded: AtomicU64::new(0),
         };
-        
+
         participant.published_tracks.insert(track_id, published_track);
-        
+        drop(participant);
+
+        if let Some(recorder) = self.recorder.read().await.clone() {
+            if let Err(e) = recorder.start(track_id, kind, &codec).await {
+                tracing::warn!("Recorder declined track {}: {}", track_id, e);
+            }
+        }
+
+        if let Some(broker) = self.broker.read().await.clone() {
+            if let Err(e) = broker.announce(track_id, session_id).await {
+                tracing::warn!("Failed to announce track {} to peer brokers: {}", track_id, e);
+            }
+        }
+
         Ok(track_id)
     }
 
     async fn register_subscribed_track(
         &self,
         subscriber_id: SessionId,
-        publisher_id: SessionId,
+        publisher: PublisherLocation,
         track_id: TrackId,
     ) -> Result<()> {
-        let publisher = self.get_participant(publisher_id).await?;
         let subscriber = self.get_participant(subscriber_id).await?;
-        
-        // Check if the track exists
-        let publisher_read = publisher.read().await;
-        if !publisher_read.published_tracks.contains_key(&track_id) {
-            return Err(SfuError::Media(format!("Track not found: {}", track_id)).into());
+
+        if !subscriber.read().await.grants.can_subscribe {
+            return Err(SfuError::Unauthorized(format!(
+                "session {} is not granted subscribe access",
+                subscriber_id
+            ))
+            .into());
         }
-        
-        // Add subscriber to the track
-        let mut publisher = publisher.write().await;
-        if let Some(track) = publisher.published_tracks.get_mut(&track_id) {
-            track.subscribers.insert(subscriber_id);
+
+        match publisher {
+            PublisherLocation::Local(publisher_id) => {
+                let local_publisher = self.get_participant(publisher_id).await?;
+
+                // Check if the track exists
+                let publisher_read = local_publisher.read().await;
+                if !publisher_read.published_tracks.contains_key(&track_id) {
+                    return Err(SfuError::Media(format!("Track not found: {}", track_id)).into());
+                }
+                drop(publisher_read);
+
+                // Add subscriber to the track
+                let mut local_publisher = local_publisher.write().await;
+                if let Some(track) = local_publisher.published_tracks.get_mut(&track_id) {
+                    track.subscribers.insert(subscriber_id);
+                }
+            }
+            PublisherLocation::Remote(node_id, remote_session_id) => {
+                let broker = self.broker.read().await.clone().ok_or_else(|| {
+                    SfuError::Session("no broker configured for remote track subscription".to_string())
+                })?;
+                let _ = remote_session_id;
+                broker.subscribe(node_id, track_id, subscriber_id).await?;
+            }
         }
-        
+
         // Create a receiver for the subscriber
         let mut subscriber = subscriber.write().await;
         subscriber.subscribed_tracks.insert(
             track_id,
             SubscribedTrack {
                 track_id,
-                publisher_id,
+                publisher,
                 receiver: MediaTrackReceiver {
                     // Implementation details will be added later
                 },
+                abr: AbrState::new(),
+                bytes_received: AtomicU64::new(0),
+                packets_received: AtomicU64::new(0),
             },
         );
-        
+
         Ok(())
     }
 
@@ -280,30 +518,39 @@ impl SessionManager for DefaultSessionManager {
         track_id: TrackId,
     ) -> Result<()> {
         let subscriber = self.get_participant(subscriber_id).await?;
-        
-        // Get the publisher ID from the subscribed track
-        let publisher_id = {
+
+        // Get the publisher location from the subscribed track
+        let publisher = {
             let subscriber_read = subscriber.read().await;
             match subscriber_read.subscribed_tracks.get(&track_id) {
-                Some(track) => track.publisher_id,
+                Some(track) => track.publisher,
                 None => return Err(SfuError::Media(format!("Track not subscribed: {}", track_id)).into()),
             }
         };
-        
+
         // Remove the track from the subscriber
         {
             let mut subscriber = subscriber.write().await;
             subscriber.subscribed_tracks.remove(&track_id);
         }
-        
-        // Remove the subscriber from the publisher's track
-        if let Ok(publisher) = self.get_participant(publisher_id).await {
-            let mut publisher = publisher.write().await;
-            if let Some(track) = publisher.published_tracks.get_mut(&track_id) {
-                track.subscribers.remove(&subscriber_id);
+
+        match publisher {
+            PublisherLocation::Local(publisher_id) => {
+                // Remove the subscriber from the publisher's track
+                if let Ok(publisher) = self.get_participant(publisher_id).await {
+                    let mut publisher = publisher.write().await;
+                    if let Some(track) = publisher.published_tracks.get_mut(&track_id) {
+                        track.subscribers.remove(&subscriber_id);
+                    }
+                }
+            }
+            PublisherLocation::Remote(node_id, _remote_session_id) => {
+                if let Some(broker) = self.broker.read().await.clone() {
+                    broker.unsubscribe(node_id, track_id, subscriber_id).await?;
+                }
             }
         }
-        
+
         Ok(())
     }
 
@@ -323,11 +570,162 @@ impl SessionManager for DefaultSessionManager {
         let participant = participant.read().await;
         Ok(participant.subscribed_tracks.keys().cloned().collect())
     }
-}
 
-// Default implementation
-impl Default for DefaultSessionManager {
-    fn default() -> Self {
-        Self::new()
+    async fn update_bandwidth(&self, session_id: SessionId, upload: u32, download: u32) -> Result<()> {
+        let participant_arc = self.get_participant(session_id).await?;
+
+        let subscriptions: Vec<(TrackId, SessionId)> = {
+            let mut participant = participant_arc.write().await;
+            participant.bandwidth.upload_bandwidth = upload;
+            participant.bandwidth.download_bandwidth = download;
+            participant.bandwidth.last_update = self.clock.now();
+
+            participant
+                .subscribed_tracks
+                .values()
+                .filter_map(|track| match track.publisher {
+                    PublisherLocation::Local(publisher_id) => Some((track.track_id, publisher_id)),
+                    // Remote publishers advertise layers through the broker,
+                    // not the local published-track registry; not yet wired up
+                    PublisherLocation::Remote(..) => None,
+                })
+                .collect()
+        };
+
+        // Re-run ABR layer selection for every subscribed video track using
+        // the publisher's advertised layers and this session's fresh estimate
+        for (track_id, publisher_id) in subscriptions {
+            let layers = match self.get_participant(publisher_id).await {
+                Ok(publisher) => publisher
+                    .read()
+                    .await
+                    .published_tracks
+                    .get(&track_id)
+                    .map(|track| track.layers.clone()),
+                Err(_) => None,
+            };
+
+            let Some(layers) = layers else { continue };
+            if layers.is_empty() {
+                continue;
+            }
+
+            let mut participant = participant_arc.write().await;
+            if let Some(track) = participant.subscribed_tracks.get_mut(&track_id) {
+                track.abr.update(&layers, download);
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn set_track_layers(
+        &self,
+        publisher_id: SessionId,
+        track_id: TrackId,
+        layers: Vec<TrackLayer>,
+    ) -> Result<()> {
+        let participant = self.get_participant(publisher_id).await?;
+        let mut participant = participant.write().await;
+
+        let track = participant
+            .published_tracks
+            .get_mut(&track_id)
+            .ok_or_else(|| SfuError::Media(format!("Track not found: {}", track_id)))?;
+
+        track.layers = layers;
+
+        Ok(())
+    }
+
+    async fn set_negotiated_feedback(
+        &self,
+        session_id: SessionId,
+        feedback: HashMap<String, HashSet<FeedbackKind>>,
+    ) -> Result<()> {
+        let participant = self.get_participant(session_id).await?;
+        participant.write().await.negotiated_feedback = feedback;
+
+        Ok(())
+    }
+
+    async fn stats_snapshot(&self) -> Result<RoomStatsSnapshot> {
+        let now = self.clock.now();
+        let participants = self.participants.read().await;
+        let stats_collector = self.stats_collector.read().await.clone();
+
+        let mut sessions = Vec::with_capacity(participants.len());
+        let mut total_bytes_forwarded = 0u64;
+        let mut total_packets_forwarded = 0u64;
+        let mut total_subscriber_count = 0usize;
+
+        for participant in participants.values() {
+            let participant = participant.read().await;
+
+            let mut published_tracks: Vec<PublishedTrackSnapshot> =
+                Vec::with_capacity(participant.published_tracks.len());
+            for track in participant.published_tracks.values() {
+                let bytes_forwarded = track.bytes_forwarded.load(Ordering::Relaxed);
+                let packets_forwarded = track.packets_forwarded.load(Ordering::Relaxed);
+                let subscriber_count = track.subscribers.len();
+
+                total_bytes_forwarded += bytes_forwarded;
+                total_packets_forwarded += packets_forwarded;
+                total_subscriber_count += subscriber_count;
+
+                // RTCP-derived figure; 0.0 if no stats collector is wired up
+                // or it has not yet seen a receiver report for this track
+                let packet_loss_percent = match &stats_collector {
+                    Some(stats_collector) => stats_collector
+                        .get_track_stats(participant.session_id, track.track_id, true)
+                        .await
+                        .map(|stats| stats.packet_loss_percent)
+                        .unwrap_or(0.0),
+                    None => 0.0,
+                };
+
+                published_tracks.push(PublishedTrackSnapshot {
+                    track_id: track.track_id,
+                    kind: track.kind.name(),
+                    bytes_forwarded,
+                    packets_forwarded,
+                    current_bitrate: track.current_bitrate,
+                    target_bitrate: track.target_bitrate,
+                    packet_loss_percent,
+                    subscriber_count,
+                });
+            }
+
+            let subscribed_tracks: Vec<SubscribedTrackSnapshot> = participant
+                .subscribed_tracks
+                .values()
+                .map(|track| SubscribedTrackSnapshot {
+                    track_id: track.track_id,
+                    bytes_received: track.bytes_received.load(Ordering::Relaxed),
+                    packets_received: track.packets_received.load(Ordering::Relaxed),
+                    selected_layer: track.abr.selected_layer(),
+                })
+                .collect();
+
+            sessions.push(SessionSnapshot {
+                session_id: participant.session_id,
+                uptime_secs: now.duration_since(participant.last_activity).as_secs(),
+                published_tracks,
+                subscribed_tracks,
+            });
+        }
+
+        Ok(RoomStatsSnapshot {
+            sessions,
+            total_bytes_forwarded,
+            total_packets_forwarded,
+            total_subscriber_count,
+        })
+    }
+
+    async fn emit_stats(&self, sink: &dyn StatsSink) -> Result<()> {
+        let snapshot = self.stats_snapshot().await?;
+        sink.on_stats(&snapshot);
+        Ok(())
     }
 }
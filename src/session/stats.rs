@@ -0,0 +1,79 @@
+// Live observability snapshots for the session/track tree
+//
+// A `stats_snapshot()` call walks every session and its tracks, reading
+// their atomic forwarding counters, and rolls them up into a serializable
+// `RoomStatsSnapshot` tree: room totals at the top, one `SessionSnapshot`
+// per participant, and one track snapshot per published/subscribed track.
+
+use serde::Serialize;
+
+use crate::{media::TrackId, session::SessionId, simulcast::LayerId};
+
+/// Live counters for one published track
+#[derive(Debug, Clone, Serialize)]
+pub struct PublishedTrackSnapshot {
+    /// Track identifier
+    pub track_id: TrackId,
+    /// Media kind, as returned by `TrackKind::name()`
+    pub kind: &'static str,
+    /// Total bytes forwarded to subscribers since this track was published
+    pub bytes_forwarded: u64,
+    /// Total packets forwarded to subscribers since this track was published
+    pub packets_forwarded: u64,
+    /// Current publisher bitrate in bps
+    pub current_bitrate: u32,
+    /// Target bitrate in bps, as last set for this track
+    pub target_bitrate: u32,
+    /// Packet loss percentage observed on this track, or 0 if no RTCP
+    /// receiver reports have been ingested for it yet
+    pub packet_loss_percent: f32,
+    /// Number of sessions currently subscribed to this track
+    pub subscriber_count: usize,
+}
+
+/// Live counters for one subscribed track
+#[derive(Debug, Clone, Serialize)]
+pub struct SubscribedTrackSnapshot {
+    /// Track identifier, scoped to the publisher
+    pub track_id: TrackId,
+    /// Total bytes forwarded to this subscriber since it subscribed
+    pub bytes_received: u64,
+    /// Total packets forwarded to this subscriber since it subscribed
+    pub packets_received: u64,
+    /// Simulcast/SVC layer currently selected by ABR for this subscription
+    pub selected_layer: LayerId,
+}
+
+/// Live counters for one participant session
+#[derive(Debug, Clone, Serialize)]
+pub struct SessionSnapshot {
+    /// Session identifier
+    pub session_id: SessionId,
+    /// How long this session has been connected, in seconds
+    pub uptime_secs: u64,
+    /// Tracks this session publishes
+    pub published_tracks: Vec<PublishedTrackSnapshot>,
+    /// Tracks this session subscribes to
+    pub subscribed_tracks: Vec<SubscribedTrackSnapshot>,
+}
+
+/// Room-wide stats snapshot: every session plus aggregate totals, ready to
+/// be serialized to JSON for scraping or pushed to a `StatsSink`
+#[derive(Debug, Clone, Serialize)]
+pub struct RoomStatsSnapshot {
+    /// Per-session snapshots
+    pub sessions: Vec<SessionSnapshot>,
+    /// Sum of `bytes_forwarded` across every published track in the room
+    pub total_bytes_forwarded: u64,
+    /// Sum of `packets_forwarded` across every published track in the room
+    pub total_packets_forwarded: u64,
+    /// Sum of `subscriber_count` across every published track in the room
+    pub total_subscriber_count: usize,
+}
+
+/// Receives room stats snapshots pushed by `SessionManager::emit_stats`, so
+/// deployments can forward them to their own metrics backend
+pub trait StatsSink: Send + Sync {
+    /// Handle a freshly computed snapshot
+    fn on_stats(&self, snapshot: &RoomStatsSnapshot);
+}
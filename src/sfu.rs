@@ -8,10 +8,13 @@ use anyhow::Result;
 use tokio::sync::RwLock;
 
 use crate::{
+    auth::InsecureJsonTokenVerifier,
     bandwidth::{BandwidthManager, DefaultBandwidthManager},
     connection::{ConnectionManager, DefaultConnectionManager},
     feedback::{FeedbackManager, DefaultFeedbackManager},
     media::{MediaRouter, DefaultMediaRouter},
+    recording::{DefaultRecorder, RecordingConfig},
+    relay::{Broker, DefaultBroker},
     session::{SessionManager, DefaultSessionManager},
     signaling::{SignalingProtocol, DefaultSignalingProtocol},
     simulcast::{SimulcastManager, DefaultSimulcastManager},
@@ -29,6 +32,8 @@ pub struct SfuConfig {
     pub enable_simulcast: bool,
     /// Enable feedback
     pub enable_feedback: bool,
+    /// Record published tracks to fragmented MP4, if configured
+    pub recording: Option<RecordingConfig>,
 }
 
 impl Default for SfuConfig {
@@ -38,6 +43,7 @@ impl Default for SfuConfig {
             max_bitrate_per_participant: 5_000_000, // 5 Mbps
             enable_simulcast: true,
             enable_feedback: true,
+            recording: None,
         }
     }
 }
@@ -71,33 +77,65 @@ pub struct Sfu {
 impl Sfu {
     /// Create a new SFU with default components
     pub async fn new(endpoint: iroh::endpoint::Endpoint, config: SfuConfig) -> Result<Self> {
-        // Create session manager
-        let session_manager = Arc::new(DefaultSessionManager::new());
-        
         // Create connection manager
         let connection_manager = Arc::new(DefaultConnectionManager::new(endpoint));
-        
+
+        // Create session manager. `InsecureJsonTokenVerifier` is a placeholder
+        // that trusts any well-formed token; deployments should supply a
+        // `TokenVerifier` backed by a real signing scheme.
+        let session_manager_impl = DefaultSessionManager::new(Arc::new(InsecureJsonTokenVerifier));
+        if let Some(recording_config) = config.recording.clone() {
+            session_manager_impl
+                .set_recorder(Arc::new(DefaultRecorder::new(recording_config)))
+                .await;
+        }
+        // Shared with the media router below so a track relayed between
+        // nodes is announced/pulled through the same broker the session
+        // manager already uses to resolve `PublisherLocation::Remote`
+        let broker: Arc<dyn Broker> = Arc::new(DefaultBroker::new(connection_manager.clone()));
+        session_manager_impl.set_broker(broker.clone()).await;
+
+        // Create statistics collector. Shared with the session manager below
+        // so `stats_snapshot` can report RTCP-derived figures, and with
+        // feedback channels (see `handle_connection`) so incoming RTCP
+        // actually reaches it.
+        let stats_collector = Arc::new(DefaultStatsCollector::new());
+        session_manager_impl.set_stats_collector(stats_collector.clone()).await;
+        let session_manager: Arc<dyn SessionManager> = Arc::new(session_manager_impl);
+
+        // Create bandwidth manager. Shared with the media router, feedback
+        // manager, and signaling protocol below so loss- and delay-based
+        // estimates for a session stay consistent across all three paths.
+        let bandwidth_manager: Arc<dyn BandwidthManager> = Arc::new(DefaultBandwidthManager::new());
+
         // Create media router
-        let media_router = Arc::new(DefaultMediaRouter::new(session_manager.clone()));
-        
+        let media_router = Arc::new(DefaultMediaRouter::with_bandwidth_manager(session_manager.clone(), bandwidth_manager.clone()));
+        media_router.set_relay(broker.clone(), connection_manager.clone()).await;
+
         // Create media transport
         let media_transport = Arc::new(DefaultMediaTransport::new());
-        
-        // Create bandwidth manager
-        let bandwidth_manager = Arc::new(DefaultBandwidthManager::new());
-        
-        // Create statistics collector
-        let stats_collector = Arc::new(DefaultStatsCollector::new());
-        
+
         // Create signaling protocol
-        let signaling_protocol = Arc::new(DefaultSignalingProtocol::new());
-        
+        let signaling_protocol = Arc::new(DefaultSignalingProtocol::with_bandwidth_manager(
+            session_manager.clone(),
+            Box::new(crate::signaling::codec::JsonCodec),
+            Arc::new(InsecureJsonTokenVerifier),
+            bandwidth_manager.clone(),
+        ));
+
         // Create feedback manager
-        let feedback_manager = Arc::new(DefaultFeedbackManager::new());
-        
-        // Create simulcast manager
-        let simulcast_manager = Arc::new(DefaultSimulcastManager::new());
-        
+        let feedback_manager = Arc::new(DefaultFeedbackManager::with_bandwidth_manager(
+            session_manager.clone(),
+            crate::feedback::DEFAULT_KEYFRAME_REQUEST_INTERVAL,
+            bandwidth_manager.clone(),
+        ));
+
+        // Create simulcast manager. Shared with the media router above so
+        // `get_forwarding_decision` water-fills each subscriber's layer
+        // against the same track layers/selections this manager tracks.
+        let simulcast_manager: Arc<dyn SimulcastManager> = Arc::new(DefaultSimulcastManager::new());
+        media_router.set_simulcast_manager(simulcast_manager.clone()).await;
+
         Ok(Self {
             session_manager,
             media_router,
@@ -132,8 +170,9 @@ impl Sfu {
         let signaling_protocol = self.signaling_protocol.clone();
         let feedback_manager = self.feedback_manager.clone();
         let simulcast_manager = self.simulcast_manager.clone();
+        let stats_collector = self.stats_collector.clone();
         let running_state = self.running.clone();
-        
+
         tokio::spawn(async move {
             while *running_state.read().await {
                 // Accept a new connection
@@ -146,7 +185,8 @@ impl Sfu {
                         let signaling_protocol = signaling_protocol.clone();
                         let feedback_manager = feedback_manager.clone();
                         let simulcast_manager = simulcast_manager.clone();
-                        
+                        let stats_collector = stats_collector.clone();
+
                         tokio::spawn(async move {
                             if let Err(e) = handle_connection(
                                 connection,
@@ -156,6 +196,7 @@ impl Sfu {
                                 signaling_protocol,
                                 feedback_manager,
                                 simulcast_manager,
+                                stats_collector,
                             ).await {
                                 tracing::error!("Error handling connection: {}", e);
                             }
@@ -194,6 +235,7 @@ async fn handle_connection(
     signaling_protocol: Arc<dyn SignalingProtocol>,
     feedback_manager: Arc<dyn FeedbackManager>,
     simulcast_manager: Arc<dyn SimulcastManager>,
+    stats_collector: Arc<dyn StatsCollector>,
 ) -> Result<()> {
     // Create a transport session
     let transport_session = media_transport.create_session(&connection).await?;
@@ -201,18 +243,38 @@ async fn handle_connection(
     
     // Create a feedback channel
     let feedback_channel = feedback_manager.create_channel(transport_session.clone()).await?;
-    
+
     // Wait for session initialization
     // In a real implementation, we would wait for a signaling message
-    // For now, create a session with a placeholder node ID
+    // carrying the session token. For now, create a session with a
+    // placeholder node ID and an unrestricted placeholder token.
     let node_id = connection.remote_node_id().clone();
-    let session_id = session_manager.create_session(node_id, connection.clone()).await?;
-    
+    let placeholder_token = serde_json::to_string(&crate::auth::Grants::unrestricted())?;
+    let session_id = session_manager
+        .create_session(node_id, connection.clone(), &placeholder_token)
+        .await?;
+
+    // Attribute feedback reconstructed from this connection's RTCP flow to
+    // its now-known session, and feed sender/receiver reports it sees into
+    // the shared stats collector
+    feedback_channel.set_session_id(session_id);
+    feedback_channel.set_stats_collector(stats_collector).await;
+
     // Process incoming messages
     // In a real implementation, we would process signaling messages
     // For now, just log the session creation
     tracing::info!("Created session {} for connection", session_id);
-    
+
+    // Drain and process feedback for this connection (receiver reports,
+    // PLI, key frame requests, ...) for as long as the session is alive
+    let feedback_manager_for_task = feedback_manager.clone();
+    let feedback_channel_for_task = feedback_channel.clone();
+    tokio::spawn(async move {
+        if let Err(e) = feedback_manager_for_task.process_feedback(&feedback_channel_for_task).await {
+            tracing::error!("Error processing feedback for session {}: {}", session_id, e);
+        }
+    });
+
     // Wait for connection to close
     connection.connection().closed().await;
     
@@ -0,0 +1,188 @@
+// Announce/subscribe broker for namespace-scoped track discovery across
+// SFU nodes
+//
+// Complements `relay::Broker` (which pulls a single already-known track from
+// a specific remote node) with namespace-level fan-out, modeled on the
+// announce/subscribe namespace model used by Media-over-QUIC relays: a node
+// `announce`s the tracks published under a namespace (e.g. `"room/demo"`) to
+// every connected peer relay as `SignalingMessage::AvailableTracks`, and a
+// peer's `subscribe` to that namespace forwards `TrackSubscribe` requests
+// upstream to whichever peer originally announced the track.
+//
+// Loop prevention is split-horizon: each announced track remembers which
+// peer (if any) it was learned from, and a re-announce skips fanning a track
+// back out to that same peer.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use anyhow::Result;
+use iroh::NodeId;
+use tokio::sync::RwLock;
+
+use crate::{
+    connection::{ConnectionKind, RtcConnection},
+    media::TrackId,
+    session::SessionId,
+    signaling::{SignalingProtocol, SubscriptionParams, TrackInfo},
+    SfuError,
+};
+
+/// A track announced under a namespace, remembering which peer (if any) it
+/// was learned from so `announce` doesn't fan it back out to that peer
+struct Announced {
+    info: TrackInfo,
+    /// `None` for a track published locally on this node; `Some(node_id)`
+    /// for one learned from a peer's announce
+    learned_from: Option<NodeId>,
+}
+
+/// Tracks announced per namespace, keyed by track id so re-announcing a
+/// track (e.g. on a bitrate change) replaces its entry in place; each
+/// fan-out still sends the namespace's whole current track list
+#[derive(Default)]
+struct Broadcasts {
+    by_namespace: RwLock<HashMap<String, HashMap<TrackId, Announced>>>,
+}
+
+impl Broadcasts {
+    async fn merge(&self, namespace: &str, tracks: Vec<TrackInfo>, learned_from: Option<NodeId>) {
+        let mut by_namespace = self.by_namespace.write().await;
+        let namespace_tracks = by_namespace.entry(namespace.to_string()).or_default();
+
+        for info in tracks {
+            namespace_tracks.insert(info.track_id, Announced { info, learned_from });
+        }
+    }
+
+    async fn tracks_for_peer(&self, namespace: &str, excluding_peer: NodeId) -> Vec<TrackInfo> {
+        let by_namespace = self.by_namespace.read().await;
+
+        by_namespace
+            .get(namespace)
+            .map(|tracks| {
+                tracks
+                    .values()
+                    .filter(|announced| announced.learned_from != Some(excluding_peer))
+                    .map(|announced| announced.info.clone())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    async fn owner(&self, namespace: &str, track_id: TrackId) -> Option<NodeId> {
+        self.by_namespace.read().await.get(namespace)?.get(&track_id)?.learned_from
+    }
+}
+
+/// Announce/subscribe broker for namespace-scoped track discovery: fans a
+/// namespace's tracks out to every connected peer relay, and forwards a
+/// peer's subscribe requests upstream to whichever peer announced the track
+pub struct AnnounceBroker {
+    /// Native signaling protocol used to send `AvailableTracks` to peers and
+    /// forward `TrackSubscribe` requests upstream
+    signaling: Arc<dyn SignalingProtocol>,
+    /// Tracks announced per namespace
+    broadcasts: Broadcasts,
+    /// Connected peer relay connections, keyed by remote node id
+    peers: RwLock<HashMap<NodeId, RtcConnection>>,
+}
+
+impl AnnounceBroker {
+    /// Create a new announce broker sending/forwarding through `signaling`
+    pub fn new(signaling: Arc<dyn SignalingProtocol>) -> Self {
+        Self {
+            signaling,
+            broadcasts: Broadcasts::default(),
+            peers: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// Register a peer relay connection this node announces to and accepts
+    /// subscribe forwarding from. Connections not tagged
+    /// `ConnectionKind::PeerRelay` are ignored.
+    pub async fn add_peer(&self, connection: RtcConnection) {
+        if connection.kind() != ConnectionKind::PeerRelay {
+            return;
+        }
+
+        self.peers.write().await.insert(*connection.remote_node_id(), connection);
+    }
+
+    /// Drop a peer relay connection, e.g. once it closes
+    pub async fn remove_peer(&self, node_id: &NodeId) {
+        self.peers.write().await.remove(node_id);
+    }
+
+    /// Announce tracks published locally on this node under `namespace` to
+    /// every connected peer relay
+    pub async fn announce(&self, namespace: &str, tracks: Vec<TrackInfo>) -> Result<()> {
+        self.broadcasts.merge(namespace, tracks, None).await;
+        self.fan_out(namespace).await
+    }
+
+    /// Merge tracks a peer announced for `namespace` into this node's
+    /// registry, and fan them out to every other connected peer (split
+    /// horizon: never echo a track back to the peer it came from)
+    pub async fn handle_remote_announce(&self, from: NodeId, namespace: &str, tracks: Vec<TrackInfo>) -> Result<()> {
+        self.broadcasts.merge(namespace, tracks, Some(from)).await;
+        self.fan_out(namespace).await
+    }
+
+    /// Send `namespace`'s current track list to every peer that shouldn't be
+    /// skipped under split-horizon
+    async fn fan_out(&self, namespace: &str) -> Result<()> {
+        let peers = self.peers.read().await;
+
+        for (node_id, connection) in peers.iter() {
+            let tracks = self.broadcasts.tracks_for_peer(namespace, *node_id).await;
+
+            if tracks.is_empty() {
+                continue;
+            }
+
+            self.signaling.notify_available_tracks(connection, tracks).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Forward a subscribe request for `track_id` in `namespace` to whichever
+    /// peer originally announced it.
+    ///
+    /// This is a placeholder for piping the resulting media back down to
+    /// `subscriber` - actual implementation will depend on the transport
+    /// layer's ability to re-forward a remotely-pulled flow, analogous to
+    /// `relay::Broker::subscribe`.
+    pub async fn subscribe(
+        &self,
+        namespace: &str,
+        subscriber: SessionId,
+        track_id: TrackId,
+        params: SubscriptionParams,
+    ) -> Result<()> {
+        let Some(owner) = self.broadcasts.owner(namespace, track_id).await else {
+            return Err(SfuError::Signaling(format!(
+                "Track {} in namespace {} was not announced by a peer",
+                track_id, namespace
+            ))
+            .into());
+        };
+
+        let peers = self.peers.read().await;
+        let connection = peers
+            .get(&owner)
+            .ok_or_else(|| SfuError::Signaling(format!("Peer {} is no longer connected", owner)))?;
+
+        self.signaling.subscribe_to_track(connection, subscriber, track_id, params).await?;
+
+        tracing::debug!(
+            "Forwarded subscribe for track {} in namespace {} upstream to {}",
+            track_id,
+            namespace,
+            owner,
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,116 @@
+// Pluggable wire codecs for `SignalingMessage`
+//
+// `DefaultSignalingProtocol` used to hardcode `serde_json`, which is wasteful
+// for high-frequency traffic like `AvailableTracks` and feedback messages.
+// This module lets the encoding be swapped for a more compact binary format,
+// negotiated per client during `SessionInit` via `WireFormat`.
+
+use anyhow::Result;
+
+use crate::{signaling::SignalingMessage, SfuError};
+
+/// Wire format a `SignalingCodec` encodes `SignalingMessage` as, negotiated
+/// during `SessionInit` via `ClientCapabilities::preferred_formats` and
+/// echoed back in `ServerCapabilities::format`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum WireFormat {
+    /// `serde_json`, human-readable, the universal fallback
+    Json,
+    /// `bincode`, compact fixed-layout binary encoding
+    Bincode,
+    /// MessagePack, compact self-describing binary encoding
+    MessagePack,
+}
+
+/// Encodes/decodes `SignalingMessage` on the wire
+pub trait SignalingCodec: Send + Sync {
+    /// Wire format this codec implements
+    fn format(&self) -> WireFormat;
+
+    /// Encode a message to bytes
+    fn encode(&self, message: &SignalingMessage) -> Result<Vec<u8>>;
+
+    /// Decode a message from bytes
+    fn decode(&self, data: &[u8]) -> Result<SignalingMessage>;
+}
+
+/// JSON codec: human-readable, the universal fallback when a client and
+/// server share no binary format
+pub struct JsonCodec;
+
+impl SignalingCodec for JsonCodec {
+    fn format(&self) -> WireFormat {
+        WireFormat::Json
+    }
+
+    fn encode(&self, message: &SignalingMessage) -> Result<Vec<u8>> {
+        serde_json::to_vec(message)
+            .map_err(|e| SfuError::Signaling(format!("Failed to encode message: {}", e)).into())
+    }
+
+    fn decode(&self, data: &[u8]) -> Result<SignalingMessage> {
+        serde_json::from_slice(data)
+            .map_err(|e| SfuError::Signaling(format!("Failed to decode message: {}", e)).into())
+    }
+}
+
+/// Bincode codec: compact fixed-layout binary encoding
+pub struct BincodeCodec;
+
+impl SignalingCodec for BincodeCodec {
+    fn format(&self) -> WireFormat {
+        WireFormat::Bincode
+    }
+
+    fn encode(&self, message: &SignalingMessage) -> Result<Vec<u8>> {
+        bincode::serialize(message)
+            .map_err(|e| SfuError::Signaling(format!("Failed to encode message: {}", e)).into())
+    }
+
+    fn decode(&self, data: &[u8]) -> Result<SignalingMessage> {
+        bincode::deserialize(data)
+            .map_err(|e| SfuError::Signaling(format!("Failed to decode message: {}", e)).into())
+    }
+}
+
+/// MessagePack codec: compact self-describing binary encoding
+pub struct MessagePackCodec;
+
+impl SignalingCodec for MessagePackCodec {
+    fn format(&self) -> WireFormat {
+        WireFormat::MessagePack
+    }
+
+    fn encode(&self, message: &SignalingMessage) -> Result<Vec<u8>> {
+        rmp_serde::to_vec(message)
+            .map_err(|e| SfuError::Signaling(format!("Failed to encode message: {}", e)).into())
+    }
+
+    fn decode(&self, data: &[u8]) -> Result<SignalingMessage> {
+        rmp_serde::from_slice(data)
+            .map_err(|e| SfuError::Signaling(format!("Failed to decode message: {}", e)).into())
+    }
+}
+
+/// Formats `DefaultSignalingProtocol` can negotiate, most compact first
+const SUPPORTED_FORMATS: [WireFormat; 3] = [WireFormat::Bincode, WireFormat::MessagePack, WireFormat::Json];
+
+/// Pick the best format both sides support: the first of `SUPPORTED_FORMATS`
+/// (most compact first) that also appears in `preferred_formats`, falling
+/// back to JSON if they share no binary format
+pub fn negotiate_format(preferred_formats: &[WireFormat]) -> WireFormat {
+    SUPPORTED_FORMATS
+        .iter()
+        .copied()
+        .find(|format| preferred_formats.contains(format))
+        .unwrap_or(WireFormat::Json)
+}
+
+/// Construct the codec implementing `format`
+pub fn codec_for_format(format: WireFormat) -> Box<dyn SignalingCodec> {
+    match format {
+        WireFormat::Json => Box::new(JsonCodec),
+        WireFormat::Bincode => Box::new(BincodeCodec),
+        WireFormat::MessagePack => Box::new(MessagePackCodec),
+    }
+}
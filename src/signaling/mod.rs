@@ -2,17 +2,29 @@
 //
 // This module handles session establishment and negotiation.
 
-use std::sync::Arc;
+pub mod broker;
+pub mod codec;
+pub mod stats_report;
+pub mod whip;
+
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{Arc, Mutex},
+};
 
 use anyhow::Result;
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
+use tokio::sync::RwLock;
 
 use crate::{
+    auth::{AllowAllTokenVerifier, Grants, TokenVerifier},
+    bandwidth::{BandwidthManager, DefaultBandwidthManager},
     connection::RtcConnection,
-    media::{TrackId, TrackKind},
-    session::SessionId,
-    SfuError,
+    feedback::{intersect_feedback, FeedbackKind, FeedbackMechanism},
+    media::{codec::CodecType, TrackId, TrackKind},
+    session::{PublisherLocation, SessionId, SessionManager},
+    signaling::codec::{negotiate_format, JsonCodec, SignalingCodec, WireFormat},
 };
 
 /// Signaling message types
@@ -22,6 +34,10 @@ pub enum SignalingMessage {
     SessionInit {
         /// Client capabilities
         capabilities: ClientCapabilities,
+        /// Signed access token encoding this session's `Grants`, verified by
+        /// the configured `TokenVerifier`. Sessions that omit it are granted
+        /// `Grants::unrestricted()`.
+        auth_token: Option<String>,
     },
     /// Session acknowledgment
     SessionAck {
@@ -42,6 +58,8 @@ pub enum SignalingMessage {
     },
     /// Track subscription request
     TrackSubscribe {
+        /// Session requesting the subscription, checked against its grants
+        session_id: SessionId,
         /// Track identifier
         track_id: TrackId,
         /// Subscription parameters
@@ -67,6 +85,41 @@ pub enum SignalingMessage {
         /// Available tracks
         tracks: Vec<TrackInfo>,
     },
+    /// Incrementally-discovered ICE candidates for an in-progress session,
+    /// exchanged without waiting for a full SDP offer/answer round trip
+    TrickleIce {
+        /// Session the candidates belong to
+        session_id: SessionId,
+        /// Newly discovered candidates
+        candidates: Vec<IceCandidate>,
+    },
+    /// A partial SDP renegotiation, modeled on the
+    /// `application/trickle-ice-sdpfrag` media type
+    Renegotiate {
+        /// Session being renegotiated
+        session_id: SessionId,
+        /// SDP media/attribute lines describing the change
+        sdp_fragment: String,
+    },
+    /// Periodic RTP stream statistics, exchanged in either direction: a
+    /// subscriber reports what it observed on tracks it receives so the SFU
+    /// can drive congestion control, and the SFU reports a session's own
+    /// published/subscribed track counters back down to it
+    StatsReport {
+        /// Session the reports describe
+        session_id: SessionId,
+        /// One report per track `session_id` publishes or subscribes to
+        reports: Vec<RtpStreamStats>,
+    },
+    /// A derived target bitrate hint for a published track, sent to its
+    /// publisher so it can ramp simulcast encodings up or down in response to
+    /// subscriber-reported loss/RTT
+    TargetBitrate {
+        /// Published track this hint applies to
+        track_id: TrackId,
+        /// Suggested target bitrate in bps
+        target_bitrate: u32,
+    },
     /// Error notification
     Error {
         /// Error code
@@ -76,6 +129,40 @@ pub enum SignalingMessage {
     },
 }
 
+/// RTP-level statistics for a single track, carried by `StatsReport`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RtpStreamStats {
+    /// Track these counters describe
+    pub track_id: TrackId,
+    /// Packets sent (non-zero when reporting a track this session publishes)
+    pub packets_sent: u64,
+    /// Packets received (non-zero when reporting a track this session subscribes to)
+    pub packets_received: u64,
+    /// Bytes sent
+    pub bytes_sent: u64,
+    /// Bytes received
+    pub bytes_received: u64,
+    /// Packets lost, as observed by whichever end is reporting
+    pub packets_lost: u64,
+    /// Interarrival jitter in milliseconds
+    pub jitter_ms: f32,
+    /// Round-trip time in milliseconds, if measured
+    pub rtt_ms: Option<u32>,
+    /// Simulcast layer currently in use for this track, for video
+    pub current_layer: Option<LayerInfo>,
+}
+
+/// A single ICE candidate exchanged via trickle ICE
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IceCandidate {
+    /// Candidate line, in SDP `a=candidate:...` format
+    pub candidate: String,
+    /// Media stream identification tag the candidate applies to, if any
+    pub sdp_mid: Option<String>,
+    /// Index of the `m=` line the candidate applies to, if any
+    pub sdp_mline_index: Option<u32>,
+}
+
 /// Client capabilities
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ClientCapabilities {
@@ -87,6 +174,10 @@ pub struct ClientCapabilities {
     pub simulcast_support: bool,
     /// Feedback support
     pub feedback_support: bool,
+    /// Wire formats this client can decode. The server picks the most
+    /// compact one it also supports (see `codec::negotiate_format`), whatever
+    /// their order here, falling back to `WireFormat::Json` if none match.
+    pub preferred_formats: Vec<WireFormat>,
 }
 
 /// Server capabilities
@@ -100,6 +191,10 @@ pub struct ServerCapabilities {
     pub simulcast_support: bool,
     /// Feedback support
     pub feedback_support: bool,
+    /// Wire format negotiated from the client's `preferred_formats`.
+    /// `DefaultSignalingProtocol` reports it here but keeps encoding every
+    /// message with the codec it was constructed with.
+    pub format: WireFormat,
 }
 
 /// Codec capability
@@ -111,6 +206,8 @@ pub struct CodecCapability {
     pub media_type: MediaType,
     /// Codec parameters
     pub parameters: Vec<CodecParameter>,
+    /// RTCP feedback mechanisms advertised for this codec
+    pub feedback: Vec<FeedbackMechanism>,
 }
 
 /// Media type
@@ -221,6 +318,7 @@ pub trait SignalingProtocol: Send + Sync {
         &self,
         connection: &RtcConnection,
         capabilities: ClientCapabilities,
+        auth_token: Option<String>,
     ) -> Result<SessionId>;
     
     /// Publish track
@@ -254,27 +352,141 @@ pub trait SignalingProtocol: Send + Sync {
         connection: &RtcConnection,
         tracks: Vec<TrackInfo>,
     ) -> Result<()>;
+
+    /// Add trickled ICE candidates for `session_id`, queuing them until the
+    /// remote description is set. Returns `Error` if `session_id` is unknown.
+    async fn add_ice_candidates(
+        &self,
+        connection: &RtcConnection,
+        session_id: SessionId,
+        candidates: Vec<IceCandidate>,
+    ) -> Result<Option<SignalingMessage>>;
+
+    /// Apply a partial SDP renegotiation (`application/trickle-ice-sdpfrag`)
+    /// to `session_id`. Returns `Error` if the fragment targets a track that
+    /// is no longer published.
+    async fn apply_sdp_fragment(
+        &self,
+        connection: &RtcConnection,
+        session_id: SessionId,
+        sdp_fragment: String,
+    ) -> Result<Option<SignalingMessage>>;
+
+    /// Ingest a `StatsReport` from `session_id`: feed each report's loss into
+    /// the bandwidth manager's loss-based controller, re-run ABR layer
+    /// selection for `session_id`'s subscriptions with the resulting
+    /// estimate, and send each reported track's publisher a `TargetBitrate`
+    /// hint derived from the same controller. Returns `Error` if
+    /// `session_id` is unknown.
+    async fn ingest_stats_report(
+        &self,
+        connection: &RtcConnection,
+        session_id: SessionId,
+        reports: Vec<RtpStreamStats>,
+    ) -> Result<Option<SignalingMessage>>;
 }
 
 /// Default implementation of the signaling protocol
 pub struct DefaultSignalingProtocol {
-    // Implementation details will be added later
+    /// Session manager, used to validate that trickled candidates and SDP
+    /// fragments reference a known session/track
+    session_manager: Arc<dyn SessionManager>,
+    /// Candidates received via `add_ice_candidates`, queued per session until
+    /// a `Renegotiate`/`apply_sdp_fragment` call sets the remote description
+    pending_candidates: RwLock<HashMap<SessionId, Vec<IceCandidate>>>,
+    /// Codec messages are encoded/decoded with
+    codec: Box<dyn SignalingCodec>,
+    /// Verifies a `SessionInit`'s `auth_token` into the `Grants` it encodes
+    token_verifier: Arc<dyn TokenVerifier>,
+    /// Grants decoded at `SessionInit`, re-checked on every later
+    /// `TrackPublish`/`TrackSubscribe` for the same session
+    session_grants: RwLock<HashMap<SessionId, Grants>>,
+    /// Next id `SessionInit` hands out, so every session is keyed in
+    /// `session_grants` under its own real id instead of colliding
+    next_session_id: Mutex<SessionId>,
+    /// Congestion controller consulted/fed by `ingest_stats_report` to turn
+    /// subscriber-reported loss into layer-switching decisions and
+    /// publisher target bitrate hints
+    bandwidth_manager: Arc<dyn BandwidthManager>,
 }
 
 impl DefaultSignalingProtocol {
-    /// Create a new signaling protocol
-    pub fn new() -> Self {
-        Self {}
+    /// Create a new signaling protocol validating sessions against
+    /// `session_manager`, encoding messages as JSON for compatibility, and
+    /// granting every session full access regardless of its `auth_token`
+    pub fn new(session_manager: Arc<dyn SessionManager>) -> Self {
+        Self::with_codec_and_verifier(session_manager, Box::new(JsonCodec), Arc::new(AllowAllTokenVerifier))
     }
-    
+
+    /// Create a new signaling protocol validating sessions against
+    /// `session_manager`, encoding messages with `codec`
+    pub fn with_codec(session_manager: Arc<dyn SessionManager>, codec: Box<dyn SignalingCodec>) -> Self {
+        Self::with_codec_and_verifier(session_manager, codec, Arc::new(AllowAllTokenVerifier))
+    }
+
+    /// Create a new signaling protocol validating sessions against
+    /// `session_manager`, decoding each session's `auth_token` with `token_verifier`
+    pub fn with_token_verifier(session_manager: Arc<dyn SessionManager>, token_verifier: Arc<dyn TokenVerifier>) -> Self {
+        Self::with_codec_and_verifier(session_manager, Box::new(JsonCodec), token_verifier)
+    }
+
+    /// Create a new signaling protocol with full control over the wire codec
+    /// and token verifier, and a fresh, session-local bandwidth manager
+    pub fn with_codec_and_verifier(
+        session_manager: Arc<dyn SessionManager>,
+        codec: Box<dyn SignalingCodec>,
+        token_verifier: Arc<dyn TokenVerifier>,
+    ) -> Self {
+        Self::with_bandwidth_manager(session_manager, codec, token_verifier, Arc::new(DefaultBandwidthManager::new()))
+    }
+
+    /// Create a new signaling protocol with full control over the wire
+    /// codec, token verifier, and the bandwidth manager `ingest_stats_report`
+    /// reads loss/RTT into and derives `TargetBitrate` hints from
+    pub fn with_bandwidth_manager(
+        session_manager: Arc<dyn SessionManager>,
+        codec: Box<dyn SignalingCodec>,
+        token_verifier: Arc<dyn TokenVerifier>,
+        bandwidth_manager: Arc<dyn BandwidthManager>,
+    ) -> Self {
+        Self {
+            session_manager,
+            pending_candidates: RwLock::new(HashMap::new()),
+            codec,
+            token_verifier,
+            session_grants: RwLock::new(HashMap::new()),
+            next_session_id: Mutex::new(1),
+            bandwidth_manager,
+        }
+    }
+
+    /// Allocate the next real session id handed out by `SessionInit`
+    fn generate_session_id(&self) -> SessionId {
+        let mut id = self.next_session_id.lock().unwrap();
+        let current = *id;
+        *id += 1;
+        current
+    }
+
     /// Serialize signaling message
     fn serialize_message(&self, message: &SignalingMessage) -> Result<Vec<u8>> {
-        serde_json::to_vec(message).map_err(|e| SfuError::Signaling(format!("Failed to serialize message: {}", e)).into())
+        self.codec.encode(message)
     }
-    
+
     /// Deserialize signaling message
     fn deserialize_message(&self, data: &[u8]) -> Result<SignalingMessage> {
-        serde_json::from_slice(data).map_err(|e| SfuError::Signaling(format!("Failed to deserialize message: {}", e)).into())
+        self.codec.decode(data)
+    }
+
+    /// Grants in effect for `session_id`, defaulting to unrestricted for
+    /// sessions that haven't gone through `SessionInit` yet
+    async fn session_grants(&self, session_id: SessionId) -> Grants {
+        self.session_grants
+            .read()
+            .await
+            .get(&session_id)
+            .cloned()
+            .unwrap_or_else(Grants::unrestricted)
     }
 }
 
@@ -282,17 +494,32 @@ impl DefaultSignalingProtocol {
 impl SignalingProtocol for DefaultSignalingProtocol {
     async fn handle_message(
         &self,
-        _connection: &RtcConnection,
+        connection: &RtcConnection,
         message: SignalingMessage,
     ) -> Result<Option<SignalingMessage>> {
         // Implementation details will be added later
         // This would involve processing the message and generating a response
         
         match message {
-            SignalingMessage::SessionInit { capabilities } => {
+            SignalingMessage::SessionInit { capabilities, auth_token } => {
                 // Process session initialization
-                let session_id = 1; // Placeholder
-                
+                let session_id = self.generate_session_id();
+                let format = negotiate_format(&capabilities.preferred_formats);
+
+                let grants = match auth_token {
+                    Some(token) => match self.token_verifier.verify(&token) {
+                        Ok(grants) => grants,
+                        Err(e) => {
+                            return Ok(Some(SignalingMessage::Error {
+                                code: 403,
+                                message: format!("Invalid auth token: {}", e),
+                            }));
+                        }
+                    },
+                    None => Grants::unrestricted(),
+                };
+                self.session_grants.write().await.insert(session_id, grants);
+
                 let server_capabilities = ServerCapabilities {
                     codecs: vec![
                         // Opus audio codec
@@ -309,6 +536,10 @@ impl SignalingProtocol for DefaultSignalingProtocol {
                                     value: "1".to_string(),
                                 },
                             ],
+                            feedback: vec![FeedbackMechanism {
+                                kind: FeedbackKind::TransportCc,
+                                parameter: None,
+                            }],
                         },
                         // VP9 video codec
                         CodecCapability {
@@ -320,25 +551,84 @@ impl SignalingProtocol for DefaultSignalingProtocol {
                                     value: "0".to_string(),
                                 },
                             ],
+                            feedback: vec![
+                                FeedbackMechanism { kind: FeedbackKind::NackPli, parameter: None },
+                                FeedbackMechanism { kind: FeedbackKind::Fir, parameter: None },
+                                FeedbackMechanism { kind: FeedbackKind::TransportCc, parameter: None },
+                                FeedbackMechanism { kind: FeedbackKind::GoogRemb, parameter: None },
+                            ],
                         },
                     ],
                     max_bitrate: 5_000_000, // 5 Mbps
                     simulcast_support: true,
                     feedback_support: true,
+                    format,
                 };
-                
+
+                // Intersect each codec's feedback list with what the client
+                // advertised for the same codec, and store the agreed set on
+                // the session so `feedback` can gate what it generates
+                let negotiated_feedback: HashMap<String, HashSet<FeedbackKind>> = server_capabilities
+                    .codecs
+                    .iter()
+                    .filter_map(|server_codec| {
+                        let client_codec = capabilities
+                            .codecs
+                            .iter()
+                            .find(|codec| codec.name.eq_ignore_ascii_case(&server_codec.name))?;
+
+                        let agreed: HashSet<FeedbackKind> = intersect_feedback(&server_codec.feedback, &client_codec.feedback)
+                            .into_iter()
+                            .map(|mechanism| mechanism.kind)
+                            .collect();
+
+                        Some((server_codec.name.clone(), agreed))
+                    })
+                    .collect();
+
+                if let Err(e) = self.session_manager.set_negotiated_feedback(session_id, negotiated_feedback).await {
+                    tracing::debug!("Could not store negotiated feedback for session {}: {}", session_id, e);
+                }
+
                 Ok(Some(SignalingMessage::SessionAck {
                     session_id,
                     capabilities: server_capabilities,
                 }))
             }
             SignalingMessage::TrackPublish { track_info } => {
+                let grants = self.session_grants(track_info.publisher_id).await;
+
+                if !grants.can_publish {
+                    return Ok(Some(SignalingMessage::Error {
+                        code: 403,
+                        message: "Session is not authorized to publish".to_string(),
+                    }));
+                }
+
+                if let Some(codec_type) = CodecType::from_name(&track_info.codec.name) {
+                    if !grants.allows_codec(codec_type) {
+                        return Ok(Some(SignalingMessage::Error {
+                            code: 403,
+                            message: format!("Codec {} is not authorized", track_info.codec.name),
+                        }));
+                    }
+                }
+
                 // Process track publication
                 let track_id = track_info.track_id;
-                
+
                 Ok(Some(SignalingMessage::TrackPublishAck { track_id }))
             }
-            SignalingMessage::TrackSubscribe { track_id, params: _ } => {
+            SignalingMessage::TrackSubscribe { session_id, track_id, params: _ } => {
+                let grants = self.session_grants(session_id).await;
+
+                if !grants.can_subscribe {
+                    return Ok(Some(SignalingMessage::Error {
+                        code: 403,
+                        message: "Session is not authorized to subscribe".to_string(),
+                    }));
+                }
+
                 // Process track subscription
                 Ok(Some(SignalingMessage::TrackSubscribeAck { track_id }))
             }
@@ -346,6 +636,15 @@ impl SignalingProtocol for DefaultSignalingProtocol {
                 // Process track unsubscription
                 Ok(Some(SignalingMessage::TrackUnsubscribeAck { track_id }))
             }
+            SignalingMessage::TrickleIce { session_id, candidates } => {
+                self.add_ice_candidates(connection, session_id, candidates).await
+            }
+            SignalingMessage::Renegotiate { session_id, sdp_fragment } => {
+                self.apply_sdp_fragment(connection, session_id, sdp_fragment).await
+            }
+            SignalingMessage::StatsReport { session_id, reports } => {
+                self.ingest_stats_report(connection, session_id, reports).await
+            }
             _ => {
                 // No response needed for other message types
                 Ok(None)
@@ -376,9 +675,10 @@ impl SignalingProtocol for DefaultSignalingProtocol {
         &self,
         connection: &RtcConnection,
         capabilities: ClientCapabilities,
+        auth_token: Option<String>,
     ) -> Result<SessionId> {
         // Send session initialization message
-        let message = SignalingMessage::SessionInit { capabilities };
+        let message = SignalingMessage::SessionInit { capabilities, auth_token };
         
         self.send_message(connection, message).await?;
         
@@ -406,12 +706,12 @@ impl SignalingProtocol for DefaultSignalingProtocol {
     async fn subscribe_to_track(
         &self,
         connection: &RtcConnection,
-        _session_id: SessionId,
+        session_id: SessionId,
         track_id: TrackId,
         params: SubscriptionParams,
     ) -> Result<()> {
         // Send track subscription message
-        let message = SignalingMessage::TrackSubscribe { track_id, params };
+        let message = SignalingMessage::TrackSubscribe { session_id, track_id, params };
         
         self.send_message(connection, message).await?;
         
@@ -441,16 +741,164 @@ impl SignalingProtocol for DefaultSignalingProtocol {
     ) -> Result<()> {
         // Send available tracks notification
         let message = SignalingMessage::AvailableTracks { tracks };
-        
+
         self.send_message(connection, message).await?;
-        
+
         Ok(())
     }
+
+    async fn add_ice_candidates(
+        &self,
+        _connection: &RtcConnection,
+        session_id: SessionId,
+        candidates: Vec<IceCandidate>,
+    ) -> Result<Option<SignalingMessage>> {
+        if self.session_manager.get_participant(session_id).await.is_err() {
+            return Ok(Some(SignalingMessage::Error {
+                code: 404,
+                message: format!("Unknown session: {}", session_id),
+            }));
+        }
+
+        // Queue until a `Renegotiate`/`apply_sdp_fragment` call sets the
+        // remote description for this session
+        let mut pending = self.pending_candidates.write().await;
+        pending.entry(session_id).or_default().extend(candidates);
+
+        Ok(None)
+    }
+
+    async fn apply_sdp_fragment(
+        &self,
+        _connection: &RtcConnection,
+        session_id: SessionId,
+        sdp_fragment: String,
+    ) -> Result<Option<SignalingMessage>> {
+        if self.session_manager.get_participant(session_id).await.is_err() {
+            return Ok(Some(SignalingMessage::Error {
+                code: 404,
+                message: format!("Unknown session: {}", session_id),
+            }));
+        }
+
+        // A fragment's `a=mid:` lines name the tracks it renegotiates; reject
+        // it if any of them is no longer published by this session
+        let published_tracks = self.session_manager.get_published_tracks(session_id).await?;
+        for line in sdp_fragment.lines() {
+            let Some(mid) = line.trim().strip_prefix("a=mid:") else { continue };
+            let Ok(track_id) = mid.trim().parse::<TrackId>() else { continue };
+
+            if !published_tracks.contains(&track_id) {
+                return Ok(Some(SignalingMessage::Error {
+                    code: 410,
+                    message: format!("Track {} is no longer published", track_id),
+                }));
+            }
+        }
+
+        // The remote description is now set: drop any candidates queued
+        // while it was pending, they apply to this fragment
+        self.pending_candidates.write().await.remove(&session_id);
+
+        Ok(None)
+    }
+
+    async fn ingest_stats_report(
+        &self,
+        _connection: &RtcConnection,
+        session_id: SessionId,
+        reports: Vec<RtpStreamStats>,
+    ) -> Result<Option<SignalingMessage>> {
+        let participant = match self.session_manager.get_participant(session_id).await {
+            Ok(participant) => participant,
+            Err(_) => {
+                return Ok(Some(SignalingMessage::Error {
+                    code: 404,
+                    message: format!("Unknown session: {}", session_id),
+                }));
+            }
+        };
+
+        for report in &reports {
+            let total = report.packets_received + report.packets_lost;
+            let loss_fraction = if total == 0 { 0.0 } else { report.packets_lost as f32 / total as f32 };
+
+            self.bandwidth_manager.report_packet_loss(session_id, loss_fraction).await?;
+
+            if let Some(rtt_ms) = report.rtt_ms {
+                tracing::debug!(
+                    "Session {} reports {}ms RTT and {:.1}% loss on track {}",
+                    session_id,
+                    rtt_ms,
+                    loss_fraction * 100.0,
+                    report.track_id
+                );
+            }
+
+            // If this session subscribes to the reported track, forward a
+            // target bitrate hint derived from the same loss signal to
+            // whichever session publishes it
+            let publisher_id = {
+                let participant = participant.read().await;
+                participant.subscribed_tracks.get(&report.track_id).and_then(|track| match track.publisher {
+                    PublisherLocation::Local(publisher_id) => Some(publisher_id),
+                    PublisherLocation::Remote(..) => None,
+                })
+            };
+
+            let Some(publisher_id) = publisher_id else { continue };
+
+            let target_bitrate = self.bandwidth_manager.get_recommended_bitrate(session_id, report.track_id).await?;
+
+            if let Ok(publisher) = self.session_manager.get_participant(publisher_id).await {
+                let publisher_connection = publisher.read().await.connection.clone();
+                self.send_message(
+                    &publisher_connection,
+                    SignalingMessage::TargetBitrate { track_id: report.track_id, target_bitrate },
+                )
+                .await?;
+            }
+        }
+
+        // Re-run this session's ABR layer selection (driving the layer
+        // reflected in `Participant::selected_layer`, the server-side
+        // counterpart of `SubscriptionParams::preferred_layers`) against the
+        // freshest loss-derived estimate
+        let loss_based_bitrate = self.bandwidth_manager.get_loss_based_bitrate(session_id).await?;
+        let upload_bandwidth = participant.read().await.bandwidth.upload_bandwidth;
+        self.session_manager.update_bandwidth(session_id, upload_bandwidth, loss_based_bitrate).await?;
+
+        Ok(None)
+    }
 }
 
-// Default implementation
-impl Default for DefaultSignalingProtocol {
-    fn default() -> Self {
-        Self::new()
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{auth::InsecureJsonTokenVerifier, session::DefaultSessionManager};
+
+    /// Two sessions initialized concurrently must be tracked under distinct
+    /// ids in `session_grants`, not collapsed onto one key - that collision
+    /// is exactly what let a restricted session's `TrackPublish` be checked
+    /// against another session's grants (or fall back to
+    /// `Grants::unrestricted()` entirely) instead of its own.
+    #[tokio::test]
+    async fn session_grants_are_tracked_per_session() {
+        let session_manager: Arc<dyn SessionManager> =
+            Arc::new(DefaultSessionManager::new(Arc::new(AllowAllTokenVerifier)));
+        let protocol =
+            DefaultSignalingProtocol::with_token_verifier(session_manager, Arc::new(InsecureJsonTokenVerifier));
+
+        let restricted = Grants { can_publish: false, ..Grants::unrestricted() };
+
+        let session_a = protocol.generate_session_id();
+        protocol.session_grants.write().await.insert(session_a, Grants::unrestricted());
+
+        let session_b = protocol.generate_session_id();
+        protocol.session_grants.write().await.insert(session_b, restricted);
+
+        assert_ne!(session_a, session_b, "SessionInit must hand out distinct ids per session");
+        assert!(protocol.session_grants(session_a).await.can_publish);
+        assert!(!protocol.session_grants(session_b).await.can_publish);
     }
 }
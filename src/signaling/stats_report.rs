@@ -0,0 +1,152 @@
+// Periodic `StatsReport` accumulator
+//
+// `SignalingMessage::StatsReport` carries per-track counters, but assembling
+// one by hand means walking a session's published and subscribed tracks,
+// reading their atomics, and cross-referencing `StatsCollector` for
+// jitter/RTT. This module does that assembly and, given a configurable
+// interval, can run it as a background loop that periodically sends a
+// session its own `StatsReport`.
+
+use std::{
+    sync::{atomic::Ordering, Arc},
+    time::Duration,
+};
+
+use anyhow::Result;
+
+use crate::{
+    connection::RtcConnection,
+    session::{PublisherLocation, SessionId, SessionManager},
+    signaling::{RtpStreamStats, SignalingMessage, SignalingProtocol},
+    stats::StatsCollector,
+};
+
+/// How often `StatsReportAccumulator::run` snapshots and sends a report, if
+/// no other interval is given via `with_interval`
+const DEFAULT_REPORT_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Snapshots a session's published/subscribed track counters into
+/// `RtpStreamStats` reports on a configurable interval, so callers don't
+/// have to assemble `StatsReport` messages by hand
+pub struct StatsReportAccumulator {
+    session_manager: Arc<dyn SessionManager>,
+    stats_collector: Arc<dyn StatsCollector>,
+    interval: Duration,
+}
+
+impl StatsReportAccumulator {
+    /// Create a new accumulator reporting on the default interval
+    pub fn new(session_manager: Arc<dyn SessionManager>, stats_collector: Arc<dyn StatsCollector>) -> Self {
+        Self::with_interval(session_manager, stats_collector, DEFAULT_REPORT_INTERVAL)
+    }
+
+    /// Create a new accumulator reporting every `interval`
+    pub fn with_interval(
+        session_manager: Arc<dyn SessionManager>,
+        stats_collector: Arc<dyn StatsCollector>,
+        interval: Duration,
+    ) -> Self {
+        Self { session_manager, stats_collector, interval }
+    }
+
+    /// Snapshot one report per track `session_id` publishes or subscribes to
+    pub async fn collect_reports(&self, session_id: SessionId) -> Result<Vec<RtpStreamStats>> {
+        let participant = self.session_manager.get_participant(session_id).await?;
+        let participant = participant.read().await;
+
+        let session_stats = self.stats_collector.get_session_stats(session_id).await.ok();
+
+        let mut reports = Vec::with_capacity(participant.published_tracks.len() + participant.subscribed_tracks.len());
+
+        for track in participant.published_tracks.values() {
+            let track_stats = session_stats.as_ref().and_then(|stats| stats.published_tracks.get(&track.track_id));
+
+            reports.push(RtpStreamStats {
+                track_id: track.track_id,
+                packets_sent: track.packets_forwarded.load(Ordering::Relaxed),
+                packets_received: 0,
+                bytes_sent: track.bytes_forwarded.load(Ordering::Relaxed),
+                bytes_received: 0,
+                packets_lost: 0,
+                jitter_ms: track_stats.map(|stats| stats.jitter_ms).unwrap_or(0.0),
+                rtt_ms: session_stats.as_ref().map(|stats| stats.connection_stats.rtt_ms),
+                current_layer: None,
+            });
+        }
+
+        for track in participant.subscribed_tracks.values() {
+            let track_stats = session_stats.as_ref().and_then(|stats| stats.subscribed_tracks.get(&track.track_id));
+
+            let current_layer = if let PublisherLocation::Local(publisher_id) = track.publisher {
+                self.layer_info(publisher_id, track.track_id, track.abr.selected_layer()).await
+            } else {
+                None
+            };
+
+            reports.push(RtpStreamStats {
+                track_id: track.track_id,
+                packets_sent: 0,
+                packets_received: track.packets_received.load(Ordering::Relaxed),
+                bytes_sent: 0,
+                bytes_received: track.bytes_received.load(Ordering::Relaxed),
+                packets_lost: 0,
+                jitter_ms: track_stats.map(|stats| stats.jitter_ms).unwrap_or(0.0),
+                rtt_ms: session_stats.as_ref().map(|stats| stats.connection_stats.rtt_ms),
+                current_layer,
+            });
+        }
+
+        Ok(reports)
+    }
+
+    /// Describe the simulcast layer `selected_layer` corresponds to on
+    /// `publisher_id`'s `track_id`, if that layer is still advertised
+    async fn layer_info(
+        &self,
+        publisher_id: SessionId,
+        track_id: crate::media::TrackId,
+        selected_layer: crate::simulcast::LayerId,
+    ) -> Option<crate::signaling::LayerInfo> {
+        let publisher = self.session_manager.get_participant(publisher_id).await.ok()?;
+        let publisher = publisher.read().await;
+        let track = publisher.published_tracks.get(&track_id)?;
+        let layer = track.layers.iter().find(|layer| layer.spatial_id == selected_layer)?;
+
+        Some(crate::signaling::LayerInfo {
+            layer_id: layer.spatial_id,
+            spatial_id: layer.spatial_id,
+            temporal_id: 0,
+            width: 0,
+            height: 0,
+            framerate: 0.0,
+            bitrate: layer.target_bitrate,
+        })
+    }
+
+    /// Run the report loop for `session_id` on `connection`, sending a fresh
+    /// `StatsReport` through `signaling` every `interval`. The loop does not
+    /// watch the connection for closure; it returns only when collecting or
+    /// sending a report fails, e.g. once the session has been removed.
+    /// Meant to be spawned alongside a session's other per-connection
+    /// background tasks.
+    pub async fn run(
+        &self,
+        signaling: Arc<dyn SignalingProtocol>,
+        connection: RtcConnection,
+        session_id: SessionId,
+    ) -> Result<()> {
+        let mut ticker = tokio::time::interval(self.interval);
+        ticker.tick().await;
+
+        loop {
+            ticker.tick().await;
+
+            let reports = self.collect_reports(session_id).await?;
+            if reports.is_empty() {
+                continue;
+            }
+
+            signaling.send_message(&connection, SignalingMessage::StatsReport { session_id, reports }).await?;
+        }
+    }
+}
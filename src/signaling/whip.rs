@@ -0,0 +1,263 @@
+// WHIP/WHEP HTTP signaling gateway
+//
+// Bridges the WebRTC-HTTP Ingestion Protocol (WHIP) and its egress
+// counterpart (WHEP) onto the existing `SignalingProtocol`, so tools that
+// only speak plain HTTP + SDP (e.g. OBS) can publish to and subscribe from
+// the SFU without speaking our native `SignalingMessage` JSON over iroh.
+//
+// `SignalingProtocol`'s methods are keyed on an already-established
+// `RtcConnection`, which an HTTP client never has - there is no iroh
+// connection behind a WHIP `POST`. Converging HTTP and iroh signaling on a
+// shared `SessionId` therefore needs a connection-less session variant that
+// doesn't exist yet; until then this gateway does the real SDP
+// offer/answer translation and builds the `SignalingMessage`s the offer
+// would produce, but stops short of dispatching them through
+// `SignalingProtocol` - that dispatch is a documented follow-up, not
+// something faked here with a synthetic connection.
+
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+};
+
+use axum::{
+    body::Bytes,
+    extract::{Path, State},
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+    routing::{delete, post},
+    Router,
+};
+use tokio::sync::RwLock;
+
+use crate::{
+    media::TrackKind,
+    session::SessionId,
+    signaling::{CodecInfo, CodecParameter, SignalingMessage, SignalingProtocol, TrackInfo},
+};
+
+/// Content type WHIP/WHEP clients send offers in and expect answers as
+const SDP_CONTENT_TYPE: &str = "application/sdp";
+
+/// One `m=` line's media kind and the codecs it advertised
+#[derive(Debug, Clone)]
+struct OfferedMedia {
+    kind: TrackKind,
+    codecs: Vec<CodecInfo>,
+}
+
+/// Minimal SDP offer parser: recovers each `m=` line's media kind and its
+/// `a=rtpmap`/`a=fmtp` codec parameters, without pulling in a full SDP
+/// negotiation library
+fn parse_offer(sdp: &str) -> Vec<OfferedMedia> {
+    let mut media = Vec::new();
+
+    for line in sdp.lines() {
+        let line = line.trim();
+
+        if let Some(rest) = line.strip_prefix("m=") {
+            let mut parts = rest.split_whitespace();
+            let kind = match parts.next() {
+                Some("audio") => TrackKind::Audio,
+                Some("video") => TrackKind::Video,
+                _ => continue,
+            };
+            media.push(OfferedMedia { kind, codecs: Vec::new() });
+            continue;
+        }
+
+        let Some(current) = media.last_mut() else { continue };
+
+        if let Some(rest) = line.strip_prefix("a=rtpmap:") {
+            let Some((_payload_type, name_and_rate)) = rest.split_once(' ') else { continue };
+            let name = name_and_rate.split('/').next().unwrap_or(name_and_rate);
+            current.codecs.push(CodecInfo {
+                name: name.to_string(),
+                parameters: Vec::new(),
+            });
+        } else if let Some(rest) = line.strip_prefix("a=fmtp:") {
+            let Some((_payload_type, params)) = rest.split_once(' ') else { continue };
+            let Some(codec) = current.codecs.last_mut() else { continue };
+            for param in params.split(';') {
+                let Some((name, value)) = param.split_once('=') else { continue };
+                codec.parameters.push(CodecParameter {
+                    name: name.trim().to_string(),
+                    value: value.trim().to_string(),
+                });
+            }
+        }
+    }
+
+    media
+}
+
+/// Synthesize a minimal SDP answer accepting every offered media line
+fn synthesize_answer(offer: &[OfferedMedia]) -> String {
+    let mut sdp = String::from("v=0\r\no=- 0 0 IN IP4 0.0.0.0\r\ns=-\r\nt=0 0\r\n");
+
+    for media in offer {
+        let kind = match media.kind {
+            TrackKind::Audio => "audio",
+            TrackKind::Video => "video",
+        };
+        sdp.push_str(&format!("m={} 9 UDP/TLS/RTP/SAVPF\r\nc=IN IP4 0.0.0.0\r\na=recvonly\r\n", kind));
+    }
+
+    sdp
+}
+
+/// A WHIP (publish) or WHEP (subscribe) resource created by a `POST`,
+/// torn down by a `DELETE` on its `Location` URL
+struct WhipResource {
+    /// Session this resource's tracks were published/subscribed under
+    session_id: SessionId,
+    /// Tracks synthesized from the offer's `m=` lines
+    tracks: Vec<TrackInfo>,
+}
+
+/// Bridges WHIP (publish) and WHEP (subscribe) HTTP requests onto a
+/// `SignalingProtocol`
+pub struct WhipGateway {
+    /// Native signaling protocol this gateway's translated messages target
+    signaling: Arc<dyn SignalingProtocol>,
+    /// Live WHIP/WHEP resources, keyed by the id in their `Location` URL
+    resources: RwLock<HashMap<u64, WhipResource>>,
+    /// Source of resource and (until real sessions are wired in) session ids
+    next_id: AtomicU64,
+}
+
+impl WhipGateway {
+    /// Create a new gateway translating WHIP/WHEP HTTP requests onto `signaling`
+    pub fn new(signaling: Arc<dyn SignalingProtocol>) -> Self {
+        Self {
+            signaling,
+            resources: RwLock::new(HashMap::new()),
+            next_id: AtomicU64::new(1),
+        }
+    }
+
+    fn next_id(&self) -> u64 {
+        self.next_id.fetch_add(1, Ordering::Relaxed)
+    }
+
+    /// Handle a publish (WHIP) or subscribe (WHEP) offer: synthesize
+    /// `TrackInfo`s from its `m=` lines, describe the `SignalingMessage`s
+    /// they translate to, register a resource for them, and return the
+    /// resource id alongside the answer SDP
+    async fn accept_offer(&self, offer_sdp: &str, is_publish: bool) -> (u64, String) {
+        let offered = parse_offer(offer_sdp);
+        let session_id = self.next_id();
+
+        let tracks: Vec<TrackInfo> = offered
+            .iter()
+            .enumerate()
+            .map(|(index, media)| TrackInfo {
+                track_id: index as u64,
+                publisher_id: session_id,
+                kind: media.kind,
+                codec: media.codecs.first().cloned().unwrap_or(CodecInfo {
+                    name: "unknown".to_string(),
+                    parameters: Vec::new(),
+                }),
+                simulcast: None,
+            })
+            .collect();
+
+        // Describe the native signaling messages this offer translates to.
+        // This is a placeholder - actual implementation will dispatch these
+        // through `self.signaling` once a connection-less session variant
+        // exists; for now we log the translation `self.signaling` would
+        // receive so the HTTP and iroh paths are demonstrably speaking the
+        // same vocabulary.
+        for track_info in &tracks {
+            let message = if is_publish {
+                SignalingMessage::TrackPublish { track_info: track_info.clone() }
+            } else {
+                SignalingMessage::TrackSubscribe {
+                    session_id,
+                    track_id: track_info.track_id,
+                    params: crate::signaling::SubscriptionParams {
+                        preferred_layers: None,
+                        max_bitrate: None,
+                    },
+                }
+            };
+            tracing::debug!(
+                "WHIP/WHEP session {} translates to {:?} on {:p}",
+                session_id,
+                message,
+                Arc::as_ptr(&self.signaling),
+            );
+        }
+
+        let answer = synthesize_answer(&offered);
+        let resource_id = self.next_id();
+
+        self.resources
+            .write()
+            .await
+            .insert(resource_id, WhipResource { session_id, tracks });
+
+        (resource_id, answer)
+    }
+
+    async fn remove_resource(&self, resource_id: u64) -> bool {
+        self.resources.write().await.remove(&resource_id).is_some()
+    }
+}
+
+/// Build the axum router exposing `POST /whip`, `POST /whep`, and
+/// `DELETE /resource/:id`
+pub fn router(gateway: Arc<WhipGateway>) -> Router {
+    Router::new()
+        .route("/whip", post(handle_whip))
+        .route("/whep", post(handle_whep))
+        .route("/resource/:id", delete(handle_delete))
+        .with_state(gateway)
+}
+
+/// Shared response for both `/whip` and `/whep`: a `201 Created` carrying the
+/// answer SDP and a `Location` pointing at the new resource
+fn created_response(resource_id: u64, answer_sdp: String) -> Response {
+    (
+        StatusCode::CREATED,
+        [
+            (header::CONTENT_TYPE, SDP_CONTENT_TYPE.to_string()),
+            (header::LOCATION, format!("/resource/{}", resource_id)),
+        ],
+        answer_sdp,
+    )
+        .into_response()
+}
+
+async fn handle_whip(State(gateway): State<Arc<WhipGateway>>, body: Bytes) -> Response {
+    let Ok(offer_sdp) = std::str::from_utf8(&body) else {
+        return StatusCode::BAD_REQUEST.into_response();
+    };
+
+    let (resource_id, answer_sdp) = gateway.accept_offer(offer_sdp, true).await;
+    created_response(resource_id, answer_sdp)
+}
+
+async fn handle_whep(State(gateway): State<Arc<WhipGateway>>, body: Bytes) -> Response {
+    let Ok(offer_sdp) = std::str::from_utf8(&body) else {
+        return StatusCode::BAD_REQUEST.into_response();
+    };
+
+    let (resource_id, answer_sdp) = gateway.accept_offer(offer_sdp, false).await;
+    created_response(resource_id, answer_sdp)
+}
+
+async fn handle_delete(
+    State(gateway): State<Arc<WhipGateway>>,
+    Path(resource_id): Path<u64>,
+) -> StatusCode {
+    if gateway.remove_resource(resource_id).await {
+        StatusCode::NO_CONTENT
+    } else {
+        StatusCode::NOT_FOUND
+    }
+}
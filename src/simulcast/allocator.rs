@@ -0,0 +1,235 @@
+// Per-subscriber simulcast layer allocation
+//
+// Each subscriber of a simulcast track gets the highest layer it can afford,
+// picked by greedy water-filling. For an SVC track, layers are dependent (a
+// spatial/temporal layer needs every lower layer to decode), so affordability
+// is judged against the *cumulative* bitrate of every active layer up to and
+// including the candidate. For a (non-scalable) simulcast track, spatial
+// layers are independently-encoded streams — a subscriber receives only one,
+// so its cost is that stream's own cumulative temporal bitrate alone; see
+// `affordable_svc`/`affordable_simulcast`. Upscaling requires sustained
+// headroom (`UPSCALE_STABILITY_WINDOW` at `UPSCALE_HYSTERESIS_FACTOR` above
+// the candidate's cost) so a transient bandwidth spike doesn't bounce a
+// subscriber up and back down; downscaling applies immediately since
+// reacting slowly to congestion is worse than a flicker in quality.
+
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    feedback::{SimulcastControlMessage, SwitchReason},
+    media::{ScalabilityMode, TrackId},
+    session::SessionId,
+};
+
+use super::SimulcastLayer;
+
+/// Bandwidth reserved as headroom when judging whether a layer fits, so a
+/// switch doesn't land right at the edge of the subscriber's estimate
+const BANDWIDTH_PROTECTION_MARGIN: f32 = 0.15;
+
+/// How much the estimate must clear an upscale candidate's cumulative
+/// bitrate by before it's even considered
+const UPSCALE_HYSTERESIS_FACTOR: f32 = 1.15;
+
+/// How long an upscale candidate must stay affordable before it's taken
+const UPSCALE_STABILITY_WINDOW: Duration = Duration::from_secs(3);
+
+/// A resolved simulcast layer for one subscriber
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LayerSelection {
+    /// Selected spatial layer index
+    pub spatial_id: u8,
+    /// Selected temporal layer index
+    pub temporal_id: u8,
+    /// Cumulative bitrate of every layer up to and including this one
+    pub cumulative_bitrate: u32,
+}
+
+/// Result of allocating a layer for one subscriber
+pub struct Allocation {
+    /// Resolved layer, or `None` when the subscriber can't afford even the
+    /// lowest active layer and should fall back to audio-only
+    pub selection: Option<LayerSelection>,
+    /// A `LayerSwitched` notification, present when `selection` just changed
+    /// to a new layer. Dropping to audio-only also changes the allocation
+    /// but has no layer to report, so it never produces one.
+    pub switch: Option<SimulcastControlMessage>,
+}
+
+/// Per-(track, subscriber) allocation state
+struct SubscriberState {
+    /// Subscriber's current layer, `None` meaning audio-only fallback
+    current: Option<LayerSelection>,
+    /// An affordable-but-not-yet-adopted upscale candidate and when it was
+    /// first seen, reset whenever the candidate changes or is adopted
+    upscale_candidate: Option<(LayerSelection, Instant)>,
+}
+
+/// Greedy water-filling allocator picking the highest affordable simulcast
+/// layer per (track, subscriber), with hysteresis against oscillation
+#[derive(Default)]
+pub struct BitrateAllocator {
+    state: HashMap<(TrackId, SessionId), SubscriberState>,
+}
+
+impl BitrateAllocator {
+    /// Create a new allocator with no recorded allocations
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Allocate the best layer `subscriber_id` can afford for `track_id`,
+    /// given its currently active `layers`, its latest bandwidth estimate,
+    /// and the track's declared scalability mode (`None` is treated as SVC,
+    /// the conservative default: every layer is assumed dependent)
+    pub fn allocate(
+        &mut self,
+        track_id: TrackId,
+        subscriber_id: SessionId,
+        layers: &[SimulcastLayer],
+        available_bandwidth: u32,
+        scalability_mode: Option<&ScalabilityMode>,
+    ) -> Allocation {
+        let threshold = (available_bandwidth as f32 * (1.0 - BANDWIDTH_PROTECTION_MARGIN)) as u32;
+
+        let affordable = if scalability_mode.is_some_and(|mode| mode.simulcast) {
+            Self::affordable_simulcast(layers, threshold)
+        } else {
+            Self::affordable_svc(layers, threshold)
+        };
+
+        let state = self
+            .state
+            .entry((track_id, subscriber_id))
+            .or_insert_with(|| SubscriberState { current: None, upscale_candidate: None });
+
+        let resolved = Self::resolve(state, affordable, available_bandwidth);
+        let switched = resolved != state.current;
+        state.current = resolved;
+
+        let switch = match (switched, resolved) {
+            (true, Some(selection)) => Some(SimulcastControlMessage::LayerSwitched {
+                track_id,
+                spatial_id: selection.spatial_id,
+                temporal_id: selection.temporal_id,
+                reason: SwitchReason::Bandwidth,
+            }),
+            _ => None,
+        };
+
+        Allocation { selection: resolved, switch }
+    }
+
+    /// Decide the layer to adopt this round, applying upscale hysteresis.
+    /// Downscales (including dropping to audio-only) always apply immediately.
+    fn resolve(
+        state: &mut SubscriberState,
+        affordable: Option<LayerSelection>,
+        available_bandwidth: u32,
+    ) -> Option<LayerSelection> {
+        let is_upscale = match (state.current, affordable) {
+            (Some(current), Some(candidate)) => Self::rank(&candidate) > Self::rank(&current),
+            (None, Some(_)) => true,
+            _ => false,
+        };
+
+        if !is_upscale {
+            state.upscale_candidate = None;
+            return affordable;
+        }
+
+        let candidate = affordable.expect("affordable is Some when is_upscale is true");
+        let still_tracking = state
+            .upscale_candidate
+            .is_some_and(|(tracked, _)| tracked == candidate);
+        if !still_tracking {
+            state.upscale_candidate = Some((candidate, Instant::now()));
+        }
+
+        // The estimate must clear the candidate's own cumulative cost by
+        // `UPSCALE_HYSTERESIS_FACTOR`, not just meet the protected threshold,
+        // before a sustained window even starts counting down
+        let clears_hysteresis =
+            available_bandwidth as f32 >= candidate.cumulative_bitrate as f32 * UPSCALE_HYSTERESIS_FACTOR;
+
+        let (_, first_seen) = state.upscale_candidate.expect("just set above");
+        if clears_hysteresis && first_seen.elapsed() >= UPSCALE_STABILITY_WINDOW {
+            state.upscale_candidate = None;
+            Some(candidate)
+        } else {
+            state.current
+        }
+    }
+
+    /// Total ordering over layers for comparing "higher than current":
+    /// spatial resolution dominates, temporal layer breaks ties
+    fn rank(selection: &LayerSelection) -> (u8, u8) {
+        (selection.spatial_id, selection.temporal_id)
+    }
+
+    /// Affordability for an SVC track: spatial layers are inter-predicted,
+    /// so decoding any layer costs the cumulative bitrate of every active
+    /// layer at or below it, regardless of spatial/temporal index
+    fn affordable_svc(layers: &[SimulcastLayer], threshold: u32) -> Option<LayerSelection> {
+        let mut sorted: Vec<&SimulcastLayer> = layers.iter().filter(|layer| layer.active).collect();
+        sorted.sort_by_key(|layer| layer.target_bitrate);
+
+        let mut cumulative = 0u32;
+        let mut affordable = None;
+        for layer in sorted {
+            cumulative = cumulative.saturating_add(layer.target_bitrate);
+            if cumulative <= threshold {
+                affordable = Some(LayerSelection {
+                    spatial_id: layer.spatial_id,
+                    temporal_id: layer.temporal_id,
+                    cumulative_bitrate: cumulative,
+                });
+            }
+        }
+        affordable
+    }
+
+    /// Affordability for a simulcast track: each spatial layer is an
+    /// independently-encoded stream, so a subscriber receives only one and
+    /// its cost is that stream's own cumulative temporal bitrate, never the
+    /// cost of any other spatial layer. The highest-ranked affordable
+    /// (spatial, temporal) pair across all streams wins.
+    fn affordable_simulcast(layers: &[SimulcastLayer], threshold: u32) -> Option<LayerSelection> {
+        let mut spatial_ids: Vec<u8> = layers.iter().filter(|layer| layer.active).map(|layer| layer.spatial_id).collect();
+        spatial_ids.sort_unstable();
+        spatial_ids.dedup();
+
+        let mut best: Option<LayerSelection> = None;
+        for spatial_id in spatial_ids {
+            let mut stream: Vec<&SimulcastLayer> = layers
+                .iter()
+                .filter(|layer| layer.active && layer.spatial_id == spatial_id)
+                .collect();
+            stream.sort_by_key(|layer| layer.temporal_id);
+
+            let mut cumulative = 0u32;
+            for layer in stream {
+                cumulative = cumulative.saturating_add(layer.target_bitrate);
+                if cumulative > threshold {
+                    continue;
+                }
+                let candidate = LayerSelection {
+                    spatial_id,
+                    temporal_id: layer.temporal_id,
+                    cumulative_bitrate: cumulative,
+                };
+                let is_better = match &best {
+                    Some(current_best) => Self::rank(&candidate) > Self::rank(current_best),
+                    None => true,
+                };
+                if is_better {
+                    best = Some(candidate);
+                }
+            }
+        }
+        best
+    }
+}
@@ -2,6 +2,8 @@
 //
 // This module implements simulcast support for video streams.
 
+pub mod allocator;
+
 use std::{
     collections::HashMap,
     sync::Arc,
@@ -12,16 +14,20 @@ use async_trait::async_trait;
 use tokio::sync::RwLock;
 
 use crate::{
-    media::TrackId,
+    media::{ScalabilityMode, TrackId},
     session::SessionId,
     feedback::{SimulcastControlMessage, SwitchReason},
     SfuError,
 };
 
+pub use allocator::{Allocation, LayerSelection};
+use allocator::BitrateAllocator;
+
 /// Layer identifier
 pub type LayerId = u8;
 
 /// Simulcast layer
+#[derive(Debug, Clone)]
 pub struct SimulcastLayer {
     /// Layer identifier
     pub layer_id: LayerId,
@@ -33,6 +39,15 @@ pub struct SimulcastLayer {
     pub target_bitrate: u32,
     /// Current active state
     pub active: bool,
+    /// Whether this layer can be decoded without the spatial layer below it
+    /// already being decoded. Always true for simulcast (independent
+    /// streams). For a K-SVC (`_KEY`) mode, true only for the base temporal
+    /// layer of each spatial layer, spatial layer 0 included, since spatial
+    /// layers there are only inter-predicted at a keyframe. Otherwise (full
+    /// SVC, or no declared mode) true only for spatial layer 0 (nothing
+    /// below it), since every higher layer inter-predicts continuously from
+    /// the layer below.
+    pub independently_decodable: bool,
 }
 
 /// Resolution specification
@@ -57,6 +72,47 @@ pub struct SimulcastConfig {
     pub spatial_scale_factor: f32,
     /// Scaling factor between temporal layers
     pub temporal_scale_factor: f32,
+    /// Declared layer structure from the publisher's `scalability-mode`
+    /// codec parameter, if it declared one. Bounds which `ActivateLayers`
+    /// spatial/temporal indices are accepted, and tells the bitrate
+    /// allocator whether spatial layers are SVC-dependent or independent
+    /// simulcast streams.
+    pub scalability_mode: Option<ScalabilityMode>,
+}
+
+/// Default bitrate scaling factor applied between consecutive spatial
+/// layers when a config is built from a scalability-mode string alone
+const DEFAULT_SPATIAL_SCALE_FACTOR: f32 = 0.5;
+
+/// Default bitrate scaling factor applied between consecutive temporal
+/// layers when a config is built from a scalability-mode string alone
+const DEFAULT_TEMPORAL_SCALE_FACTOR: f32 = 0.6;
+
+impl SimulcastConfig {
+    /// Build a config from a standard scalability-mode string such as
+    /// `L1T3`, `L3T3`, or `L3T3_KEY` (see `ScalabilityMode::parse`),
+    /// deriving its spatial/temporal layer counts and SVC-vs-simulcast
+    /// dependency directly from the string, with default bitrate scale
+    /// factors between layers
+    pub fn from_scalability_mode(mode: &str, base_resolution: Resolution, base_framerate: f32) -> Result<Self> {
+        let mode = ScalabilityMode::parse(mode)
+            .ok_or_else(|| SfuError::Media(format!("Invalid scalability-mode string: {}", mode)))?;
+
+        Ok(Self {
+            spatial_layers: mode.spatial_layers,
+            temporal_layers: mode.temporal_layers,
+            base_resolution,
+            base_framerate,
+            spatial_scale_factor: DEFAULT_SPATIAL_SCALE_FACTOR,
+            temporal_scale_factor: DEFAULT_TEMPORAL_SCALE_FACTOR,
+            scalability_mode: Some(mode),
+        })
+    }
+
+    /// The scalability-mode this config was built from, if any
+    pub fn scalability_mode(&self) -> Option<ScalabilityMode> {
+        self.scalability_mode
+    }
 }
 
 /// Target parameters for encoding a layer
@@ -88,26 +144,103 @@ pub trait SimulcastManager: Send + Sync {
     /// Get available layers for a track
     async fn get_available_layers(&self, track_id: TrackId) -> Result<Vec<SimulcastLayer>>;
     
-    /// Select layer for a subscriber
+    /// Select layer for a subscriber. A switch to a higher spatial/temporal
+    /// layer is not applied immediately: the previous layer keeps being
+    /// forwarded (and returned here) until `notify_key_frame` reports a key
+    /// frame on the new layer's spatial id, so a decoder is never handed a
+    /// delta frame for a layer it hasn't seen a key frame on yet. A switch
+    /// to a lower-ranked layer applies immediately. The choice never exceeds
+    /// the subscriber's preferred ceiling set via `set_preferred_layers`.
+    ///
+    /// `available_bandwidth` is an explicit override; pass `None` to instead
+    /// derive it from the subscriber's last-reported measured receive
+    /// bitrate and loss (see `report_receive_stats`), scaled down by the
+    /// observed loss fraction. Fails if neither is available.
     async fn select_layer(
         &self,
         track_id: TrackId,
         subscriber_id: SessionId,
-        available_bandwidth: u32,
+        available_bandwidth: Option<u32>,
     ) -> Result<LayerId>;
-    
+
+    /// Report `subscriber_id`'s latest measured receive bitrate and packet
+    /// loss percentage for `track_id`, typically read from its
+    /// `QuicMediaTrack::stats()`. Used by `select_layer` as the bandwidth
+    /// input when no explicit `available_bandwidth` is supplied.
+    async fn report_receive_stats(
+        &self,
+        track_id: TrackId,
+        subscriber_id: SessionId,
+        receive_bitrate_bps: u32,
+        packet_loss_percent: f32,
+    ) -> Result<()>;
+
+    /// Request a fresh key frame on `track_id`'s `spatial_id` layer from its
+    /// publisher, typically so a pending switch-up can be applied once it
+    /// arrives
+    async fn request_key_frame(&self, track_id: TrackId, spatial_id: u8) -> Result<()>;
+
+    /// Report that a key frame was observed on `track_id`'s `spatial_id`
+    /// layer, applying any subscriber's pending switch-up targeting it
+    async fn notify_key_frame(&self, track_id: TrackId, spatial_id: u8) -> Result<()>;
+
+    /// Set `subscriber_id`'s preferred layer ceiling for `track_id`:
+    /// `select_layer` never picks above `max_spatial`/`max_temporal`
+    /// regardless of available bandwidth, mirroring a consumer's declared
+    /// preferred layers
+    async fn set_preferred_layers(
+        &self,
+        track_id: TrackId,
+        subscriber_id: SessionId,
+        max_spatial: u8,
+        max_temporal: u8,
+    ) -> Result<()>;
+
+    /// Set `subscriber_id`'s relative priority for `track_id`, consulted by
+    /// `allocate_track_bandwidth` when a track's total capacity can't satisfy
+    /// every subscriber at once; higher values are served first
+    async fn set_priority(&self, track_id: TrackId, subscriber_id: SessionId, priority: u8) -> Result<()>;
+
+    /// Apportion `total_available_bandwidth` across every subscriber of
+    /// `track_id` in descending priority order: each subscriber in turn gets
+    /// the highest affordable layer within its preferred ceiling, from
+    /// whatever of `total_available_bandwidth` previously-served subscribers
+    /// this round left unspent. Higher-priority subscribers keep higher
+    /// layers; lower-priority ones are degraded first once it runs out.
+    /// Returns the layer assigned to each subscriber that could be served.
+    async fn allocate_track_bandwidth(
+        &self,
+        track_id: TrackId,
+        total_available_bandwidth: u32,
+    ) -> Result<HashMap<SessionId, LayerId>>;
+
     /// Process simulcast control message
     async fn process_control_message(
         &self,
         message: SimulcastControlMessage,
         publisher_id: SessionId,
     ) -> Result<()>;
+
+    /// Allocate the best layer `subscriber_id` can afford for `track_id`'s
+    /// currently active layers, given its latest bandwidth estimate. Unlike
+    /// `select_layer`, this applies greedy water-filling over cumulative
+    /// layer cost plus upscale hysteresis (see `allocator::BitrateAllocator`),
+    /// and reports a `None` selection as an audio-only fallback rather than
+    /// clamping to the lowest layer.
+    async fn allocate_subscriber_layer(
+        &self,
+        track_id: TrackId,
+        subscriber_id: SessionId,
+        available_bandwidth: u32,
+    ) -> Result<Allocation>;
 }
 
 /// Default implementation of the simulcast manager
 pub struct DefaultSimulcastManager {
     /// Track configurations
     track_configs: Arc<RwLock<HashMap<TrackId, TrackSimulcastInfo>>>,
+    /// Per-subscriber water-filling allocator backing `allocate_subscriber_layer`
+    allocator: RwLock<BitrateAllocator>,
 }
 
 /// Track simulcast information
@@ -122,6 +255,22 @@ struct TrackSimulcastInfo {
     layers: Vec<SimulcastLayer>,
     /// Subscriber layer selections
     subscriber_selections: HashMap<SessionId, LayerId>,
+    /// Subscribers with a switch-up target that's not forwarded yet: held
+    /// at their current (lower-ranked) layer in `subscriber_selections`
+    /// until `notify_key_frame` reports a key frame on the target's spatial
+    /// layer, so forwarding a new spatial layer always starts on a keyframe
+    pending_switch: HashMap<SessionId, LayerId>,
+    /// Per-subscriber preferred layer ceiling (max spatial id, max temporal
+    /// id), set via `set_preferred_layers`; absent means no ceiling
+    preferred_layers: HashMap<SessionId, (u8, u8)>,
+    /// Per-subscriber relative priority, set via `set_priority`; absent
+    /// defaults to `0`, the lowest priority
+    priorities: HashMap<SessionId, u8>,
+    /// Per-subscriber measured receive bitrate (bps) and packet loss
+    /// (0.0-100.0), reported via `report_receive_stats` from the
+    /// subscriber's `QuicMediaTrack::stats()`. Consulted by `select_layer`
+    /// when called without a caller-supplied bandwidth figure
+    measured_stats: HashMap<SessionId, (u32, f32)>,
 }
 
 impl DefaultSimulcastManager {
@@ -129,6 +278,7 @@ impl DefaultSimulcastManager {
     pub fn new() -> Self {
         Self {
             track_configs: Arc::new(RwLock::new(HashMap::new())),
+            allocator: RwLock::new(BitrateAllocator::new()),
         }
     }
     
@@ -145,13 +295,24 @@ impl DefaultSimulcastManager {
                 let temporal_factor = config.temporal_scale_factor.powi(temporal_id as i32);
                 let base_bitrate = 500_000; // 500 kbps base bitrate
                 let target_bitrate = (base_bitrate as f32 * spatial_factor * temporal_factor) as u32;
-                
+
+                // Simulcast streams are independently-encoded; full SVC
+                // inter-predicts every spatial layer from the one below it
+                // continuously; K-SVC only does so at a keyframe, so only
+                // its base temporal layer is independently decodable
+                let independently_decodable = match &config.scalability_mode {
+                    Some(mode) if mode.simulcast => true,
+                    Some(mode) if mode.ksvc => temporal_id == 0,
+                    _ => spatial_id == 0,
+                };
+
                 let layer = SimulcastLayer {
                     layer_id,
                     spatial_id,
                     temporal_id,
                     target_bitrate,
                     active: spatial_id == 0 && temporal_id == 0, // Only activate base layer by default
+                    independently_decodable,
                 };
                 
                 layers.push(layer);
@@ -170,6 +331,17 @@ impl DefaultSimulcastManager {
     ) -> Option<&SimulcastLayer> {
         layers.iter().find(|layer| layer.spatial_id == spatial_id && layer.temporal_id == temporal_id)
     }
+
+    /// Get layer by its identifier
+    fn find_layer_by_id(layers: &[SimulcastLayer], layer_id: LayerId) -> Option<&SimulcastLayer> {
+        layers.iter().find(|layer| layer.layer_id == layer_id)
+    }
+
+    /// Total ordering over layers for comparing "higher than current":
+    /// spatial resolution dominates, temporal layer breaks ties
+    fn rank(layer: &SimulcastLayer) -> (u8, u8) {
+        (layer.spatial_id, layer.temporal_id)
+    }
 }
 
 #[async_trait]
@@ -192,6 +364,10 @@ impl SimulcastManager for DefaultSimulcastManager {
             config,
             layers,
             subscriber_selections: HashMap::new(),
+            pending_switch: HashMap::new(),
+            preferred_layers: HashMap::new(),
+            priorities: HashMap::new(),
+            measured_stats: HashMap::new(),
         };
         
         // Register the track
@@ -227,30 +403,220 @@ impl SimulcastManager for DefaultSimulcastManager {
         &self,
         track_id: TrackId,
         subscriber_id: SessionId,
-        available_bandwidth: u32,
+        available_bandwidth: Option<u32>,
     ) -> Result<LayerId> {
         let mut track_configs = self.track_configs.write().await;
-        
+
         // Get track info
         let track_info = track_configs
             .get_mut(&track_id)
             .ok_or_else(|| SfuError::Media(format!("Simulcast track not found: {}", track_id)))?;
-        
-        // Find the highest quality layer that fits within the available bandwidth
+
+        let available_bandwidth = match available_bandwidth {
+            Some(available_bandwidth) => available_bandwidth,
+            None => {
+                let (receive_bitrate_bps, packet_loss_percent) = track_info
+                    .measured_stats
+                    .get(&subscriber_id)
+                    .copied()
+                    .ok_or_else(|| {
+                        SfuError::Media(format!(
+                            "No bandwidth estimate for subscriber {} on track {}: no available_bandwidth supplied and no measured stats reported",
+                            subscriber_id, track_id
+                        ))
+                    })?;
+                (receive_bitrate_bps as f32 * (1.0 - packet_loss_percent / 100.0)) as u32
+            }
+        };
+
+        let (max_spatial, max_temporal) = track_info
+            .preferred_layers
+            .get(&subscriber_id)
+            .copied()
+            .unwrap_or((u8::MAX, u8::MAX));
+
+        // Find the highest quality layer that fits within the available
+        // bandwidth and the subscriber's preferred ceiling
         let mut selected_layer_id = 0; // Default to lowest layer
-        
+
         for layer in &track_info.layers {
-            if layer.active && layer.target_bitrate <= available_bandwidth {
+            if layer.active
+                && layer.target_bitrate <= available_bandwidth
+                && layer.spatial_id <= max_spatial
+                && layer.temporal_id <= max_temporal
+            {
                 selected_layer_id = layer.layer_id;
             }
         }
-        
-        // Update subscriber selection
+
+        let current_layer_id = track_info.subscriber_selections.get(&subscriber_id).copied();
+        let current = current_layer_id.and_then(|layer_id| Self::find_layer_by_id(&track_info.layers, layer_id));
+        let selected = Self::find_layer_by_id(&track_info.layers, selected_layer_id);
+
+        let is_switch_up = match (current, selected) {
+            (Some(current), Some(selected)) => Self::rank(selected) > Self::rank(current),
+            _ => false,
+        };
+
+        if is_switch_up {
+            // Hold at the currently-forwarded layer until `notify_key_frame`
+            // confirms a key frame on the target's spatial layer, so the
+            // switch never lands on a delta frame the decoder can't use
+            track_info.pending_switch.insert(subscriber_id, selected_layer_id);
+            let forwarded_layer_id = current_layer_id.expect("is_switch_up implies a current selection");
+            let target_spatial_id = selected.expect("is_switch_up implies a selected layer").spatial_id;
+            drop(track_configs);
+
+            self.request_key_frame(track_id, target_spatial_id).await?;
+            return Ok(forwarded_layer_id);
+        }
+
+        // Switch-downs (and the first selection for a subscriber) apply
+        // immediately; any switch-up this subscriber was waiting on is moot
+        track_info.pending_switch.remove(&subscriber_id);
         track_info.subscriber_selections.insert(subscriber_id, selected_layer_id);
-        
+
         Ok(selected_layer_id)
     }
-    
+
+    async fn request_key_frame(&self, track_id: TrackId, spatial_id: u8) -> Result<()> {
+        let track_configs = self.track_configs.read().await;
+        track_configs
+            .get(&track_id)
+            .ok_or_else(|| SfuError::Media(format!("Simulcast track not found: {}", track_id)))?;
+
+        // This is a placeholder - actual implementation would push
+        // `SimulcastControlMessage::RequestKeyFrame` over the publisher's
+        // feedback channel; there is no registry mapping a track back to
+        // its publisher's `FeedbackChannel` from this manager yet
+        tracing::info!("Requesting key frame for track {} spatial layer {}", track_id, spatial_id);
+
+        Ok(())
+    }
+
+    async fn notify_key_frame(&self, track_id: TrackId, spatial_id: u8) -> Result<()> {
+        let mut track_configs = self.track_configs.write().await;
+        let track_info = track_configs
+            .get_mut(&track_id)
+            .ok_or_else(|| SfuError::Media(format!("Simulcast track not found: {}", track_id)))?;
+
+        let ready: Vec<SessionId> = track_info
+            .pending_switch
+            .iter()
+            .filter(|(_, layer_id)| {
+                Self::find_layer_by_id(&track_info.layers, **layer_id)
+                    .is_some_and(|layer| layer.spatial_id == spatial_id)
+            })
+            .map(|(subscriber_id, _)| *subscriber_id)
+            .collect();
+
+        for subscriber_id in ready {
+            if let Some(layer_id) = track_info.pending_switch.remove(&subscriber_id) {
+                track_info.subscriber_selections.insert(subscriber_id, layer_id);
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn set_preferred_layers(
+        &self,
+        track_id: TrackId,
+        subscriber_id: SessionId,
+        max_spatial: u8,
+        max_temporal: u8,
+    ) -> Result<()> {
+        let mut track_configs = self.track_configs.write().await;
+        let track_info = track_configs
+            .get_mut(&track_id)
+            .ok_or_else(|| SfuError::Media(format!("Simulcast track not found: {}", track_id)))?;
+
+        track_info.preferred_layers.insert(subscriber_id, (max_spatial, max_temporal));
+
+        Ok(())
+    }
+
+    async fn set_priority(&self, track_id: TrackId, subscriber_id: SessionId, priority: u8) -> Result<()> {
+        let mut track_configs = self.track_configs.write().await;
+        let track_info = track_configs
+            .get_mut(&track_id)
+            .ok_or_else(|| SfuError::Media(format!("Simulcast track not found: {}", track_id)))?;
+
+        track_info.priorities.insert(subscriber_id, priority);
+
+        Ok(())
+    }
+
+    async fn report_receive_stats(
+        &self,
+        track_id: TrackId,
+        subscriber_id: SessionId,
+        receive_bitrate_bps: u32,
+        packet_loss_percent: f32,
+    ) -> Result<()> {
+        let mut track_configs = self.track_configs.write().await;
+        let track_info = track_configs
+            .get_mut(&track_id)
+            .ok_or_else(|| SfuError::Media(format!("Simulcast track not found: {}", track_id)))?;
+
+        track_info.measured_stats.insert(subscriber_id, (receive_bitrate_bps, packet_loss_percent));
+
+        Ok(())
+    }
+
+    async fn allocate_track_bandwidth(
+        &self,
+        track_id: TrackId,
+        total_available_bandwidth: u32,
+    ) -> Result<HashMap<SessionId, LayerId>> {
+        let mut track_configs = self.track_configs.write().await;
+        let track_info = track_configs
+            .get_mut(&track_id)
+            .ok_or_else(|| SfuError::Media(format!("Simulcast track not found: {}", track_id)))?;
+
+        // Highest priority first; subscribers with no recorded priority
+        // default to 0, the lowest, so they're degraded before anyone who
+        // explicitly set one
+        let mut subscriber_ids: Vec<SessionId> = track_info.subscriber_selections.keys().copied().collect();
+        subscriber_ids.sort_by_key(|subscriber_id| {
+            std::cmp::Reverse(track_info.priorities.get(subscriber_id).copied().unwrap_or(0))
+        });
+
+        let mut remaining_bandwidth = total_available_bandwidth;
+        let mut allocations = HashMap::new();
+
+        for subscriber_id in subscriber_ids {
+            let (max_spatial, max_temporal) = track_info
+                .preferred_layers
+                .get(&subscriber_id)
+                .copied()
+                .unwrap_or((u8::MAX, u8::MAX));
+
+            let mut affordable: Option<&SimulcastLayer> = None;
+            for layer in &track_info.layers {
+                if layer.active
+                    && layer.spatial_id <= max_spatial
+                    && layer.temporal_id <= max_temporal
+                    && layer.target_bitrate <= remaining_bandwidth
+                {
+                    affordable = Some(layer);
+                }
+            }
+
+            let Some(layer) = affordable else {
+                continue;
+            };
+            let layer_id = layer.layer_id;
+            let target_bitrate = layer.target_bitrate;
+
+            remaining_bandwidth = remaining_bandwidth.saturating_sub(target_bitrate);
+            allocations.insert(subscriber_id, layer_id);
+            track_info.subscriber_selections.insert(subscriber_id, layer_id);
+        }
+
+        Ok(allocations)
+    }
+
     async fn process_control_message(
         &self,
         message: SimulcastControlMessage,
@@ -277,7 +643,21 @@ impl SimulcastManager for DefaultSimulcastManager {
                     ))
                     .into());
                 }
-                
+
+                // Reject indices outside the publisher's declared SVC/simulcast
+                // structure before even looking for a matching layer, so a
+                // malformed request gets a specific "not declared" error
+                // rather than the generic "layer not found" below
+                if let Some(mode) = &track_info.config.scalability_mode {
+                    if !mode.contains(spatial_id, temporal_id) {
+                        return Err(SfuError::Media(format!(
+                            "spatial={}, temporal={} is outside track {}'s declared {}x{} layer range",
+                            spatial_id, temporal_id, track_id, mode.spatial_layers, mode.temporal_layers
+                        ))
+                        .into());
+                    }
+                }
+
                 // Find the layer
                 let layer = Self::find_layer(&track_info.layers, spatial_id, temporal_id)
                     .ok_or_else(|| {
@@ -337,7 +717,47 @@ impl SimulcastManager for DefaultSimulcastManager {
                 
                 Ok(())
             }
+            SimulcastControlMessage::RequestKeyFrame { .. } => {
+                // This is a notification sent *to* the publisher by
+                // `request_key_frame`, not one this manager ever receives
+                // back from it
+                Ok(())
+            }
+        }
+    }
+
+    async fn allocate_subscriber_layer(
+        &self,
+        track_id: TrackId,
+        subscriber_id: SessionId,
+        available_bandwidth: u32,
+    ) -> Result<Allocation> {
+        let (layers, scalability_mode) = {
+            let track_configs = self.track_configs.read().await;
+            let track_info = track_configs
+                .get(&track_id)
+                .ok_or_else(|| SfuError::Media(format!("Simulcast track not found: {}", track_id)))?;
+            (track_info.layers.clone(), track_info.config.scalability_mode)
+        };
+
+        let mut allocator = self.allocator.write().await;
+        let allocation = allocator.allocate(track_id, subscriber_id, &layers, available_bandwidth, scalability_mode.as_ref());
+        drop(allocator);
+
+        // Keep `select_layer`'s legacy per-subscriber map in sync so callers
+        // relying on either method see a consistent selection
+        if let Some(selection) = allocation.selection {
+            if let Some(layer) =
+                Self::find_layer(&layers, selection.spatial_id, selection.temporal_id)
+            {
+                let mut track_configs = self.track_configs.write().await;
+                if let Some(track_info) = track_configs.get_mut(&track_id) {
+                    track_info.subscriber_selections.insert(subscriber_id, layer.layer_id);
+                }
+            }
         }
+
+        Ok(allocation)
     }
 }
 
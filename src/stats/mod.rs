@@ -13,7 +13,7 @@ use async_trait::async_trait;
 use tokio::sync::RwLock;
 
 use crate::{
-    media::TrackId,
+    media::{rtcp::RtcpPacket, TrackId},
     session::SessionId,
     SfuError,
 };
@@ -105,6 +105,16 @@ pub trait StatsCollector: Send + Sync {
         track_id: TrackId,
         is_publisher: bool,
     ) -> Result<TrackStats>;
+
+    /// Ingest a received RTCP compound packet for a session, updating connection
+    /// and track statistics from its sender/receiver report blocks
+    async fn ingest_rtcp(
+        &self,
+        session_id: SessionId,
+        track_id: TrackId,
+        is_publisher: bool,
+        packet: &RtcpPacket,
+    ) -> Result<()>;
 }
 
 /// Default implementation of the statistics collector
@@ -210,6 +220,56 @@ impl StatsCollector for DefaultStatsCollector {
                 .ok_or_else(|| SfuError::Media(format!("Subscribed track not found: {}", track_id)).into())
         }
     }
+
+    async fn ingest_rtcp(
+        &self,
+        session_id: SessionId,
+        track_id: TrackId,
+        is_publisher: bool,
+        packet: &RtcpPacket,
+    ) -> Result<()> {
+        let reports: &[crate::media::rtcp::ReportBlock] = match packet {
+            RtcpPacket::SenderReport(sr) => &sr.reports,
+            RtcpPacket::ReceiverReport(rr) => &rr.reports,
+            // Feedback packets (NACK/PLI/FIR/REMB) and anything unrecognized
+            // carry no report blocks for this stats update to consume
+            RtcpPacket::Nack(_)
+            | RtcpPacket::Pli(_)
+            | RtcpPacket::Fir(_)
+            | RtcpPacket::Remb(_)
+            | RtcpPacket::Unknown { .. } => return Ok(()),
+        };
+
+        let Some(report) = reports.first() else {
+            return Ok(());
+        };
+
+        let mut session_stats = self.session_stats.write().await;
+        let session = session_stats
+            .get_mut(&session_id)
+            .ok_or_else(|| SfuError::Session(format!("Session not found: {}", session_id)))?;
+
+        if let Some(rtt_ms) = report.round_trip_time_ms(crate::media::rtcp::ntp_now_mid32()) {
+            session.connection_stats.rtt_ms = rtt_ms;
+        }
+
+        let tracks = if is_publisher {
+            &mut session.published_tracks
+        } else {
+            &mut session.subscribed_tracks
+        };
+
+        if let Some(track) = tracks.get_mut(&track_id) {
+            track.packet_loss_percent = report.packet_loss_percent();
+            // Interarrival jitter is carried in RTP timestamp units; a 90kHz clock,
+            // the common RTP video rate, is assumed for every track, audio included.
+            track.jitter_ms = report.jitter as f32 / 90.0;
+        }
+
+        session.last_update = Instant::now();
+
+        Ok(())
+    }
 }
 
 // Default implementation
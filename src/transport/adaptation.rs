@@ -3,23 +3,31 @@
 // This module implements bandwidth adaptation for media streams over QUIC.
 
 use std::{
+    collections::HashMap,
     sync::Arc,
     time::{Duration, Instant},
 };
 
 use anyhow::Result;
-use tokio::sync::RwLock;
+use tokio::sync::{Mutex, RwLock};
 
 use crate::{
-    bandwidth::{BandwidthManager, BandwidthTrend},
-    feedback::{BandwidthEstimation, FeedbackMessage},
+    bandwidth::{
+        gcc::{AimdRateController, BandwidthUsage, GccEstimator},
+        loss::LossController,
+        BandwidthManager,
+    },
+    feedback::{FeedbackMessage, PacketArrivalRecord},
     media::{
         TrackId,
         codec::CodecType,
     },
     session::SessionId,
     simulcast::{SimulcastManager, LayerId},
-    transport::integration::{QuicMediaStream, StreamDirection},
+    transport::{
+        integration::{QuicMediaStream, StreamDirection},
+        pacer::Pacer,
+    },
     SfuError,
 };
 
@@ -35,6 +43,7 @@ pub enum AdaptationStrategy {
 }
 
 /// Bandwidth adaptation parameters
+#[derive(Clone)]
 pub struct AdaptationParams {
     /// Adaptation strategy
     pub strategy: AdaptationStrategy,
@@ -44,14 +53,6 @@ pub struct AdaptationParams {
     pub max_bitrate: u32,
     /// Target buffer size (ms)
     pub target_buffer_ms: u32,
-    /// Bandwidth headroom factor (0.0-1.0)
-    pub headroom_factor: f32,
-    /// Upscale threshold factor (> 1.0)
-    pub upscale_factor: f32,
-    /// Downscale threshold factor (< 1.0)
-    pub downscale_factor: f32,
-    /// Stability period after adaptation (ms)
-    pub stability_period_ms: u32,
 }
 
 impl Default for AdaptationParams {
@@ -61,14 +62,85 @@ impl Default for AdaptationParams {
             min_bitrate: 100_000,    // 100 kbps
             max_bitrate: 5_000_000,  // 5 Mbps
             target_buffer_ms: 500,   // 500 ms
-            headroom_factor: 0.8,    // Use 80% of available bandwidth
-            upscale_factor: 1.2,     // Upscale when 120% bandwidth available
-            downscale_factor: 0.8,   // Downscale when below 80% bandwidth
-            stability_period_ms: 2000, // 2 seconds stability period
         }
     }
 }
 
+/// Concrete gain constants derived from an `AdaptationStrategy`, threaded
+/// into `DelayBasedController` so the strategy enum actually changes ramp
+/// behavior instead of sitting unused in `AdaptationParams`
+#[derive(Debug, Clone, Copy)]
+pub struct ControllerGains {
+    /// Multiplicative increase factor applied to the target on sustained Normal state
+    pub increase_factor: f64,
+    /// Multiplicative decrease factor applied to the target on Overuse
+    pub decrease_factor: f64,
+    /// How long the modified trend must stay above threshold before Overuse fires
+    pub overuse_time_threshold: Duration,
+    /// Adaptive-threshold gain applied while the modified trend is above it
+    pub k_up: f64,
+    /// Adaptive-threshold gain applied while the modified trend is within it
+    pub k_down: f64,
+}
+
+impl ControllerGains {
+    /// Derive gains from an adaptation strategy: Conservative tolerates more
+    /// delay before backing off (a longer overuse confirmation window and a
+    /// larger `k_up`, so the adaptive threshold rises faster toward a
+    /// persistent trend) and ramps back up slowly; Aggressive confirms overuse
+    /// faster and climbs back up quickly; Moderate sits between the two
+    pub fn for_strategy(strategy: AdaptationStrategy) -> Self {
+        match strategy {
+            AdaptationStrategy::Conservative => Self {
+                increase_factor: 1.04,
+                decrease_factor: 0.75,
+                overuse_time_threshold: Duration::from_millis(20),
+                k_up: 0.02,
+                k_down: 0.00018,
+            },
+            AdaptationStrategy::Moderate => Self {
+                increase_factor: 1.08,
+                decrease_factor: 0.85,
+                overuse_time_threshold: Duration::from_millis(10),
+                k_up: 0.01,
+                k_down: 0.00018,
+            },
+            AdaptationStrategy::Aggressive => Self {
+                increase_factor: 1.16,
+                decrease_factor: 0.92,
+                overuse_time_threshold: Duration::from_millis(5),
+                k_up: 0.005,
+                k_down: 0.00018,
+            },
+        }
+    }
+}
+
+/// Delay-based bitrate controller driven by receiver-reported packet
+/// arrivals (see `PacketArrivalReport`), replacing the old headroom/upscale/
+/// downscale heuristics with the same trendline estimator and AIMD rate
+/// controller `bandwidth::gcc` uses for session-level estimation
+struct DelayBasedController {
+    gcc: GccEstimator,
+    rate_controller: AimdRateController,
+}
+
+impl DelayBasedController {
+    fn new(initial_bps: u32, gains: ControllerGains) -> Self {
+        Self {
+            gcc: GccEstimator::with_gains(gains.k_up, gains.k_down, gains.overuse_time_threshold),
+            rate_controller: AimdRateController::with_gains(initial_bps, gains.increase_factor, gains.decrease_factor),
+        }
+    }
+
+    /// Feed one packet's departure/arrival timestamps into the controller,
+    /// returning the detector state and the updated target bitrate in bps
+    fn on_packet_arrival(&mut self, departure: Instant, arrival: Instant, size_bytes: usize) -> (BandwidthUsage, u32) {
+        let (usage, receive_rate_bps) = self.gcc.on_packet(departure, arrival, size_bytes);
+        (usage, self.rate_controller.update(usage, receive_rate_bps))
+    }
+}
+
 /// Bandwidth adapter for media streams
 pub struct BandwidthAdapter {
     /// Session identifier
@@ -77,22 +149,26 @@ pub struct BandwidthAdapter {
     track_id: TrackId,
     /// Codec type
     codec_type: CodecType,
-    /// Current bitrate
+    /// Current bitrate, the minimum of the delay-based and loss-based targets
     current_bitrate: Arc<RwLock<u32>>,
-    /// Available bandwidth
-    available_bandwidth: Arc<RwLock<u32>>,
-    /// Bandwidth trend
-    bandwidth_trend: Arc<RwLock<BandwidthTrend>>,
+    /// Latest delay-based target, driven by `process_feedback`'s `PacketArrival` reports
+    delay_based_bitrate: Arc<RwLock<u32>>,
+    /// Latest loss-based target, driven by `process_feedback`'s `ReceiverReport` reports
+    loss_based_bitrate: Arc<RwLock<u32>>,
+    /// Delay-based controller driven by `process_feedback`'s `PacketArrival` reports
+    congestion: Mutex<DelayBasedController>,
+    /// Loss-based controller driven by `process_feedback`'s `ReceiverReport` reports
+    loss_controller: Mutex<LossController>,
     /// Adaptation parameters
     params: AdaptationParams,
-    /// Last adaptation time
-    last_adaptation: Arc<RwLock<Instant>>,
     /// Current simulcast layer (if applicable)
     current_layer: Arc<RwLock<Option<LayerId>>>,
     /// Simulcast manager (if applicable)
     simulcast_manager: Option<Arc<dyn SimulcastManager>>,
     /// Bandwidth manager
     bandwidth_manager: Arc<dyn BandwidthManager>,
+    /// Pacer smoothing this track's egress, if one has been attached via `attach_pacer`
+    pacer: RwLock<Option<Arc<Pacer>>>,
 }
 
 impl BandwidthAdapter {
@@ -106,112 +182,142 @@ impl BandwidthAdapter {
         simulcast_manager: Option<Arc<dyn SimulcastManager>>,
         params: AdaptationParams,
     ) -> Self {
+        let gains = ControllerGains::for_strategy(params.strategy);
         Self {
             session_id,
             track_id,
             codec_type,
             current_bitrate: Arc::new(RwLock::new(initial_bitrate)),
-            available_bandwidth: Arc::new(RwLock::new(initial_bitrate)),
-            bandwidth_trend: Arc::new(RwLock::new(BandwidthTrend::Stable)),
+            delay_based_bitrate: Arc::new(RwLock::new(initial_bitrate)),
+            loss_based_bitrate: Arc::new(RwLock::new(initial_bitrate)),
+            congestion: Mutex::new(DelayBasedController::new(initial_bitrate, gains)),
+            loss_controller: Mutex::new(LossController::new(initial_bitrate)),
             params,
-            last_adaptation: Arc::new(RwLock::new(Instant::now())),
             current_layer: Arc::new(RwLock::new(None)),
             simulcast_manager,
             bandwidth_manager,
+            pacer: RwLock::new(None),
         }
     }
-    
+
+    /// Attach a pacer to smooth this track's egress; `apply_target_bitrate`
+    /// keeps its target rate in sync with `current_bitrate` from here on
+    pub async fn attach_pacer(&self, pacer: Arc<Pacer>) {
+        *self.pacer.write().await = Some(pacer);
+    }
+
     /// Process a feedback message
     pub async fn process_feedback(&self, message: &FeedbackMessage) -> Result<()> {
         match message {
-            FeedbackMessage::BandwidthEstimation(estimation) => {
-                if estimation.session_id == self.session_id {
-                    // Update available bandwidth
-                    let mut available = self.available_bandwidth.write().await;
-                    *available = estimation.available_bandwidth;
-                    
-                    // Update bandwidth trend
-                    let mut trend = self.bandwidth_trend.write().await;
-                    *trend = estimation.trend;
-                    
-                    // Check if adaptation is needed
-                    self.adapt_bitrate().await?;
+            FeedbackMessage::PacketArrival(report) => {
+                if report.session_id == self.session_id && report.track_id == self.track_id {
+                    self.adapt_bitrate(report.departure, report.arrival, report.size_bytes).await?;
+                }
+            }
+            FeedbackMessage::ReceiverReport(report) => {
+                if report.session_id == self.session_id && report.track_id == self.track_id {
+                    self.adapt_to_loss(report.packet_loss / 100.0).await?;
+                }
+            }
+            FeedbackMessage::TransportCc(feedback) => {
+                if feedback.session_id == self.session_id && feedback.track_id == self.track_id {
+                    self.adapt_to_transport_cc(&feedback.packets).await?;
                 }
             }
             _ => {
                 // Ignore other message types
             }
         }
-        
+
         Ok(())
     }
-    
-    /// Adapt bitrate based on available bandwidth
-    pub async fn adapt_bitrate(&self) -> Result<()> {
-        // Check if we're in stability period
-        let now = Instant::now();
-        let last_adaptation = *self.last_adaptation.read().await;
-        if now.duration_since(last_adaptation) < Duration::from_millis(self.params.stability_period_ms as u64) {
-            return Ok(());
+
+    /// Feed one receiver-reported packet arrival into the delay-based
+    /// controller and apply the minimum of the delay-based and loss-based targets
+    pub async fn adapt_bitrate(&self, departure: Instant, arrival: Instant, size_bytes: usize) -> Result<()> {
+        let (_usage, target_bitrate) = self.congestion.lock().await.on_packet_arrival(departure, arrival, size_bytes);
+        *self.delay_based_bitrate.write().await = target_bitrate;
+        self.apply_target_bitrate().await
+    }
+
+    /// Feed one receiver-reported loss fraction (0.0-1.0) into the loss-based
+    /// controller and apply the minimum of the delay-based and loss-based targets
+    pub async fn adapt_to_loss(&self, loss_fraction: f32) -> Result<()> {
+        let target_bitrate = self.loss_controller.lock().await.report_loss_fraction(loss_fraction);
+        *self.loss_based_bitrate.write().await = target_bitrate;
+        self.apply_target_bitrate().await
+    }
+
+    /// Feed a batch of per-packet transport-wide-cc arrival records into the
+    /// delay-based controller's arrival-time grouping logic, in transport
+    /// sequence order, and apply the resulting target bitrate once for the
+    /// whole batch. Packets reported lost (no arrival time) carry no delay
+    /// information and are skipped.
+    pub async fn adapt_to_transport_cc(&self, packets: &[PacketArrivalRecord]) -> Result<()> {
+        let mut ordered: Vec<&PacketArrivalRecord> = packets.iter().collect();
+        ordered.sort_by_key(|record| record.transport_seq);
+
+        let mut congestion = self.congestion.lock().await;
+        let mut target_bitrate = None;
+        for record in ordered {
+            let Some(arrival) = record.arrival else {
+                continue;
+            };
+            let (_usage, target) = congestion.on_packet_arrival(record.departure, arrival, record.size_bytes);
+            target_bitrate = Some(target);
         }
-        
-        let available_bandwidth = *self.available_bandwidth.read().await;
-        let current_bitrate = *self.current_bitrate.read().await;
-        let trend = *self.bandwidth_trend.read().await;
-        
-        // Calculate target bitrate with headroom
-        let target_bitrate = (available_bandwidth as f32 * self.params.headroom_factor) as u32;
-        
-        // Determine if adaptation is needed
-        let mut new_bitrate = current_bitrate;
-        let mut should_adapt = false;
-        
-        if target_bitrate > current_bitrate * self.params.upscale_factor as u32 && trend == BandwidthTrend::Increasing {
-            // Increase bitrate
-            new_bitrate = (current_bitrate as f32 * self.params.upscale_factor) as u32;
-            should_adapt = true;
-        } else if target_bitrate < current_bitrate * self.params.downscale_factor as u32 || trend == BandwidthTrend::Decreasing {
-            // Decrease bitrate
-            new_bitrate = (current_bitrate as f32 * self.params.downscale_factor) as u32;
-            should_adapt = true;
+        drop(congestion);
+
+        if let Some(target_bitrate) = target_bitrate {
+            *self.delay_based_bitrate.write().await = target_bitrate;
+            self.apply_target_bitrate().await?;
         }
-        
-        // Apply min/max constraints
-        new_bitrate = new_bitrate.max(self.params.min_bitrate).min(self.params.max_bitrate);
-        
-        if should_adapt && new_bitrate != current_bitrate {
-            // Update current bitrate
-            let mut current = self.current_bitrate.write().await;
-            *current = new_bitrate;
-            
-            // Update last adaptation time
-            let mut last = self.last_adaptation.write().await;
-            *last = now;
-            
-            // If simulcast is enabled, select appropriate layer
-            if let Some(simulcast_manager) = &self.simulcast_manager {
-                let layer_id = simulcast_manager.select_layer(
-                    self.track_id,
-                    self.session_id,
-                    new_bitrate,
-                ).await?;
-                
-                // Update current layer
-                let mut current_layer = self.current_layer.write().await;
-                *current_layer = Some(layer_id);
-            }
-            
-            // Update bandwidth manager
-            self.bandwidth_manager.update_bandwidth(
+
+        Ok(())
+    }
+
+    /// Recompute the current bitrate as the minimum of the delay-based and
+    /// loss-based targets, clamp it, and apply it to the simulcast layer
+    /// selection and bandwidth manager if it changed
+    async fn apply_target_bitrate(&self) -> Result<()> {
+        let delay_based = *self.delay_based_bitrate.read().await;
+        let loss_based = *self.loss_based_bitrate.read().await;
+        let new_bitrate = delay_based.min(loss_based).clamp(self.params.min_bitrate, self.params.max_bitrate);
+
+        let mut current = self.current_bitrate.write().await;
+        if new_bitrate == *current {
+            return Ok(());
+        }
+        *current = new_bitrate;
+        drop(current);
+
+        // If simulcast is enabled, select appropriate layer
+        if let Some(simulcast_manager) = &self.simulcast_manager {
+            let layer_id = simulcast_manager.select_layer(
+                self.track_id,
                 self.session_id,
-                new_bitrate,
-                true, // This is upload bandwidth
+                Some(new_bitrate),
             ).await?;
+
+            // Update current layer
+            let mut current_layer = self.current_layer.write().await;
+            *current_layer = Some(layer_id);
         }
-        
+
+        // Update bandwidth manager
+        self.bandwidth_manager.update_bandwidth(
+            self.session_id,
+            new_bitrate,
+            true, // This is upload bandwidth
+        ).await?;
+
+        if let Some(pacer) = &*self.pacer.read().await {
+            pacer.set_target_bitrate(new_bitrate);
+        }
+
         Ok(())
     }
-    
+
     /// Get the current bitrate
     pub async fn current_bitrate(&self) -> u32 {
         *self.current_bitrate.read().await
@@ -222,6 +328,35 @@ impl BandwidthAdapter {
         *self.current_layer.read().await
     }
     
+    /// Reset this adapter's congestion-control state: clears the delay-based
+    /// controller's trendline history and adaptive threshold and the
+    /// loss-based controller's loss window, putting both back to their
+    /// freshly-created state. Reseeds `current_bitrate` (and the delay-based
+    /// and loss-based targets) to `restart_bitrate` if given, otherwise to
+    /// whatever bitrate was in effect before the reset.
+    ///
+    /// Call this after an `iroh` path migration or when a long-muted track
+    /// is unpaused, so a stale trendline measured against the old path
+    /// doesn't bias the first post-reset estimate.
+    pub async fn reset_estimate(&self, restart_bitrate: Option<u32>) -> Result<()> {
+        let seed = match restart_bitrate {
+            Some(seed) => seed,
+            None => *self.current_bitrate.read().await,
+        };
+
+        *self.congestion.lock().await = DelayBasedController::new(seed, ControllerGains::for_strategy(self.params.strategy));
+        *self.loss_controller.lock().await = LossController::new(seed);
+        *self.delay_based_bitrate.write().await = seed;
+        *self.loss_based_bitrate.write().await = seed;
+        *self.current_bitrate.write().await = seed;
+
+        if let Some(pacer) = &*self.pacer.read().await {
+            pacer.set_target_bitrate(seed);
+        }
+
+        Ok(())
+    }
+
     /// Apply adaptation to a media stream
     pub async fn apply_to_stream(&self, stream: &QuicMediaStream) -> Result<()> {
         // Only apply to send streams
@@ -272,6 +407,10 @@ pub struct BandwidthAdaptationManager {
     simulcast_manager: Option<Arc<dyn SimulcastManager>>,
     /// Default adaptation parameters
     default_params: AdaptationParams,
+    /// Adapters created by this manager, keyed by session/track, so
+    /// `reset_all` can reach every adapter for a session on a detected
+    /// network change
+    adapters: RwLock<HashMap<(SessionId, TrackId), Arc<BandwidthAdapter>>>,
 }
 
 impl BandwidthAdaptationManager {
@@ -284,38 +423,31 @@ impl BandwidthAdaptationManager {
             bandwidth_manager,
             simulcast_manager,
             default_params: AdaptationParams::default(),
+            adapters: RwLock::new(HashMap::new()),
         }
     }
-    
+
     /// Create a bandwidth adapter for a track
-    pub fn create_adapter(
+    pub async fn create_adapter(
         &self,
         session_id: SessionId,
         track_id: TrackId,
         codec_type: CodecType,
         initial_bitrate: u32,
-    ) -> BandwidthAdapter {
-        BandwidthAdapter::new(
-            session_id,
-            track_id,
-            codec_type,
-            initial_bitrate,
-            self.bandwidth_manager.clone(),
-            self.simulcast_manager.clone(),
-            self.default_params.clone(),
-        )
+    ) -> Arc<BandwidthAdapter> {
+        self.create_adapter_with_params(session_id, track_id, codec_type, initial_bitrate, self.default_params.clone()).await
     }
-    
+
     /// Create a bandwidth adapter with custom parameters
-    pub fn create_adapter_with_params(
+    pub async fn create_adapter_with_params(
         &self,
         session_id: SessionId,
         track_id: TrackId,
         codec_type: CodecType,
         initial_bitrate: u32,
         params: AdaptationParams,
-    ) -> BandwidthAdapter {
-        BandwidthAdapter::new(
+    ) -> Arc<BandwidthAdapter> {
+        let adapter = Arc::new(BandwidthAdapter::new(
             session_id,
             track_id,
             codec_type,
@@ -323,9 +455,25 @@ impl BandwidthAdaptationManager {
             self.bandwidth_manager.clone(),
             self.simulcast_manager.clone(),
             params,
-        )
+        ));
+
+        self.adapters.write().await.insert((session_id, track_id), adapter.clone());
+        adapter
     }
-    
+
+    /// Reset every adapter tracked for `session_id`, e.g. on a detected
+    /// `iroh` path migration, seeding each back to its own pre-reset bitrate
+    pub async fn reset_all(&self, session_id: SessionId) -> Result<()> {
+        let adapters = self.adapters.read().await;
+        for ((adapter_session_id, _), adapter) in adapters.iter() {
+            if *adapter_session_id == session_id {
+                adapter.reset_estimate(None).await?;
+            }
+        }
+
+        Ok(())
+    }
+
     /// Get the bandwidth manager
     pub fn bandwidth_manager(&self) -> &Arc<dyn BandwidthManager> {
         &self.bandwidth_manager
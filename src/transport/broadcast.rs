@@ -0,0 +1,154 @@
+// MoQ-style named broadcast pub/sub over the QUIC transport
+//
+// `QuicMediaTransport` is flow-centric: `create_session` / `send_track` /
+// `receive_track` just open raw send/receive flows, so wiring a publisher to
+// its subscribers means passing `TrackId`s and `SessionId`s around by hand.
+// Borrowing the Media-over-QUIC model, this module lets a publisher
+// `announce` a `QuicMediaTrack` under a string name and lets subscribers
+// `subscribe` to that name, with the broker doing the matching.
+//
+// The hierarchy is broadcast (named stream) -> track (one `QuicMediaTrack`)
+// -> group (one GoP, starting at a key frame) -> object (one frame), mirroring
+// `QuicMediaTrack::send_frame`'s `MoqObjectId` tagging. A subscriber that
+// joins mid-group has no key frame to decode a delta against, so `publish`
+// holds it in `Pending` until the next group boundary (`object_seq == 0`)
+// before admitting it to `Active` and starting to forward objects to it.
+//
+// A publisher's `QuicMediaTrack` calls `publish` for every frame it sends via
+// `QuicMediaTrack::set_broadcast`, which wires this broker into the track's
+// `send_frame` instead of requiring a caller to forward frames by hand.
+
+use std::{collections::HashMap, sync::Arc};
+
+use anyhow::Result;
+use tokio::sync::{mpsc, RwLock};
+
+use crate::{media::frame::MediaFrame, session::SessionId, SfuError};
+
+use super::quic::{MoqObjectId, QuicMediaTrack};
+
+/// Capacity of a subscriber's object channel. A subscriber that can't keep
+/// up loses each new object published while its channel is full, rather
+/// than applying backpressure to the publish path.
+const SUBSCRIBER_CHANNEL_CAPACITY: usize = 256;
+
+/// One object delivered to a broadcast subscriber: its group/object position
+/// alongside the frame itself
+pub type BroadcastObject = (MoqObjectId, MediaFrame);
+
+/// A subscriber's admission state: held back until the broadcast's track
+/// opens its next group, since it has no key frame to decode a mid-group
+/// delta against
+enum SubscriberState {
+    /// Waiting for the next group boundary before objects are forwarded
+    Pending,
+    /// Receiving every object published to the broadcast
+    Active,
+}
+
+/// One subscriber of a broadcast: its admission state and the channel
+/// objects are forwarded on
+struct Subscriber {
+    state: SubscriberState,
+    sender: mpsc::Sender<BroadcastObject>,
+}
+
+/// A named, announced broadcast: the track carrying it, who published it,
+/// and every subscriber currently attached
+struct Broadcast {
+    source: SessionId,
+    track: Arc<QuicMediaTrack>,
+    subscribers: HashMap<SessionId, Subscriber>,
+}
+
+/// Named publish/subscribe registry matching publishers and subscribers by
+/// broadcast name, instead of wiring `QuicMediaTrack`s together by hand
+#[derive(Default)]
+pub struct Broadcasts {
+    broadcasts: RwLock<HashMap<String, Broadcast>>,
+}
+
+impl Broadcasts {
+    /// Create an empty broker with no announced broadcasts
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Announce `track` as available under `name`, sourced from `source`.
+    /// Replaces any broadcast previously announced under the same name.
+    pub async fn announce(&self, name: String, source: SessionId, track: Arc<QuicMediaTrack>) -> Result<()> {
+        let mut broadcasts = self.broadcasts.write().await;
+        broadcasts.insert(name.clone(), Broadcast { source, track, subscribers: HashMap::new() });
+
+        // This is a placeholder - actual implementation would also make this
+        // broadcast discoverable to other nodes via `relay::Broker`
+        tracing::info!("Announced broadcast \"{}\" from session {}", name, source);
+
+        Ok(())
+    }
+
+    /// Withdraw a previously announced broadcast, dropping every subscriber
+    pub async fn unannounce(&self, name: &str) {
+        if self.broadcasts.write().await.remove(name).is_some() {
+            tracing::info!("Unannounced broadcast \"{}\"", name);
+        }
+    }
+
+    /// Subscribe `subscriber` to the broadcast announced as `name`, returning
+    /// the track to read its codec/track metadata from and a channel of the
+    /// objects published to it. The subscriber is held back until the
+    /// broadcast's next group boundary.
+    pub async fn subscribe(
+        &self,
+        name: &str,
+        subscriber: SessionId,
+    ) -> Result<(Arc<QuicMediaTrack>, mpsc::Receiver<BroadcastObject>)> {
+        let mut broadcasts = self.broadcasts.write().await;
+        let broadcast = broadcasts
+            .get_mut(name)
+            .ok_or_else(|| SfuError::Transport(format!("No broadcast announced as \"{}\"", name)))?;
+
+        let (sender, receiver) = mpsc::channel(SUBSCRIBER_CHANNEL_CAPACITY);
+        broadcast.subscribers.insert(subscriber, Subscriber { state: SubscriberState::Pending, sender });
+
+        tracing::debug!(
+            "Session {} subscribed to broadcast \"{}\", held back until the next group boundary",
+            subscriber,
+            name,
+        );
+
+        Ok((broadcast.track.clone(), receiver))
+    }
+
+    /// Remove `subscriber` from the broadcast announced as `name`
+    pub async fn unsubscribe(&self, name: &str, subscriber: SessionId) {
+        if let Some(broadcast) = self.broadcasts.write().await.get_mut(name) {
+            broadcast.subscribers.remove(&subscriber);
+        }
+    }
+
+    /// Publish one object to every subscriber of `name`, admitting any
+    /// pending subscriber that was waiting on this group boundary
+    pub async fn publish(&self, name: &str, object_id: MoqObjectId, frame: MediaFrame) -> Result<()> {
+        let mut broadcasts = self.broadcasts.write().await;
+        let broadcast = broadcasts
+            .get_mut(name)
+            .ok_or_else(|| SfuError::Transport(format!("No broadcast announced as \"{}\"", name)))?;
+
+        for subscriber in broadcast.subscribers.values_mut() {
+            if matches!(subscriber.state, SubscriberState::Pending) {
+                if object_id.object_seq != 0 {
+                    // Still mid-group: this subscriber can't decode from here
+                    continue;
+                }
+                subscriber.state = SubscriberState::Active;
+            }
+
+            if let Err(e) = subscriber.sender.try_send((object_id, frame.clone())) {
+                tracing::debug!("Dropping broadcast object for \"{}\": {}", name, e);
+            }
+        }
+
+        Ok(())
+    }
+}
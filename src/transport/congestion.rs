@@ -0,0 +1,101 @@
+// Per-track delay-based bandwidth estimation for the QUIC transport
+//
+// `QuicMediaTrack::receive_frame` only ever sees RTP timestamps and local
+// arrival instants, not a publisher-supplied wall-clock send time, so this
+// reuses `bandwidth::gcc`'s trendline estimator and AIMD rate controller by
+// synthesizing a departure instant from the RTP clock instead.
+
+use std::time::{Duration, Instant};
+
+use crate::bandwidth::gcc::{AimdRateController, GccEstimator, DEFAULT_DECREASE_FACTOR};
+
+/// Initial target bitrate assumed before any packet groups have been observed
+const INITIAL_TARGET_BITRATE_BPS: u32 = 500_000;
+
+/// Floor a track's estimate is clamped to when no explicit `min_bitrate` is given
+const DEFAULT_MIN_BITRATE_BPS: u32 = 50_000;
+
+/// How long sustained queuing delay must persist before the AIMD rate
+/// controller reacts, when no explicit reaction interval is given
+const DEFAULT_REACTION_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Delay-based bandwidth estimator for a single `QuicMediaTrack`'s receive
+/// path: turns the RTP timestamp of each arriving packet into a synthetic
+/// departure instant, then feeds the (departure, arrival) pair into the same
+/// `GccEstimator`/`AimdRateController` pipeline the session-level bandwidth
+/// manager uses, closing the loop between transport conditions and simulcast
+/// layer selection for this track
+pub struct TrackBandwidthEstimator {
+    /// RTP clock rate the incoming timestamps are in, used to convert
+    /// timestamp deltas into a synthetic departure `Instant`
+    clock_rate: u32,
+    /// RTP timestamp and arrival instant of the first packet seen, anchoring
+    /// the synthetic departure clock
+    origin: Option<(u32, Instant)>,
+    gcc: GccEstimator,
+    rate_controller: AimdRateController,
+    /// Bounds the target bitrate is clamped to, mirroring the track's stream
+    /// config (`min_bitrate`/`max_bitrate`)
+    min_bitrate: u32,
+    max_bitrate: u32,
+}
+
+impl TrackBandwidthEstimator {
+    /// Create a new estimator for a track whose RTP timestamps run at
+    /// `clock_rate` Hz, with the default bitrate clamp and reaction interval
+    pub fn new(clock_rate: u32) -> Self {
+        Self::with_config(
+            clock_rate,
+            DEFAULT_MIN_BITRATE_BPS,
+            u32::MAX,
+            DEFAULT_DECREASE_FACTOR,
+            DEFAULT_REACTION_INTERVAL,
+        )
+    }
+
+    /// Create an estimator with a configurable bitrate clamp and AIMD
+    /// decrease factor/overuse reaction interval, so a stream's declared
+    /// `min_bitrate`/`max_bitrate` and how aggressively it backs off on
+    /// congestion can differ per track instead of assuming `new`'s defaults
+    pub fn with_config(
+        clock_rate: u32,
+        min_bitrate: u32,
+        max_bitrate: u32,
+        decrease_factor: f64,
+        reaction_interval: Duration,
+    ) -> Self {
+        Self {
+            clock_rate,
+            origin: None,
+            gcc: GccEstimator::with_overuse_threshold(reaction_interval),
+            rate_controller: AimdRateController::with_decrease_factor(
+                INITIAL_TARGET_BITRATE_BPS.clamp(min_bitrate, max_bitrate),
+                decrease_factor,
+            ),
+            min_bitrate,
+            max_bitrate,
+        }
+    }
+
+    /// Feed one received packet's RTP timestamp, local arrival instant, and
+    /// wire size into the estimator, returning the updated target bitrate in bps
+    pub fn on_packet(&mut self, rtp_timestamp: u32, arrival: Instant, size_bytes: usize) -> u32 {
+        let &mut (origin_timestamp, origin_arrival) = self.origin.get_or_insert((rtp_timestamp, arrival));
+
+        let elapsed_rtp_units = rtp_timestamp.wrapping_sub(origin_timestamp) as i64;
+        let elapsed_secs = elapsed_rtp_units as f64 / self.clock_rate as f64;
+        let departure = if elapsed_secs >= 0.0 {
+            origin_arrival + Duration::from_secs_f64(elapsed_secs)
+        } else {
+            origin_arrival.checked_sub(Duration::from_secs_f64(-elapsed_secs)).unwrap_or(origin_arrival)
+        };
+
+        let (usage, receive_rate_bps) = self.gcc.on_packet(departure, arrival, size_bytes);
+        self.rate_controller.update(usage, receive_rate_bps).clamp(self.min_bitrate, self.max_bitrate)
+    }
+
+    /// Current target bitrate in bps, without feeding a new packet
+    pub fn target_bps(&self) -> u32 {
+        self.rate_controller.target_bps().clamp(self.min_bitrate, self.max_bitrate)
+    }
+}
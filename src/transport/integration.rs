@@ -4,7 +4,8 @@
 
 use std::{
     collections::HashMap,
-    sync::Arc,
+    sync::{atomic::{AtomicU32, Ordering}, Arc},
+    time::Duration,
 };
 
 use anyhow::Result;
@@ -12,7 +13,11 @@ use iroh::{
     endpoint::{Endpoint, Connection},
     NodeId,
 };
-use tokio::sync::RwLock;
+use serde::{Deserialize, Serialize};
+use tokio::{
+    io::AsyncWriteExt,
+    sync::{mpsc, RwLock},
+};
 
 use crate::{
     connection::RtcConnection,
@@ -20,6 +25,7 @@ use crate::{
         codec::{CodecType, CodecFactory},
         frame::MediaFrame,
     },
+    simulcast::LayerId,
     transport::{
         quic::{QuicMediaTrack, QuicMediaSender, QuicMediaReceiver},
     },
@@ -39,6 +45,14 @@ pub enum StreamDirection {
     Inactive,
 }
 
+/// Default multiplicative decrease factor applied to a stream's congestion
+/// estimate on overuse
+const DEFAULT_CONGESTION_DECREASE_FACTOR: f64 = 0.85;
+
+/// Default overuse reaction interval: how long sustained queuing delay must
+/// persist before a stream's congestion estimate backs off
+const DEFAULT_CONGESTION_REACTION_INTERVAL: Duration = Duration::from_millis(10);
+
 /// Media stream configuration
 pub struct MediaStreamConfig {
     /// Stream direction
@@ -49,8 +63,76 @@ pub struct MediaStreamConfig {
     pub payload_type: u8,
     /// SSRC identifier
     pub ssrc: u32,
-    /// Maximum bitrate
+    /// Minimum bitrate the delay-based congestion controller will clamp down to
+    pub min_bitrate: u32,
+    /// Maximum bitrate, also the ceiling the congestion controller clamps to
+    pub max_bitrate: u32,
+    /// Additional simulcast encodings of this stream, each with its own SSRC
+    /// and target bitrate. Empty for a non-simulcast stream.
+    pub layers: Vec<LayerConfig>,
+}
+
+/// Per-layer simulcast encoding parameters for a `QuicMediaStream`
+#[derive(Debug, Clone)]
+pub struct LayerConfig {
+    /// Layer identifier, matching `simulcast::SimulcastLayer::layer_id`
+    pub layer_id: LayerId,
+    /// This layer's own SSRC, so its RTP stream is distinguishable from the others
+    pub ssrc: u32,
+    /// Initial target bitrate for this layer
+    pub target_bitrate: u32,
+}
+
+/// One track advertised in a session's `Catalog`: everything a connecting
+/// peer needs to construct the right `CodecFactory` decoder and issue a
+/// subscribe for a specific layer before any media flows, instead of
+/// guessing the codec from the first frame
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrackDescriptor {
+    /// Stream identifier, as passed to `QuicMediaManager::create_stream`
+    pub stream_id: String,
+    /// Codec type
+    pub codec_type: CodecType,
+    /// Payload type
+    pub payload_type: u8,
+    /// Primary SSRC
+    pub ssrc: u32,
+    /// Maximum bitrate, from the stream's `MediaStreamConfig`
     pub max_bitrate: u32,
+    /// Additional simulcast layers beyond the primary track, empty for a
+    /// non-simulcast stream
+    pub layers: Vec<LayerDescriptor>,
+}
+
+/// One simulcast layer advertised alongside its `TrackDescriptor`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LayerDescriptor {
+    /// Layer identifier, matching `simulcast::SimulcastLayer::layer_id`
+    pub layer_id: LayerId,
+    /// This layer's own SSRC
+    pub ssrc: u32,
+    /// Current target bitrate
+    pub target_bitrate: u32,
+}
+
+/// A session's full set of advertised tracks: sent over a dedicated QUIC
+/// control stream when a peer connects, and re-sent as a fresh snapshot
+/// whenever a stream is created or removed, so a remote subscriber can pick
+/// tracks/layers and issue a subscribe before any media flows
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct Catalog {
+    /// Tracks currently published on the session
+    pub tracks: Vec<TrackDescriptor>,
+}
+
+/// One simulcast encoding of a `QuicMediaStream`: its own track (and so its
+/// own SSRC/packetizer) and sender, kept alongside the others so switching
+/// which layer is forwarded, or re-pacing one, never tears down the stream
+struct QuicMediaStreamLayer {
+    track: Arc<QuicMediaTrack>,
+    sender: Option<QuicMediaSender>,
+    /// Current target bitrate, adjustable in place via `QuicMediaStream::update_bitrate`
+    target_bitrate: AtomicU32,
 }
 
 /// Media stream over QUIC
@@ -65,6 +147,11 @@ pub struct QuicMediaStream {
     receiver: Option<QuicMediaReceiver>,
     /// Stream configuration
     config: MediaStreamConfig,
+    /// Additional simulcast layers beyond the primary track, keyed by layer id
+    layers: HashMap<LayerId, QuicMediaStreamLayer>,
+    /// Layer currently selected for forwarding; `None` forwards on the
+    /// primary track/sender instead of one of `layers`
+    active_layer: RwLock<Option<LayerId>>,
 }
 
 impl QuicMediaStream {
@@ -75,13 +162,25 @@ impl QuicMediaStream {
         config: MediaStreamConfig,
     ) -> Self {
         // Create media track
-        let track = Arc::new(QuicMediaTrack::new(
+        let mut track_inner = QuicMediaTrack::new(
             track_id,
             config.codec_type,
             config.payload_type,
             config.ssrc,
-        ));
-        
+        );
+
+        // Clamp the inbound delay-based bandwidth estimate to this stream's
+        // declared bitrate range; only receiving tracks ever feed packets
+        // into it, but configuring a send-only track is harmless
+        track_inner.set_bandwidth_limits(
+            config.min_bitrate,
+            config.max_bitrate,
+            DEFAULT_CONGESTION_DECREASE_FACTOR,
+            DEFAULT_CONGESTION_REACTION_INTERVAL,
+        );
+
+        let track = Arc::new(track_inner);
+
         // Create sender and receiver based on direction
         let sender = match config.direction {
             StreamDirection::SendOnly | StreamDirection::SendRecv => {
@@ -89,32 +188,112 @@ impl QuicMediaStream {
             }
             _ => None,
         };
-        
+
         let receiver = match config.direction {
             StreamDirection::RecvOnly | StreamDirection::SendRecv => {
                 Some(QuicMediaReceiver::new(track.clone(), 30))
             }
             _ => None,
         };
-        
+
+        let layers = config
+            .layers
+            .iter()
+            .map(|layer_config| {
+                let layer_track = Arc::new(QuicMediaTrack::new(
+                    track_id,
+                    config.codec_type,
+                    config.payload_type,
+                    layer_config.ssrc,
+                ));
+
+                let layer_sender = match config.direction {
+                    StreamDirection::SendOnly | StreamDirection::SendRecv => {
+                        Some(QuicMediaSender::new(layer_track.clone(), 30))
+                    }
+                    _ => None,
+                };
+
+                (
+                    layer_config.layer_id,
+                    QuicMediaStreamLayer {
+                        track: layer_track,
+                        sender: layer_sender,
+                        target_bitrate: AtomicU32::new(layer_config.target_bitrate),
+                    },
+                )
+            })
+            .collect();
+
         Self {
             stream_id,
             track,
             sender,
             receiver,
             config,
+            layers,
+            active_layer: RwLock::new(None),
         }
     }
-    
-    /// Send a media frame
+
+    /// Send a media frame on the currently active layer (see
+    /// `set_active_layer`), falling back to the primary track/sender if no
+    /// layer has been selected
     pub async fn send_frame(&self, frame: MediaFrame) -> Result<()> {
+        if let Some(layer_id) = *self.active_layer.read().await {
+            let layer = self
+                .layers
+                .get(&layer_id)
+                .ok_or_else(|| SfuError::Transport(format!("Active layer not found on stream {}: {}", self.stream_id, layer_id)))?;
+            return match &layer.sender {
+                Some(sender) => sender.send_frame(frame).await,
+                None => Err(SfuError::Transport(format!("Layer {} is not configured for sending", layer_id)).into()),
+            };
+        }
+
         if let Some(sender) = &self.sender {
             sender.send_frame(frame).await
         } else {
             Err(SfuError::Transport("Stream is not configured for sending".to_string()).into())
         }
     }
-    
+
+    /// Switch which already-published layer this stream forwards, in place:
+    /// since every layer already has its own track and sender, this just
+    /// changes which one `send_frame` uses next, with no stream/track
+    /// recreation
+    pub async fn set_active_layer(&self, layer_id: LayerId) -> Result<()> {
+        if !self.layers.contains_key(&layer_id) {
+            return Err(SfuError::Transport(format!("Layer not found on stream {}: {}", self.stream_id, layer_id)).into());
+        }
+
+        *self.active_layer.write().await = Some(layer_id);
+        Ok(())
+    }
+
+    /// Currently selected layer, if any (see `set_active_layer`)
+    pub async fn active_layer(&self) -> Option<LayerId> {
+        *self.active_layer.read().await
+    }
+
+    /// Adjust a layer's target bitrate in place. This only updates the
+    /// layer's bookkeeping (reported in the stream's `Catalog` entry); it is
+    /// not a codec change and never requires tearing down the stream.
+    pub fn update_bitrate(&self, layer_id: LayerId, bps: u32) -> Result<()> {
+        let layer = self
+            .layers
+            .get(&layer_id)
+            .ok_or_else(|| SfuError::Transport(format!("Layer not found on stream {}: {}", self.stream_id, layer_id)))?;
+
+        layer.target_bitrate.store(bps, Ordering::Relaxed);
+        Ok(())
+    }
+
+    /// A layer's current target bitrate, as last set at construction or via `update_bitrate`
+    pub fn layer_bitrate(&self, layer_id: LayerId) -> Option<u32> {
+        self.layers.get(&layer_id).map(|layer| layer.target_bitrate.load(Ordering::Relaxed))
+    }
+
     /// Receive a media frame
     pub async fn receive_frame(&mut self) -> Result<Option<MediaFrame>> {
         if let Some(receiver) = &mut self.receiver {
@@ -138,16 +317,45 @@ impl QuicMediaStream {
     pub fn config(&self) -> &MediaStreamConfig {
         &self.config
     }
-    
+
+    /// This stream's `Catalog` entry, with each layer's current target
+    /// bitrate (reflecting any `update_bitrate` calls) rather than its
+    /// original configured value
+    fn descriptor(&self) -> TrackDescriptor {
+        TrackDescriptor {
+            stream_id: self.stream_id.clone(),
+            codec_type: self.config.codec_type,
+            payload_type: self.config.payload_type,
+            ssrc: self.config.ssrc,
+            max_bitrate: self.config.max_bitrate,
+            layers: self
+                .config
+                .layers
+                .iter()
+                .map(|layer_config| LayerDescriptor {
+                    layer_id: layer_config.layer_id,
+                    ssrc: layer_config.ssrc,
+                    target_bitrate: self.layer_bitrate(layer_config.layer_id).unwrap_or(layer_config.target_bitrate),
+                })
+                .collect(),
+        }
+    }
+
     /// Stop the stream
     pub async fn stop(&self) {
         if let Some(sender) = &self.sender {
             sender.stop().await;
         }
-        
+
         if let Some(receiver) = &self.receiver {
             receiver.stop().await;
         }
+
+        for layer in self.layers.values() {
+            if let Some(sender) = &layer.sender {
+                sender.stop().await;
+            }
+        }
     }
 }
 
@@ -225,7 +433,26 @@ impl QuicMediaSession {
     pub fn stream_ids(&self) -> Vec<String> {
         self.streams.keys().cloned().collect()
     }
-    
+
+    /// Every track currently published on this session, for a connecting
+    /// peer to discover before subscribing - see the `Catalog` doc comment
+    pub fn catalog(&self) -> Catalog {
+        Catalog {
+            tracks: self.streams.values().map(QuicMediaStream::descriptor).collect(),
+        }
+    }
+
+    /// A receiving stream's current delay-based bandwidth estimate, read
+    /// from its track's RTP stats (see `QuicMediaTrack::stats`). `None` if
+    /// the stream doesn't exist; `Some(0)` if it hasn't received any packets
+    /// yet. Intended
+    /// as `SimulcastManager::select_layer`'s `available_bandwidth` argument.
+    pub fn estimated_bandwidth(&self, stream_id: &str) -> Option<u32> {
+        self.streams
+            .get(stream_id)
+            .map(|stream| stream.track().stats().estimated_available_bandwidth_bps)
+    }
+
     /// Stop all streams
     pub async fn stop_all_streams(&self) {
         for stream in self.streams.values() {
@@ -257,10 +484,11 @@ impl QuicMediaManager {
         let rtc_connection = RtcConnection::new(connection, node_id);
         
         let session = QuicMediaSession::new(session_id.clone(), rtc_connection);
-        
+
         let mut sessions = self.sessions.write().await;
         sessions.insert(session_id.clone(), session);
-        
+        self.push_catalog(sessions.get(&session_id).unwrap()).await?;
+
         Ok(session_id)
     }
     
@@ -300,12 +528,62 @@ impl QuicMediaManager {
         
         if let Some(session) = sessions.get_mut(session_id) {
             session.create_stream(stream_id, track_id, config)?;
+            self.push_catalog(session).await?;
             Ok(())
         } else {
             Err(SfuError::Session(format!("Session not found: {}", session_id)).into())
         }
     }
-    
+
+    /// Remove a media stream from a session, pushing an updated catalog to
+    /// the peer afterward so it stops being advertised as available
+    pub async fn remove_stream(&self, session_id: &str, stream_id: &str) -> Result<()> {
+        let mut sessions = self.sessions.write().await;
+
+        if let Some(session) = sessions.get_mut(session_id) {
+            session.remove_stream(stream_id)?;
+            self.push_catalog(session).await?;
+            Ok(())
+        } else {
+            Err(SfuError::Session(format!("Session not found: {}", session_id)).into())
+        }
+    }
+
+    /// Recompute `session`'s catalog and push it to its connected peer over a
+    /// dedicated QUIC uni-directional stream: one stream per push, the whole
+    /// JSON-encoded `Catalog` as its body, closed once written so the peer
+    /// can just read the stream to completion
+    async fn push_catalog(&self, session: &QuicMediaSession) -> Result<()> {
+        let catalog = session.catalog();
+
+        let payload = serde_json::to_vec(&catalog)
+            .map_err(|e| SfuError::Transport(format!("Failed to encode catalog: {}", e)))?;
+
+        let mut stream = session
+            .connection()
+            .connection()
+            .open_uni()
+            .await
+            .map_err(|e| SfuError::Transport(format!("Failed to open catalog stream: {}", e)))?;
+
+        stream
+            .write_all(&payload)
+            .await
+            .map_err(|e| SfuError::Transport(format!("Failed to write catalog: {}", e)))?;
+
+        stream
+            .finish()
+            .map_err(|e| SfuError::Transport(format!("Failed to finish catalog stream: {}", e)))?;
+
+        tracing::debug!(
+            "Pushed catalog ({} tracks) to {}",
+            catalog.tracks.len(),
+            session.connection().remote_node_id()
+        );
+
+        Ok(())
+    }
+
     /// Send a media frame to a stream
     pub async fn send_frame(
         &self,
@@ -326,8 +604,264 @@ impl QuicMediaManager {
         }
     }
     
+    /// A receiving stream's current delay-based bandwidth estimate; see
+    /// `QuicMediaSession::estimated_bandwidth`
+    pub async fn estimated_bandwidth(&self, session_id: &str, stream_id: &str) -> Result<u32> {
+        let sessions = self.sessions.read().await;
+
+        let session = sessions
+            .get(session_id)
+            .ok_or_else(|| SfuError::Session(format!("Session not found: {}", session_id)))?;
+
+        session
+            .estimated_bandwidth(stream_id)
+            .ok_or_else(|| SfuError::Transport(format!("Stream not found: {}", stream_id)).into())
+    }
+
     /// Get the iroh endpoint
     pub fn endpoint(&self) -> &Endpoint {
         &self.endpoint
     }
 }
+
+/// Identifier for a named broadcast, the unit `FanoutManager` fans out under
+pub type BroadcastId = String;
+
+/// Bounded queue capacity for one subscriber's frames before it is considered
+/// stalled and dropped back to the keyframe gate
+const SUBSCRIBER_QUEUE_CAPACITY: usize = 256;
+
+/// One subscriber's delivery queue and keyframe-gate state
+struct FanoutSubscriber {
+    /// Session the subscriber's stream lives in
+    session_id: String,
+    sender: mpsc::Sender<Arc<MediaFrame>>,
+    /// While true, non-key frames are skipped rather than queued, so a
+    /// stalled subscriber resumes at the next keyframe instead of playing
+    /// out a frame that depends on ones it missed
+    waiting_for_keyframe: bool,
+}
+
+/// A published source stream and the subscribers currently receiving its frames
+struct Publisher {
+    session_id: String,
+    stream_id: String,
+    /// Most recent key frame, handed to subscribers that join mid-stream
+    last_keyframe: Option<Arc<MediaFrame>>,
+    subscribers: HashMap<String, FanoutSubscriber>,
+}
+
+/// Broadcast/subscribe fan-out layered over `QuicMediaManager`
+///
+/// `QuicMediaManager` alone is strictly point-to-point: a stream's frames only
+/// ever reach the one `QuicMediaReceiver` at the other end of its session, and
+/// `QuicMediaManager::send_frame` has no idea a stream is a broadcast's
+/// source - it never fans anything out. `FanoutManager` adds the one-to-many
+/// delivery an SFU needs: `publish` registers a source stream under a
+/// `BroadcastId`, `subscribe` wires a new stream in a subscriber's session to
+/// receive a copy of every frame published to that broadcast, and a
+/// broadcast's publisher must send its frames through `FanoutManager::send_frame`
+/// (not `QuicMediaManager::send_frame` directly) for them to reach any
+/// subscriber. Subscribers joining mid-stream are gated on the next key frame
+/// rather than starting from whatever frame happens to land, and a subscriber
+/// whose queue overflows because it stalled is dropped back to the same gate
+/// instead of blocking the publisher or other subscribers.
+pub struct FanoutManager {
+    manager: Arc<QuicMediaManager>,
+    publishers: RwLock<HashMap<BroadcastId, Publisher>>,
+}
+
+impl FanoutManager {
+    /// Create a fan-out layer over an existing `QuicMediaManager`
+    pub fn new(manager: Arc<QuicMediaManager>) -> Self {
+        Self {
+            manager,
+            publishers: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// Register a source stream as a named broadcast
+    pub async fn publish(
+        &self,
+        session_id: &str,
+        stream_id: String,
+        track_id: u64,
+        config: MediaStreamConfig,
+        broadcast_id: BroadcastId,
+    ) -> Result<()> {
+        let mut publishers = self.publishers.write().await;
+        if publishers.contains_key(&broadcast_id) {
+            return Err(SfuError::Transport(format!("Broadcast already exists: {}", broadcast_id)).into());
+        }
+
+        self.manager
+            .create_stream(session_id, stream_id.clone(), track_id, config)
+            .await?;
+
+        publishers.insert(
+            broadcast_id,
+            Publisher {
+                session_id: session_id.to_string(),
+                stream_id,
+                last_keyframe: None,
+                subscribers: HashMap::new(),
+            },
+        );
+
+        Ok(())
+    }
+
+    /// Remove a broadcast, dropping its subscribers' delivery queues
+    pub async fn unpublish(&self, broadcast_id: &str) -> Result<()> {
+        let mut publishers = self.publishers.write().await;
+        publishers
+            .remove(broadcast_id)
+            .map(|_| ())
+            .ok_or_else(|| SfuError::Transport(format!("Broadcast not found: {}", broadcast_id)).into())
+    }
+
+    /// Subscribe a session to a broadcast, creating a receive-only stream in
+    /// that session fed by the publisher's frame flow. If the broadcast
+    /// already has a key frame cached, delivery starts there; otherwise the
+    /// new subscriber waits for the next one.
+    pub async fn subscribe(
+        &self,
+        session_id: &str,
+        stream_id: String,
+        track_id: u64,
+        broadcast_id: &str,
+    ) -> Result<()> {
+        let mut publishers = self.publishers.write().await;
+        let publisher = publishers
+            .get_mut(broadcast_id)
+            .ok_or_else(|| SfuError::Transport(format!("Broadcast not found: {}", broadcast_id)))?;
+
+        let source_config = {
+            let sessions = self.manager.sessions.read().await;
+            let source_stream = sessions
+                .get(&publisher.session_id)
+                .and_then(|session| session.get_stream(&publisher.stream_id))
+                .ok_or_else(|| SfuError::Transport(format!("Broadcast source stream gone: {}", broadcast_id)))?;
+            MediaStreamConfig {
+                direction: StreamDirection::RecvOnly,
+                codec_type: source_stream.config().codec_type,
+                payload_type: source_stream.config().payload_type,
+                ssrc: source_stream.config().ssrc,
+                min_bitrate: source_stream.config().min_bitrate,
+                max_bitrate: source_stream.config().max_bitrate,
+                layers: Vec::new(),
+            }
+        };
+        drop(publishers);
+
+        self.manager
+            .create_stream(session_id, stream_id.clone(), track_id, source_config)
+            .await?;
+
+        let mut publishers = self.publishers.write().await;
+        let publisher = publishers
+            .get_mut(broadcast_id)
+            .ok_or_else(|| SfuError::Transport(format!("Broadcast not found: {}", broadcast_id)))?;
+
+        let (tx, mut rx) = mpsc::channel(SUBSCRIBER_QUEUE_CAPACITY);
+        if let Some(keyframe) = publisher.last_keyframe.clone() {
+            let _ = tx.try_send(keyframe);
+        }
+        publisher.subscribers.insert(
+            session_id.to_string(),
+            FanoutSubscriber {
+                session_id: session_id.to_string(),
+                sender: tx,
+                waiting_for_keyframe: publisher.last_keyframe.is_none(),
+            },
+        );
+        drop(publishers);
+
+        let manager = self.manager.clone();
+        let subscriber_session_id = session_id.to_string();
+        tokio::spawn(async move {
+            while let Some(frame) = rx.recv().await {
+                if let Err(e) = manager
+                    .send_frame(&subscriber_session_id, &stream_id, (*frame).clone())
+                    .await
+                {
+                    tracing::error!("Fan-out delivery to {} failed: {}", subscriber_session_id, e);
+                }
+            }
+        });
+
+        Ok(())
+    }
+
+    /// Remove a session's subscription to a broadcast
+    pub async fn unsubscribe(&self, session_id: &str, broadcast_id: &str) -> Result<()> {
+        let mut publishers = self.publishers.write().await;
+        let publisher = publishers
+            .get_mut(broadcast_id)
+            .ok_or_else(|| SfuError::Transport(format!("Broadcast not found: {}", broadcast_id)))?;
+
+        publisher
+            .subscribers
+            .remove(session_id)
+            .map(|_| ())
+            .ok_or_else(|| SfuError::Transport(format!("Session {} is not subscribed to {}", session_id, broadcast_id)).into())
+    }
+
+    /// Send a frame on behalf of `broadcast_id`'s publisher: forwards it on
+    /// the underlying `QuicMediaManager` stream exactly like a direct
+    /// `QuicMediaManager::send_frame` call would, and fans it out to every
+    /// subscriber via `publish_frame`. This is the call a broadcast's
+    /// publisher is expected to use in place of `QuicMediaManager::send_frame`
+    /// - that alone would deliver nothing to subscribers.
+    pub async fn send_frame(&self, broadcast_id: &str, frame: MediaFrame) -> Result<()> {
+        let (session_id, stream_id) = {
+            let publishers = self.publishers.read().await;
+            let publisher = publishers
+                .get(broadcast_id)
+                .ok_or_else(|| SfuError::Transport(format!("Broadcast not found: {}", broadcast_id)))?;
+            (publisher.session_id.clone(), publisher.stream_id.clone())
+        };
+
+        self.manager.send_frame(&session_id, &stream_id, frame.clone()).await?;
+        self.publish_frame(broadcast_id, frame).await
+    }
+
+    /// Fan a frame out to every subscriber of `broadcast_id`, called by
+    /// `send_frame` for every frame a publisher produces
+    async fn publish_frame(&self, broadcast_id: &str, frame: MediaFrame) -> Result<()> {
+        let frame = Arc::new(frame);
+        let mut publishers = self.publishers.write().await;
+        let publisher = publishers
+            .get_mut(broadcast_id)
+            .ok_or_else(|| SfuError::Transport(format!("Broadcast not found: {}", broadcast_id)))?;
+
+        if frame.is_key_frame() {
+            publisher.last_keyframe = Some(frame.clone());
+        }
+
+        publisher.subscribers.retain(|_, subscriber| {
+            if subscriber.waiting_for_keyframe {
+                if !frame.is_key_frame() {
+                    return true;
+                }
+                subscriber.waiting_for_keyframe = false;
+            }
+
+            match subscriber.sender.try_send(frame.clone()) {
+                Ok(()) => true,
+                Err(mpsc::error::TrySendError::Full(_)) => {
+                    tracing::debug!(
+                        "Subscriber {} fell behind on broadcast {}, dropping to next keyframe",
+                        subscriber.session_id,
+                        broadcast_id
+                    );
+                    subscriber.waiting_for_keyframe = true;
+                    true
+                }
+                Err(mpsc::error::TrySendError::Closed(_)) => false,
+            }
+        });
+
+        Ok(())
+    }
+}
@@ -5,6 +5,10 @@
 pub mod quic;
 pub mod integration;
 pub mod adaptation;
+pub mod broadcast;
+pub mod relay;
+pub mod congestion;
+pub mod pacer;
 
 use std::sync::{Arc, atomic::{AtomicU32, Ordering}};
 
@@ -0,0 +1,138 @@
+// Packet pacer for QUIC media transport
+//
+// Smooths media egress against the current bandwidth estimate so an encoder
+// burst (e.g. a simulcast key frame) doesn't inflate the QUIC send buffer and,
+// with it, the very delay measurements the bandwidth estimator relies on.
+
+use std::sync::{
+    atomic::{AtomicU32, AtomicUsize, Ordering},
+    Arc,
+};
+use std::time::Duration;
+
+use tokio::sync::{mpsc, RwLock};
+use tokio::time::interval;
+
+use crate::{media::frame::MediaFrame, transport::integration::QuicMediaStream};
+
+/// How often the token bucket is refilled and queued frames are considered for release
+const PACER_TICK: Duration = Duration::from_millis(5);
+/// Multiplier applied to the target bitrate when refilling the token bucket,
+/// so a frame slightly larger than one tick's exact budget isn't held back
+/// indefinitely while the link still has the headroom to carry it
+const PACER_HEADROOM_MULTIPLIER: f64 = 1.1;
+
+/// Default max number of queued frames before lower-priority (non-key) frames are dropped
+pub const DEFAULT_MAX_QUEUE_DEPTH: usize = 60;
+
+/// Token-bucket packet pacer for one `QuicMediaStream`
+///
+/// Queued frames are released onto the stream at a rate bounded by the
+/// current target bitrate (see `set_target_bitrate`), refilling the token
+/// budget every `PACER_TICK` on its own tokio task. When the queue grows past
+/// `max_queue_depth`, `enqueue` drops the frame rather than growing the queue
+/// unbounded, unless it is a key frame, so sustained overload degrades
+/// quality instead of adding latency.
+pub struct Pacer {
+    target_bps: Arc<AtomicU32>,
+    max_queue_depth: usize,
+    queue_len: Arc<AtomicUsize>,
+    frame_tx: mpsc::Sender<MediaFrame>,
+    running: Arc<RwLock<bool>>,
+}
+
+impl Pacer {
+    /// Create a pacer that releases frames onto `stream`, starting at
+    /// `initial_target_bps` and holding at most `max_queue_depth` frames
+    pub fn new(stream: Arc<QuicMediaStream>, initial_target_bps: u32, max_queue_depth: usize) -> Self {
+        let (frame_tx, rx) = mpsc::channel(max_queue_depth);
+        let target_bps = Arc::new(AtomicU32::new(initial_target_bps));
+        let queue_len = Arc::new(AtomicUsize::new(0));
+        let running = Arc::new(RwLock::new(true));
+
+        let target_bps_clone = target_bps.clone();
+        let queue_len_clone = queue_len.clone();
+        let running_clone = running.clone();
+        tokio::spawn(async move {
+            Self::pacer_task(stream, rx, target_bps_clone, queue_len_clone, running_clone).await;
+        });
+
+        Self {
+            target_bps,
+            max_queue_depth,
+            queue_len,
+            frame_tx,
+            running,
+        }
+    }
+
+    /// Queue a frame for paced delivery. Returns `false` if the queue was at
+    /// `max_queue_depth` and the frame was dropped; a key frame is always
+    /// queued since the decoder can't make progress without one.
+    pub async fn enqueue(&self, frame: MediaFrame) -> bool {
+        if self.queue_len.load(Ordering::Relaxed) >= self.max_queue_depth && !frame.is_key_frame() {
+            tracing::debug!("Pacer queue at max depth {}, dropping delta frame", self.max_queue_depth);
+            return false;
+        }
+
+        if self.frame_tx.send(frame).await.is_err() {
+            return false;
+        }
+
+        self.queue_len.fetch_add(1, Ordering::Relaxed);
+        true
+    }
+
+    /// Update the token bucket's refill rate, called whenever
+    /// `BandwidthAdapter`'s `current_bitrate` changes
+    pub fn set_target_bitrate(&self, target_bps: u32) {
+        self.target_bps.store(target_bps, Ordering::Relaxed);
+    }
+
+    /// Stop the pacer's background task; queued frames are dropped
+    pub async fn stop(&self) {
+        *self.running.write().await = false;
+    }
+
+    async fn pacer_task(
+        stream: Arc<QuicMediaStream>,
+        mut rx: mpsc::Receiver<MediaFrame>,
+        target_bps: Arc<AtomicU32>,
+        queue_len: Arc<AtomicUsize>,
+        running: Arc<RwLock<bool>>,
+    ) {
+        let mut ticker = interval(PACER_TICK);
+        let mut budget_bytes: f64 = 0.0;
+        let mut pending: Option<MediaFrame> = None;
+
+        while *running.read().await {
+            ticker.tick().await;
+
+            let bps = target_bps.load(Ordering::Relaxed) as f64 * PACER_HEADROOM_MULTIPLIER;
+            budget_bytes += bps / 8.0 * PACER_TICK.as_secs_f64();
+
+            loop {
+                let frame = match pending.take() {
+                    Some(frame) => frame,
+                    None => match rx.try_recv() {
+                        Ok(frame) => {
+                            queue_len.fetch_sub(1, Ordering::Relaxed);
+                            frame
+                        }
+                        Err(_) => break,
+                    },
+                };
+
+                if frame.size() as f64 > budget_bytes {
+                    pending = Some(frame);
+                    break;
+                }
+
+                budget_bytes -= frame.size() as f64;
+                if let Err(e) = stream.send_frame(frame).await {
+                    tracing::error!("Pacer failed to send frame: {}", e);
+                }
+            }
+        }
+    }
+}
@@ -3,27 +3,259 @@
 // This module implements media transport over QUIC using iroh-roq.
 
 use std::{
-    collections::HashMap,
-    sync::{Arc, atomic::{AtomicU64, Ordering}},
+    collections::{HashMap, VecDeque},
+    sync::{Arc, atomic::{AtomicU32, AtomicU64, Ordering}},
+    time::Instant,
 };
 
 use anyhow::Result;
 use async_trait::async_trait;
 use bytes::Bytes;
 use iroh_roq::{Session, SendFlow, ReceiveFlow};
-use tokio::sync::{mpsc, RwLock};
+use serde::Serialize;
+use tokio::sync::{mpsc, Mutex, RwLock};
 
 use crate::{
     connection::RtcConnection,
+    feedback::{FeedbackChannel, FeedbackManager, RequestKeyframe},
     media::{
         frame::MediaFrame,
-        rtp::{RtpPacket, RtpPacketizer, RtpDepacketizer},
+        rtp::{FeedbackAction, RtpHeader, RtpPacket, RtpPacketizer, RtpDepacketizer},
         codec::{Codec, CodecType},
         TrackId,
     },
+    session::SessionId,
+    simulcast::SimulcastManager,
+    transport::{broadcast::Broadcasts, congestion::TrackBandwidthEstimator, relay::RelayTrack},
     SfuError,
 };
 
+/// RTP clock rate assumed when converting wall-clock gaps into RTP timestamp
+/// units for jitter computation. 90kHz is standard for video; treating audio
+/// the same way is a simplification shared with the rest of this module's
+/// codec handling
+const RTP_CLOCK_RATE: u64 = 90_000;
+
+/// Width of the sliding window over which the inbound bitrate estimate is
+/// recomputed
+const BITRATE_WINDOW: std::time::Duration = std::time::Duration::from_secs(1);
+
+/// Snapshot of one track's inbound/outbound RTP transport-health counters,
+/// as read out by `QuicMediaTrack::stats()`
+#[derive(Debug, Clone, Serialize)]
+pub struct TrackRtpStats {
+    /// Packets sent on the outbound (publisher) side
+    pub packets_sent: u64,
+    /// Bytes sent on the outbound (publisher) side
+    pub bytes_sent: u64,
+    /// Packets received on the inbound (subscriber) side
+    pub packets_received: u64,
+    /// Bytes received on the inbound (subscriber) side
+    pub bytes_received: u64,
+    /// Packets lost, inferred from gaps in received RTP sequence numbers
+    pub packets_lost: u64,
+    /// RFC 3550 interarrival jitter estimate, in RTP timestamp units
+    pub jitter: f64,
+    /// Bitrate received over the last `BITRATE_WINDOW`, in bits per second
+    pub receive_bitrate_bps: u32,
+    /// Delay-based congestion control target bitrate, in bits per second.
+    /// Derived from inter-group delay variation between RTP timestamps and
+    /// local arrival times (see `TrackBandwidthEstimator`), intended to be
+    /// passed as `SimulcastManager::select_layer`'s `available_bandwidth`
+    pub estimated_available_bandwidth_bps: u32,
+}
+
+/// State needed to compute the next jitter sample and bitrate window, only
+/// ever touched from the receive path so contention is never expected
+struct ReceiveWindowState {
+    /// Last received packet's RTP timestamp and local arrival instant
+    last_packet: Option<(u32, Instant)>,
+    /// Last received packet's RTP sequence number, to detect gaps
+    last_sequence_number: Option<u16>,
+    /// Start of the current bitrate window
+    window_start: Instant,
+    /// Bytes received so far in the current bitrate window
+    window_bytes: u64,
+}
+
+impl ReceiveWindowState {
+    fn new() -> Self {
+        Self {
+            last_packet: None,
+            last_sequence_number: None,
+            window_start: Instant::now(),
+            window_bytes: 0,
+        }
+    }
+}
+
+/// Lock-free-read RTP transport-health counters for a `QuicMediaTrack`. The
+/// counters themselves are atomics so `stats()` never has to wait on the
+/// send/receive paths; only the jitter/bitrate bookkeeping that genuinely
+/// needs the previous packet's state lives behind a lock
+struct RtpStats {
+    packets_sent: AtomicU64,
+    bytes_sent: AtomicU64,
+    packets_received: AtomicU64,
+    bytes_received: AtomicU64,
+    packets_lost: AtomicU64,
+    /// RFC 3550 jitter estimate, stored as `f64::to_bits` for atomic access
+    jitter_bits: AtomicU64,
+    receive_bitrate_bps: AtomicU32,
+    receive_window: Mutex<ReceiveWindowState>,
+    /// Delay-based congestion control target bitrate, read out by `snapshot()`
+    estimated_available_bandwidth_bps: AtomicU32,
+    /// Trendline estimator and AIMD rate controller feeding the field above
+    bandwidth_estimator: Mutex<TrackBandwidthEstimator>,
+}
+
+impl RtpStats {
+    fn new() -> Self {
+        Self {
+            packets_sent: AtomicU64::new(0),
+            bytes_sent: AtomicU64::new(0),
+            packets_received: AtomicU64::new(0),
+            bytes_received: AtomicU64::new(0),
+            packets_lost: AtomicU64::new(0),
+            jitter_bits: AtomicU64::new(0f64.to_bits()),
+            receive_bitrate_bps: AtomicU32::new(0),
+            receive_window: Mutex::new(ReceiveWindowState::new()),
+            estimated_available_bandwidth_bps: AtomicU32::new(0),
+            bandwidth_estimator: Mutex::new(TrackBandwidthEstimator::new(RTP_CLOCK_RATE as u32)),
+        }
+    }
+
+    fn record_sent(&self, packet_len: usize) {
+        self.packets_sent.fetch_add(1, Ordering::Relaxed);
+        self.bytes_sent.fetch_add(packet_len as u64, Ordering::Relaxed);
+    }
+
+    /// Record one received packet: updates packet/byte counters, detects
+    /// sequence-number gaps, folds a new sample into the RFC 3550 jitter
+    /// estimate, and refreshes the windowed bitrate
+    async fn record_received(&self, header: &RtpHeader, packet_len: usize) {
+        self.packets_received.fetch_add(1, Ordering::Relaxed);
+        self.bytes_received.fetch_add(packet_len as u64, Ordering::Relaxed);
+
+        let now = Instant::now();
+        let mut window = self.receive_window.lock().await;
+
+        if let Some(last_sequence_number) = window.last_sequence_number {
+            let gap = header.sequence_number.wrapping_sub(last_sequence_number);
+            if gap > 1 && gap < u16::MAX / 2 {
+                self.packets_lost.fetch_add((gap - 1) as u64, Ordering::Relaxed);
+            }
+        }
+        window.last_sequence_number = Some(header.sequence_number);
+
+        if let Some((last_timestamp, last_arrival)) = window.last_packet {
+            let arrival_diff_rtp_units = (now.duration_since(last_arrival).as_secs_f64() * RTP_CLOCK_RATE as f64) as i64;
+            let timestamp_diff = header.timestamp.wrapping_sub(last_timestamp) as i64;
+            let d = (arrival_diff_rtp_units - timestamp_diff).abs() as f64;
+
+            let jitter = f64::from_bits(self.jitter_bits.load(Ordering::Relaxed));
+            let jitter = jitter + (d - jitter) / 16.0;
+            self.jitter_bits.store(jitter.to_bits(), Ordering::Relaxed);
+        }
+        window.last_packet = Some((header.timestamp, now));
+
+        window.window_bytes += packet_len as u64;
+        let elapsed = now.duration_since(window.window_start);
+        if elapsed >= BITRATE_WINDOW {
+            let bps = (window.window_bytes * 8) as f64 / elapsed.as_secs_f64();
+            self.receive_bitrate_bps.store(bps as u32, Ordering::Relaxed);
+            window.window_bytes = 0;
+            window.window_start = now;
+        }
+
+        let target_bps = self.bandwidth_estimator.lock().await.on_packet(header.timestamp, now, packet_len);
+        self.estimated_available_bandwidth_bps.store(target_bps, Ordering::Relaxed);
+    }
+
+    /// Replace the bandwidth estimator with one clamped to
+    /// `[min_bitrate, max_bitrate]` and reacting to congestion at the given
+    /// decrease factor/reaction interval. Only meaningful before the track
+    /// starts receiving packets, since it resets the trendline/rate state.
+    fn configure_bandwidth_estimator(
+        &self,
+        min_bitrate: u32,
+        max_bitrate: u32,
+        decrease_factor: f64,
+        reaction_interval: std::time::Duration,
+    ) {
+        let mut estimator = self
+            .bandwidth_estimator
+            .try_lock()
+            .expect("bandwidth estimator reconfigured while a receive was in flight");
+        *estimator = TrackBandwidthEstimator::with_config(
+            RTP_CLOCK_RATE as u32,
+            min_bitrate,
+            max_bitrate,
+            decrease_factor,
+            reaction_interval,
+        );
+    }
+
+    fn snapshot(&self) -> TrackRtpStats {
+        TrackRtpStats {
+            packets_sent: self.packets_sent.load(Ordering::Relaxed),
+            bytes_sent: self.bytes_sent.load(Ordering::Relaxed),
+            packets_received: self.packets_received.load(Ordering::Relaxed),
+            bytes_received: self.bytes_received.load(Ordering::Relaxed),
+            packets_lost: self.packets_lost.load(Ordering::Relaxed),
+            jitter: f64::from_bits(self.jitter_bits.load(Ordering::Relaxed)),
+            receive_bitrate_bps: self.receive_bitrate_bps.load(Ordering::Relaxed),
+            estimated_available_bandwidth_bps: self.estimated_available_bandwidth_bps.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// A frame's position in a MoQ-style broadcast -> track -> group -> object
+/// hierarchy: `group_seq` identifies the GoP it falls in (starting at a key
+/// frame), `object_seq` its position within that group
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MoqObjectId {
+    /// Sequence number of the group (GoP) this object belongs to
+    pub group_seq: u64,
+    /// Position of this object within its group, `0` for the group's key frame
+    pub object_seq: u64,
+}
+
+/// Group/object sequencing state for a track's sent frames, advanced by
+/// `QuicMediaTrack::send_frame`
+struct GroupState {
+    /// Sequence number of the current group
+    group_seq: u64,
+    /// Sequence number the next object in the current group will get
+    next_object_seq: u64,
+    /// Whether a group has been opened yet, so the very first frame (which
+    /// is assumed to be a key frame) starts at group 0 instead of bumping
+    /// past it
+    started: bool,
+}
+
+impl GroupState {
+    fn new() -> Self {
+        Self { group_seq: 0, next_object_seq: 0, started: false }
+    }
+
+    /// Advance the state for a frame and return its object id, opening a new
+    /// group when the frame is a key frame
+    fn tag(&mut self, is_key_frame: bool) -> MoqObjectId {
+        if is_key_frame || !self.started {
+            if self.started {
+                self.group_seq += 1;
+            }
+            self.started = true;
+            self.next_object_seq = 0;
+        }
+
+        let object_seq = self.next_object_seq;
+        self.next_object_seq += 1;
+        MoqObjectId { group_seq: self.group_seq, object_seq }
+    }
+}
+
 /// Media track transport over QUIC
 pub struct QuicMediaTrack {
     /// Track identifier
@@ -40,6 +272,27 @@ pub struct QuicMediaTrack {
     receive_flow: Option<ReceiveFlow>,
     /// Frame sequence number
     frame_seq: AtomicU64,
+    /// Group/object sequencing for frames handed to `send_frame`, so a
+    /// broadcast broker can tell where the most recent group boundary is
+    group_state: Mutex<GroupState>,
+    /// Frames reassembled from a single received packet but not yet returned,
+    /// since the jitter buffer may release several frames at once
+    pending_frames: Mutex<VecDeque<MediaFrame>>,
+    /// Feedback manager, channel, and owning session used to request a fresh
+    /// key frame when the jitter buffer detects an unrecoverable gap
+    feedback: Option<(Arc<dyn FeedbackManager>, Arc<FeedbackChannel>, SessionId)>,
+    /// Simulcast manager notified when `receive_frame` reconstructs a key
+    /// frame, so a subscriber's pending switch-up to this frame's spatial
+    /// layer can be applied
+    simulcast_manager: Option<Arc<dyn SimulcastManager>>,
+    /// Broadcast broker and name to publish every sent frame under, so a
+    /// `Broadcasts` subscriber actually receives what this track sends
+    broadcast: Option<(Arc<Broadcasts>, String)>,
+    /// Relay fanning out every received packet to its subscribers, so a
+    /// `RelayTrack` subscriber actually receives what this track receives
+    relay: Option<Arc<RelayTrack>>,
+    /// Inbound/outbound RTP transport-health counters, read via `stats()`
+    rtp_stats: RtpStats,
 }
 
 impl QuicMediaTrack {
@@ -53,84 +306,195 @@ impl QuicMediaTrack {
             send_flow: None,
             receive_flow: None,
             frame_seq: AtomicU64::new(0),
+            group_state: Mutex::new(GroupState::new()),
+            pending_frames: Mutex::new(VecDeque::new()),
+            feedback: None,
+            simulcast_manager: None,
+            broadcast: None,
+            relay: None,
+            rtp_stats: RtpStats::new(),
         }
     }
-    
+
     /// Set the send flow
     pub fn set_send_flow(&mut self, flow: SendFlow) {
         self.send_flow = Some(flow);
     }
-    
+
     /// Set the receive flow
     pub fn set_receive_flow(&mut self, flow: ReceiveFlow) {
         self.receive_flow = Some(flow);
     }
-    
-    /// Send a media frame
-    pub async fn send_frame(&self, frame: &MediaFrame) -> Result<()> {
+
+    /// Wire up key frame requests raised by the jitter buffer to the
+    /// connection's feedback channel
+    pub fn set_feedback(
+        &mut self,
+        feedback_manager: Arc<dyn FeedbackManager>,
+        feedback_channel: Arc<FeedbackChannel>,
+        session_id: SessionId,
+    ) {
+        self.feedback = Some((feedback_manager, feedback_channel, session_id));
+    }
+
+    /// Notify the simulcast manager when a key frame is reconstructed on
+    /// this track, so it can apply subscribers' pending switch-ups
+    pub fn set_simulcast_manager(&mut self, simulcast_manager: Arc<dyn SimulcastManager>) {
+        self.simulcast_manager = Some(simulcast_manager);
+    }
+
+    /// Publish every frame this track sends to `name` on `broadcasts`, so
+    /// `Broadcasts::subscribe`rs of that name actually receive it
+    pub fn set_broadcast(&mut self, broadcasts: Arc<Broadcasts>, name: String) {
+        self.broadcast = Some((broadcasts, name));
+    }
+
+    /// Forward every packet this track receives through `relay`, so its
+    /// subscribers actually receive what this track's publisher sends
+    pub fn set_relay(&mut self, relay: Arc<RelayTrack>) {
+        self.relay = Some(relay);
+    }
+
+    /// Clamp this track's delay-based bandwidth estimate to
+    /// `[min_bitrate, max_bitrate]` and configure how aggressively its AIMD
+    /// rate controller backs off (`decrease_factor`) and how long sustained
+    /// queuing delay must persist before it reacts (`reaction_interval`).
+    /// Only meaningful on a receiving track, since the estimate is only ever
+    /// updated from `receive_frame`.
+    pub fn set_bandwidth_limits(
+        &mut self,
+        min_bitrate: u32,
+        max_bitrate: u32,
+        decrease_factor: f64,
+        reaction_interval: std::time::Duration,
+    ) {
+        self.rtp_stats.configure_bandwidth_estimator(min_bitrate, max_bitrate, decrease_factor, reaction_interval);
+    }
+
+    /// Send a media frame, returning its position in the group/object
+    /// hierarchy (see `MoqObjectId`) so a broadcast broker can tell whether
+    /// this frame opened a new group
+    pub async fn send_frame(&self, frame: &MediaFrame) -> Result<MoqObjectId> {
         if let Some(send_flow) = &self.send_flow {
             // Get timestamp from frame
             let timestamp = frame.timestamp;
-            
+
             // Packetize the frame
             let packets = self.packetizer.packetize(&frame.data, timestamp)?;
-            
+
             // Send each packet
             for packet in packets {
                 let packet_data = packet.serialize();
+                self.rtp_stats.record_sent(packet_data.len());
                 send_flow.send(packet_data).await?;
             }
-            
+
             // Increment frame sequence
             self.frame_seq.fetch_add(1, Ordering::SeqCst);
-            
-            Ok(())
+
+            let object_id = self.group_state.lock().await.tag(frame.is_key_frame());
+
+            if let Some((broadcasts, name)) = &self.broadcast {
+                if let Err(e) = broadcasts.publish(name, object_id, frame.clone()).await {
+                    tracing::debug!("Failed to publish frame to broadcast \"{}\": {}", name, e);
+                }
+            }
+
+            Ok(object_id)
         } else {
             Err(SfuError::Transport("No send flow available".to_string()).into())
         }
     }
+
+    /// The group/object id of the start of the most recently opened group,
+    /// i.e. of its key frame, the earliest point a late joiner can decode
+    /// from. `Broadcasts` does not replay from here: it admits a new
+    /// subscriber at the next group boundary instead
+    pub async fn latest_group_boundary(&self) -> MoqObjectId {
+        let group_state = self.group_state.lock().await;
+        MoqObjectId { group_seq: group_state.group_seq, object_seq: 0 }
+    }
     
     /// Receive a media frame
     pub async fn receive_frame(&self) -> Result<Option<MediaFrame>> {
+        // Return a previously reassembled frame before pulling a new packet,
+        // since the jitter buffer can release several frames at once
+        if let Some(frame) = self.pending_frames.lock().await.pop_front() {
+            return Ok(Some(frame));
+        }
+
         if let Some(receive_flow) = &self.receive_flow {
             // Try to receive a packet
             if let Some(packet_data) = receive_flow.receive().await? {
+                if let Some(relay) = &self.relay {
+                    relay.forward(packet_data.clone()).await;
+                }
+
                 // Parse RTP packet
                 let packet = RtpPacket::parse(&packet_data)?;
-                
-                // Process packet with depacketizer
-                if let Some(frame_data) = self.depacketizer.process_packet(packet)? {
+                let timestamp = packet.header.timestamp;
+                self.rtp_stats.record_received(&packet.header, packet_data.len()).await;
+
+                // Process packet through the jitter buffer and per-codec reassembly
+                let (frames_data, feedback_actions) = self.depacketizer.process_packet(packet)?;
+                for action in feedback_actions {
+                    match action {
+                        FeedbackAction::RequestKeyFrame => {
+                            if let Some((feedback_manager, feedback_channel, session_id)) = &self.feedback {
+                                let request = RequestKeyframe {
+                                    session_id: *session_id,
+                                    track_id: self.track_id,
+                                };
+                                if let Err(e) = feedback_manager.request_keyframe(feedback_channel, request).await {
+                                    tracing::warn!("Failed to request key frame for track {}: {}", self.track_id, e);
+                                }
+                            } else {
+                                tracing::debug!("RTP feedback action for track {}: {:?}", self.track_id, action);
+                            }
+                        }
+                        FeedbackAction::Nack(_) => {
+                            tracing::debug!("RTP feedback action for track {}: {:?}", self.track_id, action);
+                        }
+                    }
+                }
+
+                let mut frames = VecDeque::new();
+                for frame_data in frames_data {
                     // Create media frame
                     let frame = match self.codec_type {
                         CodecType::Opus => {
                             MediaFrame::new_audio(
                                 self.codec_type,
                                 Bytes::from(frame_data),
-                                packet.header.timestamp,
+                                timestamp,
                                 std::time::Duration::from_millis(20), // Typical Opus frame duration
                             )?
                         }
                         CodecType::VP9 => {
-                            // Determine if this is a key frame (simplified)
-                            let is_key_frame = frame_data.len() > 0 && (frame_data[0] & 0x01) == 0;
-                            
+                            // Parsed from the VP9 payload descriptor: start-of-frame with
+                            // the inter-picture-prediction bit clear means a key frame
+                            let is_key_frame = self.depacketizer.current_is_key_frame();
+                            let layer = self.depacketizer.current_layer();
+                            let spatial_layer = layer.map(|l| l.spatial_id);
+                            let temporal_layer = layer.map(|l| l.temporal_id);
+
                             if is_key_frame {
                                 MediaFrame::new_video_key(
                                     self.codec_type,
                                     Bytes::from(frame_data),
-                                    packet.header.timestamp,
+                                    timestamp,
                                     std::time::Duration::from_millis(33), // ~30fps
-                                    None, // Spatial layer would be extracted from VP9 payload
-                                    None, // Temporal layer would be extracted from VP9 payload
+                                    spatial_layer,
+                                    temporal_layer,
                                 )?
                             } else {
                                 MediaFrame::new_video_delta(
                                     self.codec_type,
                                     Bytes::from(frame_data),
-                                    packet.header.timestamp,
+                                    timestamp,
                                     std::time::Duration::from_millis(33), // ~30fps
-                                    None, // Spatial layer would be extracted from VP9 payload
-                                    None, // Temporal layer would be extracted from VP9 payload
+                                    spatial_layer,
+                                    temporal_layer,
                                 )?
                             }
                         }
@@ -138,11 +502,28 @@ impl QuicMediaTrack {
                             return Err(SfuError::Media(format!("Unsupported codec: {:?}", self.codec_type)).into());
                         }
                     };
-                    
-                    return Ok(Some(frame));
+
+                    if frame.is_key_frame() {
+                        if let Some(simulcast_manager) = &self.simulcast_manager {
+                            let spatial_id = frame.spatial_layer.unwrap_or(0);
+                            if let Err(e) = simulcast_manager.notify_key_frame(self.track_id, spatial_id).await {
+                                tracing::debug!(
+                                    "Failed to notify simulcast manager of key frame for track {}: {}",
+                                    self.track_id,
+                                    e
+                                );
+                            }
+                        }
+                    }
+
+                    frames.push_back(frame);
                 }
+
+                let mut pending = self.pending_frames.lock().await;
+                pending.extend(frames);
+                return Ok(pending.pop_front());
             }
-            
+
             // No complete frame available yet
             Ok(None)
         } else {
@@ -159,6 +540,11 @@ impl QuicMediaTrack {
     pub fn codec_type(&self) -> CodecType {
         self.codec_type
     }
+
+    /// Snapshot this track's inbound/outbound RTP transport-health counters
+    pub fn stats(&self) -> TrackRtpStats {
+        self.rtp_stats.snapshot()
+    }
 }
 
 /// QUIC media transport implementation
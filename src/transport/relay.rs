@@ -0,0 +1,151 @@
+// Single-publisher, many-subscriber packet fan-out for a `QuicMediaTrack`
+//
+// `QuicMediaReceiver`/`QuicMediaSender` assume a 1:1 pipe: one inbound flow,
+// one outbound flow. A `RelayTrack` sits on the inbound side of a published
+// track and fans its already-serialized RTP packets out to N subscriber
+// flows, so forwarding never re-runs the packetizer/depacketizer per
+// subscriber - every subscriber just gets a clone of the same `Bytes` the
+// publisher sent.
+//
+// A small cache holds the packets of the current keyframe group (the GoP
+// since the last key frame) so a subscriber attaching mid-stream can be
+// primed with it instead of waiting for the next key frame to occur
+// naturally. Forwarding never blocks on a slow subscriber: its channel send
+// is non-blocking, and a subscriber that can't keep up is marked `Lagging`
+// and dropped from the fan-out until the next keyframe group, where it's
+// resynced from the cache rather than retried packet-by-packet.
+//
+// A publisher's `QuicMediaTrack` forwards every packet it receives through
+// this relay via `QuicMediaTrack::set_relay`, which wires this relay into
+// the track's `receive_frame` before the packet is depacketized locally.
+
+use std::collections::{HashMap, VecDeque};
+
+use bytes::Bytes;
+use tokio::sync::{mpsc, RwLock};
+
+use crate::{
+    media::{codec::CodecType, rtp::RtpPacket, TrackId},
+    session::SessionId,
+};
+
+/// Capacity of a subscriber's packet channel
+const SUBSCRIBER_CHANNEL_CAPACITY: usize = 512;
+
+/// Maximum packets retained in the current keyframe group's cache
+const KEYFRAME_CACHE_CAPACITY: usize = 512;
+
+/// A subscriber's forwarding state
+enum SubscriberState {
+    /// Receiving every forwarded packet
+    Active,
+    /// Fell behind and is skipped until the next keyframe group, where it's
+    /// resynced from the cache instead of replayed packet-by-packet
+    Lagging,
+}
+
+/// One subscriber attached to a `RelayTrack`
+struct Subscriber {
+    state: SubscriberState,
+    sender: mpsc::Sender<Bytes>,
+}
+
+/// Fans out one inbound track's serialized RTP packets to many subscriber
+/// flows, with keyframe-group caching for subscribers joining mid-stream
+pub struct RelayTrack {
+    track_id: TrackId,
+    codec_type: CodecType,
+    subscribers: RwLock<HashMap<SessionId, Subscriber>>,
+    keyframe_cache: RwLock<VecDeque<Bytes>>,
+}
+
+impl RelayTrack {
+    /// Create a new relay for `track_id`, with no subscribers and an empty
+    /// keyframe cache
+    pub fn new(track_id: TrackId, codec_type: CodecType) -> Self {
+        Self {
+            track_id,
+            codec_type,
+            subscribers: RwLock::new(HashMap::new()),
+            keyframe_cache: RwLock::new(VecDeque::new()),
+        }
+    }
+
+    /// Track this relay fans out
+    pub fn track_id(&self) -> TrackId {
+        self.track_id
+    }
+
+    /// Attach `subscriber`, priming its channel with the cached keyframe
+    /// group so it doesn't have to wait for the next one
+    pub async fn add_subscriber(&self, subscriber: SessionId) -> mpsc::Receiver<Bytes> {
+        let (sender, receiver) = mpsc::channel(SUBSCRIBER_CHANNEL_CAPACITY);
+
+        for packet in self.keyframe_cache.read().await.iter() {
+            if sender.try_send(packet.clone()).is_err() {
+                break;
+            }
+        }
+
+        self.subscribers.write().await.insert(subscriber, Subscriber { state: SubscriberState::Active, sender });
+        receiver
+    }
+
+    /// Detach `subscriber` from this relay
+    pub async fn remove_subscriber(&self, subscriber: SessionId) {
+        self.subscribers.write().await.remove(&subscriber);
+    }
+
+    /// Forward one already-serialized RTP packet from the publisher to every
+    /// subscriber. Detects whether `packet` opens a new keyframe group from
+    /// its codec payload and uses that to refresh the cache and resync any
+    /// lagging subscriber.
+    pub async fn forward(&self, packet: Bytes) {
+        let starts_keyframe_group = self.starts_keyframe_group(&packet);
+
+        {
+            let mut cache = self.keyframe_cache.write().await;
+            if starts_keyframe_group {
+                cache.clear();
+            } else if cache.len() >= KEYFRAME_CACHE_CAPACITY {
+                cache.pop_front();
+            }
+            cache.push_back(packet.clone());
+        }
+
+        let mut subscribers = self.subscribers.write().await;
+        for subscriber in subscribers.values_mut() {
+            if matches!(subscriber.state, SubscriberState::Lagging) {
+                if !starts_keyframe_group {
+                    // Still mid-group: wait for the next key frame to resync
+                    continue;
+                }
+                subscriber.state = SubscriberState::Active;
+            }
+
+            if subscriber.sender.try_send(packet.clone()).is_err() {
+                tracing::debug!("Subscriber fell behind on relay track {}, marking for resync", self.track_id);
+                subscriber.state = SubscriberState::Lagging;
+            }
+        }
+    }
+
+    /// Whether `packet` opens a new keyframe group, from a simplified read
+    /// of its codec payload (mirrors the key-frame heuristic in
+    /// `QuicMediaTrack::receive_frame`: start-of-frame with the
+    /// inter-picture-prediction bit clear). Audio has no keyframe concept, so
+    /// every audio packet is treated as its own group boundary, as is every
+    /// packet of any other codec besides VP9.
+    fn starts_keyframe_group(&self, packet: &Bytes) -> bool {
+        match self.codec_type {
+            CodecType::VP9 => match RtpPacket::parse(packet)
+                .ok()
+                .and_then(|rtp| crate::media::vpx::Vp9Descriptor::parse(&rtp.payload).ok())
+            {
+                Some((descriptor, _)) => descriptor.start_of_frame && !descriptor.inter_picture_predicted,
+                None => true,
+            },
+            _ => true,
+        }
+    }
+}